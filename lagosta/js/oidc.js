@@ -0,0 +1,136 @@
+/*
+ * Login with OpenID Connect for Lagosta.
+ *
+ * Lagosta itself only knows about logging in with a token. This script is
+ * loaded before it and adds what is needed to use a session instead:
+ *
+ * - a single sign-on link on the login page, if krill has OpenID Connect
+ *   enabled;
+ * - marking the user as logged in when krill has set the session cookies,
+ *   without token, so that Lagosta relies on the session cookie;
+ * - the CSRF token header on requests to krill that make changes;
+ * - ending the session at krill when the user logs out.
+ */
+(function () {
+  "use strict";
+
+  var CSRF_COOKIE = "krill_csrf";
+  var CSRF_HEADER = "X-CSRF-Token";
+  var SAFE_METHODS = ["GET", "HEAD", "OPTIONS"];
+
+  function csrfToken() {
+    var cookies = document.cookie ? document.cookie.split("; ") : [];
+    for (var i = 0; i < cookies.length; i++) {
+      var parts = cookies[i].split("=");
+      if (parts[0] === CSRF_COOKIE && parts[1]) {
+        return decodeURIComponent(parts[1]);
+      }
+    }
+    return null;
+  }
+
+  function isSessionUser(item) {
+    try {
+      var user = JSON.parse(item);
+      return !!(user && user.session);
+    } catch (e) {
+      return false;
+    }
+  }
+
+  // Krill sets the CSRF token cookie when the user logs in. Lagosta only
+  // shows its pages to a known user, but it must not send a token for this
+  // one, so that the session cookie is used.
+  if (csrfToken() && !localStorage.getItem("user")) {
+    localStorage.setItem("user", JSON.stringify({ session: true }));
+  }
+
+  // Add the CSRF token to requests to krill that can make changes.
+  var open = XMLHttpRequest.prototype.open;
+  var send = XMLHttpRequest.prototype.send;
+
+  XMLHttpRequest.prototype.open = function (method, url) {
+    this.krillMethod = String(method).toUpperCase();
+    this.krillSameOrigin =
+      new URL(url, window.location.href).origin === window.location.origin;
+    return open.apply(this, arguments);
+  };
+
+  XMLHttpRequest.prototype.send = function () {
+    var token = csrfToken();
+    if (
+      token &&
+      this.krillSameOrigin &&
+      SAFE_METHODS.indexOf(this.krillMethod) < 0
+    ) {
+      this.setRequestHeader(CSRF_HEADER, token);
+    }
+    return send.apply(this, arguments);
+  };
+
+  // Lagosta logs out by forgetting the user. For a session, also end it at
+  // krill, which removes the cookies, then send the user back to the UI.
+  // Logging out is a change, so it needs the CSRF token.
+  var removeItem = Storage.prototype.removeItem;
+
+  Storage.prototype.removeItem = function (key) {
+    var endSession =
+      this === window.localStorage &&
+      key === "user" &&
+      isSessionUser(this.getItem(key));
+    var token = csrfToken();
+    removeItem.apply(this, arguments);
+    if (endSession) {
+      var req = new XMLHttpRequest();
+      req.open("POST", "/auth/logout");
+      if (token) {
+        req.setRequestHeader(CSRF_HEADER, token);
+      }
+      req.onloadend = function () {
+        window.location.href = "/";
+      };
+      send.call(req);
+    }
+  };
+
+  // Show a single sign-on link on the login page, if krill supports it.
+  function addLoginLink() {
+    var link = document.createElement("a");
+    link.href = "/auth/login";
+    link.textContent = "Log in with single sign-on";
+    link.style.cssText =
+      "position: fixed; top: 1em; right: 1em; z-index: 3000;" +
+      "padding: 0.6em 1.2em; border-radius: 4px; background: #409eff;" +
+      "color: #fff; font-family: Lato, sans-serif; text-decoration: none;";
+    document.body.appendChild(link);
+
+    function update() {
+      var onLoginPage = window.location.hash.indexOf("#/login") === 0;
+      link.style.display = onLoginPage ? "" : "none";
+    }
+
+    // The router does not always change the hash in a way that fires
+    // events, so also check when the page is rendered again.
+    window.addEventListener("hashchange", update);
+    new MutationObserver(update).observe(document.body, {
+      childList: true,
+      subtree: true
+    });
+    update();
+  }
+
+  document.addEventListener("DOMContentLoaded", function () {
+    var req = new XMLHttpRequest();
+    req.open("GET", "/auth/enabled");
+    req.onload = function () {
+      try {
+        if (req.status === 200 && JSON.parse(req.responseText).enabled) {
+          addLoginLink();
+        }
+      } catch (e) {
+        // Without the link users can still log in with a token.
+      }
+    };
+    req.send();
+  });
+})();
@@ -1,12 +1,19 @@
 extern crate krill;
 
+use krill::commons::eventsourcing::{migrate_disk_to_sqlite, migrate_sqlite_to_disk, KeyStoreType};
+use krill::commons::util::softsigner::OpenSslSigner;
+use krill::constants::{CASERVER_DIR, PUBSERVER_DIR};
 use krill::daemon::config::Config;
 use krill::daemon::http::server;
 
 fn main() {
     match Config::create() {
         Ok(config) => {
-            if let Err(e) = server::start(&config) {
+            if Config::encrypt_keys_requested() {
+                encrypt_keys(&config);
+            } else if let Some(store_type) = Config::migrate_store_requested() {
+                migrate_store(&config, store_type);
+            } else if let Err(e) = server::start(&config) {
                 eprintln!("Krill failed to start: {}", e);
                 ::std::process::exit(1);
             }
@@ -17,3 +24,49 @@ fn main() {
         }
     }
 }
+
+/// Encrypts all existing unencrypted keys in place, and exits.
+fn encrypt_keys(config: &Config) {
+    let passphrase = match config.keys_passphrase() {
+        Ok(Some(passphrase)) => passphrase,
+        Ok(None) => {
+            eprintln!("Set 'encrypt_keys = true' and a passphrase in the config to encrypt keys");
+            ::std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ::std::process::exit(1);
+        }
+    };
+
+    match OpenSslSigner::build_encrypted(&config.data_dir, &passphrase)
+        .and_then(|signer| signer.encrypt_existing_keys())
+    {
+        Ok(count) => println!("Encrypted {} keys", count),
+        Err(e) => {
+            eprintln!("Failed to encrypt keys: {}", e);
+            ::std::process::exit(1);
+        }
+    }
+}
+
+/// Copies all CAs and the repository server into a store of the given type,
+/// from the other type of store, and exits.
+fn migrate_store(config: &Config, store_type: KeyStoreType) {
+    let name_spaces = [CASERVER_DIR, PUBSERVER_DIR];
+    let res = match store_type {
+        KeyStoreType::Disk => migrate_sqlite_to_disk(&config.data_dir, &name_spaces),
+        KeyStoreType::Sqlite => migrate_disk_to_sqlite(&config.data_dir, &name_spaces),
+    };
+
+    match res {
+        Ok(count) => println!(
+            "Migrated {} CAs and repositories, set 'store_type' in the config to use them",
+            count
+        ),
+        Err(e) => {
+            eprintln!("Failed to migrate store: {}", e);
+            ::std::process::exit(1);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::{env, fmt, io};
 
+use reqwest::{Url, UrlError};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
@@ -7,11 +8,13 @@ use rpki::uri;
 
 use crate::cli::options::{
     BulkCaCommand, CaCommand, Command, KrillInitDetails, Options, PublishersCommand,
+    TaOfflineCommand,
 };
 use crate::cli::report::{ApiResponse, ReportError};
 use crate::commons::api::{
-    AllCertAuthIssues, CaRepoDetails, CertAuthIssues, ChildCaInfo, CurrentRepoState,
-    ParentCaContact, PublisherDetails, PublisherList, Token,
+    AllCertAuthIssues, AspaDefinitionUpdates, AuditCriteria, AuditLog, BgpSecDefinitionUpdates,
+    CaRepoDetails, CertAuthIssues, ChildCaInfo, CurrentRepoState, ParentCaContact,
+    PublisherDetails, PublisherList, Token,
 };
 use crate::commons::remote::rfc8183;
 use crate::commons::util::httpclient;
@@ -58,6 +61,8 @@ impl KrillClient {
             Command::Bulk(cmd) => client.bulk(cmd),
             Command::CertAuth(cmd) => client.certauth(cmd),
             Command::Publishers(cmd) => client.publishers(cmd),
+            Command::TaOffline(cmd) => client.ta_offline(cmd),
+            Command::Audit(criteria) => client.audit(criteria),
             Command::Init(details) => client.init(details),
             Command::NotSet => Err(Error::MissingCommand),
         }
@@ -90,6 +95,26 @@ impl KrillClient {
         }
     }
 
+    fn ta_offline(&self, command: TaOfflineCommand) -> Result<ApiResponse, Error> {
+        match command {
+            TaOfflineCommand::Requests(options) => {
+                let republish = options.republish();
+                let uri = format!("api/v1/ta/offline/requests?republish={}", republish);
+                let bundle = self.get_json(&uri)?;
+                Ok(ApiResponse::TaRequestBundle(bundle))
+            }
+            TaOfflineCommand::Sign(bundle) => {
+                let uri = "api/v1/ta/offline/sign";
+                let responses = self.post_json_with_response(uri, bundle)?;
+                Ok(ApiResponse::TaResponseBundle(responses))
+            }
+            TaOfflineCommand::Import(bundle) => {
+                self.post_json("api/v1/ta/offline/responses", bundle)?;
+                Ok(ApiResponse::Empty)
+            }
+        }
+    }
+
     fn certauth(&self, command: CaCommand) -> Result<ApiResponse, Error> {
         match command {
             CaCommand::Init(init) => {
@@ -97,6 +122,12 @@ impl KrillClient {
                 Ok(ApiResponse::Empty)
             }
 
+            CaCommand::Delete(handle) => {
+                let uri = format!("api/v1/cas/{}", handle);
+                self.delete(&uri)?;
+                Ok(ApiResponse::Empty)
+            }
+
             CaCommand::UpdateId(handle) => {
                 let uri = format!("api/v1/cas/{}/id", handle);
                 self.post_empty(&uri)?;
@@ -195,6 +226,26 @@ impl KrillClient {
                 self.post_empty(&uri)?;
                 Ok(ApiResponse::Empty)
             }
+            CaCommand::KeyRollEmergency(handle, request) => {
+                let uri = format!("api/v1/cas/{}/keys/roll_emergency", handle);
+                self.post_json(&uri, request)?;
+                Ok(ApiResponse::Empty)
+            }
+            CaCommand::KeyRollPolicyShow(handle) => {
+                let uri = format!("api/v1/cas/{}/keys/policy", handle);
+                let info = self.get_json(&uri)?;
+                Ok(ApiResponse::CaKeyRollInfo(info))
+            }
+            CaCommand::KeyRollPolicyUpdate(handle, policy) => {
+                let uri = format!("api/v1/cas/{}/keys/policy", handle);
+                self.post_json(&uri, policy)?;
+                Ok(ApiResponse::Empty)
+            }
+            CaCommand::KeyRollPolicyRemove(handle) => {
+                let uri = format!("api/v1/cas/{}/keys/policy", handle);
+                self.delete(&uri)?;
+                Ok(ApiResponse::Empty)
+            }
 
             CaCommand::RouteAuthorizationsList(handle) => {
                 let uri = format!("api/v1/cas/{}/routes", handle);
@@ -208,6 +259,140 @@ impl KrillClient {
                 Ok(ApiResponse::Empty)
             }
 
+            CaCommand::RouteAuthorizationsDryRun(handle, updates) => {
+                let uri = format!("api/v1/cas/{}/routes/analysis/dryrun", handle);
+                let impact = self.post_json_with_response(&uri, updates)?;
+                Ok(ApiResponse::RouteAuthorizationsImpact(impact))
+            }
+
+            CaCommand::RouteAuthorizationsAnalysis(handle, announcements) => {
+                let uri = format!("api/v1/cas/{}/routes/analysis/full", handle);
+                let analysis = match announcements {
+                    Some(announcements) => self.post_json_with_response(&uri, announcements)?,
+                    None => self.get_json(&uri)?,
+                };
+                Ok(ApiResponse::RouteAuthorizationsAnalysis(analysis))
+            }
+
+            CaCommand::RouteAuthorizationsSync(handle, desired, options) => {
+                let uri = format!("api/v1/cas/{}/routes?dry_run={}", handle, options.dry_run());
+                let updates = self.put_json_with_response(&uri, desired)?;
+                Ok(ApiResponse::RouteAuthorizationUpdates(updates))
+            }
+
+            CaCommand::RouteAuthorizationsImport(handle, options, file) => {
+                let uri = format!(
+                    "api/v1/cas/{}/routes/import?format={}&dry_run={}",
+                    handle,
+                    options.format(),
+                    options.dry_run()
+                );
+                let uri = self.resolve_uri(&uri);
+                let content_type = options.format().content_type();
+                let updates = httpclient::post_text_with_response(
+                    &uri,
+                    content_type,
+                    &file,
+                    Some(&self.token),
+                )?;
+                Ok(ApiResponse::RouteAuthorizationUpdates(updates))
+            }
+
+            CaCommand::RouteAuthorizationsExport(handle, format) => {
+                let uri = format!("api/v1/cas/{}/routes/export?format={}", handle, format);
+                let uri = self.resolve_uri(&uri);
+                let file = httpclient::get_text(&uri, Some(&self.token))?;
+                Ok(ApiResponse::GenericBody(file))
+            }
+
+            CaCommand::RoaGroupingShow(handle) => {
+                let uri = format!("api/v1/cas/{}/routes/grouping", handle);
+                let grouping = self.get_json(&uri)?;
+                Ok(ApiResponse::RoaGroupingInfo(grouping))
+            }
+
+            CaCommand::RoaGroupingUpdate(handle, strategy) => {
+                let uri = format!("api/v1/cas/{}/routes/grouping", handle);
+                self.post_json(&uri, strategy)?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::RoaGroupingReset(handle) => {
+                let uri = format!("api/v1/cas/{}/routes/grouping", handle);
+                self.delete(&uri)?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::AspasList(handle) => {
+                let uri = format!("api/v1/cas/{}/aspas", handle);
+                let aspas = self.get_json(&uri)?;
+                Ok(ApiResponse::AspaDefinitions(aspas))
+            }
+
+            CaCommand::AspasAddOrReplace(handle, aspa) => {
+                let uri = format!("api/v1/cas/{}/aspas", handle);
+                let updates = AspaDefinitionUpdates::new(vec![aspa], vec![]);
+                self.post_json(&uri, updates)?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::AspasRemove(handle, customer) => {
+                let uri = format!("api/v1/cas/{}/aspas", handle);
+                let updates = AspaDefinitionUpdates::new(vec![], vec![customer]);
+                self.post_json(&uri, updates)?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::AspasUpdate(handle, customer, update) => {
+                let uri = format!("api/v1/cas/{}/aspas/as/{}", handle, customer);
+                self.post_json(&uri, update)?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::BgpSecList(handle) => {
+                let uri = format!("api/v1/cas/{}/bgpsec", handle);
+                let definitions = self.get_json(&uri)?;
+                Ok(ApiResponse::BgpSecDefinitions(definitions))
+            }
+
+            CaCommand::BgpSecAdd(handle, definition) => {
+                let uri = format!("api/v1/cas/{}/bgpsec", handle);
+                let updates = BgpSecDefinitionUpdates::new(vec![definition], vec![]);
+                self.post_json(&uri, updates)?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::BgpSecRemove(handle, router) => {
+                let uri = format!("api/v1/cas/{}/bgpsec", handle);
+                let updates = BgpSecDefinitionUpdates::new(vec![], vec![router]);
+                self.post_json(&uri, updates)?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::SignChecklist(handle, request) => {
+                let uri = format!("api/v1/cas/{}/rsc/sign", handle);
+                let rsc = self.post_json_with_response(&uri, request)?;
+                Ok(ApiResponse::SignedChecklist(rsc))
+            }
+
+            CaCommand::TimingShow(handle) => {
+                let uri = format!("api/v1/cas/{}/timing", handle);
+                let timing = self.get_json(&uri)?;
+                Ok(ApiResponse::CaTimingInfo(timing))
+            }
+
+            CaCommand::TimingUpdate(handle, timing) => {
+                let uri = format!("api/v1/cas/{}/timing", handle);
+                self.post_json(&uri, timing)?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::TimingReset(handle) => {
+                let uri = format!("api/v1/cas/{}/timing", handle);
+                self.delete(&uri)?;
+                Ok(ApiResponse::Empty)
+            }
+
             CaCommand::Show(handle) => {
                 let uri = format!("api/v1/cas/{}", handle);
                 let ca_info = self.get_json(&uri)?;
@@ -215,6 +400,13 @@ impl KrillClient {
                 Ok(ApiResponse::CertAuthInfo(ca_info))
             }
 
+            CaCommand::ShowAt(handle, point) => {
+                let uri = format!("api/v1/cas/{}/at/{}", handle, point);
+                let ca_info = self.get_json(&uri)?;
+
+                Ok(ApiResponse::CertAuthInfo(ca_info))
+            }
+
             CaCommand::ShowHistory(handle) => {
                 let uri = format!("api/v1/cas/{}/history", handle);
                 let history = self.get_json(&uri)?;
@@ -222,6 +414,13 @@ impl KrillClient {
                 Ok(ApiResponse::CertAuthHistory(history))
             }
 
+            CaCommand::Diff(handle, from, to) => {
+                let uri = format!("api/v1/cas/{}/diff/{}/{}", handle, from, to);
+                let diff = self.get_json(&uri)?;
+
+                Ok(ApiResponse::CertAuthDiff(diff))
+            }
+
             CaCommand::Issues(ca_opt) => match ca_opt {
                 Some(ca) => {
                     let uri = format!("api/v1/cas/issues/{}", ca);
@@ -241,6 +440,23 @@ impl KrillClient {
         }
     }
 
+    fn audit(&self, criteria: AuditCriteria) -> Result<ApiResponse, Error> {
+        let mut params = vec![];
+        if let Some(since) = criteria.since() {
+            params.push(("since", since.to_string()));
+        }
+        if let Some(ca) = criteria.ca() {
+            params.push(("ca", ca.to_string()));
+        }
+        if let Some(actor) = criteria.actor() {
+            params.push(("actor", actor.clone()));
+        }
+
+        let uri = Url::parse_with_params(&self.resolve_uri("api/v1/audit"), &params)?;
+        let log: AuditLog = httpclient::get_json(uri.as_str(), Some(&self.token))?;
+        Ok(ApiResponse::AuditLog(log))
+    }
+
     fn publishers(&self, command: PublishersCommand) -> Result<ApiResponse, Error> {
         match command {
             PublishersCommand::PublisherList => {
@@ -357,6 +573,16 @@ impl KrillClient {
             .map_err(Error::HttpClientError)
     }
 
+    fn put_json_with_response<T: DeserializeOwned>(
+        &self,
+        uri: &str,
+        data: impl Serialize,
+    ) -> Result<T, Error> {
+        let uri = self.resolve_uri(uri);
+        httpclient::put_json_with_response(&uri, data, Some(&self.token))
+            .map_err(Error::HttpClientError)
+    }
+
     fn delete(&self, uri: &str) -> Result<(), Error> {
         let uri = self.resolve_uri(uri);
         httpclient::delete(&uri, Some(&self.token)).map_err(Error::HttpClientError)
@@ -390,6 +616,9 @@ pub enum Error {
 
     #[display(fmt = "{}", _0)]
     InitError(String),
+
+    #[display(fmt = "Invalid URI: {}", _0)]
+    UriError(UrlError),
 }
 
 impl Error {
@@ -404,6 +633,12 @@ impl From<httpclient::Error> for Error {
     }
 }
 
+impl From<UrlError> for Error {
+    fn from(e: UrlError) -> Self {
+        Error::UriError(e)
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
         Error::IoError(e)
@@ -5,17 +5,24 @@ use std::path::PathBuf;
 use std::str::{from_utf8_unchecked, FromStr};
 
 use bytes::Bytes;
+use chrono::DateTime;
 use clap::{App, Arg, ArgMatches, SubCommand};
 
+use rpki::csr::Csr;
 use rpki::uri;
 
 use crate::cli::report::{ReportError, ReportFormat};
 use crate::commons::api::RepositoryUpdate;
 use crate::commons::api::{
-    AddChildRequest, AuthorizationFmtError, CertAuthInit, ChildAuthRequest, ChildHandle, Handle,
-    ParentCaContact, ParentCaReq, ParentHandle, PublisherHandle, ResourceSet, ResourceSetError,
-    RoaDefinitionUpdates, Token, UpdateChildRequest,
+    AddChildRequest, AsNumber, AspaDefinition, AspaProvidersUpdate, AuditCriteria,
+    AuthorizationFmtError, BgpSecAsnKey, BgpSecDefinition, CertAuthHistoryPoint, CertAuthInit,
+    ChildAuthRequest, ChildHandle, Handle, KeyRollEmergencyRequest, KeyRollPolicy, ParentCaContact,
+    ParentCaReq, ParentHandle, PublisherHandle, ResourceSet, ResourceSetError, RoaDefinition,
+    RoaDefinitionUpdates, RoaFileFormat, RoaFileOptions, RoaPrefixGroupingStrategy, RoaSyncOptions,
+    RscFileDigest, RscRequest, TaRequestBundle, TaRequestOptions, TaResponseBundle, TimingConfig,
+    Token, UpdateChildRequest,
 };
+use crate::commons::bgp::{Announcements, BgpDumpError};
 use crate::commons::remote::id::IdCert;
 use crate::commons::remote::rfc8183;
 use crate::commons::util::file;
@@ -139,7 +146,7 @@ impl Options {
                 .short("f")
                 .long(KRILL_CLI_FORMAT_ARG)
                 .value_name("type")
-                .help("Report format: none|json|text (default) |xml. Or set env: KRILL_CLI_FORMAT")
+                .help("Report format: none|json|text (default) |xml|csv. Or set env: KRILL_CLI_FORMAT")
                 .required(false),
         )
         .arg(
@@ -290,6 +297,13 @@ impl Options {
 
         sub = Self::add_general_args(sub);
         sub = Self::add_my_ca_arg(sub);
+        sub = sub.arg(
+            Arg::with_name("at")
+                .long("at")
+                .value_name("version or time")
+                .help("Show the CA as it was at this version, or time, e.g. 2020-01-31T12:00:00Z")
+                .required(false),
+        );
 
         app.subcommand(sub)
     }
@@ -303,6 +317,31 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_diff_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("diff")
+            .about("Show the differences of a CA between two points in its history.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = sub
+            .arg(
+                Arg::with_name("from")
+                    .long("from")
+                    .value_name("version or time")
+                    .help("The earlier version, or time, e.g. 2020-01-31T12:00:00Z")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("to")
+                    .long("to")
+                    .value_name("version or time")
+                    .help("The later version, or time, e.g. 2020-02-29T12:00:00Z")
+                    .required(true),
+            );
+
+        app.subcommand(sub)
+    }
+
     fn make_cas_add_ca_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("add").about("Add a new CA.");
 
@@ -312,6 +351,16 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_delete_ca_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("delete")
+            .about("Delete a CA, withdraw its objects and revoke its keys.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
     fn make_cas_children_add_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let sub = SubCommand::with_name("add").about("Add a child to a CA.");
 
@@ -423,13 +472,20 @@ impl Options {
         remote = Self::add_general_args(remote);
         remote = Self::add_my_ca_arg(remote);
         remote = Self::add_parent_arg(remote);
-        remote = remote.arg(
-            Arg::with_name("rfc8183")
-                .long("rfc8183")
-                .help("The RFC8183 Parent Response XML")
-                .value_name("<XML file>")
-                .required(true),
-        );
+        remote = remote
+            .arg(
+                Arg::with_name("rfc8183")
+                    .long("rfc8183")
+                    .help("The RFC8183 Parent Response XML")
+                    .value_name("<XML file>")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("offline")
+                    .long("offline")
+                    .help("The parent is a TA which is kept offline, see: ta-offline")
+                    .required(false),
+            );
 
         app.subcommand(sub.subcommand(remote).subcommand(embedded))
     }
@@ -441,13 +497,20 @@ impl Options {
         sub = Self::add_general_args(sub);
         sub = Self::add_my_ca_arg(sub);
         sub = Self::add_parent_arg(sub);
-        sub = sub.arg(
-            Arg::with_name("rfc8183")
-                .long("rfc8183")
-                .help("The RFC8183 Parent Response XML")
-                .value_name("<XML file>")
-                .required(true),
-        );
+        sub = sub
+            .arg(
+                Arg::with_name("rfc8183")
+                    .long("rfc8183")
+                    .help("The RFC8183 Parent Response XML")
+                    .value_name("<XML file>")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("offline")
+                    .long("offline")
+                    .help("The parent is a TA which is kept offline, see: ta-offline")
+                    .required(false),
+            );
 
         app.subcommand(sub)
     }
@@ -506,11 +569,96 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_keyroll_emergency_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("emergency").about(
+            "Roll all keys held by this CA now, and revoke the old keys as soon as the new keys \
+             are certified, e.g. because the keys may be compromised.",
+        );
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("reason")
+                    .long("reason")
+                    .value_name("text")
+                    .help("The reason for the emergency key roll, shown in the CA history")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("confirm")
+                    .long("confirm")
+                    .help("Confirm that keys must be rolled without a staging period"),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show")
+            .about("Show the key roll policy of this CA, and key rolls in progress.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_update_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("update")
+            .about("Roll keys of this CA automatically when they get too old.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("max_age_days")
+                    .long("max-age-days")
+                    .value_name("days")
+                    .help("Start a key roll when the current key is older than this")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("staging_hours")
+                    .long("staging-hours")
+                    .value_name("hours")
+                    .help("Activate the new key after this staging period (at least 24)")
+                    .required(true),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_remove_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove")
+            .about("Remove the key roll policy, so that keys are only rolled manually.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub =
+            SubCommand::with_name("policy").about("Manage automatic key rolls for this CA.");
+
+        sub = Self::make_cas_keyroll_policy_show_sc(sub);
+        sub = Self::make_cas_keyroll_policy_update_sc(sub);
+        sub = Self::make_cas_keyroll_policy_remove_sc(sub);
+
+        app.subcommand(sub)
+    }
+
     fn make_cas_keyroll_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("keyroll").about("Perform a manual key-roll in Krill.");
 
         sub = Self::make_cas_keyroll_init_sc(sub);
         sub = Self::make_cas_keyroll_activate_sc(sub);
+        sub = Self::make_cas_keyroll_emergency_sc(sub);
+        sub = Self::make_cas_keyroll_policy_sc(sub);
 
         app.subcommand(sub)
     }
@@ -530,6 +678,16 @@ impl Options {
         sub = Self::add_general_args(sub);
         sub = Self::add_my_ca_arg(sub);
 
+        sub = sub.arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .help(concat!(
+                    "Only show which announcements in the BGP dump configured on\n",
+                    "the server would change validation state, do not apply.",
+                ))
+                .required(false),
+        );
+
         sub = sub.arg(
             Arg::with_name("delta")
                 .long("delta")
@@ -549,179 +707,644 @@ impl Options {
         app.subcommand(sub)
     }
 
-    fn make_cas_routes_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("roas").about("Manage ROAs for your CA.");
-
-        sub = Self::make_cas_routes_list_sc(sub);
-        sub = Self::make_cas_routes_update_sc(sub);
-
-        app.subcommand(sub)
+    fn add_dry_run_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .help("Only show the changes, do not apply them.")
+                .required(false),
+        )
     }
 
-    fn make_cas_repo_request_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("request").about("Show RFC8183 Publisher Request.");
+    fn make_cas_routes_sync_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("sync")
+            .about("Replace all authorizations with the desired ones, and show the changes.");
 
         sub = Self::add_general_args(sub);
         sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_dry_run_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("roas")
+                .long("roas")
+                .help(concat!(
+                    "Provide a file with all desired authorizations, e.g. as shown by\n",
+                    "'roas list', using the following format:\n",
+                    "# Some comment\n",
+                    "192.168.0.0/16 => 64496 # inline comment\n",
+                    "192.168.1.0/24-32 => 64496\n",
+                ))
+                .value_name("<file>")
+                .required(true),
+        );
 
         app.subcommand(sub)
     }
 
-    fn make_cas_repo_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("show").about("Show current repo config.");
+    fn add_roa_file_format_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("file_format")
+                .long("file-format")
+                .value_name("csv|slurm")
+                .help(concat!(
+                    "The file format, either csv (default) or slurm. For csv use one line\n",
+                    "per ROA, e.g.: AS64496,192.168.0.0/16,24\n",
+                    "For slurm use the prefix assertions from RFC 8416.",
+                ))
+                .required(false),
+        )
+    }
+
+    fn make_cas_routes_import_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("import")
+            .about("Replace all authorizations with those in a file, and show the changes.");
 
         sub = Self::add_general_args(sub);
         sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_roa_file_format_arg(sub);
+
+        sub = Self::add_dry_run_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("file")
+                .long("file")
+                .value_name("<file>")
+                .help("The file with all authorizations for the CA.")
+                .required(true),
+        );
 
         app.subcommand(sub)
     }
 
-    fn make_cas_repo_state_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("state").about("Show current repo state.");
+    fn make_cas_routes_export_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("export").about("Export all authorizations to a file.");
 
         sub = Self::add_general_args(sub);
         sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_roa_file_format_arg(sub);
 
         app.subcommand(sub)
     }
 
-    fn make_cas_repo_update_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub =
-            SubCommand::with_name("update").about("Change which repository this CA uses.");
+    fn make_cas_routes_analyse_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("analyse")
+            .about("Analyse authorizations, using the BGP dump if configured.");
 
-        let mut embedded =
-            SubCommand::with_name("embedded").about("Use the embedded server in krill");
-        embedded = Self::add_general_args(embedded);
-        embedded = Self::add_my_ca_arg(embedded);
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
 
-        let mut remote = SubCommand::with_name("remote").about("Use a remote server (RECOMMENDED)");
-        remote = Self::add_general_args(remote);
-        remote = Self::add_my_ca_arg(remote);
-        remote = remote.arg(
-            Arg::with_name("rfc8183")
-                .value_name("file")
-                .long("rfc8183")
-                .help("File containing the RFC8183 XML. Defaults to reading from STDIN")
+        sub = sub.arg(
+            Arg::with_name("announcements")
+                .long("announcements")
+                .help(concat!(
+                    "Use the announcements in this BGP dump file, rather than\n",
+                    "the BGP dump configured on the server.",
+                ))
+                .value_name("<file>")
                 .required(false),
         );
 
-        sub = sub.subcommand(embedded);
-        sub = sub.subcommand(remote);
-
         app.subcommand(sub)
     }
 
-    fn make_cas_repo_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("repo").about("Manage the repository for your CA.");
+    fn make_cas_routes_grouping_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show").about("Show the ROA grouping used by this CA.");
 
-        sub = Self::make_cas_repo_request_sc(sub);
-        sub = Self::make_cas_repo_show_sc(sub);
-        sub = Self::make_cas_repo_state_sc(sub);
-        sub = Self::make_cas_repo_update_sc(sub);
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
 
         app.subcommand(sub)
     }
 
-    fn make_cas_issues_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("issues").about("Show issues for CAs.");
+    fn make_cas_routes_grouping_update_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("update")
+            .about("Use a specific ROA grouping for this CA, and re-issue its ROAs.");
 
         sub = Self::add_general_args(sub);
         sub = Self::add_my_ca_arg(sub);
 
+        sub = sub.arg(
+            Arg::with_name("strategy")
+                .long("strategy")
+                .value_name("RoaPerPrefix|RoaPerAsn|RoaPerAsnBounded(<max>)")
+                .help(
+                    "One ROA per prefix, one ROA for all prefixes of an ASN, or ROAs of at most \
+                     <max> prefixes of an ASN. Quote the value when using RoaPerAsnBounded.",
+                )
+                .required(true),
+        );
+
         app.subcommand(sub)
     }
 
-    fn make_publishers_list_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("list").about("List all publishers.");
+    fn make_cas_routes_grouping_reset_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub =
+            SubCommand::with_name("reset").about("Use the server ROA grouping for this CA again.");
+
         sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
         app.subcommand(sub)
     }
 
-    fn make_publishers_stale_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("stale")
-            .about("List all publishers which have not published in a while.");
-        sub = Self::add_general_args(sub);
-        sub = sub.arg(
-            Arg::with_name("seconds")
-                .value_name("seconds")
-                .long("seconds")
-                .help("The number of seconds since last publication.")
-                .required(true),
-        );
+    fn make_cas_routes_grouping_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("grouping")
+            .about("Manage how prefixes are grouped into ROAs for your CA.");
+
+        sub = Self::make_cas_routes_grouping_show_sc(sub);
+        sub = Self::make_cas_routes_grouping_update_sc(sub);
+        sub = Self::make_cas_routes_grouping_reset_sc(sub);
+
         app.subcommand(sub)
     }
 
-    fn make_publishers_stats_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("stats").about("Show publication server stats.");
-        sub = Self::add_general_args(sub);
+    fn make_cas_routes_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("roas").about("Manage ROAs for your CA.");
+
+        sub = Self::make_cas_routes_list_sc(sub);
+        sub = Self::make_cas_routes_update_sc(sub);
+        sub = Self::make_cas_routes_sync_sc(sub);
+        sub = Self::make_cas_routes_import_sc(sub);
+        sub = Self::make_cas_routes_export_sc(sub);
+        sub = Self::make_cas_routes_analyse_sc(sub);
+        sub = Self::make_cas_routes_grouping_sc(sub);
+
         app.subcommand(sub)
     }
 
-    fn add_publisher_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    fn add_customer_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         app.arg(
-            Arg::with_name("publisher")
-                .value_name("handle")
-                .short("p")
-                .long("publisher")
-                .help("The handle (name) of the publisher.")
+            Arg::with_name("customer")
+                .long("customer")
+                .value_name("<asn>")
+                .help("The customer ASN of the ASPA, e.g. 65000")
                 .required(true),
         )
     }
 
-    fn make_publishers_add_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("add").about("Add a publisher.");
-        sub = Self::add_general_args(sub);
+    fn make_cas_aspas_list_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("list").about("Show current ASPA definitions.");
 
-        sub = sub.arg(
-            Arg::with_name("rfc8183")
-                .value_name("file")
-                .long("rfc8183")
-                .help("RFC8183 Publisher Request XML file containing a certificate (tag is ignored)")
-                .required(true)
-        ).arg(
-            Arg::with_name("publisher")
-                .value_name("handle")
-                .short("p")
-                .long("publisher")
-                .help("Override the publisher handle in the XML.")
-                .required(false),
-        );
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
 
         app.subcommand(sub)
     }
 
-    fn make_publishers_remove_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("remove").about("Remove a publisher.");
+    fn make_cas_aspas_add_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("add")
+            .about("Add an ASPA definition, or replace the definition for the customer.");
+
         sub = Self::add_general_args(sub);
-        sub = Self::add_publisher_arg(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("aspa")
+                .long("aspa")
+                .value_name("<definition>")
+                .help("The ASPA definition, e.g.: \"65000 => 65001, 65002\"")
+                .required(true),
+        );
+
         app.subcommand(sub)
     }
 
-    fn make_publishers_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("show").about("Show details for a publisher.");
+    fn make_cas_aspas_remove_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub =
+            SubCommand::with_name("remove").about("Remove the ASPA definition for a customer.");
+
         sub = Self::add_general_args(sub);
-        sub = Self::add_publisher_arg(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_customer_arg(sub);
+
         app.subcommand(sub)
     }
 
-    fn make_publishers_response_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("response")
-            .about("Show RFC8183 Repository Response for a publisher.");
+    fn make_cas_aspas_update_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("update")
+            .about("Add or remove providers in the ASPA definition for a customer.");
+
         sub = Self::add_general_args(sub);
-        sub = Self::add_publisher_arg(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_customer_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("add")
+                    .long("add")
+                    .value_name("<asn>")
+                    .help("Provider ASN to add. Can be specified multiple times.")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("remove")
+                    .long("remove")
+                    .value_name("<asn>")
+                    .help("Provider ASN to remove. Can be specified multiple times.")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required(false),
+            );
+
         app.subcommand(sub)
     }
 
-    fn make_publishers_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("publishers").about("Manage publishers in Krill.");
+    fn make_cas_aspas_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("aspas").about("Manage ASPAs for your CA.");
 
-        sub = Self::make_publishers_list_sc(sub);
-        sub = Self::make_publishers_stale_sc(sub);
-        sub = Self::make_publishers_stats_sc(sub);
-        sub = Self::make_publishers_add_sc(sub);
-        sub = Self::make_publishers_remove_sc(sub);
-        sub = Self::make_publishers_show_sc(sub);
-        sub = Self::make_publishers_response_sc(sub);
+        sub = Self::make_cas_aspas_list_sc(sub);
+        sub = Self::make_cas_aspas_add_sc(sub);
+        sub = Self::make_cas_aspas_remove_sc(sub);
+        sub = Self::make_cas_aspas_update_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_bgpsec_list_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub =
+            SubCommand::with_name("list").about("Show current BGPsec router certificates.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_bgpsec_add_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("add")
+            .about("Issue a BGPsec router certificate for a router key.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("asn")
+                    .long("asn")
+                    .value_name("<asn>")
+                    .help("The ASN of the router, e.g. 65000")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("csr")
+                    .long("csr")
+                    .value_name("<file>")
+                    .help("The DER encoded PKCS#10 CSR for the router key.")
+                    .required(true),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_bgpsec_remove_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove").about("Revoke a BGPsec router certificate.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("router")
+                .long("router")
+                .value_name("<router>")
+                .help(
+                    "The router certificate as shown by 'bgpsec list', e.g. ROUTER-0000FDE8-<key>",
+                )
+                .required(true),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_bgpsec_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub =
+            SubCommand::with_name("bgpsec").about("Manage BGPsec router certificates for your CA.");
+
+        sub = Self::make_cas_bgpsec_list_sc(sub);
+        sub = Self::make_cas_bgpsec_add_sc(sub);
+        sub = Self::make_cas_bgpsec_remove_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_sign_checklist_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("sign-checklist")
+            .about("Sign an RPKI Signed Checklist (RSC) for files and resources of your CA.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("asn")
+                    .long("asn")
+                    .value_name("AS resources")
+                    .help("The AS resources to sign for: e.g. AS1, AS3-4")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("ipv4")
+                    .long("ipv4")
+                    .value_name("IPv4 resources")
+                    .help("The IPv4 resources to sign for: e.g. 192.168.0.0/16")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("ipv6")
+                    .long("ipv6")
+                    .value_name("IPv6 resources")
+                    .help("The IPv6 resources to sign for: e.g. 2001:db8::/32")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("file")
+                    .long("file")
+                    .value_name("<file>")
+                    .help("File to include by name and digest. Can be specified multiple times.")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("digest")
+                    .long("digest")
+                    .value_name("<sha256> [<name>]")
+                    .help("Digest to include, as shown by sha256sum. Can be specified multiple times.")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required(false),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_timing_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show").about("Show the timing used by this CA.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_timing_update_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("update")
+            .about("Use a specific timing for this CA, instead of the server timing.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("publish_next_hours")
+                    .long("publish-next-hours")
+                    .value_name("hours")
+                    .help("The next update time for manifests and CRLs")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("publish_valid_days")
+                    .long("publish-valid-days")
+                    .value_name("days")
+                    .help("The validity of manifest EE certificates")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("publish_threshold_hours")
+                    .long("publish-threshold-hours")
+                    .value_name("hours")
+                    .help("Republish when the next update time is this close")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("child_valid_weeks")
+                    .long("child-valid-weeks")
+                    .value_name("weeks")
+                    .help("The validity of certificates issued to children")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("child_reissue_weeks")
+                    .long("child-reissue-weeks")
+                    .value_name("weeks")
+                    .help("Re-issue child certificates when they expire this soon")
+                    .required(true),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_timing_reset_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub =
+            SubCommand::with_name("reset").about("Use the server timing for this CA again.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_timing_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("timing")
+            .about("Manage the timing of published objects and issued certificates.");
+
+        sub = Self::make_cas_timing_show_sc(sub);
+        sub = Self::make_cas_timing_update_sc(sub);
+        sub = Self::make_cas_timing_reset_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_request_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("request").about("Show RFC8183 Publisher Request.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show").about("Show current repo config.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_state_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("state").about("Show current repo state.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_update_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub =
+            SubCommand::with_name("update").about("Change which repository this CA uses.");
+
+        let mut embedded =
+            SubCommand::with_name("embedded").about("Use the embedded server in krill");
+        embedded = Self::add_general_args(embedded);
+        embedded = Self::add_my_ca_arg(embedded);
+
+        let mut remote = SubCommand::with_name("remote").about("Use a remote server (RECOMMENDED)");
+        remote = Self::add_general_args(remote);
+        remote = Self::add_my_ca_arg(remote);
+        remote = remote.arg(
+            Arg::with_name("rfc8183")
+                .value_name("file")
+                .long("rfc8183")
+                .help("File containing the RFC8183 XML. Defaults to reading from STDIN")
+                .required(false),
+        );
+
+        sub = sub.subcommand(embedded);
+        sub = sub.subcommand(remote);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("repo").about("Manage the repository for your CA.");
+
+        sub = Self::make_cas_repo_request_sc(sub);
+        sub = Self::make_cas_repo_show_sc(sub);
+        sub = Self::make_cas_repo_state_sc(sub);
+        sub = Self::make_cas_repo_update_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_issues_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("issues").about("Show issues for CAs.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_audit_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("audit")
+            .about("Show the commands processed for all, or a specific, CA.");
+
+        sub = Self::add_general_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = sub
+            .arg(
+                Arg::with_name("since")
+                    .long("since")
+                    .value_name("time")
+                    .help("Only show commands since this time, e.g. 2020-01-31T12:00:00Z")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("actor")
+                    .long("actor")
+                    .value_name("name")
+                    .help("Only show commands by this actor, e.g. the name of an API token")
+                    .required(false),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_publishers_list_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("list").about("List all publishers.");
+        sub = Self::add_general_args(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_publishers_stale_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("stale")
+            .about("List all publishers which have not published in a while.");
+        sub = Self::add_general_args(sub);
+        sub = sub.arg(
+            Arg::with_name("seconds")
+                .value_name("seconds")
+                .long("seconds")
+                .help("The number of seconds since last publication.")
+                .required(true),
+        );
+        app.subcommand(sub)
+    }
+
+    fn make_publishers_stats_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("stats").about("Show publication server stats.");
+        sub = Self::add_general_args(sub);
+        app.subcommand(sub)
+    }
+
+    fn add_publisher_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("publisher")
+                .value_name("handle")
+                .short("p")
+                .long("publisher")
+                .help("The handle (name) of the publisher.")
+                .required(true),
+        )
+    }
+
+    fn make_publishers_add_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("add").about("Add a publisher.");
+        sub = Self::add_general_args(sub);
+
+        sub = sub.arg(
+            Arg::with_name("rfc8183")
+                .value_name("file")
+                .long("rfc8183")
+                .help("RFC8183 Publisher Request XML file containing a certificate (tag is ignored)")
+                .required(true)
+        ).arg(
+            Arg::with_name("publisher")
+                .value_name("handle")
+                .short("p")
+                .long("publisher")
+                .help("Override the publisher handle in the XML.")
+                .required(false),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_publishers_remove_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove").about("Remove a publisher.");
+        sub = Self::add_general_args(sub);
+        sub = Self::add_publisher_arg(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_publishers_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show").about("Show details for a publisher.");
+        sub = Self::add_general_args(sub);
+        sub = Self::add_publisher_arg(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_publishers_response_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("response")
+            .about("Show RFC8183 Repository Response for a publisher.");
+        sub = Self::add_general_args(sub);
+        sub = Self::add_publisher_arg(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_publishers_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("publishers").about("Manage publishers in Krill.");
+
+        sub = Self::make_publishers_list_sc(sub);
+        sub = Self::make_publishers_stale_sc(sub);
+        sub = Self::make_publishers_stats_sc(sub);
+        sub = Self::make_publishers_add_sc(sub);
+        sub = Self::make_publishers_remove_sc(sub);
+        sub = Self::make_publishers_show_sc(sub);
+        sub = Self::make_publishers_response_sc(sub);
 
         app.subcommand(sub)
     }
@@ -730,468 +1353,970 @@ impl Options {
         let mut sub = SubCommand::with_name("bulk")
             .about("Manually trigger refresh/republish/resync for all cas");
 
-        let mut refresh = SubCommand::with_name("refresh")
-            .about("Force that all CAs ask their parents for updated certificates");
-        refresh = Self::add_general_args(refresh);
+        let mut refresh = SubCommand::with_name("refresh")
+            .about("Force that all CAs ask their parents for updated certificates");
+        refresh = Self::add_general_args(refresh);
+
+        let mut republish = SubCommand::with_name("publish").about(
+            "Force that all CAs create new objects if needed (in which case they will also sync)",
+        );
+        republish = Self::add_general_args(republish);
+
+        let mut resync =
+            SubCommand::with_name("sync").about("Force that all CAs sync with their repo server");
+        resync = Self::add_general_args(resync);
+
+        sub = sub
+            .subcommand(refresh)
+            .subcommand(republish)
+            .subcommand(resync);
+
+        app.subcommand(sub)
+    }
+
+    fn make_ta_offline_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let sub = SubCommand::with_name("ta-offline")
+            .about("Exchange requests and responses with an offline Trust Anchor (as json)");
+
+        let mut requests = SubCommand::with_name("requests")
+            .about("Export the requests by all CAs for their offline TA parent");
+        requests = Self::add_general_args(requests);
+        requests = requests.arg(
+            Arg::with_name("republish")
+                .long("republish")
+                .help("Let the TA republish its manifest and CRL if they are about to go stale")
+                .required(false),
+        );
+
+        let mut sign = SubCommand::with_name("sign")
+            .about("Let the TA of this (offline) server process the exported requests");
+        sign = Self::add_general_args(sign);
+        sign = sign.arg(
+            Arg::with_name("requests")
+                .long("requests")
+                .help("The requests exported by the online server")
+                .value_name("<json file>")
+                .required(true),
+        );
+
+        let mut import = SubCommand::with_name("import")
+            .about("Import the responses by the offline TA, and publish its objects");
+        import = Self::add_general_args(import);
+        import = import.arg(
+            Arg::with_name("responses")
+                .long("responses")
+                .help("The responses by the offline server")
+                .value_name("<json file>")
+                .required(true),
+        );
+
+        app.subcommand(sub.subcommand(requests).subcommand(sign).subcommand(import))
+    }
+
+    fn make_health_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let health = SubCommand::with_name("health").about("Perform an authenticated health check");
+        let health = Self::add_general_args(health);
+        app.subcommand(health)
+    }
+
+    fn make_info_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let info = SubCommand::with_name("info").about("Show server info");
+        let info = Self::add_general_args(info);
+        app.subcommand(info)
+    }
+
+    fn make_matches<'a>() -> ArgMatches<'a> {
+        let mut app = App::new(KRILL_CLIENT_APP).version(KRILL_VERSION);
+
+        app = Self::make_config_sc(app);
+        app = Self::make_cas_list_sc(app);
+        app = Self::make_cas_show_ca_sc(app);
+        app = Self::make_cas_show_history_sc(app);
+        app = Self::make_cas_diff_sc(app);
+        app = Self::make_cas_add_ca_sc(app);
+        app = Self::make_cas_delete_ca_sc(app);
+        app = Self::make_cas_children_sc(app);
+        app = Self::make_cas_parents_sc(app);
+        app = Self::make_cas_keyroll_sc(app);
+        app = Self::make_cas_routes_sc(app);
+        app = Self::make_cas_aspas_sc(app);
+        app = Self::make_cas_bgpsec_sc(app);
+        app = Self::make_cas_sign_checklist_sc(app);
+        app = Self::make_cas_timing_sc(app);
+        app = Self::make_cas_repo_sc(app);
+        app = Self::make_cas_issues_sc(app);
+        app = Self::make_audit_sc(app);
+
+        app = Self::make_publishers_sc(app);
+
+        app = Self::make_ta_offline_sc(app);
+
+        app = Self::make_health_sc(app);
+
+        app = Self::make_info_sc(app);
+
+        app = Self::make_bulk_sc(app);
+
+        app.get_matches()
+    }
+
+    //---------------------- Parsing
+
+    fn read_file_arg(path: &str) -> Result<Bytes, Error> {
+        let path = PathBuf::from(path);
+        file::read(&path).map_err(Error::IoError)
+    }
+
+    fn parse_my_ca(matches: &ArgMatches) -> Result<Handle, Error> {
+        let my_ca = {
+            let mut my_ca = None;
+
+            if let Ok(my_ca_env) = env::var(KRILL_CLI_MY_CA_ENV) {
+                my_ca = Some(Handle::from_str(&my_ca_env).map_err(|_| Error::InvalidHandle)?);
+            }
+
+            if let Some(my_ca_str) = matches.value_of(KRILL_CLI_MY_CA_ARG) {
+                my_ca = Some(Handle::from_str(my_ca_str).map_err(|_| Error::InvalidHandle)?);
+            }
+
+            my_ca.ok_or_else(|| {
+                Error::missing_arg_with_env(KRILL_CLI_MY_CA_ARG, KRILL_CLI_MY_CA_ENV)
+            })?
+        };
+
+        Ok(my_ca)
+    }
+
+    fn parse_resource_args(matches: &ArgMatches) -> Result<Option<ResourceSet>, Error> {
+        let asn = matches.value_of("asn");
+        let v4 = matches.value_of("ipv4");
+        let v6 = matches.value_of("ipv6");
+
+        if asn.is_some() || v4.is_some() || v6.is_some() {
+            let asn = asn.unwrap_or_else(|| "");
+            let v4 = v4.unwrap_or_else(|| "");
+            let v6 = v6.unwrap_or_else(|| "");
+
+            Ok(Some(ResourceSet::from_strs(asn, v4, v6)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_matches_repo_config(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let rrdp_base: uri::Https = matches
+            .value_of("rrdp")
+            .map(uri::Https::from_str)
+            .unwrap()?;
+
+        if !rrdp_base.as_str().ends_with('/') {
+            return Err(Error::general("URI for --rrdp MUST end with a '/'"));
+        }
+
+        let rsync_base = matches
+            .value_of("rsync")
+            .map(uri::Rsync::from_str)
+            .unwrap()?;
+
+        if !rsync_base.to_string().ends_with('/') {
+            return Err(Error::general("URI for --rsync MUST end with a '/'"));
+        }
+
+        let mut details = KrillInitDetails::default();
+        details.with_rsync_base(rsync_base);
+        details.with_rrdp_service_uri(rrdp_base);
+
+        if let Some(data) = matches.value_of("data") {
+            if !data.ends_with('/') {
+                return Err(Error::general("Path for --data MUST end with a '/'"));
+            }
+            details.with_data_dir(data);
+        }
+
+        if let Some(log_file) = matches.value_of("logfile") {
+            details.with_log_file(log_file);
+        }
+
+        let command = Command::Init(details);
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_simple_config(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let mut details = KrillInitDetails::default();
+        if let Some(data) = matches.value_of("data") {
+            if !data.ends_with('/') {
+                return Err(Error::general("Path for --data MUST end with a '/'"));
+            }
+            details.with_data_dir(data);
+        }
+        if let Some(log_file) = matches.value_of("logfile") {
+            details.with_log_file(log_file);
+        }
+
+        let command = Command::Init(details);
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_config(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("repo") {
+            Self::parse_matches_repo_config(m)
+        } else if let Some(m) = matches.subcommand_matches("simple") {
+            Self::parse_matches_simple_config(m)
+        } else {
+            Err(Error::UnrecognisedSubCommand)
+        }
+    }
+
+    fn parse_matches_cas_list(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let command = Command::CertAuth(CaCommand::List);
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_add(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let init = CertAuthInit::new(my_ca);
+
+        let command = Command::CertAuth(CaCommand::Init(init));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_delete(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::Delete(my_ca));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_show(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = match matches.value_of("at") {
+            Some(at) => {
+                let point = Self::parse_history_point(at, "at")?;
+                Command::CertAuth(CaCommand::ShowAt(my_ca, point))
+            }
+            None => Command::CertAuth(CaCommand::Show(my_ca)),
+        };
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_history(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::ShowHistory(my_ca));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_diff(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let from = Self::parse_history_point(matches.value_of("from").unwrap(), "from")?;
+        let to = Self::parse_history_point(matches.value_of("to").unwrap(), "to")?;
+
+        let command = Command::CertAuth(CaCommand::Diff(my_ca, from, to));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_history_point(s: &str, arg: &str) -> Result<CertAuthHistoryPoint, Error> {
+        CertAuthHistoryPoint::from_str(s).map_err(|_| {
+            Error::GeneralArgumentError(format!(
+                "Invalid --{}, use a version or a time, e.g. 2020-01-31T12:00:00Z",
+                arg
+            ))
+        })
+    }
+
+    fn parse_matches_cas_children_add_general(
+        matches: &ArgMatches,
+        rfc8183_opt: Option<rfc8183::ChildRequest>,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let child = matches.value_of("child").unwrap();
+        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
+
+        let resources =
+            Self::parse_resource_args(matches)?.ok_or_else(|| Error::MissingResources)?;
+
+        let auth_request = match rfc8183_opt {
+            Some(rfc8183) => ChildAuthRequest::Rfc8183(rfc8183),
+            None => ChildAuthRequest::Embedded,
+        };
+
+        let child_request = AddChildRequest::new(child, resources, auth_request);
+        let command = Command::CertAuth(CaCommand::ChildAdd(my_ca, child_request));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_children_add(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("embedded") {
+            Self::parse_matches_cas_children_add_general(m, None)
+        } else if let Some(m) = matches.subcommand_matches("remote") {
+            let path = m.value_of("rfc8183").unwrap();
+            let bytes = Self::read_file_arg(path)?;
+            let request = rfc8183::ChildRequest::validate(bytes.as_ref())?;
+            Self::parse_matches_cas_children_add_general(m, Some(request))
+        } else {
+            Err(Error::UnrecognisedSubCommand)
+        }
+    }
+
+    fn parse_matches_cas_children_update(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let child = matches.value_of("child").unwrap();
+        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
+
+        let id_cert = {
+            if let Some(path) = matches.value_of("idcert") {
+                let bytes = Self::read_file_arg(path)?;
+                let id_cert = IdCert::decode(bytes).map_err(|_| Error::InvalidChildIdCert)?;
+                Some(id_cert)
+            } else {
+                None
+            }
+        };
+        let resources = Self::parse_resource_args(matches)?;
+
+        let update = UpdateChildRequest::new(id_cert, resources);
+
+        let command = Command::CertAuth(CaCommand::ChildUpdate(my_ca, child, update));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_children_info(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let child = matches.value_of("child").unwrap();
+        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
+
+        let command = Command::CertAuth(CaCommand::ChildInfo(my_ca, child));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_children_response(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let child = matches.value_of("child").unwrap();
+        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
+
+        let command = Command::CertAuth(CaCommand::ParentResponse(my_ca, child));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_children_remove(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let child = matches.value_of("child").unwrap();
+        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
+
+        let command = Command::CertAuth(CaCommand::ChildDelete(my_ca, child));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_children(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("add") {
+            Self::parse_matches_cas_children_add(m)
+        } else if let Some(m) = matches.subcommand_matches("response") {
+            Self::parse_matches_cas_children_response(m)
+        } else if let Some(m) = matches.subcommand_matches("info") {
+            Self::parse_matches_cas_children_info(m)
+        } else if let Some(m) = matches.subcommand_matches("update") {
+            Self::parse_matches_cas_children_update(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_cas_children_remove(m)
+        } else {
+            Err(Error::UnrecognisedSubCommand)
+        }
+    }
+
+    fn parse_matches_cas_parents_request(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::ChildRequest(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_parents_add_general(
+        matches: &ArgMatches,
+        response_opt: Option<rfc8183::ParentResponse>,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let parent = matches.value_of("parent").unwrap();
+        let parent = Handle::from_str(parent).map_err(|_| Error::InvalidHandle)?;
+        let contact = match response_opt {
+            Some(response) => {
+                if matches.is_present("offline") {
+                    ParentCaContact::Offline(response)
+                } else {
+                    ParentCaContact::for_rfc6492(response)
+                }
+            }
+            None => ParentCaContact::Embedded,
+        };
+        let parent_req = ParentCaReq::new(parent, contact);
+
+        let command = Command::CertAuth(CaCommand::AddParent(my_ca, parent_req));
+        Ok(Options::make(general_args, command))
+    }
 
-        let mut republish = SubCommand::with_name("publish").about(
-            "Force that all CAs create new objects if needed (in which case they will also sync)",
-        );
-        republish = Self::add_general_args(republish);
+    fn parse_matches_cas_parents_add(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("embedded") {
+            Self::parse_matches_cas_parents_add_general(m, None)
+        } else if let Some(m) = matches.subcommand_matches("remote") {
+            let path = m.value_of("rfc8183").unwrap();
+            let bytes = Self::read_file_arg(path)?;
+            let response = rfc8183::ParentResponse::validate(bytes.as_ref())?;
+            Self::parse_matches_cas_parents_add_general(m, Some(response))
+        } else {
+            Err(Error::UnrecognisedSubCommand)
+        }
+    }
 
-        let mut resync =
-            SubCommand::with_name("sync").about("Force that all CAs sync with their repo server");
-        resync = Self::add_general_args(resync);
+    fn parse_matches_cas_parents_update(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
 
-        sub = sub
-            .subcommand(refresh)
-            .subcommand(republish)
-            .subcommand(resync);
+        let parent = matches.value_of("parent").unwrap();
+        let parent = Handle::from_str(parent).map_err(|_| Error::InvalidHandle)?;
 
-        app.subcommand(sub)
+        let path = matches.value_of("rfc8183").unwrap();
+        let bytes = Self::read_file_arg(path)?;
+        let response = rfc8183::ParentResponse::validate(bytes.as_ref())?;
+
+        let contact = if matches.is_present("offline") {
+            ParentCaContact::Offline(response)
+        } else {
+            ParentCaContact::for_rfc6492(response)
+        };
+
+        let command = Command::CertAuth(CaCommand::UpdateParentContact(my_ca, parent, contact));
+        Ok(Options::make(general_args, command))
     }
 
-    fn make_health_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let health = SubCommand::with_name("health").about("Perform an authenticated health check");
-        let health = Self::add_general_args(health);
-        app.subcommand(health)
+    fn parse_matches_cas_parents_info(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+        let parent = matches.value_of("parent").unwrap();
+        let parent = Handle::from_str(parent).map_err(|_| Error::InvalidHandle)?;
+
+        let command = Command::CertAuth(CaCommand::MyParentCaContact(my_ca, parent));
+        Ok(Options::make(general_args, command))
     }
 
-    fn make_info_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let info = SubCommand::with_name("info").about("Show server info");
-        let info = Self::add_general_args(info);
-        app.subcommand(info)
+    fn parse_matches_cas_parents_remove(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+        let parent = matches.value_of("parent").unwrap();
+        let parent = Handle::from_str(parent).map_err(|_| Error::InvalidHandle)?;
+
+        let command = Command::CertAuth(CaCommand::RemoveParent(my_ca, parent));
+        Ok(Options::make(general_args, command))
     }
 
-    fn make_matches<'a>() -> ArgMatches<'a> {
-        let mut app = App::new(KRILL_CLIENT_APP).version(KRILL_VERSION);
+    fn parse_matches_cas_parents(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("request") {
+            Self::parse_matches_cas_parents_request(m)
+        } else if let Some(m) = matches.subcommand_matches("add") {
+            Self::parse_matches_cas_parents_add(m)
+        } else if let Some(m) = matches.subcommand_matches("update") {
+            Self::parse_matches_cas_parents_update(m)
+        } else if let Some(m) = matches.subcommand_matches("contact") {
+            Self::parse_matches_cas_parents_info(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_cas_parents_remove(m)
+        } else {
+            Err(Error::UnrecognisedSubCommand)
+        }
+    }
 
-        app = Self::make_config_sc(app);
-        app = Self::make_cas_list_sc(app);
-        app = Self::make_cas_show_ca_sc(app);
-        app = Self::make_cas_show_history_sc(app);
-        app = Self::make_cas_add_ca_sc(app);
-        app = Self::make_cas_children_sc(app);
-        app = Self::make_cas_parents_sc(app);
-        app = Self::make_cas_keyroll_sc(app);
-        app = Self::make_cas_routes_sc(app);
-        app = Self::make_cas_repo_sc(app);
-        app = Self::make_cas_issues_sc(app);
+    fn parse_matches_cas_keyroll_init(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
 
-        app = Self::make_publishers_sc(app);
+        let command = Command::CertAuth(CaCommand::KeyRollInit(my_ca));
 
-        app = Self::make_health_sc(app);
+        Ok(Options::make(general_args, command))
+    }
 
-        app = Self::make_info_sc(app);
+    fn parse_matches_cas_keyroll_activate(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
 
-        app = Self::make_bulk_sc(app);
+        let command = Command::CertAuth(CaCommand::KeyRollActivate(my_ca));
 
-        app.get_matches()
+        Ok(Options::make(general_args, command))
     }
 
-    //---------------------- Parsing
+    fn parse_matches_cas_keyroll_emergency(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
 
-    fn read_file_arg(path: &str) -> Result<Bytes, Error> {
-        let path = PathBuf::from(path);
-        file::read(&path).map_err(Error::IoError)
+        let reason = matches.value_of("reason").unwrap().to_string();
+        let confirm = matches.is_present("confirm");
+        let request = KeyRollEmergencyRequest::new(reason, confirm);
+
+        let command = Command::CertAuth(CaCommand::KeyRollEmergency(my_ca, request));
+
+        Ok(Options::make(general_args, command))
     }
 
-    fn parse_my_ca(matches: &ArgMatches) -> Result<Handle, Error> {
-        let my_ca = {
-            let mut my_ca = None;
+    fn parse_matches_cas_keyroll_policy_show(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
 
-            if let Ok(my_ca_env) = env::var(KRILL_CLI_MY_CA_ENV) {
-                my_ca = Some(Handle::from_str(&my_ca_env).map_err(|_| Error::InvalidHandle)?);
-            }
+        let command = Command::CertAuth(CaCommand::KeyRollPolicyShow(my_ca));
 
-            if let Some(my_ca_str) = matches.value_of(KRILL_CLI_MY_CA_ARG) {
-                my_ca = Some(Handle::from_str(my_ca_str).map_err(|_| Error::InvalidHandle)?);
-            }
+        Ok(Options::make(general_args, command))
+    }
 
-            my_ca.ok_or_else(|| {
-                Error::missing_arg_with_env(KRILL_CLI_MY_CA_ARG, KRILL_CLI_MY_CA_ENV)
-            })?
+    fn parse_matches_cas_keyroll_policy_update(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let number = |name: &str| {
+            u32::from_str(matches.value_of(name).unwrap())
+                .map_err(|_| Error::general(&format!("Invalid number for {}", name)))
         };
 
-        Ok(my_ca)
+        let policy = KeyRollPolicy::new(number("max_age_days")?, number("staging_hours")?);
+        policy.verify().map_err(|e| Error::general(&e))?;
+
+        let command = Command::CertAuth(CaCommand::KeyRollPolicyUpdate(my_ca, policy));
+
+        Ok(Options::make(general_args, command))
     }
 
-    fn parse_resource_args(matches: &ArgMatches) -> Result<Option<ResourceSet>, Error> {
-        let asn = matches.value_of("asn");
-        let v4 = matches.value_of("ipv4");
-        let v6 = matches.value_of("ipv6");
+    fn parse_matches_cas_keyroll_policy_remove(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
 
-        if asn.is_some() || v4.is_some() || v6.is_some() {
-            let asn = asn.unwrap_or_else(|| "");
-            let v4 = v4.unwrap_or_else(|| "");
-            let v6 = v6.unwrap_or_else(|| "");
+        let command = Command::CertAuth(CaCommand::KeyRollPolicyRemove(my_ca));
 
-            Ok(Some(ResourceSet::from_strs(asn, v4, v6)?))
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_policy(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_cas_keyroll_policy_show(m)
+        } else if let Some(m) = matches.subcommand_matches("update") {
+            Self::parse_matches_cas_keyroll_policy_update(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_cas_keyroll_policy_remove(m)
         } else {
-            Ok(None)
+            Err(Error::UnrecognisedSubCommand)
         }
     }
 
-    fn parse_matches_repo_config(matches: &ArgMatches) -> Result<Options, Error> {
-        let general_args = GeneralArgs::from_matches(matches)?;
-        let rrdp_base: uri::Https = matches
-            .value_of("rrdp")
-            .map(uri::Https::from_str)
-            .unwrap()?;
-
-        if !rrdp_base.as_str().ends_with('/') {
-            return Err(Error::general("URI for --rrdp MUST end with a '/'"));
+    fn parse_matches_cas_keyroll(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("init") {
+            Self::parse_matches_cas_keyroll_init(m)
+        } else if let Some(m) = matches.subcommand_matches("activate") {
+            Self::parse_matches_cas_keyroll_activate(m)
+        } else if let Some(m) = matches.subcommand_matches("emergency") {
+            Self::parse_matches_cas_keyroll_emergency(m)
+        } else if let Some(m) = matches.subcommand_matches("policy") {
+            Self::parse_matches_cas_keyroll_policy(m)
+        } else {
+            Err(Error::UnrecognisedSubCommand)
         }
+    }
 
-        let rsync_base = matches
-            .value_of("rsync")
-            .map(uri::Rsync::from_str)
-            .unwrap()?;
+    fn parse_matches_cas_routes_list(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
 
-        if !rsync_base.to_string().ends_with('/') {
-            return Err(Error::general("URI for --rsync MUST end with a '/'"));
-        }
+        let command = Command::CertAuth(CaCommand::RouteAuthorizationsList(my_ca));
 
-        let mut details = KrillInitDetails::default();
-        details.with_rsync_base(rsync_base);
-        details.with_rrdp_service_uri(rrdp_base);
+        Ok(Options::make(general_args, command))
+    }
 
-        if let Some(data) = matches.value_of("data") {
-            if !data.ends_with('/') {
-                return Err(Error::general("Path for --data MUST end with a '/'"));
-            }
-            details.with_data_dir(data);
-        }
+    fn parse_matches_cas_routes_update(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
 
-        if let Some(log_file) = matches.value_of("logfile") {
-            details.with_log_file(log_file);
-        }
+        let updates = {
+            let path = matches.value_of("delta").unwrap();
+            let bytes = Self::read_file_arg(path)?;
+            let updates_str = unsafe { from_utf8_unchecked(&bytes) };
+            RoaDefinitionUpdates::from_str(updates_str)?
+        };
+
+        let command = if matches.is_present("dry_run") {
+            Command::CertAuth(CaCommand::RouteAuthorizationsDryRun(my_ca, updates))
+        } else {
+            Command::CertAuth(CaCommand::RouteAuthorizationsUpdate(my_ca, updates))
+        };
 
-        let command = Command::Init(details);
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_simple_config(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_routes_sync(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
-        let mut details = KrillInitDetails::default();
-        if let Some(data) = matches.value_of("data") {
-            if !data.ends_with('/') {
-                return Err(Error::general("Path for --data MUST end with a '/'"));
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let desired = {
+            let path = matches.value_of("roas").unwrap();
+            let bytes = Self::read_file_arg(path)?;
+            let roas_str = unsafe { from_utf8_unchecked(&bytes) };
+
+            let mut desired = vec![];
+            for line in roas_str.lines() {
+                let line = match line.find('#') {
+                    None => &line,
+                    Some(pos) => &line[..pos],
+                };
+                let line = line.trim();
+                if !line.is_empty() {
+                    desired.push(RoaDefinition::from_str(line)?);
+                }
             }
-            details.with_data_dir(data);
-        }
-        if let Some(log_file) = matches.value_of("logfile") {
-            details.with_log_file(log_file);
-        }
+            desired
+        };
+        let options = RoaSyncOptions::new(matches.is_present("dry_run"));
+
+        let command =
+            Command::CertAuth(CaCommand::RouteAuthorizationsSync(my_ca, desired, options));
 
-        let command = Command::Init(details);
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_config(matches: &ArgMatches) -> Result<Options, Error> {
-        if let Some(m) = matches.subcommand_matches("repo") {
-            Self::parse_matches_repo_config(m)
-        } else if let Some(m) = matches.subcommand_matches("simple") {
-            Self::parse_matches_simple_config(m)
-        } else {
-            Err(Error::UnrecognisedSubCommand)
+    fn parse_roa_file_format(matches: &ArgMatches) -> Result<RoaFileFormat, Error> {
+        match matches.value_of("file_format") {
+            Some(format) => Ok(RoaFileFormat::from_str(format)?),
+            None => Ok(RoaFileFormat::default()),
         }
     }
 
-    fn parse_matches_cas_list(matches: &ArgMatches) -> Result<Options, Error> {
-        let general_args = GeneralArgs::from_matches(matches)?;
-        let command = Command::CertAuth(CaCommand::List);
+    fn parse_matches_cas_routes_import(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let format = Self::parse_roa_file_format(matches)?;
+        let options = RoaFileOptions::new(format, matches.is_present("dry_run"));
+
+        let file = {
+            let path = matches.value_of("file").unwrap();
+            let bytes = Self::read_file_arg(path)?;
+            String::from_utf8_lossy(&bytes).to_string()
+        };
+
+        let command = Command::CertAuth(CaCommand::RouteAuthorizationsImport(my_ca, options, file));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_routes_export(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+        let format = Self::parse_roa_file_format(matches)?;
+
+        let command = Command::CertAuth(CaCommand::RouteAuthorizationsExport(my_ca, format));
+
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_add(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_routes_analyse(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let init = CertAuthInit::new(my_ca);
+        let announcements = match matches.value_of("announcements") {
+            Some(path) => Some(Announcements::read(&PathBuf::from(path))?),
+            None => None,
+        };
 
-        let command = Command::CertAuth(CaCommand::Init(init));
+        let command =
+            Command::CertAuth(CaCommand::RouteAuthorizationsAnalysis(my_ca, announcements));
 
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_show(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_routes_grouping_show(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::Show(my_ca));
+        let command = Command::CertAuth(CaCommand::RoaGroupingShow(my_ca));
+
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_history(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_routes_grouping_update(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
+        let strategy = RoaPrefixGroupingStrategy::from_str(matches.value_of("strategy").unwrap())?;
+
+        let command = Command::CertAuth(CaCommand::RoaGroupingUpdate(my_ca, strategy));
 
-        let command = Command::CertAuth(CaCommand::ShowHistory(my_ca));
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_children_add_general(
-        matches: &ArgMatches,
-        rfc8183_opt: Option<rfc8183::ChildRequest>,
-    ) -> Result<Options, Error> {
+    fn parse_matches_cas_routes_grouping_reset(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let child = matches.value_of("child").unwrap();
-        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
-
-        let resources =
-            Self::parse_resource_args(matches)?.ok_or_else(|| Error::MissingResources)?;
-
-        let auth_request = match rfc8183_opt {
-            Some(rfc8183) => ChildAuthRequest::Rfc8183(rfc8183),
-            None => ChildAuthRequest::Embedded,
-        };
+        let command = Command::CertAuth(CaCommand::RoaGroupingReset(my_ca));
 
-        let child_request = AddChildRequest::new(child, resources, auth_request);
-        let command = Command::CertAuth(CaCommand::ChildAdd(my_ca, child_request));
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_children_add(matches: &ArgMatches) -> Result<Options, Error> {
-        if let Some(m) = matches.subcommand_matches("embedded") {
-            Self::parse_matches_cas_children_add_general(m, None)
-        } else if let Some(m) = matches.subcommand_matches("remote") {
-            let path = m.value_of("rfc8183").unwrap();
-            let bytes = Self::read_file_arg(path)?;
-            let request = rfc8183::ChildRequest::validate(bytes.as_ref())?;
-            Self::parse_matches_cas_children_add_general(m, Some(request))
+    fn parse_matches_cas_routes_grouping(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_cas_routes_grouping_show(m)
+        } else if let Some(m) = matches.subcommand_matches("update") {
+            Self::parse_matches_cas_routes_grouping_update(m)
+        } else if let Some(m) = matches.subcommand_matches("reset") {
+            Self::parse_matches_cas_routes_grouping_reset(m)
         } else {
             Err(Error::UnrecognisedSubCommand)
         }
     }
 
-    fn parse_matches_cas_children_update(matches: &ArgMatches) -> Result<Options, Error> {
-        let general_args = GeneralArgs::from_matches(matches)?;
-        let my_ca = Self::parse_my_ca(matches)?;
+    fn parse_matches_cas_routes(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("list") {
+            Self::parse_matches_cas_routes_list(m)
+        } else if let Some(m) = matches.subcommand_matches("update") {
+            Self::parse_matches_cas_routes_update(m)
+        } else if let Some(m) = matches.subcommand_matches("sync") {
+            Self::parse_matches_cas_routes_sync(m)
+        } else if let Some(m) = matches.subcommand_matches("import") {
+            Self::parse_matches_cas_routes_import(m)
+        } else if let Some(m) = matches.subcommand_matches("export") {
+            Self::parse_matches_cas_routes_export(m)
+        } else if let Some(m) = matches.subcommand_matches("analyse") {
+            Self::parse_matches_cas_routes_analyse(m)
+        } else if let Some(m) = matches.subcommand_matches("grouping") {
+            Self::parse_matches_cas_routes_grouping(m)
+        } else {
+            Err(Error::UnrecognisedSubCommand)
+        }
+    }
 
-        let child = matches.value_of("child").unwrap();
-        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
+    fn parse_customer(matches: &ArgMatches) -> Result<AsNumber, Error> {
+        let customer = matches.value_of("customer").unwrap();
+        Ok(AsNumber::from_str(customer)?)
+    }
 
-        let id_cert = {
-            if let Some(path) = matches.value_of("idcert") {
-                let bytes = Self::read_file_arg(path)?;
-                let id_cert = IdCert::decode(bytes).map_err(|_| Error::InvalidChildIdCert)?;
-                Some(id_cert)
-            } else {
-                None
+    fn parse_asns(matches: &ArgMatches, name: &str) -> Result<Vec<AsNumber>, Error> {
+        let mut asns = vec![];
+        if let Some(values) = matches.values_of(name) {
+            for value in values {
+                asns.push(AsNumber::from_str(value)?);
             }
-        };
-        let resources = Self::parse_resource_args(matches)?;
+        }
+        Ok(asns)
+    }
 
-        let update = UpdateChildRequest::new(id_cert, resources);
+    fn parse_matches_cas_aspas_list(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::AspasList(my_ca));
 
-        let command = Command::CertAuth(CaCommand::ChildUpdate(my_ca, child, update));
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_children_info(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_aspas_add(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let child = matches.value_of("child").unwrap();
-        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
+        let aspa = AspaDefinition::from_str(matches.value_of("aspa").unwrap())?;
+
+        let command = Command::CertAuth(CaCommand::AspasAddOrReplace(my_ca, aspa));
 
-        let command = Command::CertAuth(CaCommand::ChildInfo(my_ca, child));
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_children_response(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_aspas_remove(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
+        let customer = Self::parse_customer(matches)?;
 
-        let child = matches.value_of("child").unwrap();
-        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
+        let command = Command::CertAuth(CaCommand::AspasRemove(my_ca, customer));
 
-        let command = Command::CertAuth(CaCommand::ParentResponse(my_ca, child));
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_children_remove(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_aspas_update(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
+        let customer = Self::parse_customer(matches)?;
 
-        let child = matches.value_of("child").unwrap();
-        let child = Handle::from_str(child).map_err(|_| Error::InvalidHandle)?;
+        let added = Self::parse_asns(matches, "add")?;
+        let removed = Self::parse_asns(matches, "remove")?;
+        if added.is_empty() && removed.is_empty() {
+            return Err(Error::general("Specify at least one --add or --remove"));
+        }
+        let update = AspaProvidersUpdate::new(added, removed);
+
+        let command = Command::CertAuth(CaCommand::AspasUpdate(my_ca, customer, update));
 
-        let command = Command::CertAuth(CaCommand::ChildDelete(my_ca, child));
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_children(matches: &ArgMatches) -> Result<Options, Error> {
-        if let Some(m) = matches.subcommand_matches("add") {
-            Self::parse_matches_cas_children_add(m)
-        } else if let Some(m) = matches.subcommand_matches("response") {
-            Self::parse_matches_cas_children_response(m)
-        } else if let Some(m) = matches.subcommand_matches("info") {
-            Self::parse_matches_cas_children_info(m)
-        } else if let Some(m) = matches.subcommand_matches("update") {
-            Self::parse_matches_cas_children_update(m)
+    fn parse_matches_cas_aspas(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("list") {
+            Self::parse_matches_cas_aspas_list(m)
+        } else if let Some(m) = matches.subcommand_matches("add") {
+            Self::parse_matches_cas_aspas_add(m)
         } else if let Some(m) = matches.subcommand_matches("remove") {
-            Self::parse_matches_cas_children_remove(m)
+            Self::parse_matches_cas_aspas_remove(m)
+        } else if let Some(m) = matches.subcommand_matches("update") {
+            Self::parse_matches_cas_aspas_update(m)
         } else {
             Err(Error::UnrecognisedSubCommand)
         }
     }
 
-    fn parse_matches_cas_parents_request(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_bgpsec_list(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::ChildRequest(my_ca));
+        let command = Command::CertAuth(CaCommand::BgpSecList(my_ca));
 
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_parents_add_general(
-        matches: &ArgMatches,
-        response_opt: Option<rfc8183::ParentResponse>,
-    ) -> Result<Options, Error> {
+    fn parse_matches_cas_bgpsec_add(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let parent = matches.value_of("parent").unwrap();
-        let parent = Handle::from_str(parent).map_err(|_| Error::InvalidHandle)?;
-        let contact = match response_opt {
-            Some(response) => ParentCaContact::for_rfc6492(response),
-            None => ParentCaContact::Embedded,
-        };
-        let parent_req = ParentCaReq::new(parent, contact);
-
-        let command = Command::CertAuth(CaCommand::AddParent(my_ca, parent_req));
-        Ok(Options::make(general_args, command))
-    }
-
-    fn parse_matches_cas_parents_add(matches: &ArgMatches) -> Result<Options, Error> {
-        if let Some(m) = matches.subcommand_matches("embedded") {
-            Self::parse_matches_cas_parents_add_general(m, None)
-        } else if let Some(m) = matches.subcommand_matches("remote") {
-            let path = m.value_of("rfc8183").unwrap();
+        let asn = AsNumber::from_str(matches.value_of("asn").unwrap())?;
+        let csr = {
+            let path = matches.value_of("csr").unwrap();
             let bytes = Self::read_file_arg(path)?;
-            let response = rfc8183::ParentResponse::validate(bytes.as_ref())?;
-            Self::parse_matches_cas_parents_add_general(m, Some(response))
-        } else {
-            Err(Error::UnrecognisedSubCommand)
-        }
-    }
-
-    fn parse_matches_cas_parents_update(matches: &ArgMatches) -> Result<Options, Error> {
-        let general_args = GeneralArgs::from_matches(matches)?;
-        let my_ca = Self::parse_my_ca(matches)?;
-
-        let parent = matches.value_of("parent").unwrap();
-        let parent = Handle::from_str(parent).map_err(|_| Error::InvalidHandle)?;
-
-        let path = matches.value_of("rfc8183").unwrap();
-        let bytes = Self::read_file_arg(path)?;
-        let response = rfc8183::ParentResponse::validate(bytes.as_ref())?;
+            Csr::decode(bytes).map_err(|_| Error::general("Cannot decode CSR file"))?
+        };
+        let definition = BgpSecDefinition::new(asn, csr);
 
-        let contact = ParentCaContact::for_rfc6492(response);
+        let command = Command::CertAuth(CaCommand::BgpSecAdd(my_ca, definition));
 
-        let command = Command::CertAuth(CaCommand::UpdateParentContact(my_ca, parent, contact));
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_parents_info(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_bgpsec_remove(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
-        let parent = matches.value_of("parent").unwrap();
-        let parent = Handle::from_str(parent).map_err(|_| Error::InvalidHandle)?;
 
-        let command = Command::CertAuth(CaCommand::MyParentCaContact(my_ca, parent));
-        Ok(Options::make(general_args, command))
-    }
+        let router = BgpSecAsnKey::from_str(matches.value_of("router").unwrap())?;
 
-    fn parse_matches_cas_parents_remove(matches: &ArgMatches) -> Result<Options, Error> {
-        let general_args = GeneralArgs::from_matches(matches)?;
-        let my_ca = Self::parse_my_ca(matches)?;
-        let parent = matches.value_of("parent").unwrap();
-        let parent = Handle::from_str(parent).map_err(|_| Error::InvalidHandle)?;
+        let command = Command::CertAuth(CaCommand::BgpSecRemove(my_ca, router));
 
-        let command = Command::CertAuth(CaCommand::RemoveParent(my_ca, parent));
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_parents(matches: &ArgMatches) -> Result<Options, Error> {
-        if let Some(m) = matches.subcommand_matches("request") {
-            Self::parse_matches_cas_parents_request(m)
+    fn parse_matches_cas_bgpsec(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("list") {
+            Self::parse_matches_cas_bgpsec_list(m)
         } else if let Some(m) = matches.subcommand_matches("add") {
-            Self::parse_matches_cas_parents_add(m)
-        } else if let Some(m) = matches.subcommand_matches("update") {
-            Self::parse_matches_cas_parents_update(m)
-        } else if let Some(m) = matches.subcommand_matches("contact") {
-            Self::parse_matches_cas_parents_info(m)
+            Self::parse_matches_cas_bgpsec_add(m)
         } else if let Some(m) = matches.subcommand_matches("remove") {
-            Self::parse_matches_cas_parents_remove(m)
+            Self::parse_matches_cas_bgpsec_remove(m)
         } else {
             Err(Error::UnrecognisedSubCommand)
         }
     }
 
-    fn parse_matches_cas_keyroll_init(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_sign_checklist(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::KeyRollInit(my_ca));
+        let resources = Self::parse_resource_args(matches)?
+            .ok_or_else(|| Error::general("Specify resources using --asn, --ipv4 or --ipv6"))?;
+
+        let mut digests = vec![];
+        if let Some(files) = matches.values_of("file") {
+            for path in files {
+                let content = Self::read_file_arg(path)?;
+                let name = PathBuf::from(path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string());
+                digests.push(RscFileDigest::for_content(name, &content));
+            }
+        }
+        if let Some(values) = matches.values_of("digest") {
+            for value in values {
+                digests.push(RscFileDigest::from_str(value)?);
+            }
+        }
+        if digests.is_empty() {
+            return Err(Error::general(
+                "Specify files or digests using --file or --digest",
+            ));
+        }
+
+        let request = RscRequest::new(resources, digests);
+        let command = Command::CertAuth(CaCommand::SignChecklist(my_ca, request));
 
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_keyroll_activate(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_timing_show(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::KeyRollActivate(my_ca));
+        let command = Command::CertAuth(CaCommand::TimingShow(my_ca));
 
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_keyroll(matches: &ArgMatches) -> Result<Options, Error> {
-        if let Some(m) = matches.subcommand_matches("init") {
-            Self::parse_matches_cas_keyroll_init(m)
-        } else if let Some(m) = matches.subcommand_matches("activate") {
-            Self::parse_matches_cas_keyroll_activate(m)
-        } else {
-            Err(Error::UnrecognisedSubCommand)
-        }
-    }
-
-    fn parse_matches_cas_routes_list(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_timing_update(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::RouteAuthorizationsList(my_ca));
+        let number = |name: &str| {
+            u32::from_str(matches.value_of(name).unwrap())
+                .map_err(|_| Error::general(&format!("Invalid number for {}", name)))
+        };
+
+        let timing = TimingConfig::new(
+            number("publish_next_hours")?,
+            number("publish_valid_days")?,
+            number("publish_threshold_hours")?,
+            number("child_valid_weeks")?,
+            number("child_reissue_weeks")?,
+        );
+        timing.verify().map_err(|e| Error::general(&e))?;
+
+        let command = Command::CertAuth(CaCommand::TimingUpdate(my_ca, timing));
 
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_routes_update(matches: &ArgMatches) -> Result<Options, Error> {
+    fn parse_matches_cas_timing_reset(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let updates = {
-            let path = matches.value_of("delta").unwrap();
-            let bytes = Self::read_file_arg(path)?;
-            let updates_str = unsafe { from_utf8_unchecked(&bytes) };
-            RoaDefinitionUpdates::from_str(updates_str)?
-        };
-
-        let command = Command::CertAuth(CaCommand::RouteAuthorizationsUpdate(my_ca, updates));
+        let command = Command::CertAuth(CaCommand::TimingReset(my_ca));
 
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_routes(matches: &ArgMatches) -> Result<Options, Error> {
-        if let Some(m) = matches.subcommand_matches("list") {
-            Self::parse_matches_cas_routes_list(m)
+    fn parse_matches_cas_timing(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_cas_timing_show(m)
         } else if let Some(m) = matches.subcommand_matches("update") {
-            Self::parse_matches_cas_routes_update(m)
+            Self::parse_matches_cas_timing_update(m)
+        } else if let Some(m) = matches.subcommand_matches("reset") {
+            Self::parse_matches_cas_timing_reset(m)
         } else {
             Err(Error::UnrecognisedSubCommand)
         }
@@ -1271,6 +2396,34 @@ impl Options {
         Ok(Options::make(general, command))
     }
 
+    fn parse_matches_audit(matches: &ArgMatches) -> Result<Options, Error> {
+        let general = GeneralArgs::from_matches(matches)?;
+        // The CA is optional, but an invalid CA must not silently select
+        // the commands of all CAs.
+        let ca_given =
+            matches.is_present(KRILL_CLI_MY_CA_ARG) || env::var(KRILL_CLI_MY_CA_ENV).is_ok();
+        let ca = if ca_given {
+            Some(Self::parse_my_ca(matches)?)
+        } else {
+            None
+        };
+
+        let since = match matches.value_of("since") {
+            Some(since) => Some(
+                DateTime::parse_from_rfc3339(since)
+                    .map_err(|_| {
+                        Error::general("Invalid time for --since, use e.g. 2020-01-31T12:00:00Z")
+                    })?
+                    .timestamp(),
+            ),
+            None => None,
+        };
+        let actor = matches.value_of("actor").map(|s| s.to_string());
+
+        let command = Command::Audit(AuditCriteria::new(since, ca, actor));
+        Ok(Options::make(general, command))
+    }
+
     fn parse_publisher_arg(matches: &ArgMatches) -> Result<PublisherHandle, Error> {
         let publisher_str = matches.value_of("publisher").unwrap();
         PublisherHandle::from_str(publisher_str).map_err(|_| Error::InvalidHandle)
@@ -1374,6 +2527,29 @@ impl Options {
         }
     }
 
+    fn parse_matches_ta_offline(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("requests") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let options = TaRequestOptions::new(m.is_present("republish"));
+            let command = Command::TaOffline(TaOfflineCommand::Requests(options));
+            Ok(Options::make(general_args, command))
+        } else if let Some(m) = matches.subcommand_matches("sign") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let bytes = Self::read_file_arg(m.value_of("requests").unwrap())?;
+            let bundle = serde_json::from_slice(bytes.as_ref()).map_err(Error::JsonFile)?;
+            let command = Command::TaOffline(TaOfflineCommand::Sign(bundle));
+            Ok(Options::make(general_args, command))
+        } else if let Some(m) = matches.subcommand_matches("import") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let bytes = Self::read_file_arg(m.value_of("responses").unwrap())?;
+            let bundle = serde_json::from_slice(bytes.as_ref()).map_err(Error::JsonFile)?;
+            let command = Command::TaOffline(TaOfflineCommand::Import(bundle));
+            Ok(Options::make(general_args, command))
+        } else {
+            Err(Error::UnrecognisedSubCommand)
+        }
+    }
+
     fn parse_matches_health(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let command = Command::Health;
@@ -1393,10 +2569,14 @@ impl Options {
             Self::parse_matches_cas_list(m)
         } else if let Some(m) = matches.subcommand_matches("add") {
             Self::parse_matches_cas_add(m)
+        } else if let Some(m) = matches.subcommand_matches("delete") {
+            Self::parse_matches_cas_delete(m)
         } else if let Some(m) = matches.subcommand_matches("show") {
             Self::parse_matches_cas_show(m)
         } else if let Some(m) = matches.subcommand_matches("history") {
             Self::parse_matches_cas_history(m)
+        } else if let Some(m) = matches.subcommand_matches("diff") {
+            Self::parse_matches_cas_diff(m)
         } else if let Some(m) = matches.subcommand_matches("children") {
             Self::parse_matches_cas_children(m)
         } else if let Some(m) = matches.subcommand_matches("parents") {
@@ -1405,12 +2585,24 @@ impl Options {
             Self::parse_matches_cas_keyroll(m)
         } else if let Some(m) = matches.subcommand_matches("roas") {
             Self::parse_matches_cas_routes(m)
+        } else if let Some(m) = matches.subcommand_matches("aspas") {
+            Self::parse_matches_cas_aspas(m)
+        } else if let Some(m) = matches.subcommand_matches("bgpsec") {
+            Self::parse_matches_cas_bgpsec(m)
+        } else if let Some(m) = matches.subcommand_matches("sign-checklist") {
+            Self::parse_matches_cas_sign_checklist(m)
+        } else if let Some(m) = matches.subcommand_matches("timing") {
+            Self::parse_matches_cas_timing(m)
         } else if let Some(m) = matches.subcommand_matches("repo") {
             Self::parse_matches_cas_repo(m)
         } else if let Some(m) = matches.subcommand_matches("issues") {
             Self::parse_matches_cas_issues(m)
+        } else if let Some(m) = matches.subcommand_matches("audit") {
+            Self::parse_matches_audit(m)
         } else if let Some(m) = matches.subcommand_matches("publishers") {
             Self::parse_matches_publishers(m)
+        } else if let Some(m) = matches.subcommand_matches("ta-offline") {
+            Self::parse_matches_ta_offline(m)
         } else if let Some(m) = matches.subcommand_matches("bulk") {
             Self::parse_matches_bulk(m)
         } else if let Some(m) = matches.subcommand_matches("health") {
@@ -1437,6 +2629,8 @@ pub enum Command {
     Bulk(BulkCaCommand),
     CertAuth(CaCommand),
     Publishers(PublishersCommand),
+    TaOffline(TaOfflineCommand),
+    Audit(AuditCriteria),
     Init(KrillInitDetails),
 }
 
@@ -1446,6 +2640,9 @@ pub enum CaCommand {
     // Initialise a CA
     Init(CertAuthInit),
 
+    // Delete a CA
+    Delete(Handle),
+
     // Update CA id
     UpdateId(Handle),
 
@@ -1484,18 +2681,94 @@ pub enum CaCommand {
     // Activate all new keys now (finish key roll, provided new key was certified)
     KeyRollActivate(Handle),
 
+    // Roll all keys now, and revoke the old keys without a staging period
+    KeyRollEmergency(Handle, KeyRollEmergencyRequest),
+
+    // Show the key roll policy, and key rolls in progress
+    KeyRollPolicyShow(Handle),
+
+    // Roll keys automatically according to the policy
+    KeyRollPolicyUpdate(Handle, KeyRollPolicy),
+
+    // Remove the key roll policy
+    KeyRollPolicyRemove(Handle),
+
     // List the current RouteAuthorizations
     RouteAuthorizationsList(Handle),
 
     // Update the Route Authorizations for this CA
     RouteAuthorizationsUpdate(Handle, RoaDefinitionUpdates),
 
+    // Show the impact of updating the Route Authorizations on announcements
+    RouteAuthorizationsDryRun(Handle, RoaDefinitionUpdates),
+
+    // Replace the Route Authorizations for this CA with the desired ones
+    RouteAuthorizationsSync(Handle, Vec<RoaDefinition>, RoaSyncOptions),
+
+    // Replace the Route Authorizations for this CA with those in a file
+    RouteAuthorizationsImport(Handle, RoaFileOptions, String),
+
+    // Export the Route Authorizations for this CA to a file
+    RouteAuthorizationsExport(Handle, RoaFileFormat),
+
+    // Analyse the Route Authorizations for this CA, and the given announcements
+    // or else those in the BGP dump on the server if configured
+    RouteAuthorizationsAnalysis(Handle, Option<Announcements>),
+
+    // Show the ROA grouping used by this CA
+    RoaGroupingShow(Handle),
+
+    // Use a specific ROA grouping for this CA
+    RoaGroupingUpdate(Handle, RoaPrefixGroupingStrategy),
+
+    // Use the server ROA grouping for this CA
+    RoaGroupingReset(Handle),
+
+    // List the current ASPA definitions
+    AspasList(Handle),
+
+    // Add an ASPA definition, or replace the definition for the customer
+    AspasAddOrReplace(Handle, AspaDefinition),
+
+    // Remove the ASPA definition for the customer
+    AspasRemove(Handle, AsNumber),
+
+    // Update the providers in the ASPA definition for the customer
+    AspasUpdate(Handle, AsNumber, AspaProvidersUpdate),
+
+    // List the current BGPsec router certificates
+    BgpSecList(Handle),
+
+    // Issue a BGPsec router certificate
+    BgpSecAdd(Handle, BgpSecDefinition),
+
+    // Revoke a BGPsec router certificate
+    BgpSecRemove(Handle, BgpSecAsnKey),
+
+    // Sign an RPKI Signed Checklist
+    SignChecklist(Handle, RscRequest),
+
+    // Show the timing used by this CA
+    TimingShow(Handle),
+
+    // Use a specific timing for this CA
+    TimingUpdate(Handle, TimingConfig),
+
+    // Use the server timing for this CA
+    TimingReset(Handle),
+
     // Show details for this CA
     Show(Handle),
 
+    // Show this CA as it was at a version, or time, in its history
+    ShowAt(Handle, CertAuthHistoryPoint),
+
     // Show the history for this CA
     ShowHistory(Handle),
 
+    // Show the differences for this CA between two points in its history
+    Diff(Handle, CertAuthHistoryPoint, CertAuthHistoryPoint),
+
     // Show issues for all, or a specific, CA
     Issues(Option<Handle>),
 
@@ -1510,6 +2783,19 @@ pub enum BulkCaCommand {
     Sync,
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum TaOfflineCommand {
+    // Export the requests for offline TA parents
+    Requests(TaRequestOptions),
+
+    // Process exported requests by the TA of this (offline) server
+    Sign(TaRequestBundle),
+
+    // Import the responses by an offline TA
+    Import(TaResponseBundle),
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[allow(clippy::large_enum_variant)]
 pub enum PublishersCommand {
@@ -1597,6 +2883,9 @@ pub enum Error {
     #[display(fmt = "{}", _0)]
     InvalidRouteDelta(AuthorizationFmtError),
 
+    #[display(fmt = "{}", _0)]
+    BgpDump(BgpDumpError),
+
     #[display(fmt = "The publisher handle may only contain -_A-Za-z0-9, (\\ /) see issue #83")]
     InvalidHandle,
 
@@ -1619,6 +2908,9 @@ pub enum Error {
     #[display(fmt = "Invalid ID cert for child.")]
     InvalidChildIdCert,
 
+    #[display(fmt = "Invalid JSON file: {}", _0)]
+    JsonFile(serde_json::Error),
+
     #[display(fmt = "Unrecognised sub-command. Use 'help'.")]
     UnrecognisedSubCommand,
 
@@ -1671,3 +2963,9 @@ impl From<AuthorizationFmtError> for Error {
         Error::InvalidRouteDelta(e)
     }
 }
+
+impl From<BgpDumpError> for Error {
+    fn from(e: BgpDumpError) -> Self {
+        Error::BgpDump(e)
+    }
+}
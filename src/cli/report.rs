@@ -6,10 +6,13 @@ use serde::Serialize;
 use rpki::x509::Time;
 
 use crate::commons::api::{
-    AllCertAuthIssues, CaRepoDetails, CertAuthHistory, CertAuthInfo, CertAuthIssues, CertAuthList,
-    ChildCaInfo, CurrentObjects, CurrentRepoState, ParentCaContact, PublisherDetails,
-    PublisherList, RepositoryContact, RoaDefinition, ServerInfo,
+    AllCertAuthIssues, AspaDefinitionList, AuditLog, BgpSecDefinitionList, CaKeyRollInfo,
+    CaRepoDetails, CaTimingInfo, CertAuthDiff, CertAuthHistory, CertAuthInfo, CertAuthIssues,
+    CertAuthList, ChildCaInfo, CurrentObjects, CurrentRepoState, ParentCaContact, PublisherDetails,
+    PublisherList, RepositoryContact, RoaDefinition, RoaDefinitionUpdates, RoaGroupingInfo,
+    ServerInfo, SignedChecklist, TaRequestBundle, TaResponseBundle,
 };
+use crate::commons::bgp::{RoaAnalysis, RoaUpdatesImpact};
 use crate::commons::remote::api::ClientInfo;
 use crate::commons::remote::rfc8183;
 use crate::pubd::RepoStats;
@@ -25,8 +28,21 @@ pub enum ApiResponse {
 
     CertAuthInfo(CertAuthInfo),
     CertAuthHistory(CertAuthHistory),
+    CertAuthDiff(CertAuthDiff),
     CertAuths(CertAuthList),
     RouteAuthorizations(Vec<RoaDefinition>),
+    RouteAuthorizationUpdates(RoaDefinitionUpdates),
+    RouteAuthorizationsImpact(RoaUpdatesImpact),
+    RouteAuthorizationsAnalysis(RoaAnalysis),
+    AspaDefinitions(AspaDefinitionList),
+    BgpSecDefinitions(BgpSecDefinitionList),
+    SignedChecklist(SignedChecklist),
+    CaTimingInfo(CaTimingInfo),
+    RoaGroupingInfo(RoaGroupingInfo),
+    CaKeyRollInfo(CaKeyRollInfo),
+
+    TaRequestBundle(TaRequestBundle),
+    TaResponseBundle(TaResponseBundle),
 
     ParentCaContact(ParentCaContact),
 
@@ -47,6 +63,8 @@ pub enum ApiResponse {
     CertAuthIssues(CertAuthIssues),
     AllCertAuthIssues(AllCertAuthIssues),
 
+    AuditLog(AuditLog),
+
     Empty,               // Typically a successful post just gets an empty 200 response
     GenericBody(String), // For when the server echos Json to a successful post
 }
@@ -62,9 +80,24 @@ impl ApiResponse {
                 ApiResponse::CertAuths(list) => Ok(Some(list.report(fmt)?)),
                 ApiResponse::CertAuthInfo(info) => Ok(Some(info.report(fmt)?)),
                 ApiResponse::CertAuthHistory(history) => Ok(Some(history.report(fmt)?)),
+                ApiResponse::CertAuthDiff(diff) => Ok(Some(diff.report(fmt)?)),
                 ApiResponse::CertAuthIssues(issues) => Ok(Some(issues.report(fmt)?)),
                 ApiResponse::AllCertAuthIssues(issues) => Ok(Some(issues.report(fmt)?)),
+                ApiResponse::AuditLog(log) => Ok(Some(log.report(fmt)?)),
                 ApiResponse::RouteAuthorizations(auths) => Ok(Some(auths.report(fmt)?)),
+                ApiResponse::RouteAuthorizationUpdates(updates) => Ok(Some(updates.report(fmt)?)),
+                ApiResponse::RouteAuthorizationsImpact(impact) => Ok(Some(impact.report(fmt)?)),
+                ApiResponse::RouteAuthorizationsAnalysis(analysis) => {
+                    Ok(Some(analysis.report(fmt)?))
+                }
+                ApiResponse::AspaDefinitions(aspas) => Ok(Some(aspas.report(fmt)?)),
+                ApiResponse::BgpSecDefinitions(definitions) => Ok(Some(definitions.report(fmt)?)),
+                ApiResponse::SignedChecklist(rsc) => Ok(Some(rsc.report(fmt)?)),
+                ApiResponse::CaTimingInfo(timing) => Ok(Some(timing.report(fmt)?)),
+                ApiResponse::RoaGroupingInfo(grouping) => Ok(Some(grouping.report(fmt)?)),
+                ApiResponse::CaKeyRollInfo(info) => Ok(Some(info.report(fmt)?)),
+                ApiResponse::TaRequestBundle(bundle) => Ok(Some(bundle.report(fmt)?)),
+                ApiResponse::TaResponseBundle(bundle) => Ok(Some(bundle.report(fmt)?)),
                 ApiResponse::ParentCaContact(contact) => Ok(Some(contact.report(fmt)?)),
                 ApiResponse::ChildInfo(info) => Ok(Some(info.report(fmt)?)),
                 ApiResponse::PublisherList(list) => Ok(Some(list.report(fmt)?)),
@@ -91,6 +124,7 @@ pub enum ReportFormat {
     None,
     Json,
     Text,
+    Csv,
 }
 
 impl FromStr for ReportFormat {
@@ -101,6 +135,7 @@ impl FromStr for ReportFormat {
             "none" => Ok(ReportFormat::None),
             "json" => Ok(ReportFormat::Json),
             "text" => Ok(ReportFormat::Text),
+            "csv" => Ok(ReportFormat::Csv),
             _ => Err(ReportError::UnrecognisedFormat(s.to_string())),
         }
     }
@@ -129,11 +164,16 @@ trait Report: Serialize {
         serde_json::to_string_pretty(self).unwrap()
     }
 
+    fn csv(&self) -> Result<String, ReportError> {
+        Err(ReportError::UnsupportedFormat)
+    }
+
     fn report(&self, format: ReportFormat) -> Result<String, ReportError> {
         match format {
             ReportFormat::None => Ok("".to_string()),
             ReportFormat::Json => Ok(self.json()),
             ReportFormat::Text => self.text(),
+            ReportFormat::Csv => self.csv(),
         }
     }
 }
@@ -226,6 +266,12 @@ impl Report for CertAuthHistory {
     }
 }
 
+impl Report for CertAuthDiff {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.to_string())
+    }
+}
+
 impl Report for ParentCaContact {
     fn text(&self) -> Result<String, ReportError> {
         let mut res = String::new();
@@ -236,7 +282,7 @@ impl Report for ParentCaContact {
             ParentCaContact::Embedded => {
                 res.push_str("Embedded parent");
             }
-            ParentCaContact::Rfc6492(response) => {
+            ParentCaContact::Rfc6492(response) | ParentCaContact::Offline(response) => {
                 let bytes = response.encode_vec();
                 let xml = unsafe { from_utf8_unchecked(&bytes) };
                 res.push_str(xml);
@@ -360,6 +406,78 @@ impl Report for Vec<RoaDefinition> {
     }
 }
 
+impl Report for RoaDefinitionUpdates {
+    fn text(&self) -> Result<String, ReportError> {
+        if self.is_empty() {
+            Ok("no changes\n".to_string())
+        } else {
+            Ok(self.to_string())
+        }
+    }
+}
+
+impl Report for RoaUpdatesImpact {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.to_string())
+    }
+}
+
+impl Report for RoaAnalysis {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.to_string())
+    }
+}
+
+impl Report for AspaDefinitionList {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.to_string())
+    }
+}
+
+impl Report for BgpSecDefinitionList {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.to_string())
+    }
+}
+
+impl Report for SignedChecklist {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.to_string())
+    }
+}
+
+impl Report for CaTimingInfo {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.to_string())
+    }
+}
+
+impl Report for RoaGroupingInfo {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.to_string())
+    }
+}
+
+impl Report for CaKeyRollInfo {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.to_string())
+    }
+}
+
+/// The bundles are meant to be saved and taken to the other side, so they
+/// are always reported as json.
+impl Report for TaRequestBundle {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.json())
+    }
+}
+
+impl Report for TaResponseBundle {
+    fn text(&self) -> Result<String, ReportError> {
+        Ok(self.json())
+    }
+}
+
 impl Report for CaRepoDetails {
     fn text(&self) -> Result<String, ReportError> {
         let mut res = String::new();
@@ -422,6 +540,18 @@ impl Report for CertAuthIssues {
                     res.push_str(&format!("Parent '{}' has issue: {}\n", parent, issue));
                 }
             }
+            for retry in self.retry_info() {
+                res.push_str(&format!("Background task {}\n", retry));
+            }
+            for issue in self.published_issues() {
+                res.push_str(&format!("Published object issue: {}\n", issue));
+            }
+            for progress in self.key_roll_progress() {
+                res.push_str(&format!("Key roll in progress: {}\n", progress));
+            }
+            if let Some(failure) = self.key_roll_failure() {
+                res.push_str(&format!("Automatic key roll failed: {}\n", failure));
+            }
         }
         Ok(res)
     }
@@ -446,6 +576,18 @@ impl Report for AllCertAuthIssues {
                         res.push_str(&format!("   Parent '{}' has issue: {}\n", parent, issue));
                     }
                 }
+                for retry in issues.retry_info() {
+                    res.push_str(&format!("   Background task {}\n", retry));
+                }
+                for issue in issues.published_issues() {
+                    res.push_str(&format!("   Published object issue: {}\n", issue));
+                }
+                for progress in issues.key_roll_progress() {
+                    res.push_str(&format!("   Key roll in progress: {}\n", progress));
+                }
+                if let Some(failure) = issues.key_roll_failure() {
+                    res.push_str(&format!("   Automatic key roll failed: {}\n", failure));
+                }
             }
         }
         Ok(res)
@@ -463,3 +605,62 @@ impl Report for ServerInfo {
         ))
     }
 }
+
+impl Report for AuditLog {
+    fn text(&self) -> Result<String, ReportError> {
+        let mut res = String::new();
+        if self.entries().is_empty() {
+            res.push_str("no commands found\n");
+        }
+        for entry in self.entries() {
+            res.push_str(&format!(
+                "{} {} (version {}) by '{}': {}\n",
+                rfc3339(entry.time()),
+                entry.ca(),
+                entry.version(),
+                entry.actor(),
+                entry.summary()
+            ));
+            match entry.error() {
+                Some(error) => res.push_str(&format!("   error: {}\n", error)),
+                None => {
+                    for event in entry.events() {
+                        res.push_str(&format!("   event: {}\n", event));
+                    }
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    fn csv(&self) -> Result<String, ReportError> {
+        fn field(s: &str) -> String {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        }
+
+        let mut res = String::new();
+        res.push_str("time,ca,actor,version,summary,result,events\n");
+        for entry in self.entries() {
+            let result = match entry.error() {
+                Some(error) => format!("error: {}", error),
+                None => "ok".to_string(),
+            };
+            res.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                rfc3339(entry.time()),
+                field(entry.ca().as_str()),
+                field(entry.actor()),
+                entry.version(),
+                field(entry.summary()),
+                field(&result),
+                field(&entry.events().join("; "))
+            ));
+        }
+        Ok(res)
+    }
+}
+
+fn rfc3339(secs: i64) -> String {
+    let dt = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(secs, 0), Utc);
+    Time::new(dt).to_rfc3339()
+}
@@ -160,6 +160,109 @@ impl fmt::Display for Token {
     }
 }
 
+//------------ Role ----------------------------------------------------------
+
+/// The role of a named API token, determines what the token may be used for.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    /// May see everything, but change nothing.
+    ReadOnly,
+
+    /// May see everything, and manage ROAs for the CAs listed in the token.
+    RoaManager,
+
+    /// May see everything, and manage publishers in the embedded repository.
+    PublisherAdmin,
+
+    /// May do anything.
+    Admin,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Role::ReadOnly => write!(f, "read-only"),
+            Role::RoaManager => write!(f, "roa-manager"),
+            Role::PublisherAdmin => write!(f, "publisher-admin"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+//------------ ApiTokenDefinition --------------------------------------------
+
+/// Defines a named API token, without the secret token itself. This is
+/// used to add tokens through the API, and to list the known tokens.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ApiTokenDefinition {
+    name: String,
+    role: Role,
+    #[serde(default)]
+    cas: Vec<Handle>,
+}
+
+impl ApiTokenDefinition {
+    pub fn new(name: String, role: Role, cas: Vec<Handle>) -> Self {
+        ApiTokenDefinition { name, role, cas }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn cas(&self) -> &Vec<Handle> {
+        &self.cas
+    }
+}
+
+//------------ ApiToken ------------------------------------------------------
+
+/// A named API token, as defined in the config or added through the API.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ApiToken {
+    name: String,
+    token: Token,
+    role: Role,
+    #[serde(default)]
+    cas: Vec<Handle>,
+}
+
+impl ApiToken {
+    pub fn new(definition: ApiTokenDefinition, token: Token) -> Self {
+        ApiToken {
+            name: definition.name,
+            token,
+            role: definition.role,
+            cas: definition.cas,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn cas(&self) -> &Vec<Handle> {
+        &self.cas
+    }
+
+    pub fn definition(&self) -> ApiTokenDefinition {
+        ApiTokenDefinition::new(self.name.clone(), self.role, self.cas.clone())
+    }
+}
+
 //------------ PublisherSummaryInfo ------------------------------------------
 
 /// Defines a summary of publisher information to be used in the publisher
@@ -453,6 +556,12 @@ pub enum ParentCaContact {
 
     #[display(fmt = "RFC 6492 Parent")]
     Rfc6492(rfc8183::ParentResponse),
+
+    /// A Trust Anchor which is kept offline. RFC 6492 messages for this
+    /// parent are exchanged using exported and imported files, rather than
+    /// being sent over HTTPS.
+    #[display(fmt = "Offline Trust Anchor Parent")]
+    Offline(rfc8183::ParentResponse),
 }
 
 impl ParentCaContact {
@@ -473,6 +582,13 @@ impl ParentCaContact {
             _ => false,
         }
     }
+
+    pub fn is_offline(&self) -> bool {
+        match *self {
+            ParentCaContact::Offline(_) => true,
+            _ => false,
+        }
+    }
 }
 
 //------------ CertAuthInit --------------------------------------------------
@@ -0,0 +1,293 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::commons::api::{AsNumber, AuthorizationFmtError};
+
+//------------ AspaDefinition ----------------------------------------------
+
+/// This type defines the definition of an Autonomous System Provider
+/// Authorization (ASPA), i.e. the customer ASN and the set of ASNs that
+/// are authorized to act as its upstream providers.
+///
+/// Providers are kept sorted and unique, so that two definitions with the
+/// same providers are always equal, and so that they are in the order that
+/// the ASPA profile requires. This includes definitions which are
+/// deserialized, e.g. when they are received through the API.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+pub struct AspaDefinition {
+    customer: AsNumber,
+    providers: Vec<AsNumber>,
+}
+
+impl AspaDefinition {
+    pub fn new(customer: AsNumber, mut providers: Vec<AsNumber>) -> Self {
+        providers.sort();
+        providers.dedup();
+        AspaDefinition {
+            customer,
+            providers,
+        }
+    }
+
+    pub fn customer(&self) -> AsNumber {
+        self.customer
+    }
+
+    pub fn providers(&self) -> &[AsNumber] {
+        &self.providers
+    }
+
+    /// Returns true if the customer is listed as one of its own providers.
+    pub fn customer_used_as_provider(&self) -> bool {
+        self.providers.contains(&self.customer)
+    }
+
+    /// Applies an update to the providers of this definition. Returns false,
+    /// and leaves this definition unchanged, if any added provider was
+    /// already present, or any removed provider was not present.
+    pub fn apply_update(&mut self, update: &AspaProvidersUpdate) -> bool {
+        let mut providers = self.providers.clone();
+
+        for added in update.added() {
+            if providers.contains(added) {
+                return false;
+            }
+            providers.push(*added);
+        }
+
+        for removed in update.removed() {
+            match providers.iter().position(|p| p == removed) {
+                Some(pos) => {
+                    providers.remove(pos);
+                }
+                None => return false,
+            }
+        }
+
+        providers.sort();
+        self.providers = providers;
+        true
+    }
+}
+
+impl<'de> Deserialize<'de> for AspaDefinition {
+    fn deserialize<D>(deserializer: D) -> Result<AspaDefinition, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Fields {
+            customer: AsNumber,
+            providers: Vec<AsNumber>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        Ok(AspaDefinition::new(fields.customer, fields.providers))
+    }
+}
+
+/// Parses a definition like: "65000 => 65001, 65002"
+impl FromStr for AspaDefinition {
+    type Err = AuthorizationFmtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split("=>");
+
+        let customer = parts.next().ok_or_else(|| AuthorizationFmtError::aspa(s))?;
+        let customer = AsNumber::from_str(customer)?;
+
+        let providers = parts.next().ok_or_else(|| AuthorizationFmtError::aspa(s))?;
+        if parts.next().is_some() {
+            return Err(AuthorizationFmtError::aspa(s));
+        }
+
+        let mut parsed = vec![];
+        for provider in providers.split(',') {
+            parsed.push(AsNumber::from_str(provider)?);
+        }
+
+        Ok(AspaDefinition::new(customer, parsed))
+    }
+}
+
+impl fmt::Display for AspaDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let providers: Vec<String> = self.providers.iter().map(ToString::to_string).collect();
+        write!(f, "{} => {}", self.customer, providers.join(", "))
+    }
+}
+
+//------------ AspaDefinitionUpdates ---------------------------------------
+
+/// This type defines a set of ASPA definitions to add, or replace if a
+/// definition for the customer ASN exists, and a set of customer ASNs for
+/// which the definitions are to be removed.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AspaDefinitionUpdates {
+    add_or_replace: Vec<AspaDefinition>,
+    remove: Vec<AsNumber>,
+}
+
+impl AspaDefinitionUpdates {
+    pub fn new(add_or_replace: Vec<AspaDefinition>, remove: Vec<AsNumber>) -> Self {
+        AspaDefinitionUpdates {
+            add_or_replace,
+            remove,
+        }
+    }
+
+    pub fn unpack(self) -> (Vec<AspaDefinition>, Vec<AsNumber>) {
+        (self.add_or_replace, self.remove)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.add_or_replace.is_empty() && self.remove.is_empty()
+    }
+}
+
+impl fmt::Display for AspaDefinitionUpdates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.add_or_replace.is_empty() {
+            write!(f, "added:")?;
+            for definition in &self.add_or_replace {
+                write!(f, " '{}'", definition)?;
+            }
+            write!(f, " ")?;
+        }
+        if !self.remove.is_empty() {
+            write!(f, "removed:")?;
+            for customer in &self.remove {
+                write!(f, " {}", customer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//------------ AspaProvidersUpdate -----------------------------------------
+
+/// This type defines providers to add to, and to remove from, the ASPA
+/// definition for an existing customer ASN.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AspaProvidersUpdate {
+    added: Vec<AsNumber>,
+    removed: Vec<AsNumber>,
+}
+
+impl AspaProvidersUpdate {
+    pub fn new(added: Vec<AsNumber>, removed: Vec<AsNumber>) -> Self {
+        AspaProvidersUpdate { added, removed }
+    }
+
+    pub fn added(&self) -> &[AsNumber] {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &[AsNumber] {
+        &self.removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for AspaProvidersUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.added.is_empty() {
+            write!(f, "added:")?;
+            for provider in &self.added {
+                write!(f, " {}", provider)?;
+            }
+            write!(f, " ")?;
+        }
+        if !self.removed.is_empty() {
+            write!(f, "removed:")?;
+            for provider in &self.removed {
+                write!(f, " {}", provider)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//------------ AspaDefinitionList ------------------------------------------
+
+/// The ASPA definitions configured for a CA, ordered by customer ASN.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AspaDefinitionList(Vec<AspaDefinition>);
+
+impl AspaDefinitionList {
+    pub fn new(mut definitions: Vec<AspaDefinition>) -> Self {
+        definitions.sort_by_key(AspaDefinition::customer);
+        AspaDefinitionList(definitions)
+    }
+
+    pub fn definitions(&self) -> &[AspaDefinition] {
+        &self.0
+    }
+}
+
+impl fmt::Display for AspaDefinitionList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for definition in self.0.iter() {
+            writeln!(f, "{}", definition)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ Tests -------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asn(number: u32) -> AsNumber {
+        AsNumber::new(number)
+    }
+
+    #[test]
+    fn parse_and_display_definition() {
+        let definition = AspaDefinition::from_str("65000 => 65002, 65001,65002").unwrap();
+        assert_eq!(asn(65000), definition.customer());
+        assert_eq!(&[asn(65001), asn(65002)], definition.providers());
+        assert_eq!("65000 => 65001, 65002", definition.to_string());
+
+        let reparsed = AspaDefinition::from_str(&definition.to_string()).unwrap();
+        assert_eq!(definition, reparsed);
+
+        assert!(AspaDefinition::from_str("65000").is_err());
+        assert!(AspaDefinition::from_str("65000 => AS65001").is_err());
+        assert!(AspaDefinition::from_str("65000 => 65001 => 65002").is_err());
+    }
+
+    #[test]
+    fn deserialize_definition_sorts_providers() {
+        let json = r#"{"customer":65000,"providers":[65002,65001,65002]}"#;
+        let definition: AspaDefinition = serde_json::from_str(json).unwrap();
+        assert_eq!(&[asn(65001), asn(65002)], definition.providers());
+
+        let json = serde_json::to_string(&definition).unwrap();
+        assert_eq!(r#"{"customer":65000,"providers":[65001,65002]}"#, json);
+    }
+
+    #[test]
+    fn apply_providers_update() {
+        let mut definition = AspaDefinition::from_str("65000 => 65001, 65002").unwrap();
+
+        let update = AspaProvidersUpdate::new(vec![asn(65003)], vec![asn(65001)]);
+        assert!(definition.apply_update(&update));
+        assert_eq!("65000 => 65002, 65003", definition.to_string());
+
+        let add_existing = AspaProvidersUpdate::new(vec![asn(65002)], vec![]);
+        assert!(!definition.apply_update(&add_existing));
+
+        let remove_unknown = AspaProvidersUpdate::new(vec![], vec![asn(65001)]);
+        assert!(!definition.apply_update(&remove_unknown));
+
+        assert_eq!("65000 => 65002, 65003", definition.to_string());
+    }
+}
@@ -0,0 +1,242 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use serde::de;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use rpki::crypto::{KeyIdentifier, PublicKeyFormat};
+use rpki::csr::Csr;
+
+use crate::commons::api::{AsNumber, AuthorizationFmtError};
+
+//------------ BgpSecAsnKey ------------------------------------------------
+
+/// This type identifies a BGPsec router certificate by the ASN it is issued
+/// for, and the identifier of the router key. It is (de)serialized using
+/// its string representation, which is based on the subject name from
+/// RFC 8209, e.g. "ROUTER-0000FDE8-<key identifier>".
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct BgpSecAsnKey {
+    asn: AsNumber,
+    key: KeyIdentifier,
+}
+
+impl BgpSecAsnKey {
+    pub fn new(asn: AsNumber, key: KeyIdentifier) -> Self {
+        BgpSecAsnKey { asn, key }
+    }
+
+    pub fn asn(&self) -> AsNumber {
+        self.asn
+    }
+
+    pub fn key(&self) -> &KeyIdentifier {
+        &self.key
+    }
+}
+
+impl FromStr for BgpSecAsnKey {
+    type Err = AuthorizationFmtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split('-');
+
+        if parts.next() != Some("ROUTER") {
+            return Err(AuthorizationFmtError::bgpsec_asn_key(s));
+        }
+
+        let asn = parts
+            .next()
+            .and_then(|asn| u32::from_str_radix(asn, 16).ok())
+            .ok_or_else(|| AuthorizationFmtError::bgpsec_asn_key(s))?;
+
+        let key = parts
+            .next()
+            .and_then(|key| KeyIdentifier::from_str(key).ok())
+            .ok_or_else(|| AuthorizationFmtError::bgpsec_asn_key(s))?;
+
+        if parts.next().is_some() {
+            return Err(AuthorizationFmtError::bgpsec_asn_key(s));
+        }
+
+        Ok(BgpSecAsnKey::new(AsNumber::new(asn), key))
+    }
+}
+
+impl fmt::Display for BgpSecAsnKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ROUTER-{:08X}-{}", u32::from(self.asn), self.key)
+    }
+}
+
+impl Serialize for BgpSecAsnKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BgpSecAsnKey {
+    fn deserialize<D>(deserializer: D) -> Result<BgpSecAsnKey, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        BgpSecAsnKey::from_str(&string).map_err(de::Error::custom)
+    }
+}
+
+//------------ BgpSecDefinition --------------------------------------------
+
+/// This type defines a BGPsec router certificate to be issued: the ASN of
+/// the router, and the PKCS#10 certificate signing request for its key.
+///
+/// Definitions are considered equal if they are for the same ASN and key.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BgpSecDefinition {
+    asn: AsNumber,
+    csr: Csr,
+}
+
+impl BgpSecDefinition {
+    pub fn new(asn: AsNumber, csr: Csr) -> Self {
+        BgpSecDefinition { asn, csr }
+    }
+
+    pub fn asn(&self) -> AsNumber {
+        self.asn
+    }
+
+    pub fn csr(&self) -> &Csr {
+        &self.csr
+    }
+
+    pub fn key_identifier(&self) -> KeyIdentifier {
+        self.csr.public_key().key_identifier()
+    }
+
+    pub fn asn_key(&self) -> BgpSecAsnKey {
+        BgpSecAsnKey::new(self.asn, self.key_identifier())
+    }
+
+    /// Returns whether the key in the CSR can be used by a BGPsec router.
+    /// RFC 8208 only allows ECDSA keys on the P-256 curve.
+    pub fn has_router_key(&self) -> bool {
+        self.csr.public_key().algorithm() == PublicKeyFormat::EcdsaP256
+    }
+}
+
+impl PartialEq for BgpSecDefinition {
+    fn eq(&self, other: &BgpSecDefinition) -> bool {
+        self.asn_key() == other.asn_key()
+    }
+}
+
+impl Eq for BgpSecDefinition {}
+
+impl Hash for BgpSecDefinition {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.asn_key().hash(state)
+    }
+}
+
+impl fmt::Display for BgpSecDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.asn_key().fmt(f)
+    }
+}
+
+//------------ BgpSecDefinitionUpdates -------------------------------------
+
+/// This type defines BGPsec router certificates to add, and to remove.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BgpSecDefinitionUpdates {
+    add: Vec<BgpSecDefinition>,
+    remove: Vec<BgpSecAsnKey>,
+}
+
+impl BgpSecDefinitionUpdates {
+    pub fn new(add: Vec<BgpSecDefinition>, remove: Vec<BgpSecAsnKey>) -> Self {
+        BgpSecDefinitionUpdates { add, remove }
+    }
+
+    pub fn unpack(self) -> (Vec<BgpSecDefinition>, Vec<BgpSecAsnKey>) {
+        (self.add, self.remove)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.remove.is_empty()
+    }
+}
+
+impl fmt::Display for BgpSecDefinitionUpdates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.add.is_empty() {
+            write!(f, "added:")?;
+            for definition in &self.add {
+                write!(f, " {}", definition)?;
+            }
+            write!(f, " ")?;
+        }
+        if !self.remove.is_empty() {
+            write!(f, "removed:")?;
+            for asn_key in &self.remove {
+                write!(f, " {}", asn_key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//------------ BgpSecDefinitionList ----------------------------------------
+
+/// The BGPsec router certificates configured for a CA.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BgpSecDefinitionList(Vec<BgpSecAsnKey>);
+
+impl BgpSecDefinitionList {
+    pub fn new(mut asn_keys: Vec<BgpSecAsnKey>) -> Self {
+        asn_keys.sort_by_key(ToString::to_string);
+        BgpSecDefinitionList(asn_keys)
+    }
+
+    pub fn asn_keys(&self) -> &[BgpSecAsnKey] {
+        &self.0
+    }
+}
+
+impl fmt::Display for BgpSecDefinitionList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for asn_key in self.0.iter() {
+            writeln!(f, "{}", asn_key)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ Tests -------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display_asn_key() {
+        let key = "0B1D2E26EA41A2F5C7F0C5CB0F4A9F9F53D9E1A7";
+        let s = format!("ROUTER-0000FDE8-{}", key);
+        let asn_key = BgpSecAsnKey::from_str(&s).unwrap();
+        assert_eq!(AsNumber::new(65000), asn_key.asn());
+        assert_eq!(s, asn_key.to_string());
+
+        let json = serde_json::to_string(&asn_key).unwrap();
+        assert_eq!(format!("\"{}\"", s), json);
+        assert_eq!(asn_key, serde_json::from_str(&json).unwrap());
+
+        assert!(BgpSecAsnKey::from_str("ROUTER-0000FDE8").is_err());
+        assert!(BgpSecAsnKey::from_str(&format!("0000FDE8-{}", key)).is_err());
+        assert!(BgpSecAsnKey::from_str(&format!("ROUTER-AS65000-{}", key)).is_err());
+    }
+}
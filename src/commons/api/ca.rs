@@ -8,6 +8,7 @@ use std::str::{from_utf8_unchecked, FromStr};
 use std::{fmt, ops, str};
 
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use rpki::cert::Cert;
@@ -16,14 +17,16 @@ use rpki::crypto::KeyIdentifier;
 use rpki::manifest::{FileAndHash, Manifest};
 use rpki::resources::{AsBlocks, AsResources, IpBlocks, IpBlocksForFamily, IpResources};
 use rpki::roa::Roa;
+use rpki::sigobj::SignedObject;
 use rpki::uri;
 use rpki::x509::{Serial, Time};
 
 use crate::commons::api::publication;
 use crate::commons::api::publication::Publish;
 use crate::commons::api::{
-    Base64, ChildHandle, ErrorResponse, Handle, HexEncodedHash, IssuanceRequest, ListReply,
-    ParentCaContact, ParentHandle, RepositoryContact, RequestResourceLimit, RoaDefinition,
+    AsNumber, Base64, BgpSecAsnKey, ChildHandle, ErrorResponse, Handle, HexEncodedHash,
+    IssuanceRequest, KeyRollProgress, ListReply, ParentCaContact, ParentHandle, RepositoryContact,
+    RequestResourceLimit, RoaDefinition,
 };
 use crate::commons::eventsourcing::AggregateHistory;
 use crate::commons::remote::id::IdCert;
@@ -626,6 +629,20 @@ impl From<&Manifest> for CurrentObject {
     }
 }
 
+impl From<&SignedObject> for CurrentObject {
+    fn from(object: &SignedObject) -> Self {
+        let content = Base64::from(object);
+        let serial = object.cert().serial_number();
+        let expires = object.cert().validity().not_after();
+
+        CurrentObject {
+            content,
+            serial,
+            expires,
+        }
+    }
+}
+
 impl From<&Roa> for CurrentObject {
     fn from(roa: &Roa) -> Self {
         let content = Base64::from(roa);
@@ -683,6 +700,20 @@ impl From<&RoaDefinition> for ObjectName {
     }
 }
 
+/// The name of the ASPA object for a customer ASN.
+impl From<AsNumber> for ObjectName {
+    fn from(customer: AsNumber) -> Self {
+        ObjectName(format!("AS{}.asa", customer))
+    }
+}
+
+/// The name of the BGPsec router certificate for an ASN and router key.
+impl From<&BgpSecAsnKey> for ObjectName {
+    fn from(asn_key: &BgpSecAsnKey) -> Self {
+        ObjectName(format!("{}.cer", asn_key))
+    }
+}
+
 impl From<&String> for ObjectName {
     fn from(string : &String) -> Self {
         ObjectName(string.clone())
@@ -1317,6 +1348,14 @@ impl ResourceSet {
         self.asn.contains(other.asn()) && self.v4.contains(&other.v4) && self.v6.contains(&other.v6)
     }
 
+    /// Check if the given ASN is contained by this set.
+    pub fn contains_asn(&self, asn: AsNumber) -> bool {
+        match AsBlocks::from_str(&format!("AS{}", asn)) {
+            Ok(blocks) => self.asn.contains(&blocks),
+            Err(_) => false,
+        }
+    }
+
     /// Returns the union of this ResourceSet and the other. I.e. a new
     /// ResourceSet containing all resources found in one or both.
     pub fn union(&self, other: &ResourceSet) -> Self {
@@ -1445,6 +1484,9 @@ pub enum ParentKindInfo {
 
     #[display(fmt = "RFC 6492 Parent")]
     Rfc6492,
+
+    #[display(fmt = "Offline Trust Anchor Parent")]
+    Offline,
 }
 
 //------------ ParentInfo ----------------------------------------------------
@@ -1460,9 +1502,14 @@ impl ParentInfo {
             ParentCaContact::Ta(_) => ParentKindInfo::Ta,
             ParentCaContact::Embedded => ParentKindInfo::Embedded,
             ParentCaContact::Rfc6492(_) => ParentKindInfo::Rfc6492,
+            ParentCaContact::Offline(_) => ParentKindInfo::Offline,
         };
         ParentInfo { handle, kind }
     }
+
+    pub fn handle(&self) -> &ParentHandle {
+        &self.handle
+    }
 }
 
 impl fmt::Display for ParentInfo {
@@ -1576,6 +1623,16 @@ impl<S: Signer> From<AggregateHistory<CertAuth<S>>> for CertAuthHistory {
     }
 }
 
+impl CertAuthHistory {
+    pub fn init(&self) -> &ca::Ini {
+        &self.init
+    }
+
+    pub fn events(&self) -> &Vec<ca::Evt> {
+        &self.events
+    }
+}
+
 impl fmt::Display for CertAuthHistory {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "{}", self.init)?;
@@ -1586,6 +1643,368 @@ impl fmt::Display for CertAuthHistory {
     }
 }
 
+//------------ CertAuthHistoryPoint ------------------------------------------
+
+/// A point in the history of a CA. This is either a version, i.e. the CA as
+/// it was before the event with that version was applied, or a time, i.e.
+/// the CA as it was after the last command processed at or before it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CertAuthHistoryPoint {
+    Version(u64),
+    Time(Time),
+}
+
+impl FromStr for CertAuthHistoryPoint {
+    type Err = String;
+
+    /// Accepts a version number, or an RFC 3339 time, e.g.
+    /// 2020-01-31T12:00:00Z.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(version) = u64::from_str(s) {
+            Ok(CertAuthHistoryPoint::Version(version))
+        } else {
+            let time = DateTime::parse_from_rfc3339(s)
+                .map_err(|_| format!("expected a version or RFC 3339 time, found: '{}'", s))?;
+            Ok(CertAuthHistoryPoint::Time(Time::new(
+                time.with_timezone(&Utc),
+            )))
+        }
+    }
+}
+
+impl fmt::Display for CertAuthHistoryPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CertAuthHistoryPoint::Version(version) => write!(f, "{}", version),
+            CertAuthHistoryPoint::Time(time) => write!(f, "{}", time.to_rfc3339()),
+        }
+    }
+}
+
+//------------ CertAuthDiff --------------------------------------------------
+
+/// The differences between the state of a CA at two versions: the parents,
+/// children, current keys and ROA definitions which were added or removed,
+/// and the published objects which were added, updated or removed.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CertAuthDiff {
+    from: u64,
+    to: u64,
+    parents_added: Vec<ParentHandle>,
+    parents_removed: Vec<ParentHandle>,
+    children_added: Vec<ChildHandle>,
+    children_removed: Vec<ChildHandle>,
+    keys_added: Vec<KeyIdentifier>,
+    keys_removed: Vec<KeyIdentifier>,
+    routes_added: Vec<RoaDefinition>,
+    routes_removed: Vec<RoaDefinition>,
+    objects_added: Vec<uri::Rsync>,
+    objects_updated: Vec<uri::Rsync>,
+    objects_removed: Vec<uri::Rsync>,
+}
+
+impl CertAuthDiff {
+    pub fn new(
+        from: u64,
+        from_info: &CertAuthInfo,
+        from_routes: &[RoaDefinition],
+        to: u64,
+        to_info: &CertAuthInfo,
+        to_routes: &[RoaDefinition],
+    ) -> Self {
+        fn added<T: Clone + PartialEq + fmt::Display>(from: &[T], to: &[T]) -> Vec<T> {
+            let mut res: Vec<T> = to.iter().filter(|t| !from.contains(t)).cloned().collect();
+            res.sort_by_key(|t| t.to_string());
+            res
+        }
+
+        fn parents(info: &CertAuthInfo) -> Vec<ParentHandle> {
+            info.parents().iter().map(|p| p.handle().clone()).collect()
+        }
+
+        fn keys(info: &CertAuthInfo) -> Vec<KeyIdentifier> {
+            info.resource_classes()
+                .values()
+                .filter_map(|rc| rc.keys().current_key())
+                .map(|key| key.key_id().clone())
+                .collect()
+        }
+
+        fn objects(info: &CertAuthInfo) -> HashMap<uri::Rsync, Base64> {
+            info.published_objects()
+                .into_iter()
+                .map(|p| (p.uri().clone(), p.content().clone()))
+                .collect()
+        }
+
+        let from_objects = objects(from_info);
+        let to_objects = objects(to_info);
+        let from_uris: Vec<uri::Rsync> = from_objects.keys().cloned().collect();
+        let to_uris: Vec<uri::Rsync> = to_objects.keys().cloned().collect();
+        let mut objects_updated: Vec<uri::Rsync> = to_objects
+            .iter()
+            .filter(|(uri, content)| {
+                from_objects
+                    .get(*uri)
+                    .map(|c| c != *content)
+                    .unwrap_or(false)
+            })
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        objects_updated.sort_by_key(|uri| uri.to_string());
+
+        CertAuthDiff {
+            from,
+            to,
+            parents_added: added(&parents(from_info), &parents(to_info)),
+            parents_removed: added(&parents(to_info), &parents(from_info)),
+            children_added: added(from_info.children(), to_info.children()),
+            children_removed: added(to_info.children(), from_info.children()),
+            keys_added: added(&keys(from_info), &keys(to_info)),
+            keys_removed: added(&keys(to_info), &keys(from_info)),
+            routes_added: added(from_routes, to_routes),
+            routes_removed: added(to_routes, from_routes),
+            objects_added: added(&from_uris, &to_uris),
+            objects_updated,
+            objects_removed: added(&to_uris, &from_uris),
+        }
+    }
+
+    pub fn from(&self) -> u64 {
+        self.from
+    }
+
+    pub fn to(&self) -> u64 {
+        self.to
+    }
+
+    pub fn parents_added(&self) -> &Vec<ParentHandle> {
+        &self.parents_added
+    }
+
+    pub fn parents_removed(&self) -> &Vec<ParentHandle> {
+        &self.parents_removed
+    }
+
+    pub fn children_added(&self) -> &Vec<ChildHandle> {
+        &self.children_added
+    }
+
+    pub fn children_removed(&self) -> &Vec<ChildHandle> {
+        &self.children_removed
+    }
+
+    pub fn keys_added(&self) -> &Vec<KeyIdentifier> {
+        &self.keys_added
+    }
+
+    pub fn keys_removed(&self) -> &Vec<KeyIdentifier> {
+        &self.keys_removed
+    }
+
+    pub fn routes_added(&self) -> &Vec<RoaDefinition> {
+        &self.routes_added
+    }
+
+    pub fn routes_removed(&self) -> &Vec<RoaDefinition> {
+        &self.routes_removed
+    }
+
+    pub fn objects_added(&self) -> &Vec<uri::Rsync> {
+        &self.objects_added
+    }
+
+    pub fn objects_updated(&self) -> &Vec<uri::Rsync> {
+        &self.objects_updated
+    }
+
+    pub fn objects_removed(&self) -> &Vec<uri::Rsync> {
+        &self.objects_removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents_added.is_empty()
+            && self.parents_removed.is_empty()
+            && self.children_added.is_empty()
+            && self.children_removed.is_empty()
+            && self.keys_added.is_empty()
+            && self.keys_removed.is_empty()
+            && self.routes_added.is_empty()
+            && self.routes_removed.is_empty()
+            && self.objects_added.is_empty()
+            && self.objects_updated.is_empty()
+            && self.objects_removed.is_empty()
+    }
+}
+
+impl fmt::Display for CertAuthDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn section<T: fmt::Display>(
+            f: &mut fmt::Formatter,
+            label: &str,
+            items: &[T],
+        ) -> fmt::Result {
+            if !items.is_empty() {
+                writeln!(f, "{}:", label)?;
+                for item in items {
+                    writeln!(f, "  {}", item)?;
+                }
+            }
+            Ok(())
+        }
+
+        writeln!(f, "Changes from version {} to {}", self.from, self.to)?;
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        section(f, "Parents added", &self.parents_added)?;
+        section(f, "Parents removed", &self.parents_removed)?;
+        section(f, "Children added", &self.children_added)?;
+        section(f, "Children removed", &self.children_removed)?;
+        section(f, "Keys added", &self.keys_added)?;
+        section(f, "Keys removed", &self.keys_removed)?;
+        section(f, "Routes added", &self.routes_added)?;
+        section(f, "Routes removed", &self.routes_removed)?;
+        section(f, "Objects added", &self.objects_added)?;
+        section(f, "Objects updated", &self.objects_updated)?;
+        section(f, "Objects removed", &self.objects_removed)
+    }
+}
+
+//------------ AuditEntry ----------------------------------------------------
+
+/// Describes a command that was processed by a CA: who did what and when,
+/// and whether this resulted in events or an error.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AuditEntry {
+    time: i64,
+    ca: Handle,
+    actor: String,
+    version: u64,
+    summary: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    events: Vec<String>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        time: Time,
+        ca: Handle,
+        actor: String,
+        version: u64,
+        summary: String,
+        error: Option<String>,
+        events: Vec<String>,
+    ) -> Self {
+        AuditEntry {
+            time: time.timestamp(),
+            ca,
+            actor,
+            version,
+            summary,
+            error,
+            events,
+        }
+    }
+
+    /// The time the command was processed, in seconds since the epoch.
+    pub fn time(&self) -> i64 {
+        self.time
+    }
+
+    pub fn ca(&self) -> &Handle {
+        &self.ca
+    }
+
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    /// The version of the CA that the command was applied to.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn error(&self) -> Option<&String> {
+        self.error.as_ref()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Summaries of the events that resulted from the command.
+    pub fn events(&self) -> &Vec<String> {
+        &self.events
+    }
+}
+
+//------------ AuditCriteria -------------------------------------------------
+
+/// Selects which commands to include in the audit log. All criteria are
+/// optional.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AuditCriteria {
+    /// Only include commands processed at or after this time, in seconds
+    /// since the epoch.
+    since: Option<i64>,
+    ca: Option<Handle>,
+    actor: Option<String>,
+}
+
+impl AuditCriteria {
+    pub fn new(since: Option<i64>, ca: Option<Handle>, actor: Option<String>) -> Self {
+        AuditCriteria { since, ca, actor }
+    }
+
+    pub fn since(&self) -> Option<i64> {
+        self.since
+    }
+
+    pub fn ca(&self) -> Option<&Handle> {
+        self.ca.as_ref()
+    }
+
+    pub fn actor(&self) -> Option<&String> {
+        self.actor.as_ref()
+    }
+
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.since.map(|since| entry.time >= since).unwrap_or(true)
+            && self.ca.as_ref().map(|ca| &entry.ca == ca).unwrap_or(true)
+            && self
+                .actor
+                .as_ref()
+                .map(|actor| &entry.actor == actor)
+                .unwrap_or(true)
+    }
+}
+
+//------------ AuditLog ------------------------------------------------------
+
+/// The commands processed by all CAs, or a selection thereof, ordered by
+/// time.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    pub fn new(mut entries: Vec<AuditEntry>) -> Self {
+        entries.sort_by_key(|e| e.time);
+        AuditLog { entries }
+    }
+
+    pub fn entries(&self) -> &Vec<AuditEntry> {
+        &self.entries
+    }
+}
+
 //------------ KeyStateInfo -------------------------------------------------
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -1811,6 +2230,14 @@ impl AllCertAuthIssues {
 pub struct CertAuthIssues {
     repo: Option<ErrorResponse>,
     parents: HashMap<ParentHandle, ErrorResponse>,
+    #[serde(default)]
+    retries: Vec<QueueRetryInfo>,
+    #[serde(default)]
+    published: Vec<ErrorResponse>,
+    #[serde(default)]
+    key_rolls: Vec<KeyRollProgress>,
+    #[serde(default)]
+    key_roll_failure: Option<ErrorResponse>,
 }
 
 impl Default for CertAuthIssues {
@@ -1818,6 +2245,10 @@ impl Default for CertAuthIssues {
         CertAuthIssues {
             repo: None,
             parents: HashMap::new(),
+            retries: vec![],
+            published: vec![],
+            key_rolls: vec![],
+            key_roll_failure: None,
         }
     }
 }
@@ -1839,8 +2270,130 @@ impl CertAuthIssues {
         &self.parents
     }
 
+    /// Adds the retry state of a failed background task for the CA.
+    pub fn add_retry_info(&mut self, retry: QueueRetryInfo) {
+        self.retries.push(retry);
+    }
+
+    pub fn retry_info(&self) -> &[QueueRetryInfo] {
+        &self.retries
+    }
+
+    /// Adds an issue found when validating the objects published by the CA.
+    pub fn add_published_issue(&mut self, issue: ErrorResponse) {
+        self.published.push(issue);
+    }
+
+    pub fn published_issues(&self) -> &[ErrorResponse] {
+        &self.published
+    }
+
+    /// Adds the progress of a key roll in a resource class, so that key
+    /// rolls which do not finish get noticed.
+    pub fn add_key_roll_progress(&mut self, progress: KeyRollProgress) {
+        self.key_rolls.push(progress);
+    }
+
+    pub fn key_roll_progress(&self) -> &[KeyRollProgress] {
+        &self.key_rolls
+    }
+
+    /// Adds the error of the last failed automatic key roll step.
+    pub fn add_key_roll_failure(&mut self, issue: ErrorResponse) {
+        self.key_roll_failure = Some(issue);
+    }
+
+    pub fn key_roll_failure(&self) -> Option<&ErrorResponse> {
+        self.key_roll_failure.as_ref()
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.repo.is_none() && self.parents.is_empty()
+        self.repo.is_none()
+            && self.parents.is_empty()
+            && self.retries.is_empty()
+            && self.published.is_empty()
+            && self.key_rolls.is_empty()
+            && self.key_roll_failure.is_none()
+    }
+}
+
+//------------ QueueRetryInfo ------------------------------------------------
+
+/// Describes the state of retrying a failed background task (e.g. publishing,
+/// or sending requests to a parent) for a CA.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct QueueRetryInfo {
+    event: String,
+    attempts: u32,
+    last_error: ErrorResponse,
+    next_attempt: Option<Time>, // None if krill gave up
+}
+
+impl QueueRetryInfo {
+    pub fn scheduled(
+        event: String,
+        attempts: u32,
+        last_error: ErrorResponse,
+        next_attempt: Time,
+    ) -> Self {
+        QueueRetryInfo {
+            event,
+            attempts,
+            last_error,
+            next_attempt: Some(next_attempt),
+        }
+    }
+
+    pub fn given_up(event: String, attempts: u32, last_error: ErrorResponse) -> Self {
+        QueueRetryInfo {
+            event,
+            attempts,
+            last_error,
+            next_attempt: None,
+        }
+    }
+
+    /// Describes the event for the task.
+    pub fn event(&self) -> &str {
+        &self.event
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn last_error(&self) -> &ErrorResponse {
+        &self.last_error
+    }
+
+    pub fn next_attempt(&self) -> Option<Time> {
+        self.next_attempt
+    }
+
+    pub fn is_given_up(&self) -> bool {
+        self.next_attempt.is_none()
+    }
+}
+
+impl fmt::Display for QueueRetryInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.next_attempt {
+            Some(next) => write!(
+                f,
+                "'{}' failed {} time(s), will retry at {}, last error: {}",
+                self.event,
+                self.attempts,
+                next.to_rfc3339(),
+                self.last_error.msg()
+            ),
+            None => write!(
+                f,
+                "'{}' was given up after {} attempts, last error: {}",
+                self.event,
+                self.attempts,
+                self.last_error.msg()
+            ),
+        }
     }
 }
 
@@ -1850,13 +2403,16 @@ impl CertAuthIssues {
 pub struct CertAuthStats {
     roa_count: usize,
     child_count: usize,
+    #[serde(default)]
+    invalid_object_count: usize,
 }
 
 impl CertAuthStats {
-    pub fn new(roa_count: usize, child_count: usize) -> Self {
+    pub fn new(roa_count: usize, child_count: usize, invalid_object_count: usize) -> Self {
         CertAuthStats {
             roa_count,
             child_count,
+            invalid_object_count,
         }
     }
 
@@ -1867,6 +2423,11 @@ impl CertAuthStats {
     pub fn child_count(&self) -> usize {
         self.child_count
     }
+
+    /// The number of published objects which failed validation.
+    pub fn invalid_object_count(&self) -> usize {
+        self.invalid_object_count
+    }
 }
 
 //------------ ResSetErr -----------------------------------------------------
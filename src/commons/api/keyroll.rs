@@ -0,0 +1,228 @@
+use std::fmt;
+
+use chrono::Duration;
+
+use rpki::x509::Time;
+
+use crate::commons::api::ResourceClassName;
+use crate::constants::{KEY_ROLL_MIN_STAGING_HOURS, TIMING_MAX_YEARS};
+
+//------------ KeyRollPolicy -----------------------------------------------
+
+/// The policy for automatic key rolls of a CA. A key roll is initiated when
+/// the current key is older than 'max_key_age_days', and the new key is
+/// activated when it has been certified for 'staging_hours'.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KeyRollPolicy {
+    max_key_age_days: u32,
+    staging_hours: u32,
+}
+
+impl KeyRollPolicy {
+    pub fn new(max_key_age_days: u32, staging_hours: u32) -> Self {
+        KeyRollPolicy {
+            max_key_age_days,
+            staging_hours,
+        }
+    }
+
+    pub fn max_key_age(&self) -> Duration {
+        Duration::days(i64::from(self.max_key_age_days))
+    }
+
+    pub fn staging(&self) -> Duration {
+        Duration::hours(i64::from(self.staging_hours))
+    }
+
+    /// Verifies that the staging period is at least 24 hours, as required
+    /// by RFC 6489, that keys are not rolled again before the previous roll
+    /// could have finished, and that neither period is longer than can be
+    /// used in certificates. Returns the reason in case it does not.
+    pub fn verify(&self) -> Result<(), String> {
+        let max = Duration::days(i64::from(TIMING_MAX_YEARS) * 365);
+
+        if self.max_key_age() > max || self.staging() > max {
+            Err(format!(
+                "maximum key age and staging period must not exceed {} years",
+                TIMING_MAX_YEARS
+            ))
+        } else if self.staging_hours < KEY_ROLL_MIN_STAGING_HOURS {
+            Err(format!(
+                "staging period must be at least {} hours",
+                KEY_ROLL_MIN_STAGING_HOURS
+            ))
+        } else if self.max_key_age() <= self.staging() {
+            Err("maximum key age must be longer than the staging period".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Display for KeyRollPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "roll keys older than {} days, with a staging period of {} hours",
+            self.max_key_age_days, self.staging_hours
+        )
+    }
+}
+
+//------------ KeyRollStage ------------------------------------------------
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRollStage {
+    /// The new key is waiting for a certificate from the parent.
+    Pending,
+
+    /// The new key is certified and published, and waits to be activated.
+    New,
+
+    /// The new key is active, and the old key waits to be revoked by the
+    /// parent.
+    Old,
+}
+
+//------------ KeyRollProgress ---------------------------------------------
+
+/// The progress of a key roll in a resource class of a CA.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KeyRollProgress {
+    resource_class: ResourceClassName,
+    stage: KeyRollStage,
+    activate_after: Option<Time>, // Only known for new keys, under a policy
+}
+
+impl KeyRollProgress {
+    pub fn new(
+        resource_class: ResourceClassName,
+        stage: KeyRollStage,
+        activate_after: Option<Time>,
+    ) -> Self {
+        KeyRollProgress {
+            resource_class,
+            stage,
+            activate_after,
+        }
+    }
+
+    pub fn resource_class(&self) -> &ResourceClassName {
+        &self.resource_class
+    }
+
+    pub fn stage(&self) -> KeyRollStage {
+        self.stage
+    }
+
+    pub fn activate_after(&self) -> Option<Time> {
+        self.activate_after
+    }
+}
+
+impl fmt::Display for KeyRollProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "resource class '{}': ", self.resource_class)?;
+        match self.stage {
+            KeyRollStage::Pending => write!(f, "new key waits for a certificate from the parent"),
+            KeyRollStage::New => match self.activate_after {
+                Some(time) => write!(f, "new key will be activated after {}", time.to_rfc3339()),
+                None => write!(f, "new key waits for manual activation"),
+            },
+            KeyRollStage::Old => write!(f, "old key waits for revocation by the parent"),
+        }
+    }
+}
+
+//------------ CaKeyRollInfo -----------------------------------------------
+
+/// The key roll policy of a CA, if any, and the progress of key rolls.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CaKeyRollInfo {
+    policy: Option<KeyRollPolicy>,
+    progress: Vec<KeyRollProgress>,
+}
+
+impl CaKeyRollInfo {
+    pub fn new(policy: Option<KeyRollPolicy>, progress: Vec<KeyRollProgress>) -> Self {
+        CaKeyRollInfo { policy, progress }
+    }
+
+    pub fn policy(&self) -> Option<&KeyRollPolicy> {
+        self.policy.as_ref()
+    }
+
+    pub fn progress(&self) -> &[KeyRollProgress] {
+        &self.progress
+    }
+}
+
+impl fmt::Display for CaKeyRollInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.policy {
+            Some(policy) => writeln!(f, "Key roll policy: {}", policy)?,
+            None => writeln!(f, "Key roll policy: none, keys are rolled manually")?,
+        }
+        if self.progress.is_empty() {
+            writeln!(f, "No key roll in progress")
+        } else {
+            writeln!(f, "Key rolls in progress:")?;
+            for progress in self.progress.iter() {
+                writeln!(f, "  {}", progress)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+//------------ KeyRollEmergencyRequest -------------------------------------
+
+/// A request for an emergency key roll, e.g. because the current key of a
+/// CA may be compromised. The new keys are activated, and the old keys are
+/// revoked, as soon as the parent certifies the new keys, without observing
+/// the staging period. Because of this the request must be confirmed.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KeyRollEmergencyRequest {
+    reason: String,
+    #[serde(default)]
+    confirm: bool,
+}
+
+impl KeyRollEmergencyRequest {
+    pub fn new(reason: String, confirm: bool) -> Self {
+        KeyRollEmergencyRequest { reason, confirm }
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    pub fn confirmed(&self) -> bool {
+        self.confirm
+    }
+
+    pub fn unpack(self) -> (String, bool) {
+        (self.reason, self.confirm)
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_key_roll_policy() {
+        KeyRollPolicy::new(365, 24).verify().unwrap();
+        KeyRollPolicy::new(2, 36).verify().unwrap();
+        KeyRollPolicy::new(365, 12).verify().unwrap_err();
+        KeyRollPolicy::new(1, 24).verify().unwrap_err();
+        KeyRollPolicy::new(0, 24).verify().unwrap_err();
+        KeyRollPolicy::new(10_950, 24).verify().unwrap();
+        KeyRollPolicy::new(10_951, 24).verify().unwrap_err();
+        KeyRollPolicy::new(u32::MAX, 24).verify().unwrap_err();
+        KeyRollPolicy::new(u32::MAX, u32::MAX).verify().unwrap_err();
+    }
+}
@@ -3,9 +3,18 @@
 mod admin;
 pub use self::admin::*;
 
+mod aspa;
+pub use self::aspa::*;
+
+mod bgpsec;
+pub use self::bgpsec::*;
+
 mod ca;
 pub use self::ca::*;
 
+mod keyroll;
+pub use self::keyroll::*;
+
 mod provisioning;
 pub use self::provisioning::*;
 
@@ -15,11 +24,22 @@ pub use self::publication::*;
 mod roas;
 pub use self::roas::*;
 
+mod rsc;
+pub use self::rsc::*;
+
+mod ta;
+pub use self::ta::*;
+
+mod timing;
+pub use self::timing::*;
+
 pub mod rrdp;
 
 use std::collections::HashMap;
 use std::fmt;
 
+use bcder::encode::Values;
+use bcder::Mode;
 use bytes::Bytes;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -28,6 +48,7 @@ use rpki::crl::Crl;
 use rpki::crypto::KeyIdentifier;
 use rpki::manifest::Manifest;
 use rpki::roa::Roa;
+use rpki::sigobj::SignedObject;
 
 use crate::commons::util::sha256;
 use crate::daemon::ca::RouteAuthorization;
@@ -89,6 +110,12 @@ impl From<&Roa> for Base64 {
     }
 }
 
+impl From<&SignedObject> for Base64 {
+    fn from(object: &SignedObject) -> Self {
+        Base64::from_content(&object.encode_ref().to_captured(Mode::Der).into_bytes())
+    }
+}
+
 impl From<&Manifest> for Base64 {
     fn from(mft: &Manifest) -> Self {
         Base64::from_content(&mft.to_captured().into_bytes())
@@ -264,6 +291,10 @@ impl ErrorResponse {
         self.with_arg("base_uri", base_uri)
     }
 
+    pub fn with_token_name(self, name: &str) -> Self {
+        self.with_arg("token_name", name)
+    }
+
     pub fn with_ca(self, ca: &Handle) -> Self {
         self.with_arg("ca", ca)
     }
@@ -288,6 +319,14 @@ impl ErrorResponse {
         res
     }
 
+    pub fn with_asn(self, asn: AsNumber) -> Self {
+        self.with_arg("asn", asn)
+    }
+
+    pub fn with_router(self, router: &BgpSecAsnKey) -> Self {
+        self.with_arg("router", router)
+    }
+
     pub fn with_key_identifier(self, ki: &KeyIdentifier) -> Self {
         self.with_arg("key_id", ki)
     }
@@ -296,6 +335,10 @@ impl ErrorResponse {
         self.with_arg("class_name", class_name)
     }
 
+    pub fn with_resources(self, resources: &ResourceSet) -> Self {
+        self.with_arg("resources", resources)
+    }
+
     pub fn label(&self) -> &str {
         &self.label
     }
@@ -145,6 +145,27 @@ impl RoaDefinitionUpdates {
     pub fn remove(&mut self, rem: RoaDefinition) {
         self.removed.insert(rem);
     }
+
+    /// Returns the updates needed to get from the current to the desired
+    /// definitions.
+    pub fn between(current: &[RoaDefinition], desired: &HashSet<RoaDefinition>) -> Self {
+        let current: HashSet<RoaDefinition> = current.iter().cloned().collect();
+        let added = desired.difference(&current).cloned().collect();
+        let removed = current.difference(desired).cloned().collect();
+        RoaDefinitionUpdates { added, removed }
+    }
+
+    pub fn added(&self) -> &HashSet<RoaDefinition> {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &HashSet<RoaDefinition> {
+        &self.removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
 }
 
 impl Default for RoaDefinitionUpdates {
@@ -203,6 +224,379 @@ impl FromStr for RoaDefinitionUpdates {
     }
 }
 
+//------------ RoaFileFormat -----------------------------------------------
+
+/// The file formats that can be used to import and export the complete set
+/// of ROA definitions for a CA.
+///
+/// Csv: one "ASN,IP Prefix,Max Length" line per definition, e.g.
+/// "AS64496,192.168.0.0/16,24". The max length may be left empty.
+///
+/// Slurm: the local assertions format defined in RFC 8416, of which only
+/// the prefix assertions are used.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoaFileFormat {
+    Csv,
+    Slurm,
+}
+
+impl RoaFileFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            RoaFileFormat::Csv => "text/csv",
+            RoaFileFormat::Slurm => "application/json",
+        }
+    }
+
+    /// Parses the file into the set of definitions it contains.
+    pub fn parse(self, s: &str) -> Result<HashSet<RoaDefinition>, AuthorizationFmtError> {
+        match self {
+            RoaFileFormat::Csv => Self::parse_csv(s),
+            RoaFileFormat::Slurm => Self::parse_slurm(s),
+        }
+    }
+
+    /// Formats the definitions as a file, ordered by prefix and ASN so that
+    /// exported files can be compared easily.
+    pub fn format(self, definitions: &[RoaDefinition]) -> String {
+        let mut definitions = definitions.to_vec();
+        definitions.sort_by_key(|d| (d.prefix.ip_addr(), d.prefix.addr_len(), d.asn.0));
+
+        match self {
+            RoaFileFormat::Csv => {
+                let mut res = "ASN,IP Prefix,Max Length\n".to_string();
+                for def in definitions {
+                    let max_length = def.max_length.map(|l| l.to_string()).unwrap_or_default();
+                    res.push_str(&format!("AS{},{},{}\n", def.asn, def.prefix, max_length));
+                }
+                res
+            }
+            RoaFileFormat::Slurm => {
+                let slurm = Slurm::from(definitions.as_slice());
+                serde_json::to_string_pretty(&slurm).unwrap()
+            }
+        }
+    }
+
+    fn parse_csv(s: &str) -> Result<HashSet<RoaDefinition>, AuthorizationFmtError> {
+        let mut res = HashSet::new();
+
+        for line in s.lines() {
+            let line = match line.find('#') {
+                None => &line,
+                Some(pos) => &line[..pos],
+            };
+            let line = line.trim();
+
+            if line.is_empty() || line.to_lowercase().starts_with("asn") {
+                continue; // skip empty lines and the header
+            }
+
+            let mut parts = line.split(',');
+            let asn_str = parts
+                .next()
+                .ok_or_else(|| AuthorizationFmtError::auth(line))?;
+            let prefix_str = parts
+                .next()
+                .ok_or_else(|| AuthorizationFmtError::auth(line))?;
+            let max_length = match parts.next().map(str::trim) {
+                None | Some("") => None,
+                Some(length_str) => {
+                    Some(u8::from_str(length_str).map_err(|_| AuthorizationFmtError::auth(line))?)
+                }
+            };
+            if parts.next().is_some() {
+                return Err(AuthorizationFmtError::auth(line));
+            }
+
+            let asn_str = asn_str.trim();
+            let asn_str = if asn_str.to_uppercase().starts_with("AS") {
+                &asn_str[2..]
+            } else {
+                asn_str
+            };
+
+            res.insert(RoaDefinition::new(
+                AsNumber::from_str(asn_str)?,
+                TypedPrefix::from_str(prefix_str)?,
+                max_length,
+            ));
+        }
+
+        Ok(res)
+    }
+
+    fn parse_slurm(s: &str) -> Result<HashSet<RoaDefinition>, AuthorizationFmtError> {
+        let slurm: Slurm =
+            serde_json::from_str(s).map_err(|e| AuthorizationFmtError::slurm(&e.to_string()))?;
+
+        if slurm.slurm_version != 1 {
+            return Err(AuthorizationFmtError::slurm("unsupported slurmVersion"));
+        }
+
+        Ok(slurm
+            .locally_added_assertions
+            .prefix_assertions
+            .into_iter()
+            .map(|a| RoaDefinition::new(a.asn, a.prefix, a.max_prefix_length))
+            .collect())
+    }
+}
+
+impl Default for RoaFileFormat {
+    fn default() -> Self {
+        RoaFileFormat::Csv
+    }
+}
+
+impl FromStr for RoaFileFormat {
+    type Err = AuthorizationFmtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(RoaFileFormat::Csv),
+            "slurm" => Ok(RoaFileFormat::Slurm),
+            _ => Err(AuthorizationFmtError::Format(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for RoaFileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoaFileFormat::Csv => write!(f, "csv"),
+            RoaFileFormat::Slurm => write!(f, "slurm"),
+        }
+    }
+}
+
+//------------ RoaFileOptions ----------------------------------------------
+
+/// The options for importing or exporting ROA definitions as a file. A dry
+/// run only applies to imports: it shows the updates that the import would
+/// take, without applying them.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RoaFileOptions {
+    #[serde(default)]
+    format: RoaFileFormat,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+impl RoaFileOptions {
+    pub fn new(format: RoaFileFormat, dry_run: bool) -> Self {
+        RoaFileOptions { format, dry_run }
+    }
+
+    pub fn format(&self) -> RoaFileFormat {
+        self.format
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+}
+
+//------------ RoaSyncOptions ----------------------------------------------
+
+/// The options for replacing all ROA definitions of a CA with a desired
+/// set. A dry run only shows the updates that this would take, without
+/// applying them.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RoaSyncOptions {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+impl RoaSyncOptions {
+    pub fn new(dry_run: bool) -> Self {
+        RoaSyncOptions { dry_run }
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+}
+
+//------------ RoaPrefixGroupingStrategy -----------------------------------
+
+/// Defines the prefix grouping strategy to be used during ROA creation/update.
+///
+/// RoaPerPrefix: One ROA per prefix (and ASN).
+///
+/// RoaPerAsn: Groups all prefixes authorisations for an ASN in a single ROA.
+///
+/// RoaPerAsnBounded(max): Groups the prefix authorisations for an ASN in ROAs
+/// of at most 'max' prefixes. Prefixes stay in the ROA they were put in, so
+/// that adding or removing a prefix only re-issues a single ROA.
+///
+/// This is (de)serialized as a string, e.g. "RoaPerAsnBounded(10)".
+#[derive(Clone, Eq, PartialEq, Debug, Copy)]
+pub enum RoaPrefixGroupingStrategy {
+    RoaPerPrefix,
+    RoaPerAsn,
+    RoaPerAsnBounded(usize),
+}
+
+impl Default for RoaPrefixGroupingStrategy {
+    fn default() -> Self {
+        Self::RoaPerPrefix
+    }
+}
+
+impl FromStr for RoaPrefixGroupingStrategy {
+    type Err = AuthorizationFmtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "RoaPerPrefix" => Ok(RoaPrefixGroupingStrategy::RoaPerPrefix),
+            "RoaPerAsn" => Ok(RoaPrefixGroupingStrategy::RoaPerAsn),
+            _ => {
+                if s.starts_with("RoaPerAsnBounded(") && s.ends_with(')') {
+                    let max = &s["RoaPerAsnBounded(".len()..s.len() - 1];
+                    match usize::from_str(max.trim()) {
+                        Ok(max) if max > 0 => Ok(RoaPrefixGroupingStrategy::RoaPerAsnBounded(max)),
+                        _ => Err(AuthorizationFmtError::Grouping(s.to_string())),
+                    }
+                } else {
+                    Err(AuthorizationFmtError::Grouping(s.to_string()))
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for RoaPrefixGroupingStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoaPrefixGroupingStrategy::RoaPerPrefix => write!(f, "RoaPerPrefix"),
+            RoaPrefixGroupingStrategy::RoaPerAsn => write!(f, "RoaPerAsn"),
+            RoaPrefixGroupingStrategy::RoaPerAsnBounded(max) => {
+                write!(f, "RoaPerAsnBounded({})", max)
+            }
+        }
+    }
+}
+
+impl Serialize for RoaPrefixGroupingStrategy {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_string().serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for RoaPrefixGroupingStrategy {
+    fn deserialize<D>(d: D) -> Result<RoaPrefixGroupingStrategy, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(d)?;
+        RoaPrefixGroupingStrategy::from_str(&string).map_err(de::Error::custom)
+    }
+}
+
+//------------ RoaGroupingInfo ---------------------------------------------
+
+/// The ROA prefix grouping strategy used by a CA, and whether this is
+/// specific to the CA rather than the server wide default.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RoaGroupingInfo {
+    strategy: RoaPrefixGroupingStrategy,
+    custom: bool,
+}
+
+impl RoaGroupingInfo {
+    pub fn new(strategy: RoaPrefixGroupingStrategy, custom: bool) -> Self {
+        RoaGroupingInfo { strategy, custom }
+    }
+
+    pub fn strategy(&self) -> RoaPrefixGroupingStrategy {
+        self.strategy
+    }
+
+    pub fn custom(&self) -> bool {
+        self.custom
+    }
+}
+
+impl fmt::Display for RoaGroupingInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.custom {
+            writeln!(f, "ROA grouping (specific to this CA): {}", self.strategy)
+        } else {
+            writeln!(f, "ROA grouping (server default): {}", self.strategy)
+        }
+    }
+}
+
+//------------ Slurm -------------------------------------------------------
+
+/// A SLURM file as defined in RFC 8416. Filters and BGPsec assertions are
+/// kept only so that they can be read and written, they are not used.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Slurm {
+    slurm_version: u8,
+    #[serde(default)]
+    validation_output_filters: SlurmFilters,
+    locally_added_assertions: SlurmAssertions,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SlurmFilters {
+    #[serde(default)]
+    prefix_filters: Vec<serde_json::Value>,
+    #[serde(default)]
+    bgpsec_filters: Vec<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SlurmAssertions {
+    #[serde(default)]
+    prefix_assertions: Vec<SlurmPrefixAssertion>,
+    #[serde(default)]
+    bgpsec_assertions: Vec<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SlurmPrefixAssertion {
+    asn: AsNumber,
+    prefix: TypedPrefix,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_prefix_length: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+impl From<&[RoaDefinition]> for Slurm {
+    fn from(definitions: &[RoaDefinition]) -> Self {
+        let prefix_assertions = definitions
+            .iter()
+            .map(|def| SlurmPrefixAssertion {
+                asn: def.asn,
+                prefix: def.prefix,
+                max_prefix_length: def.max_length,
+                comment: None,
+            })
+            .collect();
+
+        Slurm {
+            slurm_version: 1,
+            validation_output_filters: SlurmFilters::default(),
+            locally_added_assertions: SlurmAssertions {
+                prefix_assertions,
+                bgpsec_assertions: vec![],
+            },
+        }
+    }
+}
+
 //------------ TypedPrefix -------------------------------------------------
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum TypedPrefix {
@@ -221,6 +615,28 @@ impl TypedPrefix {
             TypedPrefix::V6(v6) => IpAddr::V6(v6.0.to_v6()),
         }
     }
+
+    /// Returns whether this prefix covers the other prefix, i.e. whether the
+    /// other prefix is of the same address family and is equal to, or more
+    /// specific than, this prefix.
+    pub fn covers(&self, other: &TypedPrefix) -> bool {
+        let (bits, other_bits) = match (self.ip_addr(), other.ip_addr()) {
+            (IpAddr::V4(v4), IpAddr::V4(other)) => (
+                u128::from(u32::from(v4)) << 96,
+                u128::from(u32::from(other)) << 96,
+            ),
+            (IpAddr::V6(v6), IpAddr::V6(other)) => (u128::from(v6), u128::from(other)),
+            _ => return false,
+        };
+
+        let len = self.addr_len();
+        if len > other.addr_len() {
+            return false;
+        }
+
+        let mask = (!0u128).checked_shl(128 - u32::from(len)).unwrap_or(0);
+        (bits ^ other_bits) & mask == 0
+    }
 }
 
 impl TypedPrefix {
@@ -339,7 +755,7 @@ impl fmt::Display for Ipv6Prefix {
 
 //------------ AsNumber ----------------------------------------------------
 
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct AsNumber(u32);
 
 impl AsNumber {
@@ -354,6 +770,12 @@ impl From<AsNumber> for AsId {
     }
 }
 
+impl From<AsNumber> for u32 {
+    fn from(asn: AsNumber) -> Self {
+        asn.0
+    }
+}
+
 impl FromStr for AsNumber {
     type Err = AuthorizationFmtError;
 
@@ -385,6 +807,27 @@ pub enum AuthorizationFmtError {
 
     #[display(fmt = "Invalid authorisation delta string: {}", _0)]
     Delta(String),
+
+    #[display(fmt = "Invalid ASPA definition string: {}", _0)]
+    Aspa(String),
+
+    #[display(fmt = "Invalid BGPsec router key string: {}", _0)]
+    BgpSecAsnKey(String),
+
+    #[display(fmt = "Invalid signed checklist digest: {}", _0)]
+    RscDigest(String),
+
+    #[display(fmt = "Invalid SLURM file: {}", _0)]
+    Slurm(String),
+
+    #[display(fmt = "Unsupported ROA file format: {}, use csv or slurm", _0)]
+    Format(String),
+
+    #[display(
+        fmt = "Unknown ROA grouping strategy: {}, use RoaPerPrefix, RoaPerAsn or RoaPerAsnBounded(<max>)",
+        _0
+    )]
+    Grouping(String),
 }
 
 impl AuthorizationFmtError {
@@ -403,6 +846,22 @@ impl AuthorizationFmtError {
     pub fn delta(s: &str) -> Self {
         AuthorizationFmtError::Delta(s.to_string())
     }
+
+    pub fn aspa(s: &str) -> Self {
+        AuthorizationFmtError::Aspa(s.to_string())
+    }
+
+    pub fn bgpsec_asn_key(s: &str) -> Self {
+        AuthorizationFmtError::BgpSecAsnKey(s.to_string())
+    }
+
+    pub fn rsc_digest(s: &str) -> Self {
+        AuthorizationFmtError::RscDigest(s.to_string())
+    }
+
+    fn slurm(s: &str) -> Self {
+        AuthorizationFmtError::Slurm(s.to_string())
+    }
 }
 
 //------------ Tests -------------------------------------------------------
@@ -439,6 +898,72 @@ mod tests {
         assert_eq!(parsed, reparsed);
     }
 
+    #[test]
+    fn roa_updates_between() {
+        let def = |s: &str| RoaDefinition::from_str(s).unwrap();
+
+        let current = vec![
+            def("192.168.0.0/16 => 64496"),
+            def("192.168.1.0/24 => 64496"),
+        ];
+        let mut desired = HashSet::new();
+        desired.insert(def("192.168.0.0/16 => 64496"));
+        desired.insert(def("192.168.2.0/24 => 64496"));
+
+        let updates = RoaDefinitionUpdates::between(&current, &desired);
+        let expected = RoaDefinitionUpdates::from_str(concat!(
+            "A: 192.168.2.0/24 => 64496\n",
+            "R: 192.168.1.0/24 => 64496\n",
+        ))
+        .unwrap();
+        assert_eq!(expected, updates);
+
+        let current: Vec<RoaDefinition> = desired.iter().cloned().collect();
+        assert!(RoaDefinitionUpdates::between(&current, &desired).is_empty());
+    }
+
+    #[test]
+    fn roa_file_formats() {
+        let definitions = vec![
+            RoaDefinition::from_str("192.168.0.0/16-24 => 64496").unwrap(),
+            RoaDefinition::from_str("192.168.1.0/24 => 64497").unwrap(),
+            RoaDefinition::from_str("2001:db8::/32 => 64496").unwrap(),
+        ];
+        let expected: HashSet<RoaDefinition> = definitions.iter().cloned().collect();
+
+        let csv = RoaFileFormat::Csv.format(&definitions);
+        assert_eq!(
+            csv,
+            concat!(
+                "ASN,IP Prefix,Max Length\n",
+                "AS64496,192.168.0.0/16,24\n",
+                "AS64497,192.168.1.0/24,\n",
+                "AS64496,2001:db8::/32,\n",
+            )
+        );
+        assert_eq!(expected, RoaFileFormat::Csv.parse(&csv).unwrap());
+
+        let slurm = RoaFileFormat::Slurm.format(&definitions);
+        assert_eq!(expected, RoaFileFormat::Slurm.parse(&slurm).unwrap());
+
+        let csv = concat!(
+            "# comment\n",
+            "64496, 192.168.0.0/16 ,24\n",
+            "AS64497,192.168.1.0/24\n",
+            "\n",
+            "as64496,2001:db8::/32,"
+        );
+        assert_eq!(expected, RoaFileFormat::Csv.parse(csv).unwrap());
+
+        let slurm = include_str!("../../../test-resources/roas/slurm.json");
+        assert_eq!(expected, RoaFileFormat::Slurm.parse(slurm).unwrap());
+
+        assert!(RoaFileFormat::Csv
+            .parse("AS64496,192.168.0.0/16,24,1")
+            .is_err());
+        assert!(RoaFileFormat::Slurm.parse("{}").is_err());
+    }
+
     #[test]
     fn parse_type_prefix() {
         assert!(TypedPrefix::from_str("192.168.0.0/16").is_ok());
@@ -458,6 +983,21 @@ mod tests {
         assert_eq!(json, expected);
     }
 
+    #[test]
+    fn prefix_covers() {
+        let pfx = |s: &str| TypedPrefix::from_str(s).unwrap();
+
+        assert!(pfx("192.168.0.0/16").covers(&pfx("192.168.0.0/16")));
+        assert!(pfx("192.168.0.0/16").covers(&pfx("192.168.1.0/24")));
+        assert!(pfx("0.0.0.0/0").covers(&pfx("10.0.0.0/8")));
+        assert!(!pfx("192.168.1.0/24").covers(&pfx("192.168.0.0/16")));
+        assert!(!pfx("192.168.0.0/16").covers(&pfx("192.169.0.0/24")));
+        assert!(!pfx("0.0.0.0/0").covers(&pfx("2001:db8::/32")));
+
+        assert!(pfx("2001:db8::/32").covers(&pfx("2001:db8:1::/48")));
+        assert!(!pfx("2001:db8::/32").covers(&pfx("2001:db9::/48")));
+    }
+
     #[test]
     fn serde_roa_definition() {
         fn parse_ser_de_print_definition(s: &str) {
@@ -501,4 +1041,23 @@ mod tests {
         invalid_max_length("2001:db8::/32-129 => 64496");
     }
 
+    #[test]
+    fn roa_grouping_strategy() {
+        for strategy in &[
+            RoaPrefixGroupingStrategy::RoaPerPrefix,
+            RoaPrefixGroupingStrategy::RoaPerAsn,
+            RoaPrefixGroupingStrategy::RoaPerAsnBounded(10),
+        ] {
+            let parsed = RoaPrefixGroupingStrategy::from_str(&strategy.to_string()).unwrap();
+            assert_eq!(strategy, &parsed);
+
+            let json = serde_json::to_string(strategy).unwrap();
+            assert_eq!(format!("\"{}\"", strategy), json);
+        }
+
+        assert!(RoaPrefixGroupingStrategy::from_str("per-asn").is_err());
+        assert!(RoaPrefixGroupingStrategy::from_str("RoaPerAsnBounded").is_err());
+        assert!(RoaPrefixGroupingStrategy::from_str("RoaPerAsnBounded(0)").is_err());
+    }
+
 }
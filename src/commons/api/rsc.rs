@@ -0,0 +1,193 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bytes::Bytes;
+
+use crate::commons::api::{AuthorizationFmtError, Base64, HexEncodedHash, ResourceSet};
+
+//------------ RscFileDigest -----------------------------------------------
+
+/// This type defines an entry in a Resource Signed Checklist (RFC 9323): the
+/// SHA-256 digest of a file, and optionally the name of that file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RscFileDigest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file_name: Option<String>,
+    digest: HexEncodedHash,
+}
+
+impl RscFileDigest {
+    pub fn new(file_name: Option<String>, digest: HexEncodedHash) -> Self {
+        RscFileDigest { file_name, digest }
+    }
+
+    /// Creates an entry for the given file name and content.
+    pub fn for_content(file_name: Option<String>, content: &[u8]) -> Self {
+        RscFileDigest::new(file_name, HexEncodedHash::from_content(content))
+    }
+
+    pub fn file_name(&self) -> Option<&String> {
+        self.file_name.as_ref()
+    }
+
+    pub fn digest(&self) -> &HexEncodedHash {
+        &self.digest
+    }
+
+    /// Returns the decoded digest.
+    pub fn digest_bytes(&self) -> Bytes {
+        // The digest is validated when the checklist is signed.
+        let digest: &str = self.digest.as_ref();
+        Bytes::from(hex::decode(digest).unwrap_or_default())
+    }
+
+    /// Verifies that the digest is a hex encoded SHA-256 digest, and that
+    /// the file name, if any, only uses the portable filename characters
+    /// required by RFC 9323.
+    pub fn validate(&self) -> Result<(), AuthorizationFmtError> {
+        let digest: &str = self.digest.as_ref();
+        if digest.len() != 64 || hex::decode(digest).is_err() {
+            return Err(AuthorizationFmtError::rsc_digest(&self.to_string()));
+        }
+
+        if let Some(name) = &self.file_name {
+            let portable = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-';
+            if name.is_empty() || !name.chars().all(portable) {
+                return Err(AuthorizationFmtError::rsc_digest(&self.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a digest, optionally followed by a file name, as found in the
+/// output of sha256sum: "<sha256 hex> [<file name>]"
+impl FromStr for RscFileDigest {
+    type Err = AuthorizationFmtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+
+        let digest = parts
+            .next()
+            .ok_or_else(|| AuthorizationFmtError::rsc_digest(s))?;
+        let digest = HexEncodedHash::from(digest.to_string());
+
+        let file_name = parts
+            .next()
+            .map(|name| name.trim_start_matches('*').to_string());
+
+        if parts.next().is_some() {
+            return Err(AuthorizationFmtError::rsc_digest(s));
+        }
+
+        let entry = RscFileDigest::new(file_name, digest);
+        entry.validate()?;
+        Ok(entry)
+    }
+}
+
+impl fmt::Display for RscFileDigest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file_name {
+            None => write!(f, "{}", self.digest),
+            Some(name) => write!(f, "{} {}", self.digest, name),
+        }
+    }
+}
+
+//------------ RscRequest --------------------------------------------------
+
+/// This type defines a request to sign a Resource Signed Checklist: the
+/// resources to prove holdership of, which must be held by the CA, and the
+/// digests of the files to include in the checklist.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RscRequest {
+    resources: ResourceSet,
+    digests: Vec<RscFileDigest>,
+}
+
+impl RscRequest {
+    pub fn new(resources: ResourceSet, digests: Vec<RscFileDigest>) -> Self {
+        RscRequest { resources, digests }
+    }
+
+    pub fn resources(&self) -> &ResourceSet {
+        &self.resources
+    }
+
+    pub fn digests(&self) -> &[RscFileDigest] {
+        &self.digests
+    }
+}
+
+impl fmt::Display for RscRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "resources: {}, digests: {}",
+            self.resources,
+            self.digests.len()
+        )
+    }
+}
+
+//------------ SignedChecklist ---------------------------------------------
+
+/// A signed Resource Signed Checklist object, in its base64 encoded DER
+/// form. These objects are handed to the requester, and never published.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SignedChecklist {
+    content: Base64,
+}
+
+impl SignedChecklist {
+    pub fn new(content: Base64) -> Self {
+        SignedChecklist { content }
+    }
+
+    pub fn content(&self) -> &Base64 {
+        &self.content
+    }
+
+    /// Returns the DER encoded object, e.g. for saving to disk.
+    pub fn to_bytes(&self) -> Bytes {
+        self.content.to_bytes()
+    }
+}
+
+impl fmt::Display for SignedChecklist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.content)
+    }
+}
+
+//------------ Tests -------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_validate_digest() {
+        let hex = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+        let unnamed = RscFileDigest::from_str(hex).unwrap();
+        assert_eq!(None, unnamed.file_name());
+        assert_eq!(32, unnamed.digest_bytes().len());
+        assert_eq!(hex, unnamed.to_string());
+
+        let named = RscFileDigest::from_str(&format!("{}  *loa.pdf", hex)).unwrap();
+        assert_eq!(Some(&"loa.pdf".to_string()), named.file_name());
+        assert_eq!(format!("{} loa.pdf", hex), named.to_string());
+
+        let upper = RscFileDigest::from_str(&hex.to_uppercase()).unwrap();
+        assert_eq!(unnamed, upper);
+
+        assert!(RscFileDigest::from_str("").is_err());
+        assert!(RscFileDigest::from_str(&hex[1..]).is_err());
+        assert!(RscFileDigest::from_str(&format!("{} loa 2020.pdf", hex)).is_err());
+        assert!(RscFileDigest::from_str(&format!("{} loa/2020.pdf", hex)).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+
+use bytes::Bytes;
+
+use crate::commons::api::rrdp::PublishElement;
+use rpki::uri;
+
+use crate::commons::api::{Base64, Handle, ParentHandle, TaCertDetails};
+
+//------------ TaMessage ---------------------------------------------------
+
+/// An RFC 6492 message exchanged with an offline Trust Anchor: a signed
+/// request by a CA, or a signed response by the Trust Anchor to such a
+/// request. The CMS is kept in its base64 encoded form.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaMessage {
+    ca: Handle,
+    parent: ParentHandle,
+    cms: Base64,
+}
+
+impl TaMessage {
+    pub fn new(ca: Handle, parent: ParentHandle, cms: Bytes) -> Self {
+        let cms = Base64::from_content(cms.as_ref());
+        TaMessage { ca, parent, cms }
+    }
+
+    pub fn ca(&self) -> &Handle {
+        &self.ca
+    }
+
+    pub fn parent(&self) -> &ParentHandle {
+        &self.parent
+    }
+
+    pub fn cms(&self) -> &Base64 {
+        &self.cms
+    }
+
+    /// Returns the DER encoded CMS.
+    pub fn to_bytes(&self) -> Bytes {
+        self.cms.to_bytes()
+    }
+}
+
+impl fmt::Display for TaMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CA '{}' parent '{}'", self.ca, self.parent)
+    }
+}
+
+//------------ TaRequestOptions --------------------------------------------
+
+/// The options for exporting requests for an offline Trust Anchor.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaRequestOptions {
+    #[serde(default)]
+    republish: bool,
+}
+
+impl TaRequestOptions {
+    pub fn new(republish: bool) -> Self {
+        TaRequestOptions { republish }
+    }
+
+    pub fn republish(&self) -> bool {
+        self.republish
+    }
+}
+
+//------------ TaRequestBundle ---------------------------------------------
+
+/// The open requests of all CAs which have an offline Trust Anchor as
+/// their parent, exported so that they can be taken to the Trust Anchor.
+///
+/// If 'republish' is set, the Trust Anchor will also republish its manifest
+/// and CRL if they are close to their next update time.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaRequestBundle {
+    requests: Vec<TaMessage>,
+    #[serde(default)]
+    republish: bool,
+}
+
+impl TaRequestBundle {
+    pub fn new(requests: Vec<TaMessage>, republish: bool) -> Self {
+        TaRequestBundle {
+            requests,
+            republish,
+        }
+    }
+
+    pub fn requests(&self) -> &[TaMessage] {
+        &self.requests
+    }
+
+    pub fn republish(&self) -> bool {
+        self.republish
+    }
+
+    pub fn unpack(self) -> (Vec<TaMessage>, bool) {
+        (self.requests, self.republish)
+    }
+}
+
+impl fmt::Display for TaRequestBundle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Requests for the offline Trust Anchor:")?;
+        for request in self.requests.iter() {
+            writeln!(f, "  {}", request)?;
+        }
+        writeln!(f, "Republish: {}", self.republish)
+    }
+}
+
+//------------ TaResponses -------------------------------------------------
+
+/// The responses of an offline Trust Anchor to a TaRequestBundle. This also
+/// includes everything the online side needs to act on behalf of the Trust
+/// Anchor: its certificate and TAL, where its certificate is published, and
+/// all the objects that it publishes, including its current manifest and
+/// CRL.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaResponses {
+    ta: TaCertDetails,
+    ta_uri: uri::Rsync,
+    responses: Vec<TaMessage>,
+    objects: Vec<PublishElement>,
+}
+
+impl TaResponses {
+    pub fn new(
+        ta: TaCertDetails,
+        ta_uri: uri::Rsync,
+        responses: Vec<TaMessage>,
+        objects: Vec<PublishElement>,
+    ) -> Self {
+        TaResponses {
+            ta,
+            ta_uri,
+            responses,
+            objects,
+        }
+    }
+
+    pub fn ta(&self) -> &TaCertDetails {
+        &self.ta
+    }
+
+    pub fn ta_uri(&self) -> &uri::Rsync {
+        &self.ta_uri
+    }
+
+    pub fn responses(&self) -> &[TaMessage] {
+        &self.responses
+    }
+
+    pub fn objects(&self) -> &[PublishElement] {
+        &self.objects
+    }
+}
+
+impl fmt::Display for TaResponses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Responses from the offline Trust Anchor:")?;
+        for response in self.responses.iter() {
+            writeln!(f, "  {}", response)?;
+        }
+        writeln!(f, "Published objects:")?;
+        for object in self.objects.iter() {
+            writeln!(f, "  {}", object.uri())?;
+        }
+        Ok(())
+    }
+}
+
+//------------ TaResponseBundle --------------------------------------------
+
+/// The TaResponses of an offline Trust Anchor, signed by the Trust Anchor
+/// with its identity key. This uses the same CMS as RFC 6492 messages, with
+/// the json of the responses as its content, so that the online side can
+/// verify the whole bundle before it uses any of it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaResponseBundle {
+    cms: Base64,
+}
+
+impl TaResponseBundle {
+    pub fn new(cms: Bytes) -> Self {
+        let cms = Base64::from_content(cms.as_ref());
+        TaResponseBundle { cms }
+    }
+
+    pub fn cms(&self) -> &Base64 {
+        &self.cms
+    }
+
+    /// Returns the DER encoded CMS.
+    pub fn to_bytes(&self) -> Bytes {
+        self.cms.to_bytes()
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt;
+
+use chrono::Duration;
+
+use crate::constants::{
+    CHILD_CERTIFICATE_REISSUE_WEEKS, CHILD_CERTIFICATE_VALIDITY_WEEKS, PUBLISH_NEXT_HOURS,
+    PUBLISH_THRESHOLD_HOURS, PUBLISH_VALID_DAYS, TIMING_MAX_YEARS,
+};
+
+//------------ TimingConfig ------------------------------------------------
+
+/// The timing used by a CA for the manifests and CRLs that it publishes,
+/// and for the certificates that it issues to its children.
+///
+/// - manifests and CRLs get a next update time 'publish_next_hours' from
+///   now, and the manifest EE certificate is valid for 'publish_valid_days';
+/// - they are republished when the next update time is less than
+///   'publish_threshold_hours' away;
+/// - child certificates are valid for 'child_certificate_valid_weeks', and
+///   are re-issued when they expire in less than
+///   'child_certificate_reissue_weeks'.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimingConfig {
+    publish_next_hours: u32,
+    publish_valid_days: u32,
+    publish_threshold_hours: u32,
+    child_certificate_valid_weeks: u32,
+    child_certificate_reissue_weeks: u32,
+}
+
+impl TimingConfig {
+    pub fn new(
+        publish_next_hours: u32,
+        publish_valid_days: u32,
+        publish_threshold_hours: u32,
+        child_certificate_valid_weeks: u32,
+        child_certificate_reissue_weeks: u32,
+    ) -> Self {
+        TimingConfig {
+            publish_next_hours,
+            publish_valid_days,
+            publish_threshold_hours,
+            child_certificate_valid_weeks,
+            child_certificate_reissue_weeks,
+        }
+    }
+
+    pub fn publish_next(&self) -> Duration {
+        Duration::hours(i64::from(self.publish_next_hours))
+    }
+
+    pub fn publish_valid(&self) -> Duration {
+        Duration::days(i64::from(self.publish_valid_days))
+    }
+
+    pub fn publish_threshold(&self) -> Duration {
+        Duration::hours(i64::from(self.publish_threshold_hours))
+    }
+
+    pub fn child_certificate_valid(&self) -> Duration {
+        Duration::weeks(i64::from(self.child_certificate_valid_weeks))
+    }
+
+    pub fn child_certificate_reissue(&self) -> Duration {
+        Duration::weeks(i64::from(self.child_certificate_reissue_weeks))
+    }
+
+    /// Verifies that the timing makes sense, i.e. that no time is longer
+    /// than can be used in certificates, that objects are not published
+    /// with a next update time beyond their validity, and that they are
+    /// republished or re-issued before they go stale or expire. Returns the
+    /// reason in case it does not.
+    pub fn verify(&self) -> Result<(), String> {
+        let max = Duration::days(i64::from(TIMING_MAX_YEARS) * 365);
+        let times = [
+            self.publish_next(),
+            self.publish_valid(),
+            self.publish_threshold(),
+            self.child_certificate_valid(),
+            self.child_certificate_reissue(),
+        ];
+
+        if times.iter().any(|time| *time > max) {
+            Err(format!("times must not exceed {} years", TIMING_MAX_YEARS))
+        } else if self.publish_threshold_hours == 0 || self.child_certificate_reissue_weeks == 0 {
+            Err("republish and re-issue thresholds must be at least 1".to_string())
+        } else if self.publish_next() >= self.publish_valid() {
+            Err("publish next update must be before the end of the publish validity".to_string())
+        } else if self.publish_threshold() >= self.publish_next() {
+            Err("publish threshold must be shorter than publish next update".to_string())
+        } else if self.child_certificate_reissue() >= self.child_certificate_valid() {
+            Err(
+                "child certificate re-issue threshold must be shorter than its validity"
+                    .to_string(),
+            )
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        TimingConfig {
+            publish_next_hours: PUBLISH_NEXT_HOURS,
+            publish_valid_days: PUBLISH_VALID_DAYS,
+            publish_threshold_hours: PUBLISH_THRESHOLD_HOURS,
+            child_certificate_valid_weeks: CHILD_CERTIFICATE_VALIDITY_WEEKS,
+            child_certificate_reissue_weeks: CHILD_CERTIFICATE_REISSUE_WEEKS,
+        }
+    }
+}
+
+impl fmt::Display for TimingConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "publish next update: {}h, publish validity: {}d, republish threshold: {}h, \
+             child certificate validity: {}w, child certificate re-issue threshold: {}w",
+            self.publish_next_hours,
+            self.publish_valid_days,
+            self.publish_threshold_hours,
+            self.child_certificate_valid_weeks,
+            self.child_certificate_reissue_weeks
+        )
+    }
+}
+
+//------------ CaTimingInfo ------------------------------------------------
+
+/// The timing used by a CA, and whether this is specific to the CA rather
+/// than the server wide default.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CaTimingInfo {
+    timing: TimingConfig,
+    custom: bool,
+}
+
+impl CaTimingInfo {
+    pub fn new(timing: TimingConfig, custom: bool) -> Self {
+        CaTimingInfo { timing, custom }
+    }
+
+    pub fn timing(&self) -> &TimingConfig {
+        &self.timing
+    }
+
+    pub fn custom(&self) -> bool {
+        self.custom
+    }
+}
+
+impl fmt::Display for CaTimingInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let timing = &self.timing;
+        if self.custom {
+            writeln!(f, "Timing (specific to this CA):")?;
+        } else {
+            writeln!(f, "Timing (server default):")?;
+        }
+        writeln!(
+            f,
+            "  Publish next update (hours): {}",
+            timing.publish_next_hours
+        )?;
+        writeln!(
+            f,
+            "  Publish validity (days): {}",
+            timing.publish_valid_days
+        )?;
+        writeln!(
+            f,
+            "  Republish threshold (hours): {}",
+            timing.publish_threshold_hours
+        )?;
+        writeln!(
+            f,
+            "  Child certificate validity (weeks): {}",
+            timing.child_certificate_valid_weeks
+        )?;
+        writeln!(
+            f,
+            "  Child certificate re-issue threshold (weeks): {}",
+            timing.child_certificate_reissue_weeks
+        )
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_timing() {
+        TimingConfig::default().verify().unwrap();
+        TimingConfig::new(1, 1, 1, 2, 1).verify().unwrap_err();
+        TimingConfig::new(24, 1, 8, 52, 4).verify().unwrap_err();
+        TimingConfig::new(24, 7, 24, 52, 4).verify().unwrap_err();
+        TimingConfig::new(24, 7, 0, 52, 4).verify().unwrap_err();
+        TimingConfig::new(24, 7, 8, 4, 4).verify().unwrap_err();
+        TimingConfig::new(12, 2, 2, 8, 2).verify().unwrap();
+    }
+
+    #[test]
+    fn verify_timing_maximum() {
+        TimingConfig::new(24, 7, 8, 1560, 4).verify().unwrap();
+        TimingConfig::new(24, 7, 8, 1600, 4).verify().unwrap_err();
+
+        let max = u32::MAX;
+        TimingConfig::new(max, 7, 8, 52, 4).verify().unwrap_err();
+        TimingConfig::new(24, max, 8, 52, 4).verify().unwrap_err();
+        TimingConfig::new(24, 7, max, 52, 4).verify().unwrap_err();
+        TimingConfig::new(24, 7, 8, max, 4).verify().unwrap_err();
+        TimingConfig::new(24, 7, 8, 52, max).verify().unwrap_err();
+    }
+}
@@ -0,0 +1,1189 @@
+//! Analysis of the effect of ROA changes on announced routes, and of the
+//! ROA definitions of a CA, using a dump of the BGP announcements seen by
+//! route collectors, e.g. RIPE RIS.
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::str::{from_utf8, FromStr};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::commons::api::{
+    AsNumber, ResourceSet, RoaDefinition, RoaDefinitionUpdates, TypedPrefix,
+};
+use crate::commons::util::file;
+
+// MRT record types and subtypes, and BGP path attributes, used for reading
+// MRT RIB dumps, see RFC 6396 and RFC 8050.
+const MRT_TABLE_DUMP: u16 = 12;
+const MRT_TABLE_DUMP_V2: u16 = 13;
+const MRT_KNOWN_TYPES: [u16; 9] = [11, 12, 13, 16, 17, 32, 33, 48, 49];
+
+const TABLE_DUMP_AFI_IPV4: u16 = 1;
+const TABLE_DUMP_AFI_IPV6: u16 = 2;
+
+const RIB_IPV4_UNICAST: u16 = 2;
+const RIB_IPV6_UNICAST: u16 = 4;
+const RIB_IPV4_UNICAST_ADDPATH: u16 = 8;
+const RIB_IPV6_UNICAST_ADDPATH: u16 = 10;
+
+const ATTR_FLAG_EXTENDED_LENGTH: u8 = 0x10;
+const ATTR_AS_PATH: u8 = 2;
+const ATTR_AS4_PATH: u8 = 17;
+
+const AS_SET: u8 = 1;
+const AS_SEQUENCE: u8 = 2;
+const AS_TRANS: u32 = 23456;
+
+//------------ Announcement --------------------------------------------------
+
+/// A route announcement seen in BGP, i.e. a prefix and its origin ASN.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Announcement {
+    asn: AsNumber,
+    prefix: TypedPrefix,
+}
+
+impl Announcement {
+    pub fn new(asn: AsNumber, prefix: TypedPrefix) -> Self {
+        Announcement { asn, prefix }
+    }
+
+    pub fn asn(&self) -> AsNumber {
+        self.asn
+    }
+
+    pub fn prefix(&self) -> TypedPrefix {
+        self.prefix
+    }
+
+    /// Returns the route origin validation state of this announcement, as
+    /// defined in RFC 6811, given the ROA definitions.
+    pub fn validate(&self, roas: &[RoaDefinition]) -> RouteValidity {
+        let mut covered = false;
+        for roa in roas.iter().filter(|roa| roa.prefix().covers(&self.prefix)) {
+            if roa.asn() == self.asn && self.prefix.addr_len() <= roa.effective_max_length() {
+                return RouteValidity::Valid;
+            }
+            covered = true;
+        }
+
+        if covered {
+            RouteValidity::Invalid
+        } else {
+            RouteValidity::NotFound
+        }
+    }
+
+    fn sort_key(&self) -> (IpAddr, u8, AsNumber) {
+        (self.prefix.ip_addr(), self.prefix.addr_len(), self.asn)
+    }
+}
+
+impl fmt::Display for Announcement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} => {}", self.prefix, self.asn)
+    }
+}
+
+//------------ Announcements -------------------------------------------------
+
+/// The unique announcements found in a BGP dump, ordered by prefix and ASN.
+///
+/// Announcements are (de-)serialized as a JSON array, so that they can be
+/// sent to the API. When deserializing they are sorted and de-duplicated.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Announcements(Vec<Announcement>);
+
+impl From<Vec<Announcement>> for Announcements {
+    fn from(mut announcements: Vec<Announcement>) -> Self {
+        announcements.sort_by_key(Announcement::sort_key);
+        announcements.dedup();
+        Announcements(announcements)
+    }
+}
+
+impl Serialize for Announcements {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Announcements {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Announcement>::deserialize(deserializer).map(Announcements::from)
+    }
+}
+
+impl Announcements {
+    /// Reads the announcements from a dump file. This can be an MRT RIB dump
+    /// (see `from_mrt`), or a text dump (see `from_str`).
+    pub fn read(path: &PathBuf) -> Result<Self, BgpDumpError> {
+        let bytes = file::read(path).map_err(|e| BgpDumpError::Io(e.to_string()))?;
+        if Self::is_mrt(&bytes) {
+            Self::from_mrt(&bytes)
+        } else {
+            let dump = from_utf8(&bytes).map_err(|_| BgpDumpError::NotText)?;
+            Self::from_str(dump)
+        }
+    }
+
+    /// Returns true if the dump starts with an MRT record header. Text dumps
+    /// never do, because the record type would consist of printable
+    /// characters, and all MRT types are below 0x100.
+    fn is_mrt(bytes: &[u8]) -> bool {
+        bytes.len() >= 12
+            && MRT_KNOWN_TYPES.contains(&(u16::from(bytes[4]) << 8 | u16::from(bytes[5])))
+    }
+
+    /// Parses an uncompressed MRT file, taking the announcements from the
+    /// RIB entries in TABLE_DUMP and TABLE_DUMP_V2 records, as found in e.g.
+    /// the RIPE RIS 'bview' files. Other records, like BGP4MP update
+    /// messages, are skipped. Like for text dumps, routes originated by an
+    /// AS_SET are skipped.
+    pub fn from_mrt(bytes: &[u8]) -> Result<Self, BgpDumpError> {
+        let mut announcements = HashSet::new();
+
+        let mut reader = MrtReader::new(bytes);
+        while !reader.is_empty() {
+            let offset = reader.pos;
+            let err = |msg| BgpDumpError::Mrt(offset, msg);
+
+            reader.take(4).map_err(err)?; // timestamp
+            let typ = reader.u16().map_err(err)?;
+            let subtype = reader.u16().map_err(err)?;
+            let len = reader.u32().map_err(err)? as usize;
+            let mut record = MrtReader::new(reader.take(len).map_err(err)?);
+
+            Self::parse_mrt_record(typ, subtype, &mut record, &mut announcements).map_err(err)?;
+        }
+
+        let announcements: Vec<Announcement> = announcements.into_iter().collect();
+        Ok(Announcements::from(announcements))
+    }
+
+    fn parse_mrt_record(
+        typ: u16,
+        subtype: u16,
+        record: &mut MrtReader,
+        announcements: &mut HashSet<Announcement>,
+    ) -> Result<(), &'static str> {
+        match (typ, subtype) {
+            (MRT_TABLE_DUMP, TABLE_DUMP_AFI_IPV4) | (MRT_TABLE_DUMP, TABLE_DUMP_AFI_IPV6) => {
+                let v6 = subtype == TABLE_DUMP_AFI_IPV6;
+                let addr_len = if v6 { 16 } else { 4 };
+
+                record.take(4)?; // view and sequence number
+                let addr = record.take(addr_len)?;
+                let prefix = Self::mrt_prefix(v6, addr, record.u8()?)?;
+                record.take(1 + 4 + addr_len + 2)?; // status, time, peer address and AS
+                let attrs_len = record.u16()? as usize;
+                let attrs = record.take(attrs_len)?;
+
+                if let Some(asn) = Self::mrt_origin(attrs, 2)? {
+                    announcements.insert(Announcement::new(asn, prefix));
+                }
+            }
+            (MRT_TABLE_DUMP_V2, RIB_IPV4_UNICAST)
+            | (MRT_TABLE_DUMP_V2, RIB_IPV6_UNICAST)
+            | (MRT_TABLE_DUMP_V2, RIB_IPV4_UNICAST_ADDPATH)
+            | (MRT_TABLE_DUMP_V2, RIB_IPV6_UNICAST_ADDPATH) => {
+                let v6 = subtype == RIB_IPV6_UNICAST || subtype == RIB_IPV6_UNICAST_ADDPATH;
+                let add_path = subtype >= RIB_IPV4_UNICAST_ADDPATH;
+
+                record.take(4)?; // sequence number
+                let len = record.u8()?;
+                let addr = record.take(usize::from(len / 8 + u8::from(len % 8 != 0)))?;
+                let prefix = Self::mrt_prefix(v6, addr, len)?;
+
+                for _ in 0..record.u16()? {
+                    record.take(2 + 4)?; // peer index, originated time
+                    if add_path {
+                        record.take(4)?; // path identifier
+                    }
+                    let attrs_len = record.u16()? as usize;
+                    let attrs = record.take(attrs_len)?;
+
+                    if let Some(asn) = Self::mrt_origin(attrs, 4)? {
+                        announcements.insert(Announcement::new(asn, prefix));
+                    }
+                }
+            }
+            _ => {} // peer index tables, BGP4MP messages, etc.
+        }
+        Ok(())
+    }
+
+    fn mrt_prefix(v6: bool, addr: &[u8], len: u8) -> Result<TypedPrefix, &'static str> {
+        let max_len = if v6 { 128 } else { 32 };
+        if len > max_len || addr.len() > 16 {
+            return Err("invalid prefix length");
+        }
+
+        // Clear any bits after the prefix length
+        let mut octets = [0_u8; 16];
+        octets[..addr.len()].copy_from_slice(addr);
+        for (i, octet) in octets.iter_mut().enumerate() {
+            let keep = (len as usize).saturating_sub(i * 8).min(8) as u32;
+            *octet &= !0xff_u8.checked_shr(keep).unwrap_or(0);
+        }
+
+        let prefix = if v6 {
+            format!("{}/{}", Ipv6Addr::from(octets), len)
+        } else {
+            let addr = Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]);
+            format!("{}/{}", addr, len)
+        };
+        TypedPrefix::from_str(&prefix).map_err(|_| "invalid prefix")
+    }
+
+    /// Returns the origin ASN of the route from its path attributes, or None
+    /// if there is no AS path, or if the route is originated by an AS_SET.
+    /// Records with 2 byte ASNs use the AS4_PATH, if the origin in the
+    /// AS_PATH is AS_TRANS.
+    fn mrt_origin(attrs: &[u8], asn_len: usize) -> Result<Option<AsNumber>, &'static str> {
+        let mut as_path = None;
+        let mut as4_path = None;
+
+        let mut reader = MrtReader::new(attrs);
+        while !reader.is_empty() {
+            let flags = reader.u8()?;
+            let typ = reader.u8()?;
+            let len = if flags & ATTR_FLAG_EXTENDED_LENGTH != 0 {
+                reader.u16()? as usize
+            } else {
+                reader.u8()? as usize
+            };
+            let value = reader.take(len)?;
+
+            match typ {
+                ATTR_AS_PATH => as_path = Some(value),
+                ATTR_AS4_PATH => as4_path = Some(value),
+                _ => {}
+            }
+        }
+
+        let origin = match as_path {
+            Some(path) => Self::mrt_path_origin(path, asn_len)?,
+            None => return Ok(None),
+        };
+
+        match (origin, as4_path) {
+            (Some(asn), Some(path)) if asn_len == 2 && asn == AsNumber::new(AS_TRANS) => {
+                Self::mrt_path_origin(path, 4)
+            }
+            _ => Ok(origin),
+        }
+    }
+
+    fn mrt_path_origin(path: &[u8], asn_len: usize) -> Result<Option<AsNumber>, &'static str> {
+        let mut origin = None;
+
+        let mut reader = MrtReader::new(path);
+        while !reader.is_empty() {
+            let segment_type = reader.u8()?;
+            let count = reader.u8()? as usize;
+            let asns = reader.take(count * asn_len)?;
+
+            match segment_type {
+                AS_SEQUENCE => {
+                    if let Some(asn) = asns.chunks(asn_len).last() {
+                        let asn = asn.iter().fold(0, |acc, b| acc << 8 | u32::from(*b));
+                        origin = Some(AsNumber::new(asn));
+                    }
+                }
+                AS_SET => origin = None,
+                _ => {} // confederation segments
+            }
+        }
+
+        Ok(origin)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Announcement> {
+        self.0.iter()
+    }
+
+    /// Parses a single line, returns None for lines without an announcement
+    /// that can be validated.
+    fn parse_line(line: &str) -> Result<Option<Announcement>, ()> {
+        let (prefix, origin) = if line.contains('|') {
+            // bgpdump -m: type|time|subtype|peer ip|peer asn|prefix|as path|..
+            let fields: Vec<&str> = line.split('|').collect();
+            match fields.get(2) {
+                Some(&"B") | Some(&"A") if fields.len() >= 7 => {}
+                _ => return Ok(None), // withdrawals, state changes
+            }
+            let origin = fields[6].split_whitespace().last().ok_or(())?;
+            (fields[5], origin)
+        } else {
+            // "<origin> <prefix> [<peers>]" or "<prefix> <origin>"
+            let mut tokens = line.split_whitespace();
+            let first = tokens.next().ok_or(())?;
+            let second = tokens.next().ok_or(())?;
+            if first.contains('/') {
+                (first, second)
+            } else {
+                (second, first)
+            }
+        };
+
+        // Routes originated by an AS_SET cannot be valid (RFC 6811), and
+        // ROA changes will not affect them.
+        if origin.starts_with('{') {
+            return Ok(None);
+        }
+
+        let origin = origin.trim_start_matches("AS").trim_start_matches("as");
+        let asn = AsNumber::from_str(origin).map_err(|_| ())?;
+        let prefix = TypedPrefix::from_str(prefix).map_err(|_| ())?;
+
+        Ok(Some(Announcement::new(asn, prefix)))
+    }
+}
+
+impl FromStr for Announcements {
+    type Err = BgpDumpError;
+
+    /// Parses a text dump with one announcement per line, in any of the
+    /// following formats:
+    ///
+    ///   RIS whois dumps:  "64496  192.168.0.0/16  250"
+    ///   bgpdump -m:       "TABLE_DUMP2|1583020800|B|192.0.2.1|64511|192.168.0.0/16|64511 64496|IGP|.."
+    ///   simple:           "192.168.0.0/16 64496"
+    ///
+    /// Empty lines, and lines starting with '%' or '#' are ignored. MRT files
+    /// can be read directly, see `from_mrt`.
+    fn from_str(dump: &str) -> Result<Self, Self::Err> {
+        let mut announcements = HashSet::new();
+
+        for (nr, line) in dump.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('%') || line.starts_with('#') {
+                continue;
+            }
+            match Self::parse_line(line) {
+                Ok(Some(announcement)) => {
+                    announcements.insert(announcement);
+                }
+                Ok(None) => {}
+                Err(_) => return Err(BgpDumpError::Line(nr + 1, line.to_string())),
+            }
+        }
+
+        let announcements: Vec<Announcement> = announcements.into_iter().collect();
+        Ok(Announcements::from(announcements))
+    }
+}
+
+//------------ MrtReader -----------------------------------------------------
+
+/// Reads the fields of MRT records, and of the BGP attributes in them.
+struct MrtReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> MrtReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        MrtReader { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        if self.bytes.len() - self.pos < len {
+            return Err("unexpected end of data");
+        }
+        let res = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(res)
+    }
+
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, &'static str> {
+        let bytes = self.take(2)?;
+        Ok(u16::from(bytes[0]) << 8 | u16::from(bytes[1]))
+    }
+
+    fn u32(&mut self) -> Result<u32, &'static str> {
+        let bytes = self.take(4)?;
+        Ok(bytes.iter().fold(0, |acc, b| acc << 8 | u32::from(*b)))
+    }
+}
+
+//------------ AnnouncementsCache --------------------------------------------
+
+/// Keeps the parsed announcements of a BGP dump file, so that the file is
+/// only read and parsed again when its modification time changes.
+#[derive(Debug)]
+pub struct AnnouncementsCache {
+    path: PathBuf,
+    cached: RwLock<Option<(SystemTime, Arc<Announcements>)>>,
+}
+
+impl AnnouncementsCache {
+    pub fn new(path: PathBuf) -> Self {
+        AnnouncementsCache {
+            path,
+            cached: RwLock::new(None),
+        }
+    }
+
+    /// Returns the announcements in the dump file, reading it only if it
+    /// was not read before, or if it was modified since.
+    pub fn get(&self) -> Result<Arc<Announcements>, BgpDumpError> {
+        let modified = fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .map_err(|e| BgpDumpError::Io(e.to_string()))?;
+
+        if let Some((time, announcements)) = self.cached.read().unwrap().as_ref() {
+            if time == &modified {
+                return Ok(announcements.clone());
+            }
+        }
+
+        let announcements = Arc::new(Announcements::read(&self.path)?);
+        *self.cached.write().unwrap() = Some((modified, announcements.clone()));
+        Ok(announcements)
+    }
+}
+
+//------------ RouteValidity -------------------------------------------------
+
+/// The route origin validation state of an announcement, see RFC 6811.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteValidity {
+    Valid,
+    Invalid,
+    NotFound,
+}
+
+impl fmt::Display for RouteValidity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RouteValidity::Valid => write!(f, "valid"),
+            RouteValidity::Invalid => write!(f, "invalid"),
+            RouteValidity::NotFound => write!(f, "not found"),
+        }
+    }
+}
+
+//------------ ValidityChange ------------------------------------------------
+
+/// An announcement of which the validation state would change.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ValidityChange {
+    announcement: Announcement,
+    before: RouteValidity,
+    after: RouteValidity,
+}
+
+impl ValidityChange {
+    pub fn announcement(&self) -> &Announcement {
+        &self.announcement
+    }
+
+    pub fn before(&self) -> RouteValidity {
+        self.before
+    }
+
+    pub fn after(&self) -> RouteValidity {
+        self.after
+    }
+}
+
+impl fmt::Display for ValidityChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.announcement, self.before, self.after
+        )
+    }
+}
+
+//------------ RoaDefinitionImpact -------------------------------------------
+
+/// The announcements that change validation state because of an added, or
+/// removed, ROA definition.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RoaDefinitionImpact {
+    definition: RoaDefinition,
+    changes: Vec<ValidityChange>,
+}
+
+impl RoaDefinitionImpact {
+    pub fn definition(&self) -> &RoaDefinition {
+        &self.definition
+    }
+
+    pub fn changes(&self) -> &[ValidityChange] {
+        &self.changes
+    }
+}
+
+//------------ RoaUpdatesImpact ----------------------------------------------
+
+/// The impact of ROA definition updates on the announcements in a BGP dump.
+///
+/// Note that validation states are determined using the ROA definitions of
+/// the CA only. Announcements can be covered by ROAs of other CAs as well.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RoaUpdatesImpact {
+    announcements: usize,
+    added: Vec<RoaDefinitionImpact>,
+    removed: Vec<RoaDefinitionImpact>,
+}
+
+impl RoaUpdatesImpact {
+    /// Determines which announcements would change validation state if the
+    /// updates were applied to the current ROA definitions. Each change is
+    /// reported for every added or removed definition covering the prefix.
+    pub fn analyse(
+        current: &[RoaDefinition],
+        updates: &RoaDefinitionUpdates,
+        announcements: &Announcements,
+    ) -> Self {
+        let mut desired: Vec<RoaDefinition> = current
+            .iter()
+            .filter(|def| !updates.removed().contains(def))
+            .cloned()
+            .collect();
+        for def in updates.added() {
+            if !desired.contains(def) {
+                desired.push(*def);
+            }
+        }
+
+        let changes: Vec<ValidityChange> = announcements
+            .iter()
+            .filter(|ann| {
+                updates
+                    .added()
+                    .iter()
+                    .chain(updates.removed().iter())
+                    .any(|def| def.prefix().covers(&ann.prefix()))
+            })
+            .filter_map(|ann| {
+                let before = ann.validate(current);
+                let after = ann.validate(&desired);
+                if before != after {
+                    Some(ValidityChange {
+                        announcement: *ann,
+                        before,
+                        after,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let impact = |definitions: &HashSet<RoaDefinition>| {
+            let mut impact: Vec<RoaDefinitionImpact> = definitions
+                .iter()
+                .map(|def| RoaDefinitionImpact {
+                    definition: *def,
+                    changes: changes
+                        .iter()
+                        .filter(|change| def.prefix().covers(&change.announcement.prefix))
+                        .cloned()
+                        .collect(),
+                })
+                .collect();
+            impact.sort_by_key(|i| {
+                let def = i.definition;
+                (def.prefix().ip_addr(), def.prefix().addr_len(), def.asn())
+            });
+            impact
+        };
+
+        RoaUpdatesImpact {
+            announcements: announcements.len(),
+            added: impact(updates.added()),
+            removed: impact(updates.removed()),
+        }
+    }
+
+    /// The number of announcements in the BGP dump.
+    pub fn announcements(&self) -> usize {
+        self.announcements
+    }
+
+    pub fn added(&self) -> &[RoaDefinitionImpact] {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &[RoaDefinitionImpact] {
+        &self.removed
+    }
+}
+
+impl fmt::Display for RoaUpdatesImpact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Announcements in BGP dump: {}", self.announcements)?;
+        for (kind, impact) in self
+            .added
+            .iter()
+            .map(|i| ("A", i))
+            .chain(self.removed.iter().map(|i| ("R", i)))
+        {
+            writeln!(f, "{}: {}", kind, impact.definition)?;
+            if impact.changes.is_empty() {
+                writeln!(f, "   no announcements affected")?;
+            }
+            for change in &impact.changes {
+                writeln!(f, "   {}", change)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//------------ RedundantRoa --------------------------------------------------
+
+/// A ROA definition that is redundant, because another definition for the
+/// same ASN covers its prefix with an equal or wider max length.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RedundantRoa {
+    definition: RoaDefinition,
+    covered_by: RoaDefinition,
+}
+
+impl RedundantRoa {
+    pub fn definition(&self) -> &RoaDefinition {
+        &self.definition
+    }
+
+    pub fn covered_by(&self) -> &RoaDefinition {
+        &self.covered_by
+    }
+}
+
+//------------ PermissiveRoa -------------------------------------------------
+
+/// A ROA definition with a max length that allows more-specific prefixes
+/// which are not announced, making them available for forged-origin
+/// hijacks (RFC 9319). The announced prefixes are those seen in the BGP dump
+/// for the ASN within the range of the definition. ROAs without max length
+/// for each of these would be safe replacements.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PermissiveRoa {
+    definition: RoaDefinition,
+    announced: Vec<TypedPrefix>,
+}
+
+impl PermissiveRoa {
+    pub fn definition(&self) -> &RoaDefinition {
+        &self.definition
+    }
+
+    pub fn announced(&self) -> &[TypedPrefix] {
+        &self.announced
+    }
+}
+
+//------------ RoaAnalysis ---------------------------------------------------
+
+/// The analysis of the ROA definitions of a CA. Flags definitions that are
+/// redundant, that have an overly permissive max length, or that are for
+/// prefixes no longer held by the CA. If a BGP dump is available, then this
+/// also suggests ROA definitions for announcements that are not covered by
+/// any definition, for prefixes held by the CA.
+///
+/// Note that, like for the ROA updates impact, only the ROA definitions of
+/// the CA itself are used. Without a BGP dump, all definitions with a max
+/// length are reported as permissive.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RoaAnalysis {
+    announcements: Option<usize>,
+    redundant: Vec<RedundantRoa>,
+    permissive: Vec<PermissiveRoa>,
+    not_held: Vec<RoaDefinition>,
+    suggested: Vec<RoaDefinition>,
+}
+
+impl RoaAnalysis {
+    pub fn analyse(
+        definitions: &[RoaDefinition],
+        resources: &ResourceSet,
+        announcements: Option<&Announcements>,
+    ) -> Self {
+        let mut definitions = definitions.to_vec();
+        definitions.sort_by_key(Self::sort_key);
+
+        // Definitions which are equivalent, e.g. '10.0.0.0/24-24' and
+        // '10.0.0.0/24', cover each other. Only the later one in sort order
+        // is flagged for these.
+        let redundant = definitions
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, def)| {
+                definitions
+                    .iter()
+                    .enumerate()
+                    .find(|(other_idx, other)| {
+                        other_idx != &idx
+                            && other.asn() == def.asn()
+                            && other.prefix().covers(&def.prefix())
+                            && other.effective_max_length() >= def.effective_max_length()
+                            && (other.prefix() != def.prefix()
+                                || other.effective_max_length() > def.effective_max_length()
+                                || other_idx < &idx)
+                    })
+                    .map(|(_, other)| RedundantRoa {
+                        definition: *def,
+                        covered_by: *other,
+                    })
+            })
+            .collect();
+
+        let permissive = definitions
+            .iter()
+            .filter(|def| def.effective_max_length() > def.prefix().addr_len())
+            .filter_map(|def| {
+                let announced: Vec<TypedPrefix> = announcements
+                    .into_iter()
+                    .flat_map(Announcements::iter)
+                    .filter(|ann| {
+                        ann.asn() == def.asn()
+                            && def.prefix().covers(&ann.prefix())
+                            && ann.prefix().addr_len() <= def.effective_max_length()
+                    })
+                    .map(Announcement::prefix)
+                    .collect();
+
+                // The number of prefixes allowed by the max length doubles
+                // with every bit, the ROA is fine if all are announced.
+                let span = u32::from(def.effective_max_length() - def.prefix().addr_len());
+                let allowed = 2u64.checked_pow(span + 1).map(|nr| nr - 1);
+                if allowed == Some(announced.len() as u64) {
+                    None
+                } else {
+                    Some(PermissiveRoa {
+                        definition: *def,
+                        announced,
+                    })
+                }
+            })
+            .collect();
+
+        let not_held = definitions
+            .iter()
+            .filter(|def| !resources.contains(&ResourceSet::from(def.prefix())))
+            .cloned()
+            .collect();
+
+        let suggested = announcements
+            .into_iter()
+            .flat_map(Announcements::iter)
+            .filter(|ann| resources.contains(&ResourceSet::from(ann.prefix())))
+            .filter(|ann| ann.validate(&definitions) == RouteValidity::NotFound)
+            .map(|ann| RoaDefinition::new(ann.asn(), ann.prefix(), None))
+            .collect();
+
+        RoaAnalysis {
+            announcements: announcements.map(Announcements::len),
+            redundant,
+            permissive,
+            not_held,
+            suggested,
+        }
+    }
+
+    fn sort_key(def: &RoaDefinition) -> (IpAddr, u8, AsNumber, u8) {
+        (
+            def.prefix().ip_addr(),
+            def.prefix().addr_len(),
+            def.asn(),
+            def.effective_max_length(),
+        )
+    }
+
+    /// The number of announcements in the BGP dump, if available.
+    pub fn announcements(&self) -> Option<usize> {
+        self.announcements
+    }
+
+    pub fn redundant(&self) -> &[RedundantRoa] {
+        &self.redundant
+    }
+
+    pub fn permissive(&self) -> &[PermissiveRoa] {
+        &self.permissive
+    }
+
+    pub fn not_held(&self) -> &[RoaDefinition] {
+        &self.not_held
+    }
+
+    pub fn suggested(&self) -> &[RoaDefinition] {
+        &self.suggested
+    }
+
+    /// Returns true if nothing was flagged, and nothing is suggested.
+    pub fn is_clean(&self) -> bool {
+        self.redundant.is_empty()
+            && self.permissive.is_empty()
+            && self.not_held.is_empty()
+            && self.suggested.is_empty()
+    }
+}
+
+impl fmt::Display for RoaAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.announcements {
+            Some(nr) => writeln!(f, "Announcements in BGP dump: {}", nr)?,
+            None => writeln!(f, "No BGP dump configured, no announcements analysed")?,
+        }
+        if self.is_clean() {
+            writeln!(f, "No issues found")?;
+        }
+        for redundant in &self.redundant {
+            writeln!(
+                f,
+                "Redundant: {}, covered by: {}",
+                redundant.definition, redundant.covered_by
+            )?;
+        }
+        for permissive in &self.permissive {
+            writeln!(f, "Permissive max length: {}", permissive.definition)?;
+            for prefix in &permissive.announced {
+                writeln!(f, "   announced: {}", prefix)?;
+            }
+        }
+        for definition in &self.not_held {
+            writeln!(f, "Prefix not held: {}", definition)?;
+        }
+        for definition in &self.suggested {
+            writeln!(f, "Suggested for uncovered announcement: {}", definition)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ BgpDumpError --------------------------------------------------
+
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+pub enum BgpDumpError {
+    #[display(fmt = "Cannot read BGP dump: {}", _0)]
+    Io(String),
+
+    #[display(fmt = "BGP dump is neither a text file, nor an uncompressed MRT file")]
+    NotText,
+
+    #[display(fmt = "Cannot parse MRT record at offset {} of BGP dump: {}", _0, _1)]
+    Mrt(usize, &'static str),
+
+    #[display(fmt = "Cannot parse line {} of BGP dump: {}", _0, _1)]
+    Line(usize, String),
+}
+
+impl std::error::Error for BgpDumpError {}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def(s: &str) -> RoaDefinition {
+        RoaDefinition::from_str(s).unwrap()
+    }
+
+    fn ann(prefix: &str, asn: u32) -> Announcement {
+        Announcement::new(AsNumber::new(asn), TypedPrefix::from_str(prefix).unwrap())
+    }
+
+    #[test]
+    fn parse_dump_formats() {
+        let dump = concat!(
+            "% RIS whois dump\n",
+            "64496\t192.168.0.0/16\t250\n",
+            "{64496,64497}\t192.168.4.0/24\t2\n",
+            "TABLE_DUMP2|1583020800|B|192.0.2.1|64511|192.168.1.0/24|64511 64497|IGP|192.0.2.1|0|0||NAG||\n",
+            "BGP4MP|1583020800|A|192.0.2.1|64511|2001:db8::/32|64511 AS64496|IGP|192.0.2.1|0|0||NAG||\n",
+            "BGP4MP|1583020800|W|192.0.2.1|64511|192.168.2.0/24\n",
+            "BGP4MP|1583020800|STATE|192.0.2.1|64511|1|2\n",
+            "\n",
+            "# simple\n",
+            "192.168.0.0/16 64496\n",
+        );
+
+        let announcements = Announcements::from_str(dump).unwrap();
+        let expected = vec![
+            ann("192.168.0.0/16", 64496),
+            ann("192.168.1.0/24", 64497),
+            ann("2001:db8::/32", 64496),
+        ];
+        assert_eq!(Announcements(expected), announcements);
+
+        assert_eq!(
+            Err(BgpDumpError::Line(2, "192.168.0.0/16".to_string())),
+            Announcements::from_str("# header\n192.168.0.0/16\n")
+        );
+    }
+
+    fn mrt_record(typ: u16, subtype: u16, body: &[u8]) -> Vec<u8> {
+        let mut record = vec![0x5e, 0x5b, 0, 0]; // timestamp
+        record.extend_from_slice(&typ.to_be_bytes());
+        record.extend_from_slice(&subtype.to_be_bytes());
+        record.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        record.extend_from_slice(body);
+        record
+    }
+
+    fn path_attr(typ: u8, segments: &[(u8, Vec<u32>)], asn_len: usize) -> Vec<u8> {
+        let mut value = vec![];
+        for (segment_type, asns) in segments {
+            value.push(*segment_type);
+            value.push(asns.len() as u8);
+            for asn in asns {
+                value.extend_from_slice(&asn.to_be_bytes()[4 - asn_len..]);
+            }
+        }
+
+        let mut attr = vec![0x40 | ATTR_FLAG_EXTENDED_LENGTH, typ];
+        attr.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        attr.extend(value);
+        attr
+    }
+
+    fn rib_entry(attrs: &[u8]) -> Vec<u8> {
+        let mut entry = vec![0, 0, 0, 0, 0, 0]; // peer index, originated time
+        entry.extend_from_slice(&(attrs.len() as u16).to_be_bytes());
+        entry.extend_from_slice(attrs);
+        entry
+    }
+
+    #[test]
+    fn parse_mrt_dump() {
+        // peer index table, skipped
+        let mut dump = mrt_record(MRT_TABLE_DUMP_V2, 1, &[0; 10]);
+
+        // 192.168.0.0/16 seen by two peers, and once originated by an AS_SET
+        let mut body = vec![0, 0, 0, 1, 16, 192, 168, 0, 3];
+        for segments in &[
+            vec![(AS_SEQUENCE, vec![64511, 64496])],
+            vec![(AS_SEQUENCE, vec![64510, 64496])],
+            vec![(AS_SEQUENCE, vec![64511]), (AS_SET, vec![64497, 64498])],
+        ] {
+            body.extend(rib_entry(&path_attr(ATTR_AS_PATH, segments, 4)));
+        }
+        dump.extend(mrt_record(MRT_TABLE_DUMP_V2, RIB_IPV4_UNICAST, &body));
+
+        let mut body = vec![0, 0, 0, 2, 32, 0x20, 0x01, 0x0d, 0xb8, 0, 1];
+        let segments = vec![(AS_SEQUENCE, vec![64511, 4_200_000_000])];
+        body.extend(rib_entry(&path_attr(ATTR_AS_PATH, &segments, 4)));
+        dump.extend(mrt_record(MRT_TABLE_DUMP_V2, RIB_IPV6_UNICAST, &body));
+
+        // legacy table dump with 2 byte ASNs, the origin is in the AS4_PATH
+        let mut body = vec![
+            0, 0, 0, 3, 10, 0, 0, 0, 8, 1, 0, 0, 0, 0, 192, 0, 2, 1, 0xfb, 0xff,
+        ];
+        let segments = vec![(AS_SEQUENCE, vec![64511, AS_TRANS])];
+        let mut attrs = path_attr(ATTR_AS_PATH, &segments, 2);
+        let segments = vec![(AS_SEQUENCE, vec![64511, 4_200_000_001])];
+        attrs.extend(path_attr(ATTR_AS4_PATH, &segments, 4));
+        body.extend_from_slice(&(attrs.len() as u16).to_be_bytes());
+        body.extend(attrs);
+        dump.extend(mrt_record(MRT_TABLE_DUMP, TABLE_DUMP_AFI_IPV4, &body));
+
+        assert!(Announcements::is_mrt(&dump));
+        assert!(!Announcements::is_mrt(b"64496\t192.168.0.0/16\t250\n"));
+
+        let announcements = Announcements::from_mrt(&dump).unwrap();
+        let expected = vec![
+            ann("10.0.0.0/8", 4_200_000_001),
+            ann("192.168.0.0/16", 64496),
+            ann("2001:db8::/32", 4_200_000_000),
+        ];
+        assert_eq!(Announcements(expected), announcements);
+
+        assert_eq!(
+            Err(BgpDumpError::Mrt(0, "unexpected end of data")),
+            Announcements::from_mrt(&dump[..20])
+        );
+    }
+
+    #[test]
+    fn deserialize_announcements_sorts_and_dedups() {
+        let json = concat!(
+            "[{\"asn\":64497,\"prefix\":\"192.168.1.0/24\"},",
+            "{\"asn\":64496,\"prefix\":\"192.168.0.0/16\"},",
+            "{\"asn\":64497,\"prefix\":\"192.168.1.0/24\"}]"
+        );
+        let announcements: Announcements = serde_json::from_str(json).unwrap();
+        let expected = vec![ann("192.168.0.0/16", 64496), ann("192.168.1.0/24", 64497)];
+        assert_eq!(Announcements(expected), announcements);
+    }
+
+    #[test]
+    fn validate_announcements() {
+        let roas = vec![def("192.168.0.0/16-20 => 64496"), def("10.0.0.0/8 => 0")];
+
+        assert_eq!(
+            RouteValidity::Valid,
+            ann("192.168.0.0/16", 64496).validate(&roas)
+        );
+        assert_eq!(
+            RouteValidity::Valid,
+            ann("192.168.0.0/20", 64496).validate(&roas)
+        );
+        assert_eq!(
+            RouteValidity::Invalid,
+            ann("192.168.0.0/24", 64496).validate(&roas)
+        );
+        assert_eq!(
+            RouteValidity::Invalid,
+            ann("192.168.0.0/16", 64497).validate(&roas)
+        );
+        assert_eq!(
+            RouteValidity::Invalid,
+            ann("10.1.0.0/16", 64496).validate(&roas)
+        );
+        assert_eq!(
+            RouteValidity::NotFound,
+            ann("172.16.0.0/12", 64496).validate(&roas)
+        );
+    }
+
+    #[test]
+    fn analyse_roa_definitions() {
+        let definitions = vec![
+            def("192.168.0.0/16-24 => 64496"),
+            def("192.168.1.0/24 => 64496"),
+            def("192.168.0.0/23-24 => 64497"),
+            def("192.168.0.0/24 => 64497"),
+            def("10.0.0.0/8 => 64496"),
+        ];
+        let resources = ResourceSet::from_strs("", "172.16.0.0/12, 192.168.0.0/16", "").unwrap();
+
+        let announcements = Announcements::from_str(concat!(
+            "64497 192.168.0.0/23\n",
+            "64497 192.168.0.0/24\n",
+            "64497 192.168.1.0/24\n",
+            "64496 192.168.2.0/24\n",
+            "64498 192.168.128.0/17\n",
+            "64498 172.16.0.0/12\n",
+            "64498 198.51.100.0/24\n",
+        ))
+        .unwrap();
+
+        let analysis = RoaAnalysis::analyse(&definitions, &resources, Some(&announcements));
+        assert_eq!(Some(7), analysis.announcements());
+
+        let redundant: Vec<(RoaDefinition, RoaDefinition)> = analysis
+            .redundant()
+            .iter()
+            .map(|r| (*r.definition(), *r.covered_by()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    def("192.168.0.0/24 => 64497"),
+                    def("192.168.0.0/23-24 => 64497")
+                ),
+                (
+                    def("192.168.1.0/24 => 64496"),
+                    def("192.168.0.0/16-24 => 64496")
+                ),
+            ],
+            redundant
+        );
+
+        // The /23-24 for 64497 is fine, as all three prefixes are announced
+        assert_eq!(1, analysis.permissive().len());
+        assert_eq!(
+            &def("192.168.0.0/16-24 => 64496"),
+            analysis.permissive()[0].definition()
+        );
+        assert_eq!(
+            &[TypedPrefix::from_str("192.168.2.0/24").unwrap()],
+            analysis.permissive()[0].announced()
+        );
+
+        assert_eq!(&[def("10.0.0.0/8 => 64496")], analysis.not_held());
+        // Not suggested: the invalid /17, and the prefix not held
+        assert_eq!(&[def("172.16.0.0/12 => 64498")], analysis.suggested());
+
+        let analysis = RoaAnalysis::analyse(&definitions, &resources, None);
+        assert_eq!(None, analysis.announcements());
+        assert_eq!(2, analysis.permissive().len());
+        assert!(analysis.suggested().is_empty());
+
+        // Equivalent definitions do not both flag each other as redundant
+        let definitions = vec![def("10.0.0.0/24-24 => 64496"), def("10.0.0.0/24 => 64496")];
+        let analysis = RoaAnalysis::analyse(&definitions, &resources, None);
+        assert_eq!(1, analysis.redundant().len());
+        assert_eq!(
+            &def("10.0.0.0/24 => 64496"),
+            analysis.redundant()[0].definition()
+        );
+        assert_eq!(
+            &def("10.0.0.0/24-24 => 64496"),
+            analysis.redundant()[0].covered_by()
+        );
+    }
+
+    #[test]
+    fn analyse_updates_impact() {
+        let current = vec![def("192.168.0.0/16 => 64496")];
+        let updates = RoaDefinitionUpdates::from_str(concat!(
+            "A: 192.168.1.0/24 => 64497\n",
+            "A: 10.0.0.0/8 => 64496\n",
+            "R: 192.168.0.0/16 => 64496\n",
+        ))
+        .unwrap();
+
+        let announcements = Announcements::from_str(concat!(
+            "64496 192.168.0.0/16\n",
+            "64497 192.168.1.0/24\n",
+            "64496 172.16.0.0/12\n",
+        ))
+        .unwrap();
+
+        let impact = RoaUpdatesImpact::analyse(&current, &updates, &announcements);
+        assert_eq!(3, impact.announcements());
+
+        let changes = |impact: &RoaDefinitionImpact| -> Vec<String> {
+            impact.changes().iter().map(ToString::to_string).collect()
+        };
+
+        assert_eq!(2, impact.added().len());
+        assert_eq!(&def("10.0.0.0/8 => 64496"), impact.added()[0].definition());
+        assert!(impact.added()[0].changes().is_empty());
+        assert_eq!(
+            vec!["192.168.1.0/24 => 64497: invalid -> valid"],
+            changes(&impact.added()[1])
+        );
+
+        assert_eq!(1, impact.removed().len());
+        assert_eq!(
+            vec![
+                "192.168.0.0/16 => 64496: valid -> not found",
+                "192.168.1.0/24 => 64497: invalid -> valid",
+            ],
+            changes(&impact.removed()[0])
+        );
+    }
+}
@@ -11,9 +11,10 @@ use rpki::x509::ValidationError;
 
 use crate::commons::api::rrdp::PublicationDeltaError;
 use crate::commons::api::{
-    ChildHandle, ErrorResponse, Handle, ParentHandle, PublisherHandle, ResourceClassName,
-    ResourceSetError,
+    AsNumber, AuthorizationFmtError, BgpSecAsnKey, ChildHandle, ErrorResponse, Handle,
+    ParentHandle, PublisherHandle, ResourceClassName, ResourceSet, ResourceSetError,
 };
+use crate::commons::bgp::BgpDumpError;
 use crate::commons::eventsourcing::AggregateStoreError;
 use crate::commons::remote::rfc6492;
 use crate::commons::remote::rfc8181;
@@ -54,6 +55,30 @@ pub enum Error {
     #[display(fmt = "Unknown resource")]
     ApiUnknownResource,
 
+    //-----------------------------------------------------------------
+    // API Token Issues
+    //-----------------------------------------------------------------
+    #[display(fmt = "Duplicate API token '{}'", _0)]
+    ApiTokenDuplicate(String),
+
+    #[display(fmt = "Unknown API token '{}'", _0)]
+    ApiTokenUnknown(String),
+
+    #[display(fmt = "API token '{}' is defined in the config and cannot be removed", _0)]
+    ApiTokenInConfig(String),
+
+    #[display(fmt = "Invalid API token '{}': {}", _0, _1)]
+    ApiTokenInvalid(String, String),
+
+    //-----------------------------------------------------------------
+    // Login Issues
+    //-----------------------------------------------------------------
+    #[display(fmt = "Login with OpenID Connect is not enabled")]
+    OidcNotEnabled,
+
+    #[display(fmt = "Login failed: {}", _0)]
+    OidcLogin(String),
+
     //-----------------------------------------------------------------
     // Repository Issues
     //-----------------------------------------------------------------
@@ -121,6 +146,9 @@ pub enum Error {
     #[display(fmt = "CA '{}' got error from parent '{}': {}", _0, _1, _2)]
     CaParentIssue(Handle, ParentHandle, String),
 
+    #[display(fmt = "CA '{}' has no offline TA parent named '{}'", _0, _1)]
+    CaParentNotOffline(Handle, ParentHandle),
+
     #[display(fmt = "CA '{}' got invalid parent response xml: {}", _0, _1)]
     CaParentResponseInvalidXml(Handle, String),
 
@@ -155,6 +183,9 @@ pub enum Error {
     #[display(fmt = "CA '{}' does not know id certificate for child '{}'", _0, _1)]
     CaChildUnauthorised(Handle, ChildHandle),
 
+    #[display(fmt = "CA '{}' could not remove all children: {}", _0, _1)]
+    CaChildrenNotRemoved(Handle, String),
+
     // RouteAuthorizations - ROAs
     #[display(fmt = "Cannot remove unknown ROA '{}' from CA '{}'", _0, _1)]
     CaAuthorisationUnknown(Handle, RouteAuthorization),
@@ -168,6 +199,97 @@ pub enum Error {
     #[display(fmt = "Prefix in ROA '{}' not held by CA '{}'.", _1, _0)]
     CaAuthorisationNotEntitled(Handle, RouteAuthorization),
 
+    #[display(fmt = "Invalid ROA file for CA '{}': {}", _0, _1)]
+    CaAuthorisationFileInvalid(Handle, AuthorizationFmtError),
+
+    #[display(fmt = "ROAs for CA '{}' were changed concurrently, please retry", _0)]
+    CaAuthorisationConcurrentUpdate(Handle),
+
+    // ASPAs
+    #[display(fmt = "No ASPA for customer AS '{}' in CA '{}'", _1, _0)]
+    CaAspaUnknown(Handle, AsNumber),
+
+    #[display(fmt = "Customer AS '{}' in ASPA not held by CA '{}'", _1, _0)]
+    CaAspaNotEntitled(Handle, AsNumber),
+
+    #[display(fmt = "ASPA for customer AS '{}' in CA '{}' has no providers", _1, _0)]
+    CaAspaProvidersEmpty(Handle, AsNumber),
+
+    #[display(
+        fmt = "ASPA for customer AS '{}' in CA '{}' lists the customer as provider",
+        _1,
+        _0
+    )]
+    CaAspaCustomerAsProvider(Handle, AsNumber),
+
+    #[display(
+        fmt = "Providers update for ASPA for customer AS '{}' in CA '{}' adds existing, or removes unknown providers",
+        _1,
+        _0
+    )]
+    CaAspaProvidersUpdateConflict(Handle, AsNumber),
+
+    // BGPsec router certificates
+    #[display(fmt = "No BGPsec router certificate '{}' in CA '{}'", _1, _0)]
+    CaBgpSecUnknown(Handle, BgpSecAsnKey),
+
+    #[display(fmt = "Router AS '{}' not held by CA '{}'", _1, _0)]
+    CaBgpSecNotEntitled(Handle, AsNumber),
+
+    #[display(fmt = "Invalid BGPsec router CSR for CA '{}': {}", _0, _1)]
+    CaBgpSecCsrInvalid(Handle, String),
+
+    #[display(fmt = "BGPsec router key '{}' is not an ECDSA P-256 key", _0)]
+    BgpSecRouterKeyUnsupported(BgpSecAsnKey),
+
+    // Resource Signed Checklists
+    #[display(
+        fmt = "Cannot sign checklist for resources '{}' not held by CA '{}'",
+        _1,
+        _0
+    )]
+    CaRscNotEntitled(Handle, ResourceSet),
+
+    #[display(fmt = "Invalid signed checklist request for CA '{}': {}", _0, _1)]
+    CaRscInvalid(Handle, String),
+
+    // Validation of published objects
+    #[display(fmt = "Published object '{}' of CA '{}' is invalid: {}", _1, _0, _2)]
+    CaPublishedObjectInvalid(Handle, uri::Rsync, String),
+
+    // Timing
+    #[display(fmt = "Invalid timing for CA '{}': {}", _0, _1)]
+    CaTimingInvalid(Handle, String),
+
+    // Key roll policy
+    #[display(fmt = "Invalid key roll policy for CA '{}': {}", _0, _1)]
+    CaKeyRollPolicyInvalid(Handle, String),
+
+    #[display(fmt = "Emergency key roll for CA '{}' must be confirmed", _0)]
+    CaKeyRollEmergencyNotConfirmed(Handle),
+
+    #[display(fmt = "Emergency key roll for CA '{}' is not possible: {}", _0, _1)]
+    CaKeyRollEmergencyNotPossible(Handle, String),
+
+    // History
+    #[display(fmt = "Invalid point in the history of CA '{}': {}", _0, _1)]
+    CaHistoryPointInvalid(Handle, String),
+
+    #[display(fmt = "CA '{}' has no version {}", _0, _1)]
+    CaHistoryPointUnknown(Handle, u64),
+
+    #[display(fmt = "CA '{}' did not exist at {}", _0, _1)]
+    CaHistoryPointBeforeCreation(Handle, String),
+
+    //-----------------------------------------------------------------
+    // BGP Analysis Issues
+    //-----------------------------------------------------------------
+    #[display(fmt = "No BGP dump configured, please set 'bgp_dump_file'")]
+    BgpDumpNotConfigured,
+
+    #[display(fmt = "{}", _0)]
+    BgpDumpError(BgpDumpError),
+
     //-----------------------------------------------------------------
     // Key Usage Issues
     //-----------------------------------------------------------------
@@ -213,6 +335,12 @@ pub enum Error {
     #[display(fmt = "TrustAnchor was already initialised")]
     TaAlreadyInitialised,
 
+    #[display(fmt = "Invalid response from offline Trust Anchor: {}", _0)]
+    TaOfflineResponseInvalid(String),
+
+    #[display(fmt = "No identity certificate configured for the offline Trust Anchor")]
+    TaOfflineIdCertMissing,
+
     //-----------------------------------------------------------------
     // If we really don't know any more..
     //-----------------------------------------------------------------
@@ -250,6 +378,12 @@ impl From<rfc8181::MessageError> for Error {
     }
 }
 
+impl From<BgpDumpError> for Error {
+    fn from(e: BgpDumpError) -> Self {
+        Error::BgpDumpError(e)
+    }
+}
+
 impl From<ResourceSetError> for Error {
     fn from(e: ResourceSetError) -> Self {
         Error::ResourceSetError(e)
@@ -281,14 +415,22 @@ impl Error {
     pub fn status(&self) -> StatusCode {
         match self {
             // Most is bad requests by users, so just mapping the things that are not
-            Error::IoError(_) | Error::SignerError(_) | Error::AggregateStoreError(_) => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
+            Error::IoError(_)
+            | Error::SignerError(_)
+            | Error::AggregateStoreError(_)
+            | Error::BgpDumpError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::PublisherUnknown(_)
             | Error::CaUnknown(_)
             | Error::CaChildUnknown(_, _)
             | Error::CaParentUnknown(_, _)
-            | Error::ApiUnknownResource => StatusCode::NOT_FOUND,
+            | Error::CaHistoryPointUnknown(_, _)
+            | Error::CaHistoryPointBeforeCreation(_, _)
+            | Error::ApiUnknownResource
+            | Error::ApiTokenUnknown(_) => StatusCode::NOT_FOUND,
+
+            Error::OidcLogin(_) => StatusCode::FORBIDDEN,
+
+            Error::CaAuthorisationConcurrentUpdate(_) => StatusCode::CONFLICT,
 
             _ => StatusCode::BAD_REQUEST,
         }
@@ -328,6 +470,34 @@ impl Error {
             // NOT FOUND (generic API not found)
             Error::ApiUnknownResource => ErrorResponse::new("api-unknown-resource", &self),
 
+            //-----------------------------------------------------------------
+            // API Token Issues (label: api-token-*)
+            //-----------------------------------------------------------------
+            Error::ApiTokenDuplicate(name) => {
+                ErrorResponse::new("api-token-duplicate", &self).with_token_name(name)
+            }
+
+            Error::ApiTokenUnknown(name) => {
+                ErrorResponse::new("api-token-unknown", &self).with_token_name(name)
+            }
+
+            Error::ApiTokenInConfig(name) => {
+                ErrorResponse::new("api-token-in-config", &self).with_token_name(name)
+            }
+
+            Error::ApiTokenInvalid(name, reason) => ErrorResponse::new("api-token-invalid", &self)
+                .with_token_name(name)
+                .with_cause(reason),
+
+            //-----------------------------------------------------------------
+            // Login Issues (label: login-*)
+            //-----------------------------------------------------------------
+            Error::OidcNotEnabled => ErrorResponse::new("login-oidc-not-enabled", &self),
+
+            Error::OidcLogin(reason) => {
+                ErrorResponse::new("login-oidc-failed", &self).with_cause(reason)
+            }
+
             //-----------------------------------------------------------------
             // Repository Issues (label: repo-*)
             //-----------------------------------------------------------------
@@ -408,6 +578,12 @@ impl Error {
                 .with_parent(parent)
                 .with_cause(err),
 
+            Error::CaParentNotOffline(ca, parent) => {
+                ErrorResponse::new("ca-parent-not-offline", &self)
+                    .with_ca(ca)
+                    .with_parent(parent)
+            }
+
             Error::CaParentResponseInvalidXml(ca, err) => {
                 ErrorResponse::new("ca-parent-response-invalid-xml", &self)
                     .with_ca(ca)
@@ -451,6 +627,11 @@ impl Error {
                     .with_ca(ca)
                     .with_child(child)
             }
+            Error::CaChildrenNotRemoved(ca, children) => {
+                ErrorResponse::new("ca-children-not-removed", &self)
+                    .with_ca(ca)
+                    .with_cause(children)
+            }
 
             // RouteAuthorizations
             Error::CaAuthorisationUnknown(ca, auth) => ErrorResponse::new("ca-roa-unknown", &self)
@@ -475,6 +656,128 @@ impl Error {
                     .with_auth(auth)
             }
 
+            Error::CaAuthorisationFileInvalid(ca, e) => {
+                ErrorResponse::new("ca-roa-invalid-file", &self)
+                    .with_ca(ca)
+                    .with_cause(e)
+            }
+
+            Error::CaAuthorisationConcurrentUpdate(ca) => {
+                ErrorResponse::new("ca-roa-concurrent-update", &self).with_ca(ca)
+            }
+
+            // ASPAs
+            Error::CaAspaUnknown(ca, customer) => ErrorResponse::new("ca-aspa-unknown", &self)
+                .with_ca(ca)
+                .with_asn(*customer),
+
+            Error::CaAspaNotEntitled(ca, customer) => {
+                ErrorResponse::new("ca-aspa-not-entitled", &self)
+                    .with_ca(ca)
+                    .with_asn(*customer)
+            }
+
+            Error::CaAspaProvidersEmpty(ca, customer) => {
+                ErrorResponse::new("ca-aspa-providers-empty", &self)
+                    .with_ca(ca)
+                    .with_asn(*customer)
+            }
+
+            Error::CaAspaCustomerAsProvider(ca, customer) => {
+                ErrorResponse::new("ca-aspa-customer-as-provider", &self)
+                    .with_ca(ca)
+                    .with_asn(*customer)
+            }
+
+            Error::CaAspaProvidersUpdateConflict(ca, customer) => {
+                ErrorResponse::new("ca-aspa-providers-update-conflict", &self)
+                    .with_ca(ca)
+                    .with_asn(*customer)
+            }
+
+            // BGPsec router certificates
+            Error::CaBgpSecUnknown(ca, router) => ErrorResponse::new("ca-bgpsec-unknown", &self)
+                .with_ca(ca)
+                .with_router(router),
+
+            Error::CaBgpSecNotEntitled(ca, asn) => {
+                ErrorResponse::new("ca-bgpsec-not-entitled", &self)
+                    .with_ca(ca)
+                    .with_asn(*asn)
+            }
+
+            Error::CaBgpSecCsrInvalid(ca, cause) => {
+                ErrorResponse::new("ca-bgpsec-csr-invalid", &self)
+                    .with_ca(ca)
+                    .with_cause(cause)
+            }
+
+            Error::BgpSecRouterKeyUnsupported(router) => {
+                ErrorResponse::new("bgpsec-router-key-unsupported", &self).with_router(router)
+            }
+
+            // Resource Signed Checklists
+            Error::CaRscNotEntitled(ca, resources) => {
+                ErrorResponse::new("ca-rsc-not-entitled", &self)
+                    .with_ca(ca)
+                    .with_resources(resources)
+            }
+
+            Error::CaRscInvalid(ca, cause) => ErrorResponse::new("ca-rsc-invalid", &self)
+                .with_ca(ca)
+                .with_cause(cause),
+
+            // Validation of published objects
+            Error::CaPublishedObjectInvalid(ca, uri, cause) => {
+                ErrorResponse::new("ca-published-object-invalid", &self)
+                    .with_ca(ca)
+                    .with_uri(uri)
+                    .with_cause(cause)
+            }
+
+            // Timing
+            Error::CaTimingInvalid(ca, cause) => ErrorResponse::new("ca-timing-invalid", &self)
+                .with_ca(ca)
+                .with_cause(cause),
+
+            // Key roll policy
+            Error::CaKeyRollPolicyInvalid(ca, cause) => {
+                ErrorResponse::new("ca-key-roll-policy-invalid", &self)
+                    .with_ca(ca)
+                    .with_cause(cause)
+            }
+            Error::CaKeyRollEmergencyNotConfirmed(ca) => {
+                ErrorResponse::new("ca-key-roll-emergency-not-confirmed", &self).with_ca(ca)
+            }
+            Error::CaKeyRollEmergencyNotPossible(ca, cause) => {
+                ErrorResponse::new("ca-key-roll-emergency-not-possible", &self)
+                    .with_ca(ca)
+                    .with_cause(cause)
+            }
+
+            // History
+            Error::CaHistoryPointInvalid(ca, cause) => {
+                ErrorResponse::new("ca-history-point-invalid", &self)
+                    .with_ca(ca)
+                    .with_cause(cause)
+            }
+            Error::CaHistoryPointUnknown(ca, version) => {
+                ErrorResponse::new("ca-history-point-unknown", &self)
+                    .with_ca(ca)
+                    .with_cause(version)
+            }
+            Error::CaHistoryPointBeforeCreation(ca, time) => {
+                ErrorResponse::new("ca-history-point-before-creation", &self)
+                    .with_ca(ca)
+                    .with_cause(time)
+            }
+
+            //-----------------------------------------------------------------
+            // BGP Analysis Issues (label: bgp-*)
+            //-----------------------------------------------------------------
+            Error::BgpDumpNotConfigured => ErrorResponse::new("bgp-dump-not-configured", &self),
+            Error::BgpDumpError(e) => ErrorResponse::new("bgp-dump-invalid", &self).with_cause(e),
+
             //-----------------------------------------------------------------
             // Key Usage Issues (key-*)
             //-----------------------------------------------------------------
@@ -502,6 +805,12 @@ impl Error {
             Error::TaNotAllowed => ErrorResponse::new("ta-not-allowed", &self),
             Error::TaNameReserved => ErrorResponse::new("ta-name-reserved", &self),
             Error::TaAlreadyInitialised => ErrorResponse::new("ta-initialised", &self),
+            Error::TaOfflineResponseInvalid(err) => {
+                ErrorResponse::new("ta-offline-response-invalid", &self).with_cause(err)
+            }
+            Error::TaOfflineIdCertMissing => {
+                ErrorResponse::new("ta-offline-id-cert-missing", &self)
+            }
 
             //-----------------------------------------------------------------
             // If we really don't know any more..
@@ -610,6 +919,38 @@ mod tests {
             Error::ApiUnknownResource,
         );
 
+        //-----------------------------------------------------------------
+        // API Token Issues
+        //-----------------------------------------------------------------
+        verify(
+            include_str!("../../test-resources/errors/api-token-duplicate.json"),
+            Error::ApiTokenDuplicate("roas".to_string()),
+        );
+        verify(
+            include_str!("../../test-resources/errors/api-token-unknown.json"),
+            Error::ApiTokenUnknown("roas".to_string()),
+        );
+        verify(
+            include_str!("../../test-resources/errors/api-token-in-config.json"),
+            Error::ApiTokenInConfig("roas".to_string()),
+        );
+        verify(
+            include_str!("../../test-resources/errors/api-token-invalid.json"),
+            Error::ApiTokenInvalid("roas".to_string(), "no CAs".to_string()),
+        );
+
+        //-----------------------------------------------------------------
+        // Login Issues
+        //-----------------------------------------------------------------
+        verify(
+            include_str!("../../test-resources/errors/login-oidc-not-enabled.json"),
+            Error::OidcNotEnabled,
+        );
+        verify(
+            include_str!("../../test-resources/errors/login-oidc-failed.json"),
+            Error::OidcLogin("wrong nonce".to_string()),
+        );
+
         //-----------------------------------------------------------------
         // Repository Issues
         //-----------------------------------------------------------------
@@ -704,6 +1045,10 @@ mod tests {
             include_str!("../../test-resources/errors/ca-parent-unknown.json"),
             Error::CaParentUnknown(ca.clone(), parent.clone()),
         );
+        verify(
+            include_str!("../../test-resources/errors/ca-parent-not-offline.json"),
+            Error::CaParentNotOffline(ca.clone(), parent.clone()),
+        );
         verify(
             include_str!("../../test-resources/errors/ca-parent-issue.json"),
             Error::CaParentIssue(ca.clone(), parent, "connection refused".to_string()),
@@ -750,6 +1095,10 @@ mod tests {
             include_str!("../../test-resources/errors/ca-child-unauthorised.json"),
             Error::CaChildUnauthorised(ca.clone(), child),
         );
+        verify(
+            include_str!("../../test-resources/errors/ca-children-not-removed.json"),
+            Error::CaChildrenNotRemoved(ca.clone(), "'child': CA 'child' is unknown".to_string()),
+        );
 
         verify(
             include_str!("../../test-resources/errors/ca-roa-unknown.json"),
@@ -765,7 +1114,130 @@ mod tests {
         );
         verify(
             include_str!("../../test-resources/errors/ca-roa-not-entitled.json"),
-            Error::CaAuthorisationNotEntitled(ca, auth),
+            Error::CaAuthorisationNotEntitled(ca.clone(), auth),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-roa-invalid-file.json"),
+            Error::CaAuthorisationFileInvalid(ca.clone(), AuthorizationFmtError::auth("AS1,1")),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-roa-concurrent-update.json"),
+            Error::CaAuthorisationConcurrentUpdate(ca.clone()),
+        );
+
+        let customer = AsNumber::new(64496);
+        verify(
+            include_str!("../../test-resources/errors/ca-aspa-unknown.json"),
+            Error::CaAspaUnknown(ca.clone(), customer),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-aspa-not-entitled.json"),
+            Error::CaAspaNotEntitled(ca.clone(), customer),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-aspa-providers-empty.json"),
+            Error::CaAspaProvidersEmpty(ca.clone(), customer),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-aspa-customer-as-provider.json"),
+            Error::CaAspaCustomerAsProvider(ca.clone(), customer),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-aspa-providers-update-conflict.json"),
+            Error::CaAspaProvidersUpdateConflict(ca.clone(), customer),
+        );
+
+        let router =
+            BgpSecAsnKey::from_str("ROUTER-0000FBF0-0B1D2E26EA41A2F5C7F0C5CB0F4A9F9F53D9E1A7")
+                .unwrap();
+        verify(
+            include_str!("../../test-resources/errors/ca-bgpsec-unknown.json"),
+            Error::CaBgpSecUnknown(ca.clone(), router),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-bgpsec-not-entitled.json"),
+            Error::CaBgpSecNotEntitled(ca.clone(), router.asn()),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-bgpsec-csr-invalid.json"),
+            Error::CaBgpSecCsrInvalid(ca.clone(), "invalid signature".to_string()),
+        );
+        verify(
+            include_str!("../../test-resources/errors/bgpsec-router-key-unsupported.json"),
+            Error::BgpSecRouterKeyUnsupported(router),
+        );
+
+        let resources = ResourceSet::from_strs("AS64496", "192.0.2.0/24", "").unwrap();
+        verify(
+            include_str!("../../test-resources/errors/ca-rsc-not-entitled.json"),
+            Error::CaRscNotEntitled(ca.clone(), resources),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-rsc-invalid.json"),
+            Error::CaRscInvalid(ca.clone(), "no digests".to_string()),
+        );
+
+        verify(
+            include_str!("../../test-resources/errors/ca-timing-invalid.json"),
+            Error::CaTimingInvalid(
+                ca.clone(),
+                "publish threshold must be shorter than publish next update".to_string(),
+            ),
+        );
+
+        verify(
+            include_str!("../../test-resources/errors/ca-key-roll-policy-invalid.json"),
+            Error::CaKeyRollPolicyInvalid(
+                ca.clone(),
+                "staging period must be at least 24 hours".to_string(),
+            ),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-key-roll-emergency-not-confirmed.json"),
+            Error::CaKeyRollEmergencyNotConfirmed(ca.clone()),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-key-roll-emergency-not-possible.json"),
+            Error::CaKeyRollEmergencyNotPossible(
+                ca.clone(),
+                "the CA has no resource classes".to_string(),
+            ),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-history-point-invalid.json"),
+            Error::CaHistoryPointInvalid(
+                ca.clone(),
+                "expected a version or RFC 3339 time, found: 'yesterday'".to_string(),
+            ),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-history-point-unknown.json"),
+            Error::CaHistoryPointUnknown(ca.clone(), 42),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-history-point-before-creation.json"),
+            Error::CaHistoryPointBeforeCreation(
+                ca.clone(),
+                "2020-01-31T12:00:00+00:00".to_string(),
+            ),
+        );
+
+        verify(
+            include_str!("../../test-resources/errors/ca-published-object-invalid.json"),
+            Error::CaPublishedObjectInvalid(
+                ca,
+                uri::Rsync::from_str("rsync://host/module/ca/0/file.roa").unwrap(),
+                "certificate has expired".to_string(),
+            ),
+        );
+
+        verify(
+            include_str!("../../test-resources/errors/bgp-dump-not-configured.json"),
+            Error::BgpDumpNotConfigured,
+        );
+        verify(
+            include_str!("../../test-resources/errors/bgp-dump-invalid.json"),
+            Error::BgpDumpError(BgpDumpError::Line(2, "192.168.0.0/16".to_string())),
         );
 
         verify(
@@ -821,6 +1293,14 @@ mod tests {
             include_str!("../../test-resources/errors/ta-initialised.json"),
             Error::TaAlreadyInitialised,
         );
+        verify(
+            include_str!("../../test-resources/errors/ta-offline-response-invalid.json"),
+            Error::TaOfflineResponseInvalid("no response for CA 'ca'".to_string()),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ta-offline-id-cert-missing.json"),
+            Error::TaOfflineIdCertMissing,
+        );
 
         verify(
             include_str!("../../test-resources/errors/general-error.json"),
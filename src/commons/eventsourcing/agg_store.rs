@@ -4,14 +4,16 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::RwLock;
 
+use rpki::x509::Time;
+
 use crate::commons::api::Handle;
 use crate::commons::eventsourcing::agg::AggregateHistory;
-use crate::commons::eventsourcing::cmd::{Command, StoredCommandBuilder};
+use crate::commons::eventsourcing::cmd::{Command, StoredCommand, StoredCommandBuilder};
 use crate::commons::eventsourcing::{
-    Aggregate, DiskKeyStore, Event, EventListener, KeyStore, KeyStoreError,
+    Aggregate, DiskKeyStore, Event, EventListener, KeyStore, KeyStoreError, KeyStoreType,
+    KrillKeyStore,
 };
-
-const SNAPSHOT_FREQ: u64 = 5;
+use crate::constants::SNAPSHOT_EVENTS;
 
 pub type StoreResult<T> = Result<T, AggregateStoreError>;
 
@@ -24,8 +26,9 @@ where
     /// does not exist.
     fn get_latest(&self, id: &Handle) -> StoreResult<Arc<A>>;
 
-    /// Adds a new aggregate instance based on the init event.
-    fn add(&self, init: A::InitEvent) -> StoreResult<Arc<A>>;
+    /// Adds a new aggregate instance based on the init event. The creation
+    /// is recorded in the command history as done by the actor.
+    fn add(&self, init: A::InitEvent, actor: &str) -> StoreResult<Arc<A>>;
 
     /// Sends a command to the appropriate aggregate, and on
     /// success: save command and events, return aggregate
@@ -45,6 +48,26 @@ where
 
     /// Lists the complete history for an aggregate.
     fn history(&self, id: &Handle) -> StoreResult<AggregateHistory<A>>;
+
+    /// Lists all commands that were processed for an aggregate, including
+    /// the commands that failed.
+    fn command_history(&self, id: &Handle) -> StoreResult<Vec<StoredCommand>>;
+
+    /// Removes an aggregate from the store, but keeps its history in an
+    /// archive. Returns an AggregateStoreError::UnknownAggregate in case the
+    /// aggregate does not exist.
+    fn archive(&self, id: &Handle) -> StoreResult<()>;
+
+    /// Saves a snapshot of the latest version of all aggregates, so that no
+    /// events need to be replayed when they are loaded again, e.g. after a
+    /// restart.
+    fn snapshot_all(&self) -> StoreResult<()>;
+
+    /// Moves the commands that were processed before the given time, and
+    /// their events, out of the store into compressed bundles. These are
+    /// still included in the history of the aggregates. Returns the number
+    /// of archived commands.
+    fn compact(&self, before: Time) -> StoreResult<usize>;
 }
 
 /// This type defines possible Errors for the AggregateStore
@@ -73,27 +96,50 @@ impl From<KeyStoreError> for AggregateStoreError {
 }
 
 pub struct DiskAggregateStore<A: Aggregate> {
-    store: DiskKeyStore,
+    store: KrillKeyStore,
     cache: RwLock<HashMap<Handle, Arc<A>>>,
     use_cache: bool,
+    snapshot_events: u64,
     listeners: Vec<Arc<dyn EventListener<A>>>,
     outer_lock: RwLock<()>,
 }
 
 impl<A: Aggregate> DiskAggregateStore<A> {
     pub fn new(work_dir: &PathBuf, name_space: &str) -> Result<Self, io::Error> {
-        let store = DiskKeyStore::under_work_dir(work_dir, name_space)?;
+        let store = KrillKeyStore::Disk(DiskKeyStore::under_work_dir(work_dir, name_space)?);
+        Ok(Self::with_store(store))
+    }
+
+    /// Creates a store for the name_space under the work_dir, which keeps
+    /// the aggregates in a KeyStore of the given type.
+    pub fn build(
+        work_dir: &PathBuf,
+        name_space: &str,
+        store_type: KeyStoreType,
+    ) -> StoreResult<Self> {
+        let store = KrillKeyStore::build(work_dir, name_space, store_type)?;
+        Ok(Self::with_store(store))
+    }
+
+    fn with_store(store: KrillKeyStore) -> Self {
         let cache = RwLock::new(HashMap::new());
         let use_cache = true;
+        let snapshot_events = SNAPSHOT_EVENTS;
         let listeners = vec![];
         let lock = RwLock::new(());
-        Ok(DiskAggregateStore {
+        DiskAggregateStore {
             store,
             cache,
             use_cache,
+            snapshot_events,
             listeners,
             outer_lock: lock,
-        })
+        }
+    }
+
+    /// Sets the number of events after which a new snapshot is saved.
+    pub fn set_snapshot_events(&mut self, snapshot_events: u64) {
+        self.snapshot_events = snapshot_events;
     }
 }
 
@@ -155,10 +201,12 @@ where
         self.get_latest_no_lock(handle)
     }
 
-    fn add(&self, init: A::InitEvent) -> StoreResult<Arc<A>> {
+    fn add(&self, init: A::InitEvent, actor: &str) -> StoreResult<Arc<A>> {
         let _lock = self.outer_lock.write().unwrap();
 
         self.store.store_event(&init)?;
+        self.store
+            .store_command(StoredCommand::init(&init, actor))?;
 
         let handle = init.handle().clone();
 
@@ -239,26 +287,26 @@ where
                         }
                     }
 
-                    // Time to start saving things.
+                    for event in &events {
+                        agg.apply(event.clone());
+                    }
+
+                    // Time to start saving things. The command, events and snapshot
+                    // are saved together, so that the store can do this in a single
+                    // transaction if it supports this.
                     let stored_command =
                         stored_command_builder.finish_with_events(events.as_slice());
+                    let snapshot = if (version_before + 1..=agg.version())
+                        .any(|v| v % self.snapshot_events == 0)
+                    {
+                        Some(&*agg)
+                    } else {
+                        None
+                    };
                     self.store
-                        .store_command(stored_command)
+                        .store_update(&handle, stored_command, &events, snapshot)
                         .map_err(AggregateStoreError::KeyStoreError)?;
 
-                    for event in &events {
-                        self.store
-                            .store_event(event)
-                            .map_err(AggregateStoreError::KeyStoreError)?;
-
-                        agg.apply(event.clone());
-                        if agg.version() % SNAPSHOT_FREQ == 0 {
-                            self.store
-                                .store_aggregate(&handle, agg)
-                                .map_err(AggregateStoreError::KeyStoreError)?;
-                        }
-                    }
-
                     cache.insert(handle, Arc::new(agg.clone()));
 
                     // Only send this to listeners after everything has been saved.
@@ -294,4 +342,51 @@ where
             .history::<A>(id)
             .map_err(AggregateStoreError::KeyStoreError)
     }
+
+    fn command_history(&self, id: &Handle) -> StoreResult<Vec<StoredCommand>> {
+        if !self.has(id) {
+            return Err(AggregateStoreError::UnknownAggregate(id.clone()));
+        }
+        self.store
+            .stored_commands(id)
+            .map_err(AggregateStoreError::KeyStoreError)
+    }
+
+    fn archive(&self, id: &Handle) -> StoreResult<()> {
+        let _lock = self.outer_lock.write().unwrap();
+        if !self.store.has_aggregate(id) {
+            return Err(AggregateStoreError::UnknownAggregate(id.clone()));
+        }
+        self.cache.write().unwrap().remove(id);
+        self.store.archive_aggregate(id)?;
+        Ok(())
+    }
+
+    fn snapshot_all(&self) -> StoreResult<()> {
+        let _lock = self.outer_lock.write().unwrap();
+        for id in self.store.aggregates() {
+            let latest = self.get_latest_no_lock(&id)?;
+            self.store.store_aggregate(&id, latest.as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn compact(&self, before: Time) -> StoreResult<usize> {
+        let mut archived = 0;
+        for id in self.list() {
+            // Lock for each aggregate only, so that commands for other
+            // aggregates can be processed while compacting.
+            let _lock = self.outer_lock.write().unwrap();
+            if !self.store.has_aggregate(&id) {
+                continue; // archived in the meantime
+            }
+
+            // Save a snapshot first, so that all events before the latest
+            // version can be archived.
+            let latest = self.get_latest_no_lock(&id)?;
+            self.store.store_aggregate(&id, latest.as_ref())?;
+            archived += self.store.archive_commands(&id, before, latest.version())?;
+        }
+        Ok(archived)
+    }
 }
@@ -5,6 +5,10 @@ use rpki::x509::Time;
 use crate::commons::api::Handle;
 use crate::commons::eventsourcing::Event;
 
+/// The actor used for commands which are not sent on behalf of an API user,
+/// e.g. commands triggered by background jobs.
+pub const DEFAULT_ACTOR: &str = "krill";
+
 //------------ Command -------------------------------------------------------
 
 /// Commands are used to send an intent to change an aggregate.
@@ -29,7 +33,7 @@ pub trait Command: fmt::Display {
     /// The actor who sent the command. Defaults to "krill" so that it is not
     /// mandatory to implement this.
     fn actor(&self) -> &str {
-        "krill"
+        DEFAULT_ACTOR
     }
 
     /// In case of concurrent processing of commands, the aggregate may be
@@ -57,6 +61,7 @@ pub trait Command: fmt::Display {
 pub struct SentCommand<C: CommandDetails> {
     handle: Handle,
     version: Option<u64>,
+    actor: String,
     details: C,
 }
 
@@ -71,6 +76,10 @@ impl<C: CommandDetails> Command for SentCommand<C> {
         self.version
     }
 
+    fn actor(&self) -> &str {
+        &self.actor
+    }
+
     fn summary(&self) -> String {
         self.details.to_string()
     }
@@ -81,10 +90,18 @@ impl<C: CommandDetails> SentCommand<C> {
         SentCommand {
             handle: id.clone(),
             version,
+            actor: DEFAULT_ACTOR.to_string(),
             details,
         }
     }
 
+    /// Sets the actor who sent this command, e.g. the name of the API token
+    /// that was used.
+    pub fn with_actor(mut self, actor: &str) -> Self {
+        self.actor = actor.to_string();
+        self
+    }
+
     pub fn into_details(self) -> C {
         self.details
     }
@@ -129,6 +146,24 @@ pub struct StoredCommand {
 }
 
 impl StoredCommand {
+    /// Describes the creation of an aggregate by the given actor, so that it
+    /// shows up in the command history. The init event is not listed as an
+    /// effect, so that it is never archived with the command.
+    pub fn init<E: Event>(init: &E, actor: &str) -> Self {
+        StoredCommand {
+            actor: actor.to_string(),
+            time: Time::now(),
+            handle: init.handle().clone(),
+            version: init.version(),
+            summary: init.to_string(),
+            effect: StoredEffect::Events(vec![]),
+        }
+    }
+
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
     pub fn time(&self) -> Time {
         self.time
     }
@@ -136,6 +171,18 @@ impl StoredCommand {
     pub fn handle(&self) -> &Handle {
         &self.handle
     }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn effect(&self) -> &StoredEffect {
+        &self.effect
+    }
 }
 
 //------------ StoredEffect --------------------------------------------------
@@ -8,10 +8,17 @@ mod evt;
 pub use self::evt::{Event, StoredEvent};
 
 mod cmd;
-pub use self::cmd::{Command, CommandDetails, SentCommand};
+pub use self::cmd::{
+    Command, CommandDetails, SentCommand, StoredCommand, StoredEffect, DEFAULT_ACTOR,
+};
 
 mod store;
-pub use self::store::{DiskKeyStore, KeyStore, KeyStoreError, Storable};
+pub use self::store::{
+    DiskKeyStore, KeyStore, KeyStoreError, KeyStoreType, KrillKeyStore, Storable,
+};
+
+mod sqlite;
+pub use self::sqlite::{migrate_disk_to_sqlite, migrate_sqlite_to_disk, SqliteKeyStore};
 
 mod agg_store;
 pub use self::agg_store::{AggregateStore, AggregateStoreError, DiskAggregateStore};
@@ -30,10 +37,14 @@ mod tests {
     //!
 
     use std::fmt;
+    use std::fs;
     use std::sync::Arc;
 
+    use chrono::Duration;
     use serde::Serialize;
 
+    use rpki::x509::Time;
+
     use crate::commons::api::Handle;
     use crate::commons::util::test;
 
@@ -267,7 +278,7 @@ mod tests {
             let id_alice = Handle::from_str_unsafe("alice");
             let alice_init = InitPersonEvent::init(&id_alice, "alice smith");
 
-            manager.add(alice_init).unwrap();
+            manager.add(alice_init, DEFAULT_ACTOR).unwrap();
 
             let mut alice = manager.get_latest(&id_alice).unwrap();
             assert_eq!("alice smith", alice.name());
@@ -330,6 +341,155 @@ mod tests {
             );
 
             assert_eq!(history.to_string().as_str(), expected_history);
+
+            let commands = manager.command_history(&id_alice).unwrap();
+            assert_eq!(23, commands.len());
+            assert_eq!(DEFAULT_ACTOR, commands[0].actor());
+            assert_eq!(0, commands[0].version());
+            assert_eq!(&StoredEffect::Events(vec![]), commands[0].effect());
+            assert_eq!(DEFAULT_ACTOR, commands[1].actor());
+            assert_eq!("Go around the sun", commands[1].summary());
+            assert_eq!(&StoredEffect::Events(vec![22]), commands[22].effect());
+
+            // Archived aggregates are no longer known, but their history is kept
+            manager.archive(&id_alice).unwrap();
+            assert!(!manager.has(&id_alice));
+            assert!(manager.list().is_empty());
+            assert!(manager.get_latest(&id_alice).is_err());
+
+            let mut archived = d.clone();
+            archived.push(crate::constants::ARCHIVE_DIR);
+            archived.push("person");
+            archived.push("alice");
+            assert_eq!(1, std::fs::read_dir(archived).unwrap().count());
+        })
+    }
+
+    #[test]
+    fn event_sourcing_compaction() {
+        test::test_under_tmp(|d| {
+            let manager = DiskAggregateStore::<Person>::new(&d, "person").unwrap();
+
+            let id_alice = Handle::from_str_unsafe("alice");
+            let alice_init = InitPersonEvent::init(&id_alice, "alice smith");
+            manager.add(alice_init, DEFAULT_ACTOR).unwrap();
+
+            for _ in 0..12 {
+                let get_older = PersonCommand::go_around_sun(&id_alice, None);
+                manager.command(get_older).unwrap();
+            }
+
+            // Commands processed after the given time are not archived
+            let yesterday = Time::now() - Duration::days(1);
+            assert_eq!(0, manager.compact(yesterday).unwrap());
+
+            let tomorrow = Time::now() + Duration::days(1);
+            assert_eq!(13, manager.compact(tomorrow).unwrap());
+
+            // Only the init event, the snapshot and the bundles are left
+            let mut dir = d.clone();
+            dir.push("person");
+            dir.push("alice");
+            let mut names: Vec<String> = fs::read_dir(&dir)
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            assert_eq!(vec!["bundles", "delta-0.json", "snapshot.json"], names);
+
+            // The history includes the archived events and commands
+            let manager = DiskAggregateStore::<Person>::new(&d, "person").unwrap();
+            let (_init, events) = manager.history(&id_alice).unwrap().unpack();
+            assert_eq!(12, events.len());
+            assert_eq!(13, manager.command_history(&id_alice).unwrap().len());
+
+            let get_older = PersonCommand::go_around_sun(&id_alice, None);
+            manager.command(get_older).unwrap();
+            assert_eq!(14, manager.command_history(&id_alice).unwrap().len());
+
+            // Without a snapshot the archived events are replayed
+            dir.push("snapshot.json");
+            fs::remove_file(dir).unwrap();
+
+            let manager = DiskAggregateStore::<Person>::new(&d, "person").unwrap();
+            let alice = manager.get_latest(&id_alice).unwrap();
+            assert_eq!(13, alice.age());
+        })
+    }
+
+    #[test]
+    fn event_sourcing_sqlite() {
+        test::test_under_tmp(|d| {
+            let manager =
+                DiskAggregateStore::<Person>::build(&d, "person", KeyStoreType::Sqlite).unwrap();
+
+            let id_alice = Handle::from_str_unsafe("alice");
+            let alice_init = InitPersonEvent::init(&id_alice, "alice smith");
+            manager.add(alice_init, DEFAULT_ACTOR).unwrap();
+
+            for _ in 0..12 {
+                let get_older = PersonCommand::go_around_sun(&id_alice, None);
+                manager.command(get_older).unwrap();
+            }
+
+            // Should read state from the database
+            let manager =
+                DiskAggregateStore::<Person>::build(&d, "person", KeyStoreType::Sqlite).unwrap();
+            let alice = manager.get_latest(&id_alice).unwrap();
+            assert_eq!("alice smith", alice.name());
+            assert_eq!(12, alice.age());
+            assert_eq!(vec![id_alice.clone()], manager.list());
+
+            let (_init, events) = manager.history(&id_alice).unwrap().unpack();
+            assert_eq!(12, events.len());
+            assert_eq!(13, manager.command_history(&id_alice).unwrap().len());
+
+            // Migrate to disk and back, this fails if the target has data
+            assert!(migrate_disk_to_sqlite(&d, &["person"]).is_err());
+
+            // A failed migration leaves nothing behind, so it can be retried
+            let mut db = d.clone();
+            db.push(crate::constants::SQLITE_DB_FILE);
+            let conn = rusqlite::Connection::open(&db).unwrap();
+            let broken = "INSERT INTO store VALUES ('person', 'zed', 'snapshot.json', 'not json')";
+            conn.execute(broken, rusqlite::NO_PARAMS).unwrap();
+            assert!(migrate_sqlite_to_disk(&d, &["person"]).is_err());
+            assert!(!DiskKeyStore::has_name_space(&d, "person"));
+            let fixed = "DELETE FROM store WHERE aggregate = 'zed'";
+            conn.execute(fixed, rusqlite::NO_PARAMS).unwrap();
+
+            assert_eq!(1, migrate_sqlite_to_disk(&d, &["person"]).unwrap());
+            assert!(migrate_sqlite_to_disk(&d, &["person"]).is_err());
+
+            let manager = DiskAggregateStore::<Person>::new(&d, "person").unwrap();
+            let alice = manager.get_latest(&id_alice).unwrap();
+            assert_eq!(12, alice.age());
+            assert_eq!(13, manager.command_history(&id_alice).unwrap().len());
+
+            manager.compact(Time::now() + Duration::days(1)).unwrap();
+
+            drop(conn);
+            fs::remove_file(db).unwrap();
+
+            let mut broken = d.clone();
+            broken.push("person/zed");
+            fs::create_dir_all(&broken).unwrap();
+            fs::write(broken.join("snapshot.json"), "not json").unwrap();
+            assert!(migrate_disk_to_sqlite(&d, &["person"]).is_err());
+            fs::remove_dir_all(&broken).unwrap();
+
+            assert_eq!(1, migrate_disk_to_sqlite(&d, &["person"]).unwrap());
+
+            let manager =
+                DiskAggregateStore::<Person>::build(&d, "person", KeyStoreType::Sqlite).unwrap();
+            let (_init, events) = manager.history(&id_alice).unwrap().unpack();
+            assert_eq!(12, events.len());
+            assert_eq!(13, manager.command_history(&id_alice).unwrap().len());
+
+            // Archived aggregates are no longer known
+            manager.archive(&id_alice).unwrap();
+            assert!(!manager.has(&id_alice));
+            assert!(manager.list().is_empty());
         })
     }
 }
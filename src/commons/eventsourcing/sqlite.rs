@@ -0,0 +1,539 @@
+use std::any::Any;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{Connection, OptionalExtension, NO_PARAMS};
+use serde::Serialize;
+use serde_json;
+
+use rpki::x509::Time;
+
+use crate::commons::api::Handle;
+use crate::commons::eventsourcing::agg::AggregateHistory;
+use crate::commons::eventsourcing::cmd::StoredCommand;
+use crate::commons::eventsourcing::{
+    Aggregate, DiskKeyStore, Event, KeyStore, KeyStoreError, Storable,
+};
+use crate::constants::{ARCHIVE_DIR, MIGRATION_TMP_DIR, SQLITE_DB_FILE};
+
+/// The time to wait for a lock on the database held by another connection.
+const BUSY_TIMEOUT_SECS: u64 = 10;
+
+//------------ SqliteKeyStore ------------------------------------------------
+
+/// This type can store and retrieve values in an embedded SQLite database,
+/// using json serialization. All name spaces share a single database file
+/// in the work_dir, and a single table where each value is identified by
+/// the name space, the aggregate and its key.
+///
+/// Commands are saved in a single transaction together with their events,
+/// and snapshot, so that a crash cannot leave a partial update behind.
+pub struct SqliteKeyStore {
+    conn: Mutex<Connection>,
+    name_space: String,
+}
+
+impl SqliteKeyStore {
+    /// Opens, or creates, the database in the work_dir.
+    pub fn build(work_dir: &PathBuf, name_space: &str) -> Result<Self, KeyStoreError> {
+        Ok(SqliteKeyStore {
+            conn: Mutex::new(Self::open(work_dir)?),
+            name_space: name_space.to_string(),
+        })
+    }
+
+    /// Opens a connection to the database in the work_dir, and creates the
+    /// table if needed.
+    fn open(work_dir: &PathBuf) -> Result<Connection, KeyStoreError> {
+        let mut path = work_dir.clone();
+        path.push(SQLITE_DB_FILE);
+
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(Duration::from_secs(BUSY_TIMEOUT_SECS))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS store (
+                name_space TEXT NOT NULL,
+                aggregate TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (name_space, aggregate, key)
+            )",
+            NO_PARAMS,
+        )?;
+
+        Ok(conn)
+    }
+
+    /// Returns true if the database file exists in the work_dir, and has any
+    /// aggregates for the name_space.
+    pub fn has_name_space(work_dir: &PathBuf, name_space: &str) -> Result<bool, KeyStoreError> {
+        let mut path = work_dir.clone();
+        path.push(SQLITE_DB_FILE);
+        if path.exists() {
+            Ok(!Self::build(work_dir, name_space)?.aggregates().is_empty())
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Removes all values for the name_space.
+    pub fn remove_name_space(&self) -> Result<(), KeyStoreError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM store WHERE name_space = ?1",
+            params![self.name_space],
+        )?;
+        Ok(())
+    }
+
+    /// Returns all keys and values for the aggregate.
+    pub fn values(&self, id: &Handle) -> Result<Vec<(String, serde_json::Value)>, KeyStoreError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT key, value FROM store WHERE name_space = ?1 AND aggregate = ?2 ORDER BY key",
+        )?;
+        let rows = stmt.query_map(params![self.name_space, id.to_string()], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+
+        let mut values = vec![];
+        for row in rows {
+            let (key, json): (String, String) = row?;
+            values.push((key, serde_json::from_str(&json)?));
+        }
+        Ok(values)
+    }
+
+    /// Inserts all the keys and values for the aggregate in the name_space,
+    /// using the connection, which may be a transaction.
+    fn insert_values(
+        conn: &Connection,
+        name_space: &str,
+        id: &Handle,
+        values: &[(String, serde_json::Value)],
+    ) -> Result<(), KeyStoreError> {
+        for (key, value) in values {
+            conn.execute(
+                "INSERT INTO store (name_space, aggregate, key, value) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    name_space,
+                    id.to_string(),
+                    key,
+                    serde_json::to_string(value)?
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn get_json(&self, id: &Handle, key: &str) -> Result<Option<String>, KeyStoreError> {
+        let conn = self.conn.lock().unwrap();
+        let json = conn
+            .query_row(
+                "SELECT value FROM store WHERE name_space = ?1 AND aggregate = ?2 AND key = ?3",
+                params![self.name_space, id.to_string(), key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(json)
+    }
+
+    /// Inserts a new value, this fails if the key already exists.
+    fn insert<V: Serialize>(
+        &self,
+        conn: &Connection,
+        id: &Handle,
+        key: &str,
+        value: &V,
+    ) -> Result<(), KeyStoreError> {
+        conn.execute(
+            "INSERT INTO store (name_space, aggregate, key, value) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                self.name_space,
+                id.to_string(),
+                key,
+                serde_json::to_string(value)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Inserts a value, or replaces it if the key already exists.
+    fn replace<V: Serialize>(
+        &self,
+        conn: &Connection,
+        id: &Handle,
+        key: &str,
+        value: &V,
+    ) -> Result<(), KeyStoreError> {
+        conn.execute(
+            "INSERT OR REPLACE INTO store (name_space, aggregate, key, value) VALUES (?1, ?2, ?3, ?4)",
+            params![self.name_space, id.to_string(), key, serde_json::to_string(value)?],
+        )?;
+        Ok(())
+    }
+}
+
+impl KeyStore for SqliteKeyStore {
+    type Key = String;
+
+    fn key_for_snapshot() -> Self::Key {
+        "snapshot.json".to_string()
+    }
+
+    fn key_for_event(version: u64) -> Self::Key {
+        format!("delta-{}.json", version)
+    }
+
+    fn key_for_command(time: Time) -> Self::Key {
+        let seconds = time.timestamp();
+        let micros = time.timestamp_subsec_micros();
+        format!("{}_{}.cmd", seconds, micros)
+    }
+
+    fn has_key(&self, id: &Handle, key: &Self::Key) -> bool {
+        self.get_json(id, key)
+            .map(|json| json.is_some())
+            .unwrap_or(false)
+    }
+
+    fn has_aggregate(&self, id: &Handle) -> bool {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT 1 FROM store WHERE name_space = ?1 AND aggregate = ?2 LIMIT 1",
+            params![self.name_space, id.to_string()],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()
+        .map(|found| found.is_some())
+        .unwrap_or(false)
+    }
+
+    /// Returns the aggregates in the name_space. Errors querying the
+    /// database are logged, and rows which cannot be read, or which do not
+    /// contain a valid handle, are logged and skipped.
+    fn aggregates(&self) -> Vec<Handle> {
+        let conn = self.conn.lock().unwrap();
+        let mut res: Vec<Handle> = Vec::new();
+
+        let mut stmt =
+            match conn.prepare("SELECT DISTINCT aggregate FROM store WHERE name_space = ?1") {
+                Ok(stmt) => stmt,
+                Err(e) => {
+                    error!("Could not list aggregates in '{}': {}", self.name_space, e);
+                    return res;
+                }
+            };
+
+        let rows = match stmt.query_map(params![self.name_space], |row| row.get::<_, String>(0)) {
+            Ok(rows) => rows,
+            Err(e) => {
+                error!("Could not list aggregates in '{}': {}", self.name_space, e);
+                return res;
+            }
+        };
+
+        for row in rows {
+            match row.map(|id| Handle::from_str(&id)) {
+                Ok(Ok(handle)) => res.push(handle),
+                Ok(Err(e)) => warn!("Skipping aggregate in '{}': {}", self.name_space, e),
+                Err(e) => warn!("Cannot read aggregate in '{}': {}", self.name_space, e),
+            }
+        }
+
+        res
+    }
+
+    fn store<V: Any + Serialize>(
+        &self,
+        id: &Handle,
+        key: &Self::Key,
+        value: &V,
+    ) -> Result<(), KeyStoreError> {
+        let conn = self.conn.lock().unwrap();
+        self.replace(&conn, id, key, value)
+    }
+
+    fn get<V: Any + Storable>(
+        &self,
+        id: &Handle,
+        key: &Self::Key,
+    ) -> Result<Option<V>, KeyStoreError> {
+        match self.get_json(id, key)? {
+            Some(json) => match serde_json::from_str(&json) {
+                Err(e) => {
+                    warn!(
+                        "Could not deserialize json for '{}' key '{}', got error: '{}'. Will fall back to events.",
+                        id, key, e
+                    );
+                    Ok(None)
+                }
+                Ok(v) => Ok(Some(v)),
+            },
+            None => {
+                trace!("Could not find key '{}' for '{}'", key, id);
+                Ok(None)
+            }
+        }
+    }
+
+    fn get_event<V: Event>(&self, id: &Handle, version: u64) -> Result<Option<V>, KeyStoreError> {
+        match self.get_json(id, &Self::key_for_event(version))? {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => {
+                trace!("No more events for '{}' after version: {}", id, version);
+                Ok(None)
+            }
+        }
+    }
+
+    fn store_event<V: Event>(&self, event: &V) -> Result<(), KeyStoreError> {
+        trace!("Storing event: {}", event);
+
+        let id = event.handle();
+        let key = Self::key_for_event(event.version());
+        if self.has_key(id, &key) {
+            Err(KeyStoreError::KeyExists(key))
+        } else {
+            let conn = self.conn.lock().unwrap();
+            self.insert(&conn, id, &key, event)
+        }
+    }
+
+    fn store_command(&self, command: StoredCommand) -> Result<(), KeyStoreError> {
+        let id = command.handle();
+        let key = Self::key_for_command(command.time());
+
+        if self.has_key(id, &key) {
+            Err(KeyStoreError::KeyExists(key))
+        } else {
+            let conn = self.conn.lock().unwrap();
+            self.insert(&conn, id, &key, &command)
+        }
+    }
+
+    fn get_aggregate<V: Aggregate>(&self, id: &Handle) -> Result<Option<V>, KeyStoreError> {
+        let key = Self::key_for_snapshot();
+        let aggregate_opt = match self.get::<V>(id, &key)? {
+            Some(aggregate) => Some(aggregate),
+            None => match self.get_event::<V::InitEvent>(id, 0)? {
+                Some(e) => Some(V::init(e).map_err(|_| KeyStoreError::InitError)?),
+                None => None,
+            },
+        };
+
+        match aggregate_opt {
+            None => Ok(None),
+            Some(mut aggregate) => {
+                self.update_aggregate(id, &mut aggregate)?;
+                Ok(Some(aggregate))
+            }
+        }
+    }
+
+    fn store_aggregate<V: Aggregate>(
+        &self,
+        id: &Handle,
+        aggregate: &V,
+    ) -> Result<(), KeyStoreError> {
+        let key = Self::key_for_snapshot();
+        self.store(id, &key, aggregate)
+    }
+
+    /// Moves all values for the aggregate to the archive name space, under
+    /// the id of the aggregate and the current time.
+    fn archive_aggregate(&self, id: &Handle) -> Result<(), KeyStoreError> {
+        let archive_name_space = format!("{}/{}", ARCHIVE_DIR, self.name_space);
+        let archive_id = format!("{}/{}", id, Time::now().timestamp());
+
+        info!("Archiving '{}' to '{}'", id, archive_id);
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE store SET name_space = ?1, aggregate = ?2 WHERE name_space = ?3 AND aggregate = ?4",
+            params![archive_name_space, archive_id, self.name_space, id.to_string()],
+        )?;
+
+        if updated == 0 {
+            Err(KeyStoreError::NoHistory(id.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Saves the command, events and snapshot in a single transaction. If
+    /// any of them cannot be saved nothing is saved.
+    fn store_update<A: Aggregate>(
+        &self,
+        id: &Handle,
+        command: StoredCommand,
+        events: &[A::Event],
+        snapshot: Option<&A>,
+    ) -> Result<(), KeyStoreError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        self.insert(&tx, id, &Self::key_for_command(command.time()), &command)?;
+        for event in events {
+            self.insert(&tx, id, &Self::key_for_event(event.version()), event)?;
+        }
+        if let Some(aggregate) = snapshot {
+            self.replace(&tx, id, &Self::key_for_snapshot(), aggregate)?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn stored_commands(&self, id: &Handle) -> Result<Vec<StoredCommand>, KeyStoreError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT value FROM store WHERE name_space = ?1 AND aggregate = ?2 AND key LIKE '%.cmd'",
+        )?;
+        let rows = stmt.query_map(params![self.name_space, id.to_string()], |row| row.get(0))?;
+
+        let mut commands = vec![];
+        for row in rows {
+            let json: String = row?;
+            let command: StoredCommand = serde_json::from_str(&json)?;
+            commands.push(command);
+        }
+
+        commands.sort_by_key(|c| (c.time().timestamp(), c.time().timestamp_subsec_micros()));
+
+        Ok(commands)
+    }
+
+    fn history<A: Aggregate>(&self, id: &Handle) -> Result<AggregateHistory<A>, KeyStoreError> {
+        let init = self
+            .get_event::<A::InitEvent>(id, 0)?
+            .ok_or_else(|| KeyStoreError::NoHistory(id.clone()))?;
+
+        let mut events: Vec<A::Event> = vec![];
+        let mut version = 1;
+        while let Some(e) = self.get_event(id, version)? {
+            events.push(e);
+            version += 1;
+        }
+
+        Ok(AggregateHistory::new(init, events))
+    }
+
+    /// Commands are never archived in the database. Unlike files on disk
+    /// their number does not affect how quickly the store can be used.
+    fn archive_commands(
+        &self,
+        _id: &Handle,
+        _before: Time,
+        _version: u64,
+    ) -> Result<usize, KeyStoreError> {
+        Ok(0)
+    }
+}
+
+//------------ Migration -----------------------------------------------------
+
+/// Copies all aggregates in the name spaces from disk into the database,
+/// including their archived commands and events. Returns the number of
+/// copied aggregates. Nothing is copied if the database already has any
+/// aggregates in these name spaces. The files on disk are left in place.
+///
+/// Everything is copied in a single transaction, so that nothing is left
+/// in the database if the migration fails, and it can simply be retried.
+pub fn migrate_disk_to_sqlite(
+    work_dir: &PathBuf,
+    name_spaces: &[&str],
+) -> Result<usize, KeyStoreError> {
+    for name_space in name_spaces {
+        if SqliteKeyStore::has_name_space(work_dir, name_space)? {
+            return Err(KeyStoreError::MigrationTargetNotEmpty(
+                name_space.to_string(),
+            ));
+        }
+    }
+
+    let mut conn = SqliteKeyStore::open(work_dir)?;
+    let tx = conn.transaction()?;
+
+    let mut migrated = 0;
+    for name_space in name_spaces {
+        let disk = DiskKeyStore::new(work_dir, name_space);
+        for id in disk.aggregates() {
+            info!("Migrating '{}' in '{}' to the database", id, name_space);
+            SqliteKeyStore::insert_values(&tx, name_space, &id, &disk.values(&id)?)?;
+            migrated += 1;
+        }
+    }
+
+    tx.commit()?;
+    Ok(migrated)
+}
+
+/// Copies all aggregates in the name spaces from the database to disk.
+/// Returns the number of copied aggregates. Nothing is copied if there
+/// already is a directory for any of these name spaces. The database is
+/// left in place.
+///
+/// The aggregates are written to a temporary directory first, and the name
+/// spaces are only moved in place after all aggregates were written, so that
+/// nothing is left behind if the migration fails, and it can be retried.
+pub fn migrate_sqlite_to_disk(
+    work_dir: &PathBuf,
+    name_spaces: &[&str],
+) -> Result<usize, KeyStoreError> {
+    for name_space in name_spaces {
+        if DiskKeyStore::has_name_space(work_dir, name_space) {
+            return Err(KeyStoreError::MigrationTargetNotEmpty(
+                name_space.to_string(),
+            ));
+        }
+    }
+
+    let mut tmp_dir = work_dir.clone();
+    tmp_dir.push(MIGRATION_TMP_DIR);
+    if tmp_dir.exists() {
+        fs::remove_dir_all(&tmp_dir)?;
+    }
+
+    let res = copy_sqlite_to_disk(work_dir, &tmp_dir, name_spaces).and_then(|migrated| {
+        for name_space in name_spaces {
+            let mut from = tmp_dir.clone();
+            from.push(name_space);
+            let mut to = work_dir.clone();
+            to.push(name_space);
+
+            // An existing directory is empty, see the check above.
+            if to.exists() {
+                fs::remove_dir(&to)?;
+            }
+            fs::rename(from, to)?;
+        }
+        Ok(migrated)
+    });
+
+    if let Err(e) = fs::remove_dir_all(&tmp_dir) {
+        warn!("Could not remove '{}': {}", tmp_dir.to_string_lossy(), e);
+    }
+
+    res
+}
+
+fn copy_sqlite_to_disk(
+    work_dir: &PathBuf,
+    target_dir: &PathBuf,
+    name_spaces: &[&str],
+) -> Result<usize, KeyStoreError> {
+    let mut migrated = 0;
+    for name_space in name_spaces {
+        let sqlite = SqliteKeyStore::build(work_dir, name_space)?;
+        let disk = DiskKeyStore::under_work_dir(target_dir, name_space)?;
+        for id in sqlite.aggregates() {
+            info!("Migrating '{}' in '{}' to disk", id, name_space);
+            disk.store_values(&id, &sqlite.values(&id)?)?;
+            migrated += 1;
+        }
+    }
+    Ok(migrated)
+}
@@ -1,21 +1,30 @@
 use std::any::Any;
+use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::Write;
 use std::path::PathBuf;
-
-use serde::de::DeserializeOwned;
-use serde::Serialize;
+use std::str::FromStr;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite;
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json;
 
 use rpki::x509::Time;
 
 use crate::commons::api::Handle;
 use crate::commons::eventsourcing::agg::AggregateHistory;
-use crate::commons::eventsourcing::cmd::StoredCommand;
-use crate::commons::eventsourcing::{Aggregate, Event};
+use crate::commons::eventsourcing::cmd::{StoredCommand, StoredEffect};
+use crate::commons::eventsourcing::{Aggregate, Event, SqliteKeyStore};
 use crate::commons::util::file;
+use crate::constants::{ARCHIVE_DIR, BUNDLES_DIR};
+
+const BUNDLE_EXTENSION: &str = ".json.gz";
 
 //------------ Storable ------------------------------------------------------
 
@@ -75,6 +84,50 @@ pub trait KeyStore {
         id: &Handle,
         aggregate: &V,
     ) -> Result<(), KeyStoreError>;
+
+    /// Moves all keys for the aggregate out of the store, so that it is no
+    /// longer known, but keeps them around for later inspection.
+    fn archive_aggregate(&self, id: &Handle) -> Result<(), KeyStoreError>;
+
+    /// Saves a processed command together with its events, and a snapshot
+    /// of the updated aggregate if one is given.
+    fn store_update<A: Aggregate>(
+        &self,
+        id: &Handle,
+        command: StoredCommand,
+        events: &[A::Event],
+        snapshot: Option<&A>,
+    ) -> Result<(), KeyStoreError>;
+
+    /// Applies all events after the current version of the aggregate.
+    fn update_aggregate<A: Aggregate>(
+        &self,
+        id: &Handle,
+        aggregate: &mut A,
+    ) -> Result<(), KeyStoreError> {
+        while let Some(e) = self.get_event(id, aggregate.version())? {
+            aggregate.apply(e);
+        }
+        Ok(())
+    }
+
+    /// Returns all commands stored for the aggregate, in the order in which
+    /// they were processed.
+    fn stored_commands(&self, id: &Handle) -> Result<Vec<StoredCommand>, KeyStoreError>;
+
+    /// Returns the full history of the aggregate.
+    fn history<A: Aggregate>(&self, id: &Handle) -> Result<AggregateHistory<A>, KeyStoreError>;
+
+    /// Moves the commands which were processed before the given time, and
+    /// the events that resulted from them, out of the way of the aggregate.
+    /// Only commands with events before the given version are moved. Returns
+    /// the number of archived commands.
+    fn archive_commands(
+        &self,
+        id: &Handle,
+        before: Time,
+        version: u64,
+    ) -> Result<usize, KeyStoreError>;
 }
 
 //------------ KeyStoreError -------------------------------------------------
@@ -88,6 +141,9 @@ pub enum KeyStoreError {
     #[display(fmt = "{}", _0)]
     JsonError(serde_json::Error),
 
+    #[display(fmt = "{}", _0)]
+    SqliteError(rusqlite::Error),
+
     #[display(fmt = "Key '{}' already exists", _0)]
     KeyExists(String),
 
@@ -96,6 +152,12 @@ pub enum KeyStoreError {
 
     #[display(fmt = "No history for aggregate with key '{}'", _0)]
     NoHistory(Handle),
+
+    #[display(fmt = "Cannot migrate '{}', the target already has data for it", _0)]
+    MigrationTargetNotEmpty(String),
+
+    #[display(fmt = "Archived events for '{}' are missing version {}", _0, _1)]
+    ArchivedEventMissing(Handle, u64),
 }
 
 impl From<io::Error> for KeyStoreError {
@@ -110,14 +172,33 @@ impl From<serde_json::Error> for KeyStoreError {
     }
 }
 
+impl From<rusqlite::Error> for KeyStoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        KeyStoreError::SqliteError(e)
+    }
+}
+
 impl std::error::Error for KeyStoreError {}
 
+//------------ EventBundle ---------------------------------------------------
+
+/// Commands which were processed in the same month, and the events which
+/// resulted from them, archived in a single compressed file. The events are
+/// kept as plain json, so that they can be archived without knowing the
+/// type of the aggregate.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct EventBundle {
+    events: BTreeMap<u64, serde_json::Value>,
+    commands: Vec<StoredCommand>,
+}
+
 //------------ DiskKeyStore --------------------------------------------------
 
 /// This type can store and retrieve values to/from disk, using json
 /// serialization.
 pub struct DiskKeyStore {
     dir: PathBuf,
+    archive: PathBuf,
 }
 
 impl KeyStore for DiskKeyStore {
@@ -254,7 +335,11 @@ impl KeyStore for DiskKeyStore {
         let aggregate_opt = match self.get::<V>(id, &key)? {
             Some(aggregate) => Some(aggregate),
             None => match self.get_event::<V::InitEvent>(id, 0)? {
-                Some(e) => Some(V::init(e).map_err(|_| KeyStoreError::InitError)?),
+                Some(e) => {
+                    let mut aggregate = V::init(e).map_err(|_| KeyStoreError::InitError)?;
+                    self.apply_archived_events(id, &mut aggregate)?;
+                    Some(aggregate)
+                }
                 None => None,
             },
         };
@@ -276,13 +361,198 @@ impl KeyStore for DiskKeyStore {
         let key = Self::key_for_snapshot();
         self.store(id, &key, aggregate)
     }
+
+    fn archive_aggregate(&self, id: &Handle) -> Result<(), KeyStoreError> {
+        let dir = self.dir_for_aggregate(id);
+        if !dir.is_dir() {
+            return Err(KeyStoreError::NoHistory(id.clone()));
+        }
+
+        let mut archive_dir = self.archive.clone();
+        archive_dir.push(id.to_path_buf());
+        fs::create_dir_all(&archive_dir)?;
+        archive_dir.push(Time::now().timestamp().to_string());
+
+        info!("Archiving '{}' to '{}'", id, archive_dir.to_string_lossy());
+        fs::rename(dir, archive_dir)?;
+        Ok(())
+    }
+
+    /// Saves the command, events and snapshot one by one. Note that this is
+    /// not atomic, if saving fails halfway some files may have been written.
+    fn store_update<A: Aggregate>(
+        &self,
+        id: &Handle,
+        command: StoredCommand,
+        events: &[A::Event],
+        snapshot: Option<&A>,
+    ) -> Result<(), KeyStoreError> {
+        self.store_command(command)?;
+        for event in events {
+            self.store_event(event)?;
+        }
+        if let Some(aggregate) = snapshot {
+            self.store_aggregate(id, aggregate)?;
+        }
+        Ok(())
+    }
+
+    /// Returns all commands stored for the aggregate, in the order in which
+    /// they were processed. This includes archived commands.
+    fn stored_commands(&self, id: &Handle) -> Result<Vec<StoredCommand>, KeyStoreError> {
+        let mut commands = vec![];
+
+        let dir = self.dir_for_aggregate(id);
+        if !dir.is_dir() {
+            return Ok(commands);
+        }
+
+        for period in self.bundle_periods(id)? {
+            commands.append(&mut self.get_bundle(id, &period)?.commands);
+        }
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map(|ext| ext == "cmd").unwrap_or(false) {
+                let f = File::open(&path)?;
+                let command: StoredCommand = serde_json::from_reader(f)?;
+                commands.push(command);
+            }
+        }
+
+        commands.sort_by_key(|c| (c.time().timestamp(), c.time().timestamp_subsec_micros()));
+
+        // Commands may be both archived and on disk, if archiving them was
+        // interrupted before they could be removed.
+        commands.dedup();
+
+        Ok(commands)
+    }
+
+    /// Returns the full history of the aggregate, including archived events.
+    fn history<A: Aggregate>(&self, id: &Handle) -> Result<AggregateHistory<A>, KeyStoreError> {
+        let init = self
+            .get_event::<A::InitEvent>(id, 0)?
+            .ok_or_else(|| KeyStoreError::NoHistory(id.clone()))?;
+
+        let mut archived = BTreeMap::new();
+        for period in self.bundle_periods(id)? {
+            archived.append(&mut self.get_bundle(id, &period)?.events);
+        }
+
+        let mut events: Vec<A::Event> = vec![];
+        let mut version = 1;
+        loop {
+            let event = match archived.remove(&version) {
+                Some(json) => Some(serde_json::from_value(json)?),
+                None => self.get_event(id, version)?,
+            };
+            match event {
+                Some(e) => events.push(e),
+                None => break,
+            }
+            version += 1;
+        }
+
+        Ok(AggregateHistory::new(init, events))
+    }
+
+    /// Moves the commands which were processed before the given time, and
+    /// the events that resulted from them, into compressed bundles for each
+    /// month. Only commands with events before the given version are moved.
+    /// This should be the version of the latest snapshot, so that the
+    /// aggregate can be loaded without the archived events. Returns the
+    /// number of archived commands.
+    fn archive_commands(
+        &self,
+        id: &Handle,
+        before: Time,
+        version: u64,
+    ) -> Result<usize, KeyStoreError> {
+        let dir = self.dir_for_aggregate(id);
+        if !dir.is_dir() {
+            return Ok(0);
+        }
+
+        let mut bundles: BTreeMap<String, EventBundle> = BTreeMap::new();
+        let mut archived_files = vec![];
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.extension().map(|ext| ext == "cmd").unwrap_or(false) {
+                continue;
+            }
+
+            let f = File::open(&path)?;
+            let command: StoredCommand = serde_json::from_reader(f)?;
+            if command.time().timestamp() >= before.timestamp() {
+                continue;
+            }
+
+            let versions = match command.effect() {
+                StoredEffect::Error(_) => vec![],
+                StoredEffect::Events(versions) => versions.clone(),
+            };
+            if versions.iter().any(|v| *v >= version) {
+                continue;
+            }
+
+            let period = command.time().format("%Y-%m").to_string();
+            let bundle = bundles.entry(period).or_insert_with(EventBundle::default);
+
+            for v in versions {
+                let event_path = self.path_for_event(id, v);
+                if event_path.exists() {
+                    let f = File::open(&event_path)?;
+                    bundle.events.insert(v, serde_json::from_reader(f)?);
+                    archived_files.push(event_path);
+                }
+            }
+            bundle.commands.push(command);
+            archived_files.push(path);
+        }
+
+        let mut archived = 0;
+        for (period, mut new) in bundles {
+            archived += new.commands.len();
+
+            let mut bundle = self.get_bundle(id, &period)?;
+            bundle.events.append(&mut new.events);
+            for command in new.commands {
+                if !bundle.commands.contains(&command) {
+                    bundle.commands.push(command);
+                }
+            }
+            bundle
+                .commands
+                .sort_by_key(|c| (c.time().timestamp(), c.time().timestamp_subsec_micros()));
+
+            self.save_bundle(id, &period, &bundle)?;
+        }
+
+        // Only remove the files after they were saved in the bundles.
+        for path in archived_files {
+            fs::remove_file(path)?;
+        }
+
+        if archived > 0 {
+            info!("Archived {} commands for '{}'", archived, id);
+        }
+
+        Ok(archived)
+    }
 }
 
 impl DiskKeyStore {
     pub fn new(work_dir: &PathBuf, name_space: &str) -> Self {
         let mut dir = work_dir.clone();
         dir.push(name_space);
-        DiskKeyStore { dir }
+
+        let mut archive = work_dir.clone();
+        archive.push(ARCHIVE_DIR);
+        archive.push(name_space);
+
+        DiskKeyStore { dir, archive }
     }
 
     /// Creates a directory for the name_space under the work_dir.
@@ -295,6 +565,66 @@ impl DiskKeyStore {
         Ok(Self::new(work_dir, name_space))
     }
 
+    /// Returns true if there is a directory for the name_space under the
+    /// work_dir, which is not empty.
+    pub fn has_name_space(work_dir: &PathBuf, name_space: &str) -> bool {
+        let mut path = work_dir.clone();
+        path.push(name_space);
+        fs::read_dir(path)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false)
+    }
+
+    /// Removes the directory for the name_space.
+    pub fn remove_name_space(&self) -> Result<(), KeyStoreError> {
+        fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+
+    /// Returns all keys and values for the aggregate. Archived commands and
+    /// events are included as if they were never archived.
+    pub fn values(&self, id: &Handle) -> Result<Vec<(String, serde_json::Value)>, KeyStoreError> {
+        let mut values = BTreeMap::new();
+
+        for period in self.bundle_periods(id)? {
+            let bundle = self.get_bundle(id, &period)?;
+            for (version, event) in bundle.events {
+                let key = Self::key_for_event(version);
+                values.insert(key.to_string_lossy().to_string(), event);
+            }
+            for command in bundle.commands {
+                let key = Self::key_for_command(command.time());
+                values.insert(
+                    key.to_string_lossy().to_string(),
+                    serde_json::to_value(command)?,
+                );
+            }
+        }
+
+        for entry in fs::read_dir(self.dir_for_aggregate(id))? {
+            let path = entry?.path();
+            if path.is_file() {
+                let key = path.file_name().unwrap().to_string_lossy().to_string();
+                let f = File::open(&path)?;
+                values.insert(key, serde_json::from_reader(f)?);
+            }
+        }
+
+        Ok(values.into_iter().collect())
+    }
+
+    /// Saves all the keys and values for the aggregate.
+    pub fn store_values(
+        &self,
+        id: &Handle,
+        values: &[(String, serde_json::Value)],
+    ) -> Result<(), KeyStoreError> {
+        for (key, value) in values {
+            self.store(id, &PathBuf::from(key), value)?;
+        }
+        Ok(())
+    }
+
     fn file_path(&self, id: &Handle, key: &<Self as KeyStore>::Key) -> PathBuf {
         let mut file_path = self.dir_for_aggregate(id);
         file_path.push(key);
@@ -312,30 +642,322 @@ impl DiskKeyStore {
         file_path.push(format!("delta-{}.json", version));
         file_path
     }
+}
 
-    pub fn update_aggregate<A: Aggregate>(
+/// # Archiving events and commands
+///
+impl DiskKeyStore {
+    /// Applies the archived events to an aggregate which was initialised
+    /// from its init event, i.e. when there is no usable snapshot. Events
+    /// after the archived events are applied by update_aggregate. Returns an
+    /// error if an event is missing, rather than returning an aggregate
+    /// which silently lacks the events after it.
+    fn apply_archived_events<A: Aggregate>(
         &self,
         id: &Handle,
         aggregate: &mut A,
     ) -> Result<(), KeyStoreError> {
-        while let Some(e) = self.get_event(id, aggregate.version())? {
-            aggregate.apply(e);
+        for period in self.bundle_periods(id)? {
+            for (version, json) in self.get_bundle(id, &period)?.events {
+                if version > aggregate.version() {
+                    return Err(KeyStoreError::ArchivedEventMissing(
+                        id.clone(),
+                        aggregate.version(),
+                    ));
+                } else if version == aggregate.version() {
+                    aggregate.apply(serde_json::from_value(json)?);
+                }
+            }
         }
         Ok(())
     }
 
-    pub fn history<A: Aggregate>(&self, id: &Handle) -> Result<AggregateHistory<A>, KeyStoreError> {
-        let init = self
-            .get_event::<A::InitEvent>(id, 0)?
-            .ok_or_else(|| KeyStoreError::NoHistory(id.clone()))?;
+    /// Returns the months for which there are bundles, oldest first.
+    fn bundle_periods(&self, id: &Handle) -> Result<Vec<String>, KeyStoreError> {
+        let mut periods = vec![];
 
-        let mut events: Vec<A::Event> = vec![];
-        let mut version = 1;
-        while let Some(e) = self.get_event(id, version)? {
-            events.push(e);
-            version += 1;
+        let dir = self.dir_for_bundles(id);
+        if dir.is_dir() {
+            for entry in fs::read_dir(dir)? {
+                let name = entry?.file_name().to_string_lossy().to_string();
+                if name.ends_with(BUNDLE_EXTENSION) {
+                    periods.push(name.trim_end_matches(BUNDLE_EXTENSION).to_string());
+                }
+            }
         }
 
-        Ok(AggregateHistory::new(init, events))
+        periods.sort();
+        Ok(periods)
+    }
+
+    fn get_bundle(&self, id: &Handle, period: &str) -> Result<EventBundle, KeyStoreError> {
+        let path = self.path_for_bundle(id, period);
+        if path.exists() {
+            let f = File::open(path)?;
+            Ok(serde_json::from_reader(GzDecoder::new(f))?)
+        } else {
+            Ok(EventBundle::default())
+        }
+    }
+
+    /// Saves the bundle to a temporary file first, so that an existing
+    /// bundle is not lost if saving fails.
+    fn save_bundle(
+        &self,
+        id: &Handle,
+        period: &str,
+        bundle: &EventBundle,
+    ) -> Result<(), KeyStoreError> {
+        let path = self.path_for_bundle(id, period);
+        let mut tmp_path = path.clone();
+        tmp_path.set_extension("gz.tmp");
+
+        let f = file::create_file_with_path(&tmp_path)?;
+        let mut encoder = GzEncoder::new(f, Compression::default());
+        serde_json::to_writer(&mut encoder, bundle)?;
+        encoder.finish()?;
+
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    fn dir_for_bundles(&self, id: &Handle) -> PathBuf {
+        let mut dir_path = self.dir_for_aggregate(id);
+        dir_path.push(BUNDLES_DIR);
+        dir_path
+    }
+
+    fn path_for_bundle(&self, id: &Handle, period: &str) -> PathBuf {
+        let mut file_path = self.dir_for_bundles(id);
+        file_path.push(format!("{}{}", period, BUNDLE_EXTENSION));
+        file_path
+    }
+}
+
+//------------ KeyStoreType --------------------------------------------------
+
+/// The type of KeyStore used for the aggregates, as set in the config.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyStoreType {
+    Disk,
+    Sqlite,
+}
+
+impl FromStr for KeyStoreType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disk" => Ok(KeyStoreType::Disk),
+            "sqlite" => Ok(KeyStoreType::Sqlite),
+            _ => Err(format!(
+                "expected \"disk\", or \"sqlite\", found: \"{}\"",
+                s
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyStoreType {
+    fn deserialize<D>(d: D) -> Result<KeyStoreType, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(d)?;
+        KeyStoreType::from_str(&string).map_err(de::Error::custom)
+    }
+}
+
+//------------ KrillKeyStore -------------------------------------------------
+
+/// This type wraps the KeyStore implementations, so that the type of store
+/// can be chosen in the config. Keys are the same for all implementations.
+pub enum KrillKeyStore {
+    Disk(DiskKeyStore),
+    Sqlite(SqliteKeyStore),
+}
+
+impl KrillKeyStore {
+    /// Creates the KeyStore of the given type for the name_space under the
+    /// work_dir.
+    pub fn build(
+        work_dir: &PathBuf,
+        name_space: &str,
+        store_type: KeyStoreType,
+    ) -> Result<Self, KeyStoreError> {
+        match store_type {
+            KeyStoreType::Disk => Ok(KrillKeyStore::Disk(DiskKeyStore::under_work_dir(
+                work_dir, name_space,
+            )?)),
+            KeyStoreType::Sqlite => Ok(KrillKeyStore::Sqlite(SqliteKeyStore::build(
+                work_dir, name_space,
+            )?)),
+        }
+    }
+
+    /// Returns true if there are any aggregates for the name_space in the
+    /// store of the given type.
+    pub fn has_name_space(
+        work_dir: &PathBuf,
+        name_space: &str,
+        store_type: KeyStoreType,
+    ) -> Result<bool, KeyStoreError> {
+        match store_type {
+            KeyStoreType::Disk => Ok(DiskKeyStore::has_name_space(work_dir, name_space)),
+            KeyStoreType::Sqlite => SqliteKeyStore::has_name_space(work_dir, name_space),
+        }
+    }
+
+    /// Removes everything stored for the name_space.
+    pub fn remove_name_space(&self) -> Result<(), KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.remove_name_space(),
+            KrillKeyStore::Sqlite(store) => store.remove_name_space(),
+        }
+    }
+}
+
+impl KeyStore for KrillKeyStore {
+    type Key = String;
+
+    fn key_for_snapshot() -> Self::Key {
+        SqliteKeyStore::key_for_snapshot()
+    }
+
+    fn key_for_event(version: u64) -> Self::Key {
+        SqliteKeyStore::key_for_event(version)
+    }
+
+    fn key_for_command(time: Time) -> Self::Key {
+        SqliteKeyStore::key_for_command(time)
+    }
+
+    fn has_key(&self, id: &Handle, key: &Self::Key) -> bool {
+        match self {
+            KrillKeyStore::Disk(store) => store.has_key(id, &PathBuf::from(key)),
+            KrillKeyStore::Sqlite(store) => store.has_key(id, key),
+        }
+    }
+
+    fn has_aggregate(&self, id: &Handle) -> bool {
+        match self {
+            KrillKeyStore::Disk(store) => store.has_aggregate(id),
+            KrillKeyStore::Sqlite(store) => store.has_aggregate(id),
+        }
+    }
+
+    fn aggregates(&self) -> Vec<Handle> {
+        match self {
+            KrillKeyStore::Disk(store) => store.aggregates(),
+            KrillKeyStore::Sqlite(store) => store.aggregates(),
+        }
+    }
+
+    fn store<V: Any + Serialize>(
+        &self,
+        id: &Handle,
+        key: &Self::Key,
+        value: &V,
+    ) -> Result<(), KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.store(id, &PathBuf::from(key), value),
+            KrillKeyStore::Sqlite(store) => store.store(id, key, value),
+        }
+    }
+
+    fn get<V: Any + Storable>(
+        &self,
+        id: &Handle,
+        key: &Self::Key,
+    ) -> Result<Option<V>, KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.get(id, &PathBuf::from(key)),
+            KrillKeyStore::Sqlite(store) => store.get(id, key),
+        }
+    }
+
+    fn get_event<V: Event>(&self, id: &Handle, version: u64) -> Result<Option<V>, KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.get_event(id, version),
+            KrillKeyStore::Sqlite(store) => store.get_event(id, version),
+        }
+    }
+
+    fn store_event<V: Event>(&self, event: &V) -> Result<(), KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.store_event(event),
+            KrillKeyStore::Sqlite(store) => store.store_event(event),
+        }
+    }
+
+    fn store_command(&self, command: StoredCommand) -> Result<(), KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.store_command(command),
+            KrillKeyStore::Sqlite(store) => store.store_command(command),
+        }
+    }
+
+    fn get_aggregate<V: Aggregate>(&self, id: &Handle) -> Result<Option<V>, KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.get_aggregate(id),
+            KrillKeyStore::Sqlite(store) => store.get_aggregate(id),
+        }
+    }
+
+    fn store_aggregate<V: Aggregate>(
+        &self,
+        id: &Handle,
+        aggregate: &V,
+    ) -> Result<(), KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.store_aggregate(id, aggregate),
+            KrillKeyStore::Sqlite(store) => store.store_aggregate(id, aggregate),
+        }
+    }
+
+    fn archive_aggregate(&self, id: &Handle) -> Result<(), KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.archive_aggregate(id),
+            KrillKeyStore::Sqlite(store) => store.archive_aggregate(id),
+        }
+    }
+
+    fn store_update<A: Aggregate>(
+        &self,
+        id: &Handle,
+        command: StoredCommand,
+        events: &[A::Event],
+        snapshot: Option<&A>,
+    ) -> Result<(), KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.store_update(id, command, events, snapshot),
+            KrillKeyStore::Sqlite(store) => store.store_update(id, command, events, snapshot),
+        }
+    }
+
+    fn stored_commands(&self, id: &Handle) -> Result<Vec<StoredCommand>, KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.stored_commands(id),
+            KrillKeyStore::Sqlite(store) => store.stored_commands(id),
+        }
+    }
+
+    fn history<A: Aggregate>(&self, id: &Handle) -> Result<AggregateHistory<A>, KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.history(id),
+            KrillKeyStore::Sqlite(store) => store.history(id),
+        }
+    }
+
+    fn archive_commands(
+        &self,
+        id: &Handle,
+        before: Time,
+        version: u64,
+    ) -> Result<usize, KeyStoreError> {
+        match self {
+            KrillKeyStore::Disk(store) => store.archive_commands(id, before, version),
+            KrillKeyStore::Sqlite(store) => store.archive_commands(id, before, version),
+        }
     }
 }
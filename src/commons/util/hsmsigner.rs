@@ -0,0 +1,409 @@
+//! Support for signing things using keys kept in a Hardware Security Module
+//! (HSM), accessed through a PKCS#11 library.
+//!
+//! Keys are generated on the token and never leave it. We find them again
+//! using their key identifier, which is stored in the CKA_ID attribute of
+//! both the public and the private key object.
+use std::fmt;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use openssl::bn::BigNum;
+use openssl::rsa::Rsa;
+use pkcs11::errors::Error as Pkcs11Error;
+use pkcs11::types::*;
+use pkcs11::Ctx;
+
+use rpki::crypto::signer::KeyError;
+use rpki::crypto::{
+    KeyIdentifier, PublicKey, PublicKeyFormat, Signature, SignatureAlgorithm, Signer, SigningError,
+};
+
+use crate::commons::util::softsigner::SignerError;
+
+const RSA_KEY_BITS: CK_ULONG = 2048;
+const RSA_PUBLIC_EXPONENT: [u8; 3] = [0x01, 0x00, 0x01];
+
+//------------ Pkcs11Signer --------------------------------------------------
+
+/// A signer which keeps its keys on a PKCS#11 token.
+///
+/// All operations use a single logged in session. PKCS#11 sessions must not
+/// be used for concurrent operations, so access is serialized.
+#[derive(Clone)]
+pub struct Pkcs11Signer {
+    lib: PathBuf,
+    slot: CK_SLOT_ID,
+    session: Arc<Pkcs11Session>,
+}
+
+impl Pkcs11Signer {
+    /// Loads the PKCS#11 library, and logs in to the token in the slot with
+    /// the given id, or else the slot holding a token with the given label.
+    pub fn build(
+        lib: &PathBuf,
+        slot: Option<u64>,
+        token_label: Option<&str>,
+        pin: &str,
+    ) -> Result<Self, SignerError> {
+        let ctx = Ctx::new_and_initialize(lib)?;
+
+        let slot = match (slot, token_label) {
+            (Some(slot), _) => slot as CK_SLOT_ID,
+            (None, Some(label)) => Self::find_slot(&ctx, label)?,
+            (None, None) => {
+                return Err(SignerError::Pkcs11Error(
+                    "either a slot or a token label must be configured".to_string(),
+                ))
+            }
+        };
+
+        let session = ctx.open_session(slot, CKF_SERIAL_SESSION | CKF_RW_SESSION, None, None)?;
+        ctx.login(session, CKU_USER, Some(pin))?;
+
+        info!(
+            "Using PKCS#11 library '{}' with slot '{}'",
+            lib.to_string_lossy(),
+            slot
+        );
+
+        Ok(Pkcs11Signer {
+            lib: lib.clone(),
+            slot,
+            session: Arc::new(Pkcs11Session {
+                ctx,
+                handle: session,
+                lock: Mutex::new(()),
+            }),
+        })
+    }
+
+    fn find_slot(ctx: &Ctx, label: &str) -> Result<CK_SLOT_ID, SignerError> {
+        for slot in ctx.get_slot_list(true)? {
+            let info = ctx.get_token_info(slot)?;
+            // Token labels are padded with spaces to 32 bytes.
+            let token_label = String::from_utf8_lossy(&info.label).trim_end().to_string();
+            if token_label == label {
+                return Ok(slot);
+            }
+        }
+        Err(SignerError::Pkcs11Error(format!(
+            "no token found with label '{}'",
+            label
+        )))
+    }
+}
+
+impl fmt::Debug for Pkcs11Signer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Pkcs11Signer {{ lib: {}, slot: {} }}",
+            self.lib.to_string_lossy(),
+            self.slot
+        )
+    }
+}
+
+impl Pkcs11Signer {
+    fn mechanism(mechanism: CK_MECHANISM_TYPE) -> CK_MECHANISM {
+        CK_MECHANISM {
+            mechanism,
+            pParameter: ptr::null_mut(),
+            ulParameterLen: 0,
+        }
+    }
+
+    /// Generates an RSA key pair, on the token if persistent is true, or
+    /// else as session objects which disappear when the session is closed.
+    fn generate_key_pair(
+        &self,
+        id: &[u8],
+        persistent: bool,
+    ) -> Result<(CK_OBJECT_HANDLE, CK_OBJECT_HANDLE), SignerError> {
+        let token = if persistent { CK_TRUE } else { CK_FALSE };
+
+        let pub_template = vec![
+            CK_ATTRIBUTE::new(CKA_TOKEN).with_bool(&token),
+            CK_ATTRIBUTE::new(CKA_VERIFY).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_MODULUS_BITS).with_ck_ulong(&RSA_KEY_BITS),
+            CK_ATTRIBUTE::new(CKA_PUBLIC_EXPONENT).with_bytes(&RSA_PUBLIC_EXPONENT),
+            CK_ATTRIBUTE::new(CKA_ID).with_bytes(id),
+        ];
+
+        let priv_template = vec![
+            CK_ATTRIBUTE::new(CKA_TOKEN).with_bool(&token),
+            CK_ATTRIBUTE::new(CKA_PRIVATE).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_SENSITIVE).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_EXTRACTABLE).with_bool(&CK_FALSE),
+            CK_ATTRIBUTE::new(CKA_SIGN).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_ID).with_bytes(id),
+        ];
+
+        let mechanism = Self::mechanism(CKM_RSA_PKCS_KEY_PAIR_GEN);
+
+        let _lock = self.session.lock.lock().unwrap();
+        Ok(self.session.ctx.generate_key_pair(
+            self.session.handle,
+            &mechanism,
+            &pub_template,
+            &priv_template,
+        )?)
+    }
+
+    /// Reads the modulus and exponent from a public key object and returns
+    /// the public key info.
+    fn public_key(&self, key: CK_OBJECT_HANDLE) -> Result<PublicKey, SignerError> {
+        let _lock = self.session.lock.lock().unwrap();
+        let ctx = &self.session.ctx;
+
+        // First get the lengths, then the actual values.
+        let mut template = vec![
+            CK_ATTRIBUTE::new(CKA_MODULUS),
+            CK_ATTRIBUTE::new(CKA_PUBLIC_EXPONENT),
+        ];
+        let (_, lengths) = ctx.get_attribute_value(self.session.handle, key, &mut template)?;
+
+        let modulus = vec![0_u8; lengths[0].ulValueLen as usize];
+        let exponent = vec![0_u8; lengths[1].ulValueLen as usize];
+
+        let mut template = vec![
+            CK_ATTRIBUTE::new(CKA_MODULUS).with_bytes(&modulus),
+            CK_ATTRIBUTE::new(CKA_PUBLIC_EXPONENT).with_bytes(&exponent),
+        ];
+        ctx.get_attribute_value(self.session.handle, key, &mut template)?;
+
+        let rsa =
+            Rsa::from_public_components(BigNum::from_slice(&modulus)?, BigNum::from_slice(&exponent)?)?;
+        let mut b = Bytes::from(rsa.public_key_to_der()?);
+        PublicKey::decode(&mut b).map_err(|_| SignerError::DecodeError)
+    }
+
+    /// Finds the object of the given class (public or private key) for the
+    /// key identifier.
+    fn find_key(
+        &self,
+        key_id: &KeyIdentifier,
+        class: CK_OBJECT_CLASS,
+    ) -> Result<CK_OBJECT_HANDLE, KeyError<SignerError>> {
+        let template = vec![
+            CK_ATTRIBUTE::new(CKA_CLASS).with_ck_ulong(&class),
+            CK_ATTRIBUTE::new(CKA_ID).with_bytes(key_id.as_ref()),
+        ];
+
+        let _lock = self.session.lock.lock().unwrap();
+        let ctx = &self.session.ctx;
+        let session = self.session.handle;
+
+        ctx.find_objects_init(session, &template)
+            .map_err(SignerError::from)?;
+        let found = ctx.find_objects(session, 1).map_err(SignerError::from);
+        ctx.find_objects_final(session).map_err(SignerError::from)?;
+
+        found?.first().cloned().ok_or(KeyError::KeyNotFound)
+    }
+
+    fn sign_with_key<D: AsRef<[u8]> + ?Sized>(
+        &self,
+        key: CK_OBJECT_HANDLE,
+        data: &D,
+    ) -> Result<Signature, SignerError> {
+        let mechanism = Self::mechanism(CKM_SHA256_RSA_PKCS);
+
+        let _lock = self.session.lock.lock().unwrap();
+        let ctx = &self.session.ctx;
+        ctx.sign_init(self.session.handle, &mechanism, key)?;
+        let signature = ctx.sign(self.session.handle, data.as_ref())?;
+
+        Ok(Signature::new(
+            SignatureAlgorithm::default(),
+            Bytes::from(signature),
+        ))
+    }
+
+    fn destroy_object(&self, object: CK_OBJECT_HANDLE) -> Result<(), SignerError> {
+        let _lock = self.session.lock.lock().unwrap();
+        Ok(self
+            .session
+            .ctx
+            .destroy_object(self.session.handle, object)?)
+    }
+
+    /// Sets the CKA_ID of a newly generated key pair to the key identifier
+    /// of its public key.
+    fn set_key_id(
+        &self,
+        pub_key: CK_OBJECT_HANDLE,
+        priv_key: CK_OBJECT_HANDLE,
+    ) -> Result<KeyIdentifier, SignerError> {
+        let key_id = self.public_key(pub_key)?.key_identifier();
+
+        let template = vec![CK_ATTRIBUTE::new(CKA_ID).with_bytes(key_id.as_ref())];
+
+        let _lock = self.session.lock.lock().unwrap();
+        let ctx = &self.session.ctx;
+        ctx.set_attribute_value(self.session.handle, pub_key, &template)?;
+        ctx.set_attribute_value(self.session.handle, priv_key, &template)?;
+
+        Ok(key_id)
+    }
+
+    /// Uses a random CKA_ID until we know the key identifier, which can only
+    /// be derived after the key was generated.
+    fn temp_id(&self) -> Result<Vec<u8>, SignerError> {
+        let mut id = vec![0_u8; 20];
+        self.rand(&mut id)?;
+        Ok(id)
+    }
+}
+
+impl Signer for Pkcs11Signer {
+    type KeyId = KeyIdentifier;
+    type Error = SignerError;
+
+    fn create_key(&mut self, _algorithm: PublicKeyFormat) -> Result<Self::KeyId, Self::Error> {
+        let temp_id = self.temp_id()?;
+        let (pub_key, priv_key) = self.generate_key_pair(&temp_id, true)?;
+
+        let res = self.set_key_id(pub_key, priv_key);
+        if res.is_err() {
+            // Do not leave an unusable key pair behind on the token. The
+            // original error is more useful than any error destroying it.
+            let _ = self.destroy_object(priv_key);
+            let _ = self.destroy_object(pub_key);
+        }
+        res
+    }
+
+    fn get_key_info(&self, key_id: &Self::KeyId) -> Result<PublicKey, KeyError<Self::Error>> {
+        let pub_key = self.find_key(key_id, CKO_PUBLIC_KEY)?;
+        Ok(self.public_key(pub_key)?)
+    }
+
+    fn destroy_key(&mut self, key_id: &Self::KeyId) -> Result<(), KeyError<Self::Error>> {
+        let priv_key = self.find_key(key_id, CKO_PRIVATE_KEY)?;
+        self.destroy_object(priv_key)?;
+
+        if let Ok(pub_key) = self.find_key(key_id, CKO_PUBLIC_KEY) {
+            self.destroy_object(pub_key)?;
+        }
+        Ok(())
+    }
+
+    fn sign<D: AsRef<[u8]> + ?Sized>(
+        &self,
+        key_id: &Self::KeyId,
+        _algorithm: SignatureAlgorithm,
+        data: &D,
+    ) -> Result<Signature, SigningError<Self::Error>> {
+        let priv_key = match self.find_key(key_id, CKO_PRIVATE_KEY) {
+            Ok(key) => key,
+            Err(KeyError::KeyNotFound) => return Err(SigningError::KeyNotFound),
+            Err(KeyError::Signer(e)) => return Err(SigningError::Signer(e)),
+        };
+        self.sign_with_key(priv_key, data)
+            .map_err(SigningError::Signer)
+    }
+
+    fn sign_one_off<D: AsRef<[u8]> + ?Sized>(
+        &self,
+        _algorithm: SignatureAlgorithm,
+        data: &D,
+    ) -> Result<(Signature, PublicKey), SignerError> {
+        let temp_id = self.temp_id()?;
+        let (pub_key, priv_key) = self.generate_key_pair(&temp_id, false)?;
+
+        let res = self
+            .sign_with_key(priv_key, data)
+            .and_then(|signature| Ok((signature, self.public_key(pub_key)?)));
+
+        self.destroy_object(priv_key)?;
+        self.destroy_object(pub_key)?;
+
+        res
+    }
+
+    fn rand(&self, target: &mut [u8]) -> Result<(), SignerError> {
+        let _lock = self.session.lock.lock().unwrap();
+        let random = self
+            .session
+            .ctx
+            .generate_random(self.session.handle, target.len() as CK_ULONG)?;
+        target.copy_from_slice(&random);
+        Ok(())
+    }
+}
+
+//------------ Pkcs11Session -------------------------------------------------
+
+/// Wraps the loaded library and logged in session, so that we can log out
+/// and close the session when the last signer clone is dropped.
+struct Pkcs11Session {
+    ctx: Ctx,
+    handle: CK_SESSION_HANDLE,
+    lock: Mutex<()>,
+}
+
+// The context is only used while holding the lock.
+unsafe impl Send for Pkcs11Session {}
+unsafe impl Sync for Pkcs11Session {}
+
+impl Drop for Pkcs11Session {
+    fn drop(&mut self) {
+        let _ = self.ctx.logout(self.handle);
+        let _ = self.ctx.close_session(self.handle);
+    }
+}
+
+impl From<Pkcs11Error> for SignerError {
+    fn from(e: Pkcs11Error) -> Self {
+        SignerError::Pkcs11Error(e.to_string())
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    /// Runs against a local token, e.g. SoftHSM:
+    ///
+    ///   softhsm2-util --init-token --free --label krill --pin 1234 --so-pin 1234
+    ///   KRILL_TEST_PKCS11_LIB=/usr/lib/softhsm/libsofthsm2.so \
+    ///   KRILL_TEST_PKCS11_TOKEN=krill KRILL_TEST_PKCS11_PIN=1234 \
+    ///   cargo test --features hsm
+    ///
+    /// The test is skipped if no library is configured.
+    #[test]
+    fn should_create_sign_and_destroy_key_on_token() {
+        let lib = match env::var("KRILL_TEST_PKCS11_LIB") {
+            Ok(lib) => PathBuf::from(lib),
+            Err(_) => return,
+        };
+        let label = env::var("KRILL_TEST_PKCS11_TOKEN").unwrap_or_else(|_| "krill".to_string());
+        let pin = env::var("KRILL_TEST_PKCS11_PIN").unwrap_or_else(|_| "1234".to_string());
+
+        let mut s = Pkcs11Signer::build(&lib, None, Some(&label), &pin).unwrap();
+
+        let ki = s.create_key(PublicKeyFormat::default()).unwrap();
+        let key = s.get_key_info(&ki).unwrap();
+        assert_eq!(ki, key.key_identifier());
+
+        let data = b"some data to sign";
+        let signature = s.sign(&ki, SignatureAlgorithm::default(), data).unwrap();
+        key.verify(data, &signature).unwrap();
+
+        let (signature, key) = s
+            .sign_one_off(SignatureAlgorithm::default(), data)
+            .unwrap();
+        key.verify(data, &signature).unwrap();
+
+        s.destroy_key(&ki).unwrap();
+        assert!(s.get_key_info(&ki).is_err());
+    }
+}
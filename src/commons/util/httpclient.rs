@@ -52,7 +52,26 @@ fn report_post_and_exit(
     token: Option<&Token>,
     body: PostBody,
 ) {
-    println!("POST:\n  {}", uri);
+    report_body_and_exit("POST", uri, content_type, token, body)
+}
+
+fn report_put_and_exit(
+    uri: &str,
+    content_type: Option<&str>,
+    token: Option<&Token>,
+    body: PostBody,
+) {
+    report_body_and_exit("PUT", uri, content_type, token, body)
+}
+
+fn report_body_and_exit(
+    method: &str,
+    uri: &str,
+    content_type: Option<&str>,
+    token: Option<&Token>,
+    body: PostBody,
+) {
+    println!("{}:\n  {}", method, uri);
 
     if content_type.is_some() || token.is_some() {
         println!("Headers:");
@@ -165,6 +184,55 @@ pub fn post_json_with_response<T: DeserializeOwned>(
     process_json_response(res)
 }
 
+/// Performs a PUT of data that can be serialized into json, and expects
+/// a json response that can be deserialized into the an owned value of the
+/// expected type.
+pub fn put_json_with_response<T: DeserializeOwned>(
+    uri: &str,
+    data: impl Serialize,
+    token: Option<&Token>,
+) -> Result<T, Error> {
+    if env::var(KRILL_CLI_API_ENV).is_ok() {
+        let body = serde_json::to_string_pretty(&data)?;
+        report_put_and_exit(uri, Some(JSON_CONTENT), token, PostBody::String(&body));
+    }
+
+    let body = serde_json::to_string(&data)?;
+    let headers = headers(Some(JSON_CONTENT), token)?;
+    let res = client(uri)?.put(uri).headers(headers).body(body).send()?;
+    process_json_response(res)
+}
+
+/// Performs a POST of a text document, e.g. a CSV file, and expects a json
+/// response that can be deserialized into the an owned value of the
+/// expected type.
+pub fn post_text_with_response<T: DeserializeOwned>(
+    uri: &str,
+    content_type: &str,
+    text: &str,
+    token: Option<&Token>,
+) -> Result<T, Error> {
+    let body = text.to_string();
+    if env::var(KRILL_CLI_API_ENV).is_ok() {
+        report_post_and_exit(uri, Some(content_type), token, PostBody::String(&body));
+    }
+
+    let headers = headers(Some(content_type), token)?;
+    let res = client(uri)?.post(uri).headers(headers).body(body).send()?;
+    process_json_response(res)
+}
+
+/// Performs a POST of an urlencoded form, and expects a json response that
+/// can be deserialized into the an owned value of the expected type.
+pub fn post_form_with_response<T: DeserializeOwned>(
+    uri: &str,
+    form: &[(&str, &str)],
+) -> Result<T, Error> {
+    let headers = headers(None, None)?;
+    let res = client(uri)?.post(uri).headers(headers).form(form).send()?;
+    process_json_response(res)
+}
+
 /// Performs a POST with no data to the given URI and expects and empty 200 OK response.
 pub fn post_empty(uri: &str, token: Option<&Token>) -> Result<(), Error> {
     if env::var(KRILL_CLI_API_ENV).is_ok() {
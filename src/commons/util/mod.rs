@@ -4,7 +4,10 @@ use rpki::crypto::DigestAlgorithm;
 
 pub mod ext_serde;
 pub mod file;
+#[cfg(feature = "hsm")]
+pub mod hsmsigner;
 pub mod httpclient;
+pub mod signer;
 pub mod softsigner;
 pub mod test;
 pub mod xml;
@@ -0,0 +1,124 @@
+//! The signer used by the Krill server, as selected in the configuration.
+use rpki::crypto::signer::KeyError;
+use rpki::crypto::{
+    KeyIdentifier, PublicKey, PublicKeyFormat, Signature, SignatureAlgorithm, Signer, SigningError,
+};
+
+#[cfg(feature = "hsm")]
+use crate::commons::util::hsmsigner::Pkcs11Signer;
+use crate::commons::util::softsigner::{OpenSslSigner, SignerError};
+use crate::daemon::config::{Config, SignerType};
+
+//------------ KrillSigner ---------------------------------------------------
+
+/// Dispatches to the configured signer implementation, so that the rest of
+/// the server does not need to know which one is used.
+#[derive(Clone, Debug)]
+pub enum KrillSigner {
+    OpenSsl(OpenSslSigner),
+
+    #[cfg(feature = "hsm")]
+    Pkcs11(Pkcs11Signer),
+}
+
+impl KrillSigner {
+    pub fn build(config: &Config) -> Result<Self, SignerError> {
+        match config.signer_type {
+            SignerType::OpenSsl => {
+                let passphrase = config
+                    .keys_passphrase()
+                    .map_err(|e| SignerError::KeyEncryption(e.to_string()))?;
+                let signer = match passphrase {
+                    Some(passphrase) => OpenSslSigner::build_encrypted(&config.data_dir, &passphrase)?,
+                    None => OpenSslSigner::build(&config.data_dir)?,
+                };
+                Ok(KrillSigner::OpenSsl(signer))
+            }
+            #[cfg(feature = "hsm")]
+            SignerType::Pkcs11 => {
+                // The presence of these settings is checked in Config::verify
+                let lib = config.pkcs11_lib.as_ref().unwrap();
+                let pin = config.pkcs11_pin.as_ref().unwrap();
+                let token_label = config.pkcs11_token_label.as_ref().map(String::as_str);
+                Ok(KrillSigner::Pkcs11(Pkcs11Signer::build(
+                    lib,
+                    config.pkcs11_slot,
+                    token_label,
+                    pin,
+                )?))
+            }
+            #[cfg(not(feature = "hsm"))]
+            SignerType::Pkcs11 => Err(SignerError::Pkcs11Error(
+                "krill was built without the 'hsm' feature".to_string(),
+            )),
+        }
+    }
+}
+
+impl From<OpenSslSigner> for KrillSigner {
+    fn from(signer: OpenSslSigner) -> Self {
+        KrillSigner::OpenSsl(signer)
+    }
+}
+
+impl Signer for KrillSigner {
+    type KeyId = KeyIdentifier;
+    type Error = SignerError;
+
+    fn create_key(&mut self, algorithm: PublicKeyFormat) -> Result<Self::KeyId, Self::Error> {
+        match self {
+            KrillSigner::OpenSsl(signer) => signer.create_key(algorithm),
+            #[cfg(feature = "hsm")]
+            KrillSigner::Pkcs11(signer) => signer.create_key(algorithm),
+        }
+    }
+
+    fn get_key_info(&self, key_id: &Self::KeyId) -> Result<PublicKey, KeyError<Self::Error>> {
+        match self {
+            KrillSigner::OpenSsl(signer) => signer.get_key_info(key_id),
+            #[cfg(feature = "hsm")]
+            KrillSigner::Pkcs11(signer) => signer.get_key_info(key_id),
+        }
+    }
+
+    fn destroy_key(&mut self, key_id: &Self::KeyId) -> Result<(), KeyError<Self::Error>> {
+        match self {
+            KrillSigner::OpenSsl(signer) => signer.destroy_key(key_id),
+            #[cfg(feature = "hsm")]
+            KrillSigner::Pkcs11(signer) => signer.destroy_key(key_id),
+        }
+    }
+
+    fn sign<D: AsRef<[u8]> + ?Sized>(
+        &self,
+        key_id: &Self::KeyId,
+        algorithm: SignatureAlgorithm,
+        data: &D,
+    ) -> Result<Signature, SigningError<Self::Error>> {
+        match self {
+            KrillSigner::OpenSsl(signer) => signer.sign(key_id, algorithm, data),
+            #[cfg(feature = "hsm")]
+            KrillSigner::Pkcs11(signer) => signer.sign(key_id, algorithm, data),
+        }
+    }
+
+    fn sign_one_off<D: AsRef<[u8]> + ?Sized>(
+        &self,
+        algorithm: SignatureAlgorithm,
+        data: &D,
+    ) -> Result<(Signature, PublicKey), Self::Error> {
+        match self {
+            KrillSigner::OpenSsl(signer) => signer.sign_one_off(algorithm, data),
+            #[cfg(feature = "hsm")]
+            KrillSigner::Pkcs11(signer) => signer.sign_one_off(algorithm, data),
+        }
+    }
+
+    fn rand(&self, target: &mut [u8]) -> Result<(), Self::Error> {
+        match self {
+            KrillSigner::OpenSsl(signer) => signer.rand(target),
+            #[cfg(feature = "hsm")]
+            KrillSigner::Pkcs11(signer) => signer.rand(target),
+        }
+    }
+}
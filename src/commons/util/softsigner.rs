@@ -1,15 +1,18 @@
 //! Support for signing things using software keys (through openssl) and
-//! storing them unencrypted on disk.
+//! storing them on disk, optionally encrypted with a key derived from a
+//! passphrase.
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
-use std::{fs, io};
+use std::{fmt, fs, io};
 
 use bytes::Bytes;
 use openssl::error::ErrorStack;
 use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
 use openssl::pkey::{PKey, PKeyRef, Private};
 use openssl::rsa::Rsa;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
 use serde::{de, ser};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -18,31 +21,139 @@ use rpki::crypto::{
     KeyIdentifier, PublicKey, PublicKeyFormat, Signature, SignatureAlgorithm, Signer, SigningError,
 };
 
+const KEYS_DIR: &str = "keys";
+const KEYS_SALT_FILE: &str = "keys.salt";
+const KDF_ITERATIONS: usize = 100_000;
+
 //------------ OpenSslSigner -------------------------------------------------
 
 /// An openssl based signer.
 ///
-/// Keeps the keys in memory (for now).
+/// Keeps the keys on disk, encrypted if a secret is used.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OpenSslSigner {
     keys_dir: PathBuf,
+
+    #[serde(skip)]
+    secret: Option<KeySecret>,
 }
 
 impl OpenSslSigner {
+    /// Builds a signer which stores keys unencrypted. Fails if encrypted
+    /// keys are found, because they could not be used.
     pub fn build(work_dir: &PathBuf) -> Result<Self, SignerError> {
+        let keys_dir = Self::keys_dir(work_dir)?;
+        let signer = OpenSslSigner {
+            keys_dir,
+            secret: None,
+        };
+
+        for name in signer.key_names()? {
+            if let StoredKey::Encrypted(_) = signer.load_stored_key(&name)? {
+                return Err(SignerError::KeyEncrypted);
+            }
+        }
+
+        Ok(signer)
+    }
+
+    /// Builds a signer which encrypts keys using a secret derived from the
+    /// passphrase. Verifies that existing encrypted keys can be decrypted
+    /// with it, so that a wrong passphrase is caught at startup.
+    pub fn build_encrypted(work_dir: &PathBuf, passphrase: &str) -> Result<Self, SignerError> {
+        if passphrase.is_empty() {
+            return Err(SignerError::KeyEncryption(
+                "passphrase must not be empty".to_string(),
+            ));
+        }
+
+        let keys_dir = Self::keys_dir(work_dir)?;
+        let salt = Self::salt(work_dir)?;
+        let secret = KeySecret::derive(passphrase.as_bytes(), &salt)?;
+
+        let signer = OpenSslSigner {
+            keys_dir,
+            secret: Some(secret),
+        };
+
+        for name in signer.key_names()? {
+            if let StoredKey::Encrypted(_) = signer.load_stored_key(&name)? {
+                signer.load_key_with_name(&name)?;
+                break;
+            }
+        }
+
+        Ok(signer)
+    }
+
+    /// Encrypts all keys which are still stored unencrypted, in place.
+    /// Returns the number of keys which were encrypted.
+    pub fn encrypt_existing_keys(&self) -> Result<usize, SignerError> {
+        if self.secret.is_none() {
+            return Err(SignerError::KeyEncryption(
+                "no secret configured to encrypt keys with".to_string(),
+            ));
+        }
+
+        let mut count = 0;
+        for name in self.key_names()? {
+            if let StoredKey::Plain(kp) = self.load_stored_key(&name)? {
+                self.save_key_with_name(&name, &kp)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn keys_dir(work_dir: &PathBuf) -> Result<PathBuf, SignerError> {
         let meta_data = fs::metadata(&work_dir)?;
         if meta_data.is_dir() {
             let mut keys_dir = PathBuf::from(work_dir);
-            keys_dir.push("keys");
+            keys_dir.push(KEYS_DIR);
             if !keys_dir.is_dir() {
                 fs::create_dir_all(&keys_dir)?;
             }
-
-            Ok(OpenSslSigner { keys_dir })
+            Ok(keys_dir)
         } else {
             Err(SignerError::InvalidWorkDir(work_dir.clone()))
         }
     }
+
+    /// Gets the salt used for deriving the secret from the passphrase, or
+    /// creates a new random salt if there is none.
+    fn salt(work_dir: &PathBuf) -> Result<Vec<u8>, SignerError> {
+        let mut path = work_dir.clone();
+        path.push(KEYS_SALT_FILE);
+
+        if path.exists() {
+            let base64 = fs::read_to_string(&path)?;
+            base64::decode(base64.trim()).map_err(|_| {
+                SignerError::KeyEncryption(format!("invalid salt in {}", path.to_string_lossy()))
+            })
+        } else {
+            let mut salt = vec![0_u8; 16];
+            openssl::rand::rand_bytes(&mut salt)?;
+            let mut f = File::create(&path)?;
+            f.write_all(base64::encode(&salt).as_bytes())?;
+            f.sync_all()?;
+            Ok(salt)
+        }
+    }
+
+    fn key_names(&self) -> Result<Vec<String>, SignerError> {
+        let mut names = vec![];
+        for entry in fs::read_dir(&self.keys_dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                    if !name.ends_with(".tmp") {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+        Ok(names)
+    }
 }
 
 impl OpenSslSigner {
@@ -62,19 +173,68 @@ impl OpenSslSigner {
     }
 
     fn load_key(&self, id: &KeyIdentifier) -> Result<OpenSslKeyPair, SignerError> {
-        let path = self.key_path(id);
+        self.load_key_with_name(&id.to_string())
+    }
+
+    fn load_key_with_name(&self, name: &str) -> Result<OpenSslKeyPair, SignerError> {
+        match self.load_stored_key(name)? {
+            StoredKey::Plain(kp) => {
+                if self.secret.is_some() {
+                    warn!(
+                        "Key '{}' is not encrypted, use 'krill --encrypt-keys' to encrypt it",
+                        name
+                    );
+                }
+                Ok(kp)
+            }
+            StoredKey::Encrypted(encrypted) => match &self.secret {
+                None => Err(SignerError::KeyEncrypted),
+                Some(secret) => {
+                    let der = secret.decrypt(&encrypted, name)?;
+                    let pkey = PKey::private_key_from_der(&der)?;
+                    Ok(OpenSslKeyPair { pkey })
+                }
+            },
+        }
+    }
+
+    fn load_stored_key(&self, name: &str) -> Result<StoredKey, SignerError> {
+        let path = self.key_path(name);
         if path.exists() {
             let f = File::open(path)?;
-            let kp: OpenSslKeyPair = serde_json::from_reader(f)?;
-            Ok(kp)
+            let stored: StoredKey = serde_json::from_reader(f)?;
+            Ok(stored)
         } else {
             Err(SignerError::KeyNotFound)
         }
     }
 
-    fn key_path(&self, key_id: &KeyIdentifier) -> PathBuf {
+    /// Saves the key, encrypted if a secret is used. Writes to a temporary
+    /// file first, so that a key is never left half-written when keys are
+    /// encrypted in place.
+    fn save_key_with_name(&self, name: &str, kp: &OpenSslKeyPair) -> Result<(), SignerError> {
+        let json = match &self.secret {
+            None => serde_json::to_string(kp)?,
+            Some(secret) => {
+                let der = kp.pkey.private_key_to_der()?;
+                serde_json::to_string(&secret.encrypt(&der, name)?)?
+            }
+        };
+
+        let path = self.key_path(name);
+        let tmp_path = self.key_path(&format!("{}.tmp", name));
+
+        let mut f = File::create(&tmp_path)?;
+        f.write_all(json.as_ref())?;
+        f.sync_all()?;
+        fs::rename(tmp_path, path)?;
+
+        Ok(())
+    }
+
+    fn key_path(&self, name: &str) -> PathBuf {
         let mut path = self.keys_dir.clone();
-        path.push(&key_id.to_string());
+        path.push(name);
         path
     }
 }
@@ -89,11 +249,7 @@ impl Signer for OpenSslSigner {
         let pk = &kp.subject_public_key_info()?;
         let key_id = pk.key_identifier();
 
-        let path = self.key_path(&key_id);
-        let json = serde_json::to_string(&kp)?;
-
-        let mut f = File::create(path)?;
-        f.write_all(json.as_ref())?;
+        self.save_key_with_name(&key_id.to_string(), &kp)?;
 
         Ok(key_id)
     }
@@ -104,7 +260,7 @@ impl Signer for OpenSslSigner {
     }
 
     fn destroy_key(&mut self, key_id: &Self::KeyId) -> Result<(), KeyError<Self::Error>> {
-        let path = self.key_path(key_id);
+        let path = self.key_path(&key_id.to_string());
         if path.exists() {
             fs::remove_file(path).map_err(SignerError::IoError)?;
         }
@@ -197,6 +353,89 @@ impl OpenSslKeyPair {
     }
 }
 
+//------------ StoredKey -----------------------------------------------------
+
+/// A key as it is stored on disk. Unencrypted keys are stored as a base64
+/// string of the DER encoded private key, encrypted keys as a json object.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum StoredKey {
+    Encrypted(EncryptedKey),
+    Plain(OpenSslKeyPair),
+}
+
+/// The DER encoded private key encrypted using AES-256-GCM. The name of the
+/// key file is used as additional authenticated data, so that key files
+/// cannot be swapped.
+#[derive(Deserialize, Serialize)]
+struct EncryptedKey {
+    nonce: String,
+    tag: String,
+    ciphertext: String,
+}
+
+//------------ KeySecret -----------------------------------------------------
+
+/// The secret used to encrypt keys at rest, derived from a passphrase using
+/// PBKDF2 with HMAC-SHA256.
+#[derive(Clone)]
+struct KeySecret([u8; 32]);
+
+impl KeySecret {
+    fn derive(passphrase: &[u8], salt: &[u8]) -> Result<Self, SignerError> {
+        let mut key = [0_u8; 32];
+        pbkdf2_hmac(
+            passphrase,
+            salt,
+            KDF_ITERATIONS,
+            MessageDigest::sha256(),
+            &mut key,
+        )?;
+        Ok(KeySecret(key))
+    }
+
+    fn encrypt(&self, plain: &[u8], name: &str) -> Result<EncryptedKey, SignerError> {
+        let mut nonce = [0_u8; 12];
+        openssl::rand::rand_bytes(&mut nonce)?;
+        let mut tag = [0_u8; 16];
+
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(&nonce),
+            name.as_bytes(),
+            plain,
+            &mut tag,
+        )?;
+
+        Ok(EncryptedKey {
+            nonce: base64::encode(&nonce),
+            tag: base64::encode(&tag),
+            ciphertext: base64::encode(&ciphertext),
+        })
+    }
+
+    fn decrypt(&self, encrypted: &EncryptedKey, name: &str) -> Result<Vec<u8>, SignerError> {
+        let decode = |s: &str| base64::decode(s).map_err(|_| SignerError::DecodeError);
+
+        decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(&decode(&encrypted.nonce)?),
+            name.as_bytes(),
+            &decode(&encrypted.ciphertext)?,
+            &decode(&encrypted.tag)?,
+        )
+        .map_err(|_| SignerError::KeyDecrypt(name.to_string()))
+    }
+}
+
+impl fmt::Debug for KeySecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "KeySecret(..)")
+    }
+}
+
 //------------ OpenSslKeyError -----------------------------------------------
 
 #[derive(Debug, Display)]
@@ -218,6 +457,20 @@ pub enum SignerError {
 
     #[display(fmt = "Could not decode key")]
     DecodeError,
+
+    #[display(fmt = "PKCS#11 error: {}", _0)]
+    Pkcs11Error(String),
+
+    #[display(
+        fmt = "Found encrypted key, but no passphrase is configured. Set 'encrypt_keys' and a passphrase in the config"
+    )]
+    KeyEncrypted,
+
+    #[display(fmt = "Could not decrypt key '{}', is the passphrase correct?", _0)]
+    KeyDecrypt(String),
+
+    #[display(fmt = "Key encryption: {}", _0)]
+    KeyEncryption(String),
 }
 
 impl From<ErrorStack> for SignerError {
@@ -256,6 +509,49 @@ pub mod tests {
         })
     }
 
+    #[test]
+    fn should_encrypt_keys_at_rest() {
+        test::test_under_tmp(|d| {
+            let mut s = OpenSslSigner::build_encrypted(&d, "secret").unwrap();
+            let ki = s.create_key(PublicKeyFormat::default()).unwrap();
+            s.get_key_info(&ki).unwrap();
+
+            match s.load_stored_key(&ki.to_string()).unwrap() {
+                StoredKey::Encrypted(_) => {}
+                StoredKey::Plain(_) => panic!("key should be encrypted"),
+            }
+
+            // Same passphrase can read the key, others fail at startup.
+            let s = OpenSslSigner::build_encrypted(&d, "secret").unwrap();
+            s.get_key_info(&ki).unwrap();
+
+            match OpenSslSigner::build_encrypted(&d, "wrong") {
+                Err(SignerError::KeyDecrypt(_)) => {}
+                _ => panic!("expected decryption error"),
+            }
+            match OpenSslSigner::build(&d) {
+                Err(SignerError::KeyEncrypted) => {}
+                _ => panic!("expected missing secret error"),
+            }
+        })
+    }
+
+    #[test]
+    fn should_encrypt_existing_keys_in_place() {
+        test::test_under_tmp(|d| {
+            let mut s = OpenSslSigner::build(&d).unwrap();
+            let ki = s.create_key(PublicKeyFormat::default()).unwrap();
+            let key = s.get_key_info(&ki).unwrap();
+
+            let s = OpenSslSigner::build_encrypted(&d, "secret").unwrap();
+            assert_eq!(1, s.encrypt_existing_keys().unwrap());
+            assert_eq!(0, s.encrypt_existing_keys().unwrap());
+
+            assert_eq!(key, s.get_key_info(&ki).unwrap());
+            assert!(OpenSslSigner::build(&d).is_err());
+        })
+    }
+
     #[test]
     fn should_serialize_and_deserialize_key() {
         let key = OpenSslKeyPair::build().unwrap();
@@ -5,13 +5,31 @@ pub const KRILL_CLIENT_APP: &str = "Krill Client";
 pub const KRILL_DEFAULT_CONFIG_FILE: &str = "./defaults/krill.conf";
 
 pub const CASERVER_DIR: &str = "cas";
+pub const ARCHIVE_DIR: &str = "archive";
+pub const BUNDLES_DIR: &str = "bundles";
+pub const SQLITE_DB_FILE: &str = "krill.db";
+pub const MIGRATION_TMP_DIR: &str = "migration.tmp";
+
+pub const SNAPSHOT_EVENTS: u64 = 5; // save a snapshot of an aggregate every 5 events
+
+pub const EVENT_QUEUE_DIR: &str = "mq";
+pub const QUEUE_RETRY_BASE_SECONDS: i64 = 10; // first retry of a failed event after ~10 seconds
+pub const QUEUE_RETRY_MAX_SECONDS: i64 = 3600; // never wait more than an hour between retries
+pub const QUEUE_RETRY_MAX_ATTEMPTS: u32 = 10; // give up on an event after 10 failed attempts
 
 pub const PUBSERVER_DFLT: &str = "0";
 pub const PUBSERVER_DIR: &str = "pubd";
 
-pub const PUBLISH_VALID_DAYS: i64 = 7; // mft is valid for 7 days
-pub const PUBLISH_NEXT_HOURS: i64 = 24; // next update in 24 hours (otherwise mft and crl will become stale)
-pub const PUBLISH_THRESHOLD_HOURS: i64 = 8; // republish 8 hours before stale
+// Defaults for the timing config, which can be overridden per CA
+pub const PUBLISH_VALID_DAYS: u32 = 7; // mft is valid for 7 days
+pub const PUBLISH_NEXT_HOURS: u32 = 24; // next update in 24 hours (otherwise mft and crl will become stale)
+pub const PUBLISH_THRESHOLD_HOURS: u32 = 8; // republish 8 hours before stale
+
+pub const KEY_ROLL_MIN_STAGING_HOURS: u32 = 24; // RFC 6489 requires a staging period of at least 24 hours
+
+pub const TIMING_MAX_YEARS: u32 = 30; // keep configured times well within the range of X.509 times
+
+pub const PUBLISHED_VALIDATION_MINUTES: i64 = 10; // validate unchanged CAs again after 10 minutes, objects may become stale
 
 pub const REPOSITORY_DIR: &str = "repo";
 pub const REPOSITORY_RRDP_DIR: &str = "rrdp";
@@ -36,6 +54,6 @@ pub const KRILL_CLI_MY_CA_ENV: &str = "KRILL_CLI_MY_CA";
 
 pub const KRILL_HTTPS_ROOT_CERTS_ENV: &str = "KRILL_HTTPS_ROOT_CERTS";
 
-pub const CHILD_CERTIFICATE_VALIDITY_WEEKS: i64 = 52;
-pub const CHILD_CERTIFICATE_REISSUE_WEEKS: i64 = 4;
+pub const CHILD_CERTIFICATE_VALIDITY_WEEKS: u32 = 52;
+pub const CHILD_CERTIFICATE_REISSUE_WEEKS: u32 = 4;
 pub const ID_CERTIFICATE_VALIDITY_YEARS: i32 = 15;
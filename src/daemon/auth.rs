@@ -1,34 +1,246 @@
 //! Authorization for the API
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use actix_web::dev::Payload;
-use actix_web::{Error, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use actix_web::http::Method;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+
+use crate::commons::api::{ApiToken, ApiTokenDefinition, Handle, Role, Token};
+use crate::commons::error::Error as KrillError;
+use crate::commons::util::file;
+use crate::commons::{KrillEmptyResult, KrillResult};
+use crate::daemon::oidc::{OidcAuthenticator, OidcCallback, CSRF_HEADER, SESSION_COOKIE};
+
+/// The actor recorded for commands sent using the master 'auth_token'.
+pub const ADMIN_TOKEN_ACTOR: &str = "admin";
+
+const API_TOKENS_FILE: &str = "api_tokens.json";
+
+//------------ Permission ----------------------------------------------------
+
+/// The permission needed to use an API end-point.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Permission {
+    /// See anything, but change nothing.
+    Read,
 
-use crate::commons::api::Token;
+    /// Manage the ROAs for the given CA.
+    RoaUpdate(Handle),
+
+    /// Manage the publishers of the embedded repository.
+    PublisherAdmin,
+
+    /// Make any change, including managing API tokens.
+    Admin,
+}
 
 //------------ Authorizer ----------------------------------------------------
 
 /// This type is responsible for checking authorisations when the API is
 /// accessed.
+///
+/// Besides the master 'auth_token', which may do anything, named tokens
+/// with a role can be defined in the config, or added through the API.
+/// Tokens added through the API are kept in the data directory.
+///
+/// If OpenID Connect is configured, users can also log in and use the
+/// API with a session cookie. Their role follows from the claims of the
+/// identity provider.
 #[derive(Clone, Debug)]
 pub struct Authorizer {
     krill_auth_token: Token,
+    config_tokens: Vec<ApiToken>,
+    api_tokens: Vec<ApiToken>,
+    api_tokens_file: PathBuf,
+    oidc: Option<Arc<OidcAuthenticator>>,
 }
 
 impl Authorizer {
-    pub fn new(krill_auth_token: &Token) -> Self {
-        Authorizer {
+    pub fn build(
+        krill_auth_token: &Token,
+        config_tokens: &[ApiToken],
+        work_dir: &PathBuf,
+    ) -> KrillResult<Self> {
+        let api_tokens_file = file::file_path(work_dir, API_TOKENS_FILE);
+        let api_tokens = if api_tokens_file.exists() {
+            file::load_json(&api_tokens_file)?
+        } else {
+            vec![]
+        };
+
+        Ok(Authorizer {
             krill_auth_token: krill_auth_token.clone(),
-        }
+            config_tokens: config_tokens.to_vec(),
+            api_tokens,
+            api_tokens_file,
+            oidc: None,
+        })
+    }
+
+    /// Enables logging in through OpenID Connect.
+    pub fn with_oidc(mut self, oidc: OidcAuthenticator) -> Self {
+        self.oidc = Some(Arc::new(oidc));
+        self
     }
 
-    pub fn is_api_allowed(&self, auth: &Auth) -> bool {
+    /// Returns the actor, i.e. the name of the token or of the logged in
+    /// user, if the token or session has the permission asked for. Returns
+    /// None if it does not.
+    pub fn actor(&self, auth: &Auth, permission: &Permission) -> Option<String> {
         match auth {
-            Auth::Bearer(token) => &self.krill_auth_token == token,
+            Auth::Bearer(token) => {
+                if &self.krill_auth_token == token {
+                    return Some(ADMIN_TOKEN_ACTOR.to_string());
+                }
+                self.config_tokens
+                    .iter()
+                    .chain(self.api_tokens.iter())
+                    .find(|api_token| api_token.token() == token)
+                    .filter(|api_token| Self::allows(api_token.role(), api_token.cas(), permission))
+                    .map(|api_token| api_token.name().to_string())
+            }
+            Auth::Session(id, csrf_token) => self
+                .oidc
+                .as_ref()
+                .and_then(|oidc| oidc.session(id))
+                .filter(|session| match csrf_token {
+                    Some(token) => session.csrf_token_matches(token),
+                    None => true,
+                })
+                .filter(|session| Self::allows(session.role(), session.cas(), permission))
+                .map(|session| session.name().to_string()),
         }
     }
+
+    fn allows(role: Role, cas: &[Handle], permission: &Permission) -> bool {
+        match (role, permission) {
+            (Role::Admin, _) => true,
+            (_, Permission::Read) => true,
+            (Role::RoaManager, Permission::RoaUpdate(ca)) => cas.contains(ca),
+            (Role::PublisherAdmin, Permission::PublisherAdmin) => true,
+            _ => false,
+        }
+    }
+}
+
+/// # Log in using OpenID Connect
+impl Authorizer {
+    /// Starts a login, returns the URI at the identity provider to send
+    /// the user to.
+    pub fn oidc_login_uri(&self) -> KrillResult<String> {
+        self.oidc()?.login_uri()
+    }
+
+    /// Completes a login, returns the session id and its CSRF token.
+    pub fn oidc_login(&self, callback: OidcCallback) -> KrillResult<(String, String)> {
+        self.oidc()?.callback(callback)
+    }
+
+    /// Ends the session if the CSRF token matches it, returns whether it
+    /// did.
+    pub fn oidc_logout(&self, session: &str, csrf_token: &str) -> KrillResult<bool> {
+        Ok(self.oidc()?.logout(session, csrf_token))
+    }
+
+    pub fn oidc_enabled(&self) -> bool {
+        self.oidc.is_some()
+    }
+
+    fn oidc(&self) -> KrillResult<&OidcAuthenticator> {
+        self.oidc
+            .as_ref()
+            .map(Arc::as_ref)
+            .ok_or(KrillError::OidcNotEnabled)
+    }
+}
+
+/// # Manage named tokens
+impl Authorizer {
+    /// Returns the definitions of all named tokens, without the tokens
+    /// themselves.
+    pub fn tokens(&self) -> Vec<ApiTokenDefinition> {
+        self.config_tokens
+            .iter()
+            .chain(self.api_tokens.iter())
+            .map(ApiToken::definition)
+            .collect()
+    }
+
+    /// Adds a named token, and returns it including the (random) token.
+    pub fn add_token(&mut self, definition: ApiTokenDefinition) -> KrillResult<ApiToken> {
+        Self::verify_definition(&definition).map_err(|reason| {
+            KrillError::ApiTokenInvalid(definition.name().to_string(), reason)
+        })?;
+
+        let name = definition.name();
+        if name == ADMIN_TOKEN_ACTOR || self.tokens().iter().any(|t| t.name() == name) {
+            return Err(KrillError::ApiTokenDuplicate(name.to_string()));
+        }
+
+        let api_token = ApiToken::new(definition, Self::random_token()?);
+        self.api_tokens.push(api_token.clone());
+        self.save()?;
+
+        Ok(api_token)
+    }
+
+    /// Removes a named token that was added through the API.
+    pub fn remove_token(&mut self, name: &str) -> KrillEmptyResult {
+        if self.config_tokens.iter().any(|t| t.name() == name) {
+            return Err(KrillError::ApiTokenInConfig(name.to_string()));
+        }
+
+        let before = self.api_tokens.len();
+        self.api_tokens.retain(|t| t.name() != name);
+        if self.api_tokens.len() == before {
+            return Err(KrillError::ApiTokenUnknown(name.to_string()));
+        }
+
+        self.save()
+    }
+
+    /// Verifies that a token definition makes sense. Returns the reason if
+    /// it does not. Also used to verify the tokens in the config.
+    pub fn verify_definition(definition: &ApiTokenDefinition) -> Result<(), String> {
+        if definition.name().is_empty() {
+            Err("name must not be empty".to_string())
+        } else {
+            Self::verify_role(definition.role(), definition.cas())
+        }
+    }
+
+    /// Verifies that the CAs make sense for the role. Returns the reason if
+    /// they do not.
+    pub fn verify_role(role: Role, cas: &[Handle]) -> Result<(), String> {
+        if role == Role::RoaManager && cas.is_empty() {
+            Err("role 'roa-manager' requires at least one CA".to_string())
+        } else if role != Role::RoaManager && !cas.is_empty() {
+            Err(format!("CAs cannot be set for role '{}'", role))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn random_token() -> KrillResult<Token> {
+        let mut bytes = [0_u8; 20];
+        openssl::rand::rand_bytes(&mut bytes).map_err(KrillError::signer)?;
+        Ok(Token::from(hex::encode(bytes)))
+    }
+
+    fn save(&self) -> KrillEmptyResult {
+        file::save_json(&self.api_tokens, &self.api_tokens_file)?;
+        Ok(())
+    }
 }
 
 pub enum Auth {
     Bearer(Token),
+
+    /// The session id from the cookie, and the CSRF token from the header.
+    /// The token is only required, and only taken, for requests that can
+    /// make changes.
+    Session(String, Option<String>),
 }
 
 impl Auth {
@@ -47,6 +259,22 @@ impl Auth {
 
         Err(AuthError::InvalidToken)
     }
+
+    /// Extracts the CSRF token for requests that can make changes. Other
+    /// requests do not need it.
+    fn extract_csrf_token(req: &HttpRequest) -> Result<Option<String>, AuthError> {
+        match *req.method() {
+            Method::GET | Method::HEAD | Method::OPTIONS => Ok(None),
+            _ => {
+                let header = req
+                    .headers()
+                    .get(CSRF_HEADER)
+                    .ok_or(AuthError::MissingCsrfToken)?;
+                let token = header.to_str().map_err(|_| AuthError::MissingCsrfToken)?;
+                Ok(Some(token.to_string()))
+            }
+        }
+    }
 }
 
 impl FromRequest for Auth {
@@ -60,6 +288,9 @@ impl FromRequest for Auth {
                 Auth::extract_bearer_token(header.to_str().map_err(|_| AuthError::InvalidToken)?)?;
 
             Ok(Auth::Bearer(token))
+        } else if let Some(cookie) = req.cookie(SESSION_COOKIE) {
+            let csrf_token = Auth::extract_csrf_token(req)?;
+            Ok(Auth::Session(cookie.value().to_string(), csrf_token))
         } else {
             Err(AuthError::Unauthorised.into())
         }
@@ -68,11 +299,14 @@ impl FromRequest for Auth {
 
 #[derive(Debug, Display)]
 pub enum AuthError {
-    #[display(fmt = "No bearer token found")]
+    #[display(fmt = "No bearer token or session found")]
     Unauthorised,
 
     #[display(fmt = "Invalid token")]
     InvalidToken,
+
+    #[display(fmt = "No CSRF token found for session")]
+    MissingCsrfToken,
 }
 
 impl ResponseError for AuthError {
@@ -80,3 +314,103 @@ impl ResponseError for AuthError {
         HttpResponse::Forbidden().finish()
     }
 }
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::commons::util::test;
+
+    fn definition(name: &str, role: Role, cas: Vec<Handle>) -> ApiTokenDefinition {
+        ApiTokenDefinition::new(name.to_string(), role, cas)
+    }
+
+    #[test]
+    fn check_permissions_for_roles() {
+        test::test_under_tmp(|d| {
+            let ca = Handle::from_str_unsafe("ca");
+            let other = Handle::from_str_unsafe("other");
+
+            let roas = ApiToken::new(
+                definition("roas", Role::RoaManager, vec![ca.clone()]),
+                Token::from("roas-token"),
+            );
+            let pubs = ApiToken::new(
+                definition("pubs", Role::PublisherAdmin, vec![]),
+                Token::from("pubs-token"),
+            );
+            let authorizer = Authorizer::build(&Token::from("secret"), &[roas, pubs], &d).unwrap();
+
+            let master = Auth::Bearer(Token::from("secret"));
+            let roas = Auth::Bearer(Token::from("roas-token"));
+            let pubs = Auth::Bearer(Token::from("pubs-token"));
+            let unknown = Auth::Bearer(Token::from("unknown"));
+
+            let actor = |auth: &Auth, permission: Permission| authorizer.actor(auth, &permission);
+
+            assert_eq!(Some("admin".to_string()), actor(&master, Permission::Admin));
+
+            assert_eq!(Some("roas".to_string()), actor(&roas, Permission::Read));
+            assert_eq!(
+                Some("roas".to_string()),
+                actor(&roas, Permission::RoaUpdate(ca.clone()))
+            );
+            assert_eq!(None, actor(&roas, Permission::RoaUpdate(other)));
+            assert_eq!(None, actor(&roas, Permission::PublisherAdmin));
+            assert_eq!(None, actor(&roas, Permission::Admin));
+
+            assert_eq!(
+                Some("pubs".to_string()),
+                actor(&pubs, Permission::PublisherAdmin)
+            );
+            assert_eq!(None, actor(&pubs, Permission::RoaUpdate(ca)));
+            assert_eq!(None, actor(&pubs, Permission::Admin));
+
+            assert_eq!(None, actor(&unknown, Permission::Read));
+        })
+    }
+
+    #[test]
+    fn manage_tokens_through_api() {
+        test::test_under_tmp(|d| {
+            let master = Token::from("secret");
+            let config_tokens = vec![ApiToken::new(
+                definition("config", Role::ReadOnly, vec![]),
+                Token::from("config-token"),
+            )];
+
+            let mut authorizer = Authorizer::build(&master, &config_tokens, &d).unwrap();
+
+            let added = authorizer
+                .add_token(definition("pubs", Role::PublisherAdmin, vec![]))
+                .unwrap();
+
+            assert!(authorizer
+                .add_token(definition("config", Role::Admin, vec![]))
+                .is_err());
+            assert!(authorizer
+                .add_token(definition("admin", Role::Admin, vec![]))
+                .is_err());
+            assert!(authorizer
+                .add_token(definition("roas", Role::RoaManager, vec![]))
+                .is_err());
+
+            // Tokens added through the API are kept across restarts
+            let mut authorizer = Authorizer::build(&master, &config_tokens, &d).unwrap();
+            assert_eq!(2, authorizer.tokens().len());
+
+            let auth = Auth::Bearer(added.token().clone());
+            assert_eq!(
+                Some("pubs".to_string()),
+                authorizer.actor(&auth, &Permission::PublisherAdmin)
+            );
+
+            assert!(authorizer.remove_token("config").is_err());
+            assert!(authorizer.remove_token("unknown").is_err());
+            authorizer.remove_token("pubs").unwrap();
+            assert_eq!(None, authorizer.actor(&auth, &Permission::Read));
+        })
+    }
+}
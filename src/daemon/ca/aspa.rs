@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use bcder::encode::{Constructed, PrimitiveContent, Values};
+use bcder::{encode, Mode, Oid, Tag};
+use bytes::Bytes;
+
+use rpki::sigobj::{SignedObject, SignedObjectBuilder};
+use rpki::uri;
+use rpki::x509::{Serial, Time};
+
+use crate::commons::api::{
+    AsNumber, AspaDefinition, AspaDefinitionList, AspaProvidersUpdate, CurrentObject, ObjectName,
+    ReplacedObject, ResourceSet,
+};
+use crate::commons::KrillResult;
+use crate::daemon::ca::events::AspaObjectsUpdates;
+use crate::daemon::ca::{self, CertifiedKey, SignSupport, Signer};
+
+/// The content type for ASPA objects: id-ct-ASPA (1.2.840.113549.1.9.16.1.49)
+const ASPA_CONTENT_TYPE: [u8; 11] = [42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 49];
+
+//------------ AspaDefinitions ---------------------------------------------
+
+/// The ASPA definitions configured for a CA, keyed by customer ASN.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AspaDefinitions {
+    attestations: HashMap<AsNumber, AspaDefinition>,
+}
+
+impl Default for AspaDefinitions {
+    fn default() -> Self {
+        AspaDefinitions {
+            attestations: HashMap::new(),
+        }
+    }
+}
+
+impl AspaDefinitions {
+    pub fn get(&self, customer: AsNumber) -> Option<&AspaDefinition> {
+        self.attestations.get(&customer)
+    }
+
+    pub fn has(&self, customer: AsNumber) -> bool {
+        self.attestations.contains_key(&customer)
+    }
+
+    /// Adds a new definition, or replaces the existing definition for the
+    /// same customer ASN.
+    pub fn add_or_replace(&mut self, definition: AspaDefinition) {
+        self.attestations.insert(definition.customer(), definition);
+    }
+
+    pub fn remove(&mut self, customer: AsNumber) {
+        self.attestations.remove(&customer);
+    }
+
+    /// Applies an update to the providers for an existing customer ASN.
+    pub fn apply_update(&mut self, customer: AsNumber, update: &AspaProvidersUpdate) {
+        if let Some(definition) = self.attestations.get_mut(&customer) {
+            definition.apply_update(update);
+        }
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &AspaDefinition> {
+        self.attestations.values()
+    }
+
+    pub fn as_list(&self) -> AspaDefinitionList {
+        AspaDefinitionList::new(self.attestations.values().cloned().collect())
+    }
+}
+
+//------------ AspaInfo ----------------------------------------------------
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AspaInfo {
+    definition: AspaDefinition,       // definition used for the object
+    object: CurrentObject,            // actual ASPA
+    since: Time,                      // first ASPA in RC created
+    replaces: Option<ReplacedObject>, // for revoking when re-newing
+}
+
+impl AspaInfo {
+    pub fn new_aspa(definition: AspaDefinition, aspa: &SignedObject) -> Self {
+        AspaInfo {
+            definition,
+            object: CurrentObject::from(aspa),
+            since: Time::now(),
+            replaces: None,
+        }
+    }
+
+    pub fn updated_aspa(old: &AspaInfo, definition: AspaDefinition, aspa: &SignedObject) -> Self {
+        AspaInfo {
+            definition,
+            object: CurrentObject::from(aspa),
+            since: old.since,
+            replaces: Some(ReplacedObject::from(old.object())),
+        }
+    }
+
+    pub fn definition(&self) -> &AspaDefinition {
+        &self.definition
+    }
+
+    pub fn object(&self) -> &CurrentObject {
+        &self.object
+    }
+
+    pub fn name(&self) -> ObjectName {
+        ObjectName::from(self.definition.customer())
+    }
+
+    pub fn since(&self) -> Time {
+        self.since
+    }
+
+    pub fn replaces(&self) -> Option<&ReplacedObject> {
+        self.replaces.as_ref()
+    }
+}
+
+//------------ AspaObjects -------------------------------------------------
+
+/// ASPA objects held by a resource class in a CA.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AspaObjects {
+    inner: HashMap<AsNumber, AspaInfo>,
+}
+
+impl Default for AspaObjects {
+    fn default() -> Self {
+        AspaObjects {
+            inner: HashMap::new(),
+        }
+    }
+}
+
+impl AspaObjects {
+    pub fn get(&self, customer: AsNumber) -> Option<&AspaInfo> {
+        self.inner.get(&customer)
+    }
+
+    pub fn updated(&mut self, updates: AspaObjectsUpdates) {
+        let (updated, removed) = updates.unpack();
+
+        for (customer, info) in updated.into_iter() {
+            self.inner.insert(customer, info);
+        }
+
+        for customer in removed.keys() {
+            self.inner.remove(customer);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&AsNumber, &AspaInfo)> {
+        self.inner.iter()
+    }
+
+    pub fn current(&self) -> impl Iterator<Item = &AspaInfo> {
+        self.inner.values()
+    }
+
+    /// Returns the definitions for which objects were issued, so that they
+    /// can be re-issued e.g. when activating a new key.
+    pub fn definitions(&self) -> AspaDefinitions {
+        let mut definitions = AspaDefinitions::default();
+        for info in self.inner.values() {
+            definitions.add_or_replace(info.definition().clone());
+        }
+        definitions
+    }
+
+    pub fn make_aspa<S: Signer>(
+        definition: &AspaDefinition,
+        certified_key: &CertifiedKey,
+        new_repo: Option<&uri::Rsync>,
+        signer: &S,
+    ) -> KrillResult<SignedObject> {
+        let name = ObjectName::from(definition.customer());
+
+        let incoming_cert = certified_key.incoming_cert();
+        let crl_uri = match &new_repo {
+            None => incoming_cert.crl_uri(),
+            Some(base_uri) => base_uri.join(incoming_cert.crl_name().as_bytes()),
+        };
+
+        let aspa_uri = match &new_repo {
+            None => incoming_cert.uri_for_object(name),
+            Some(base_uri) => base_uri.join(name.as_bytes()),
+        };
+
+        let aia = incoming_cert.uri();
+
+        let signing_key = certified_key.key_id();
+
+        let customer_resources =
+            ResourceSet::from_strs(&format!("AS{}", definition.customer()), "", "")?;
+
+        let mut object_builder = SignedObjectBuilder::new(
+            Serial::random(signer).map_err(ca::Error::signer)?,
+            SignSupport::sign_validity_year(),
+            crl_uri,
+            aia.clone(),
+            aspa_uri,
+        );
+        object_builder.set_issuer(Some(incoming_cert.cert().subject().clone()));
+        object_builder.set_signing_time(Some(Time::now()));
+        object_builder.set_as_resources(customer_resources.to_as_resources());
+
+        object_builder
+            .finalize(
+                Oid(Bytes::from_static(&ASPA_CONTENT_TYPE)),
+                Self::encode_content(definition),
+                signer,
+                signing_key,
+            )
+            .map_err(ca::Error::signer)
+    }
+
+    /// Encodes the ASProviderAttestation content:
+    ///
+    /// ```text
+    /// ASProviderAttestation ::= SEQUENCE {
+    ///   version [0] INTEGER (1),
+    ///   customerASID ASID,
+    ///   providers ProviderASSet }
+    ///
+    /// ProviderASSet ::= SEQUENCE (SIZE(1..MAX)) OF ASID
+    /// ```
+    fn encode_content(definition: &AspaDefinition) -> Bytes {
+        let providers: Vec<u32> = definition
+            .providers()
+            .iter()
+            .map(|provider| u32::from(*provider))
+            .collect();
+
+        encode::sequence((
+            Constructed::new(Tag::CTX_0, 1.encode()),
+            u32::from(definition.customer()).encode(),
+            encode::sequence(encode::slice(&providers, |provider| provider.encode())),
+        ))
+        .to_captured(Mode::Der)
+        .into_bytes()
+    }
+}
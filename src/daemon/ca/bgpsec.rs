@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use bcder::encode::{PrimitiveContent, Values};
+use bcder::{encode, Mode, Tag};
+
+use rpki::cert::{Cert, ExtendedKeyUsage, KeyUsage, Overclaim, TbsCert};
+use rpki::oid;
+use rpki::uri;
+use rpki::x509::{Name, Serial};
+
+use crate::commons::api::{
+    BgpSecAsnKey, BgpSecDefinition, BgpSecDefinitionList, CurrentObject, ObjectName,
+    ReplacedObject, ResourceSet,
+};
+use crate::commons::error::Error;
+use crate::commons::KrillResult;
+use crate::daemon::ca::events::BgpSecCertificateUpdates;
+use crate::daemon::ca::{self, CertifiedKey, SignSupport, Signer};
+
+//------------ BgpSecDefinitions -------------------------------------------
+
+/// The BGPsec router certificates configured for a CA, keyed by ASN and
+/// router key.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BgpSecDefinitions {
+    definitions: HashMap<BgpSecAsnKey, BgpSecDefinition>,
+}
+
+impl Default for BgpSecDefinitions {
+    fn default() -> Self {
+        BgpSecDefinitions {
+            definitions: HashMap::new(),
+        }
+    }
+}
+
+impl BgpSecDefinitions {
+    pub fn has(&self, asn_key: &BgpSecAsnKey) -> bool {
+        self.definitions.contains_key(asn_key)
+    }
+
+    pub fn add(&mut self, definition: BgpSecDefinition) {
+        self.definitions.insert(definition.asn_key(), definition);
+    }
+
+    pub fn remove(&mut self, asn_key: &BgpSecAsnKey) {
+        self.definitions.remove(asn_key);
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &BgpSecDefinition> {
+        self.definitions.values()
+    }
+
+    pub fn as_list(&self) -> BgpSecDefinitionList {
+        BgpSecDefinitionList::new(self.definitions.keys().cloned().collect())
+    }
+}
+
+//------------ BgpSecCertInfo ----------------------------------------------
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BgpSecCertInfo {
+    definition: BgpSecDefinition,     // definition used for the certificate
+    object: CurrentObject,            // actual router certificate
+    replaces: Option<ReplacedObject>, // for revoking when re-newing
+}
+
+impl BgpSecCertInfo {
+    pub fn new_cert(definition: BgpSecDefinition, cert: &Cert) -> Self {
+        BgpSecCertInfo {
+            definition,
+            object: CurrentObject::from(cert),
+            replaces: None,
+        }
+    }
+
+    pub fn updated_cert(old: &BgpSecCertInfo, cert: &Cert) -> Self {
+        BgpSecCertInfo {
+            definition: old.definition.clone(),
+            object: CurrentObject::from(cert),
+            replaces: Some(ReplacedObject::from(old.object())),
+        }
+    }
+
+    pub fn definition(&self) -> &BgpSecDefinition {
+        &self.definition
+    }
+
+    pub fn object(&self) -> &CurrentObject {
+        &self.object
+    }
+
+    pub fn name(&self) -> ObjectName {
+        ObjectName::from(&self.definition.asn_key())
+    }
+
+    pub fn replaces(&self) -> Option<&ReplacedObject> {
+        self.replaces.as_ref()
+    }
+}
+
+//------------ BgpSecCertificates ------------------------------------------
+
+/// BGPsec router certificates issued by a resource class in a CA.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BgpSecCertificates {
+    inner: HashMap<BgpSecAsnKey, BgpSecCertInfo>,
+}
+
+impl Default for BgpSecCertificates {
+    fn default() -> Self {
+        BgpSecCertificates {
+            inner: HashMap::new(),
+        }
+    }
+}
+
+impl BgpSecCertificates {
+    pub fn get(&self, asn_key: &BgpSecAsnKey) -> Option<&BgpSecCertInfo> {
+        self.inner.get(asn_key)
+    }
+
+    pub fn updated(&mut self, updates: BgpSecCertificateUpdates) {
+        let (updated, removed) = updates.unpack();
+
+        for (asn_key, info) in updated.into_iter() {
+            self.inner.insert(asn_key, info);
+        }
+
+        for asn_key in removed.keys() {
+            self.inner.remove(asn_key);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&BgpSecAsnKey, &BgpSecCertInfo)> {
+        self.inner.iter()
+    }
+
+    pub fn current(&self) -> impl Iterator<Item = &BgpSecCertInfo> {
+        self.inner.values()
+    }
+
+    /// Returns the definitions for which certificates were issued, so that
+    /// they can be re-issued e.g. when activating a new key.
+    pub fn definitions(&self) -> BgpSecDefinitions {
+        let mut definitions = BgpSecDefinitions::default();
+        for info in self.inner.values() {
+            definitions.add(info.definition().clone());
+        }
+        definitions
+    }
+
+    /// Makes an RFC 8209 router certificate: an EE certificate for the key
+    /// in the CSR, with the BGPsec router extended key usage, and the ASN
+    /// of the router as its only resource.
+    pub fn make_router_cert<S: Signer>(
+        definition: &BgpSecDefinition,
+        certified_key: &CertifiedKey,
+        new_repo: Option<&uri::Rsync>,
+        signer: &S,
+    ) -> KrillResult<Cert> {
+        if !definition.has_router_key() {
+            return Err(Error::BgpSecRouterKeyUnsupported(definition.asn_key()));
+        }
+
+        let signing_cert = certified_key.incoming_cert();
+
+        let crl_uri = match &new_repo {
+            None => signing_cert.crl_uri(),
+            Some(base_uri) => base_uri.join(signing_cert.crl_name().as_bytes()),
+        };
+
+        let serial = Serial::random(signer).map_err(ca::Error::signer)?;
+        let issuer = signing_cert.cert().subject().clone();
+        let validity = SignSupport::sign_validity_year();
+
+        let pub_key = definition.csr().public_key().clone();
+        let subject = Some(Self::router_subject(definition)?);
+
+        let mut cert = TbsCert::new(
+            serial,
+            issuer,
+            validity,
+            subject,
+            pub_key,
+            KeyUsage::Ee,
+            Overclaim::Refuse,
+        );
+        cert.set_extended_key_usage(Some(ExtendedKeyUsage::create_router()));
+
+        cert.set_authority_key_identifier(Some(signing_cert.cert().subject_key_identifier()));
+        cert.set_ca_issuer(Some(signing_cert.uri().clone()));
+        cert.set_crl_uri(Some(crl_uri));
+
+        let router_resources = ResourceSet::from_strs(&format!("AS{}", definition.asn()), "", "")?;
+        cert.set_as_resources(Some(router_resources.to_as_resources()));
+
+        cert.into_cert(signer, &certified_key.key_id())
+            .map_err(ca::Error::signer)
+    }
+
+    /// Makes the subject for a router certificate as recommended in section
+    /// 3.1.1 of RFC 8209: the common name is "ROUTER-" followed by the ASN
+    /// as eight hexadecimal digits. The BGP identifier of the router is not
+    /// known, so the serial number is the identifier of the router key, which
+    /// also keeps the names for different routers in the same AS apart.
+    fn router_subject(definition: &BgpSecDefinition) -> KrillResult<Name> {
+        let common_name = format!("ROUTER-{:08X}", u32::from(definition.asn()));
+        let serial_number = definition.key_identifier().into_hex();
+
+        let encoded = encode::sequence((
+            encode::set(encode::sequence((
+                oid::AT_COMMON_NAME.encode(),
+                common_name.as_bytes().encode_as(Tag::PRINTABLE_STRING),
+            ))),
+            encode::set(encode::sequence((
+                oid::AT_SERIAL_NUMBER.encode(),
+                (&serial_number[..]).encode_as(Tag::PRINTABLE_STRING),
+            ))),
+        ))
+        .to_captured(Mode::Der);
+
+        Mode::Der
+            .decode(encoded.as_slice(), Name::take_from)
+            .map_err(|_| Error::signer("cannot encode router certificate subject"))
+    }
+}
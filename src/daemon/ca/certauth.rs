@@ -15,11 +15,13 @@ use rpki::x509::{Serial, Time, Validity};
 
 use crate::commons::api::rrdp::PublishElement;
 use crate::commons::api::{
-    self, CertAuthInfo, ChildHandle, EntitlementClass, Entitlements, Handle, IdCertPem,
-    IssuanceRequest, IssuedCert, ObjectsDelta, ParentCaContact, ParentHandle, RcvdCert,
-    RepositoryContact, RequestResourceLimit, ResourceClassName, ResourceSet, RevocationRequest,
-    RevocationResponse, RoaDefinition, SigningCert, TaCertDetails, TrustAnchorLocator,
-    UpdateChildRequest,
+    self, AsNumber, AspaDefinitionList, AspaDefinitionUpdates, AspaProvidersUpdate, Base64,
+    BgpSecDefinitionList, BgpSecDefinitionUpdates, CertAuthInfo, ChildHandle, EntitlementClass,
+    Entitlements, Handle, IdCertPem, IssuanceRequest, IssuedCert, KeyRollPolicy, KeyRollProgress,
+    KeyRollStage, ObjectsDelta, ParentCaContact, ParentHandle, RcvdCert, RepositoryContact,
+    RequestResourceLimit, ResourceClassName, ResourceSet, RevocationRequest, RevocationResponse,
+    RoaDefinition, RoaPrefixGroupingStrategy, RscRequest, SignedChecklist, SigningCert,
+    TaCertDetails, TimingConfig, TrustAnchorLocator, UpdateChildRequest,
 };
 use crate::commons::error::Error;
 use crate::commons::eventsourcing::{Aggregate, StoredEvent};
@@ -29,14 +31,14 @@ use crate::commons::remote::rfc6492;
 use crate::commons::remote::rfc8183;
 use crate::commons::remote::sigmsg::SignedMessage;
 use crate::commons::KrillResult;
-use crate::constants::CHILD_CERTIFICATE_REISSUE_WEEKS;
-use crate::daemon::ca::events::ChildCertificateUpdates;
+use crate::daemon::ca::events::{ChildCertificateUpdates, ObjectUpdates};
 use crate::daemon::ca::rc::PublishMode;
-use crate::daemon::ca::server::RoaPrefixGroupingStrategy;
 use crate::daemon::ca::signing::CsrInfo;
 use crate::daemon::ca::{
-    ta_handle, ChildDetails, Cmd, CmdDet, CurrentObjectSetDelta, Evt, EvtDet, Ini, ResourceClass,
-    RouteAuthorization, RouteAuthorizationUpdates, Routes, Signer,
+    ta_handle, AspaDefinitions, BgpSecDefinitions, ChildDetails, Cmd, CmdDet,
+    CurrentObjectSetDelta, Evt, EvtDet, Ini, InvalidObject, PublishedObjectsValidator,
+    ResourceClass, RouteAuthorization, RouteAuthorizationUpdates, Routes, SignedChecklistBuilder,
+    Signer,
 };
 
 //------------ Rfc8183Id ---------------------------------------------------
@@ -67,11 +69,15 @@ impl Rfc8183Id {
 //------------ CertAuth ----------------------------------------------------
 
 lazy_static! {
-    /// The RoaPrefixGroupingStrategy to be used by all CAs. Ideally this would be a struct property,
-    /// but since this is currently shared among all CAs and in order to allow it to be modified when restating the
-    /// server, it's being defined here as a mutable static variable for now.
+    /// The RoaPrefixGroupingStrategy used by all CAs that do not have their
+    /// own strategy. This is set from the config when the server starts.
     static ref ROA_PREFIX_GROUPING_STRATEGY: Mutex<RoaPrefixGroupingStrategy> =
         Mutex::new(RoaPrefixGroupingStrategy::default());
+
+    /// The TimingConfig used by all CAs that do not have their own timing
+    /// config. Like the RoaPrefixGroupingStrategy this is set when the
+    /// server starts.
+    static ref TIMING_CONFIG: Mutex<TimingConfig> = Mutex::new(TimingConfig::default());
 }
 
 /// This type defines a Certification Authority at a slightly higher level
@@ -95,6 +101,24 @@ pub struct CertAuth<S: Signer> {
 
     routes: Routes,
 
+    #[serde(default)]
+    aspas: AspaDefinitions,
+
+    #[serde(default)]
+    bgpsec_defs: BgpSecDefinitions,
+
+    #[serde(default)]
+    timing: Option<TimingConfig>,
+
+    #[serde(default)]
+    roa_grouping: Option<RoaPrefixGroupingStrategy>,
+
+    #[serde(default)]
+    key_roll_policy: Option<KeyRollPolicy>,
+
+    #[serde(default)]
+    key_roll_emergency: bool, // new keys are activated as soon as they are certified
+
     phantom_signer: PhantomData<S>,
 }
 
@@ -144,6 +168,18 @@ impl<S: Signer> Aggregate for CertAuth<S> {
 
             routes,
 
+            aspas: AspaDefinitions::default(),
+
+            bgpsec_defs: BgpSecDefinitions::default(),
+
+            timing: None,
+
+            roa_grouping: None,
+
+            key_roll_policy: None,
+
+            key_roll_emergency: false,
+
             phantom_signer: PhantomData,
         })
     }
@@ -248,6 +284,7 @@ impl<S: Signer> Aggregate for CertAuth<S> {
             }
             EvtDet::ResourceClassRemoved(name, _delta, _parent, _revocations) => {
                 self.resources.remove(&name);
+                self.key_roll_emergency_check();
             }
             EvtDet::CertificateRequested(name, req, status) => {
                 self.resources
@@ -288,6 +325,7 @@ impl<S: Signer> Aggregate for CertAuth<S> {
                     .get_mut(&class_name)
                     .unwrap()
                     .new_key_activated(revoke_req);
+                self.key_roll_emergency_check();
             }
             EvtDet::KeyRollFinished(class_name, _delta) => {
                 self.resources
@@ -298,6 +336,8 @@ impl<S: Signer> Aggregate for CertAuth<S> {
             EvtDet::UnexpectedKeyFound(_, _) => {
                 // no action needed, this is marked to flag that a key may be removed
             }
+            EvtDet::KeyRollPolicyUpdated(policy) => self.key_roll_policy = policy,
+            EvtDet::KeyRollEmergencyStarted(_reason) => self.key_roll_emergency = true,
 
             //-----------------------------------------------------------------------
             // Route Authorizations
@@ -307,6 +347,37 @@ impl<S: Signer> Aggregate for CertAuth<S> {
             EvtDet::RoasUpdated(rcn, updates) => {
                 self.resources.get_mut(&rcn).unwrap().roas_updated(updates)
             }
+            EvtDet::RoaGroupingUpdated(strategy) => self.roa_grouping = strategy,
+
+            //-----------------------------------------------------------------------
+            // ASPAs
+            //-----------------------------------------------------------------------
+            EvtDet::AspaConfigAdded(definition) => self.aspas.add_or_replace(definition),
+            EvtDet::AspaConfigUpdated(customer, update) => {
+                self.aspas.apply_update(customer, &update)
+            }
+            EvtDet::AspaConfigRemoved(customer) => self.aspas.remove(customer),
+            EvtDet::AspaObjectsUpdated(rcn, updates) => self
+                .resources
+                .get_mut(&rcn)
+                .unwrap()
+                .aspa_objects_updated(updates),
+
+            //-----------------------------------------------------------------------
+            // BGPsec router certificates
+            //-----------------------------------------------------------------------
+            EvtDet::BgpSecDefinitionAdded(definition) => self.bgpsec_defs.add(definition),
+            EvtDet::BgpSecDefinitionRemoved(asn_key) => self.bgpsec_defs.remove(&asn_key),
+            EvtDet::BgpSecCertificatesUpdated(rcn, updates) => self
+                .resources
+                .get_mut(&rcn)
+                .unwrap()
+                .bgpsec_certificates_updated(updates),
+
+            //-----------------------------------------------------------------------
+            // Timing
+            //-----------------------------------------------------------------------
+            EvtDet::TimingUpdated(timing) => self.timing = timing,
 
             //-----------------------------------------------------------------------
             // Publication
@@ -369,11 +440,31 @@ impl<S: Signer> Aggregate for CertAuth<S> {
             CmdDet::KeyRollInitiate(duration, signer) => self.keyroll_initiate(duration, signer),
             CmdDet::KeyRollActivate(duration, signer) => self.keyroll_activate(duration, signer),
             CmdDet::KeyRollFinish(rcn, response) => self.keyroll_finish(rcn, response),
+            CmdDet::KeyRollPolicyUpdate(policy) => self.key_roll_policy_update(policy),
+            CmdDet::KeyRollEmergency(reason, signer) => self.keyroll_emergency(reason, signer),
 
             // Route Authorizations
             CmdDet::RouteAuthorizationsUpdate(updates, signer) => {
                 self.route_authorizations_update(updates, signer)
             }
+            CmdDet::RoaGroupingUpdate(strategy, signer) => {
+                self.roa_grouping_update(strategy, signer)
+            }
+            CmdDet::RoaGroupingMigrate(signer) => self.roa_grouping_migrate(signer),
+
+            // ASPAs
+            CmdDet::AspasUpdate(updates, signer) => self.aspas_update(updates, signer),
+            CmdDet::AspasUpdateProviders(customer, update, signer) => {
+                self.aspas_update_providers(customer, update, signer)
+            }
+
+            // BGPsec router certificates
+            CmdDet::BgpSecUpdateDefinitions(updates, signer) => {
+                self.bgpsec_update_definitions(updates, signer)
+            }
+
+            // Timing
+            CmdDet::TimingUpdate(timing) => self.timing_update(timing),
 
             // Republish
             CmdDet::Republish(signer) => self.republish(signer),
@@ -415,6 +506,14 @@ impl<S: Signer> CertAuth<S> {
             .collect()
     }
 
+    pub fn aspa_definitions(&self) -> AspaDefinitionList {
+        self.aspas.as_list()
+    }
+
+    pub fn bgpsec_definitions(&self) -> BgpSecDefinitionList {
+        self.bgpsec_defs.as_list()
+    }
+
     pub fn child_request(&self) -> rfc8183::ChildRequest {
         rfc8183::ChildRequest::new(self.handle.clone(), self.id.cert.clone())
     }
@@ -429,6 +528,16 @@ impl<S: Signer> CertAuth<S> {
     pub fn id_key(&self) -> &KeyIdentifier {
         &self.id.key
     }
+
+    /// Returns the identifiers of all keys held by this CA, i.e. its
+    /// identity key and the keys of all its resource classes.
+    pub fn all_key_ids(&self) -> Vec<KeyIdentifier> {
+        let mut keys = vec![*self.id_key()];
+        for rc in self.resources.values() {
+            keys.extend(rc.key_ids().into_iter().cloned());
+        }
+        keys
+    }
     pub fn handle(&self) -> &Handle {
         &self.handle
     }
@@ -466,9 +575,45 @@ impl<S: Signer> CertAuth<S> {
     }
 }
 
+/// # Validating published objects
+///
+impl<S: Signer> CertAuth<S> {
+    /// Validates the objects which this CA believes are published, like a
+    /// relying party would, and returns any objects found to be invalid.
+    pub fn validate_published(&self) -> Vec<InvalidObject> {
+        self.validate_objects(&self.all_objects(), Time::now())
+    }
+
+    /// Validates the given objects as if they were published by this CA,
+    /// at the given time.
+    fn validate_objects(&self, objects: &[PublishElement], now: Time) -> Vec<InvalidObject> {
+        if self.repository.is_none() {
+            return vec![]; // nothing is published
+        }
+
+        let mut validator = PublishedObjectsValidator::new(objects, now);
+        for rc in self.resources.values() {
+            for key in rc.certified_keys() {
+                validator.validate_key(key);
+            }
+        }
+        validator.finish()
+    }
+}
+
 /// # Being a trustanchor
 ///
 impl<S: Signer> CertAuth<S> {
+    /// Returns the certificate of the current key of this Trust Anchor.
+    pub fn trust_anchor_cert(&self) -> KrillResult<&RcvdCert> {
+        self.resources
+            .values()
+            .filter_map(|rc| rc.current_key())
+            .map(|key| key.incoming_cert())
+            .next()
+            .ok_or_else(|| Error::KeyUseNoCurrentKey)
+    }
+
     fn trust_anchor_make(
         &self,
         uris: Vec<uri::Https>,
@@ -627,7 +772,7 @@ impl<S: Signer> CertAuth<S> {
         for ki in child_keys {
             if let Some(issued) = my_rc.issued(&ki) {
                 issued_certs.push(issued.clone());
-                let eligble_not_after = Self::eligible_not_after(issued);
+                let eligble_not_after = self.eligible_not_after(issued);
                 if eligble_not_after > not_after {
                     not_after = eligble_not_after
                 }
@@ -643,13 +788,13 @@ impl<S: Signer> CertAuth<S> {
         ))
     }
 
-    fn eligible_not_after(issued: &IssuedCert) -> Time {
+    fn eligible_not_after(&self, issued: &IssuedCert) -> Time {
+        let timing = self.timing_config();
         let expiration_time = issued.validity().not_after();
-        if expiration_time > Time::now() + chrono::Duration::weeks(CHILD_CERTIFICATE_REISSUE_WEEKS)
-        {
+        if expiration_time > Time::now() + timing.child_certificate_reissue() {
             expiration_time
         } else {
-            Time::next_year()
+            Time::now() + timing.child_certificate_valid()
         }
     }
 
@@ -760,7 +905,8 @@ impl<S: Signer> CertAuth<S> {
         let child = self.get_child(&child)?;
         child.resources().apply_limit(&limit)?;
 
-        my_rc.issue_cert(csr_info, child.resources(), limit, signer)
+        let timing = self.timing_config();
+        my_rc.issue_cert(csr_info, child.resources(), limit, &timing, signer)
     }
 
     /// Create a publish event details including the revocations, update, withdrawals needed
@@ -777,7 +923,13 @@ impl<S: Signer> CertAuth<S> {
         self.resources
             .get(&rcn)
             .ok_or_else(|| Error::ResourceClassUnknown(rcn.clone()))?
-            .republish_certs(issued_certs, removed_certs, repo.repo_info(), signer)
+            .republish_certs(
+                issued_certs,
+                removed_certs,
+                repo.repo_info(),
+                &self.timing_config(),
+                signer,
+            )
     }
 
     /// Updates child IdCert and/or Resource entitlements.
@@ -1050,6 +1202,20 @@ impl<S: Signer> CertAuth<S> {
         None
     }
 
+    /// Returns the name of the resource class of this CA for a resource
+    /// class of the parent, e.g. to match a response by the parent.
+    pub fn rc_name_for_parent_class(
+        &self,
+        parent: &ParentHandle,
+        parent_rcn: &ResourceClassName,
+    ) -> KrillResult<ResourceClassName> {
+        self.resources
+            .iter()
+            .find(|(_, rc)| rc.parent_handle() == parent && rc.parent_rc_name() == parent_rcn)
+            .map(|(name, _)| name.clone())
+            .ok_or_else(|| Error::ResourceClassUnknown(parent_rcn.clone()))
+    }
+
     /// Get all the current open certificate requests for a parent.
     /// Returns an empty list if the parent is not found.
     pub fn cert_requests(
@@ -1108,6 +1274,22 @@ impl<S: Signer> CertAuth<S> {
         res
     }
 
+    /// Returns revocation requests for all keys certified by the given
+    /// parent, for when this CA is removed.
+    pub fn revoke_all_requests(
+        &self,
+        parent: &ParentHandle,
+        signer: &S,
+    ) -> KrillResult<HashMap<ResourceClassName, Vec<RevocationRequest>>> {
+        let mut res = HashMap::new();
+        for (name, rc) in self.resources.iter() {
+            if rc.parent_handle() == parent {
+                res.insert(name.clone(), rc.revoke(signer)?);
+            }
+        }
+        Ok(res)
+    }
+
     /// This processes entitlements from a parent, and updates the resource
     /// classes for this CA as needed. I.e.
     ///
@@ -1266,8 +1448,8 @@ impl<S: Signer> CertAuth<S> {
 
         let repo = self.get_repository_contact()?;
 
-        let evt_details = rc.update_received_cert(rcvd_cert, repo.repo_info(), 
-                                                  signer.deref(), &get_roa_prefix_grouping_strategy())?;
+        let evt_details = rc.update_received_cert(rcvd_cert, repo.repo_info(), &self.timing_config(),
+                                                  signer.deref(), &self.roa_prefix_grouping_strategy())?;
 
         let mut res = vec![];
         let mut version = self.version;
@@ -1332,7 +1514,7 @@ impl<S: Signer> CertAuth<S> {
             let repo = self.get_repository_contact()?;
 
             for details in rc
-                .keyroll_activate(repo.repo_info(), staging, signer.deref(), &get_roa_prefix_grouping_strategy())?
+                .keyroll_activate(repo.repo_info(), staging, &self.timing_config(), signer.deref(), &self.roa_prefix_grouping_strategy())?
                 .into_iter()
             {
                 activated = true;
@@ -1348,6 +1530,79 @@ impl<S: Signer> CertAuth<S> {
         Ok(res)
     }
 
+    /// Rolls all keys in an emergency. Resource classes with an active key
+    /// only get a new key, which is activated as soon as it is certified, see
+    /// 'key_roll_emergency'. New keys which are certified already are
+    /// activated immediately, and the old keys are revoked.
+    fn keyroll_emergency(&self, reason: String, signer: Arc<RwLock<S>>) -> KrillResult<Vec<Evt>> {
+        if self.is_ta() {
+            return Err(Error::TaNotAllowed);
+        }
+
+        let mut signer = signer.write().unwrap();
+        let mut version = self.version + 1; // leave room for the emergency event
+        let mut res = vec![];
+
+        for (rcn, rc) in self.resources.iter() {
+            let repo = self.get_repository_contact()?;
+
+            let mut details =
+                rc.keyroll_initiate(repo.repo_info(), Duration::zero(), signer.deref_mut())?;
+            details.append(&mut rc.keyroll_activate(
+                repo.repo_info(),
+                Duration::zero(),
+                &self.timing_config(),
+                signer.deref(),
+                &self.roa_prefix_grouping_strategy(),
+            )?);
+
+            if !details.is_empty() {
+                warn!("Emergency key roll for ca: {}, rc: {}", &self.handle, rcn);
+            }
+
+            for details in details.into_iter() {
+                res.push(StoredEvent::new(self.handle(), version, details));
+                version += 1;
+            }
+        }
+
+        if res.is_empty() {
+            let cause = if self.resources.is_empty() {
+                "the CA has no resource classes"
+            } else {
+                "no resource class has a key that can be rolled, its keys may still \
+                 be waiting for a certificate from the parent"
+            };
+            return Err(Error::CaKeyRollEmergencyNotPossible(
+                self.handle.clone(),
+                cause.to_string(),
+            ));
+        }
+
+        let started = EvtDet::KeyRollEmergencyStarted(reason);
+        res.insert(0, StoredEvent::new(self.handle(), self.version, started));
+        Ok(res)
+    }
+
+    /// Returns whether an emergency key roll is in progress, i.e. whether
+    /// new keys are activated as soon as they are certified.
+    pub fn key_roll_emergency(&self) -> bool {
+        self.key_roll_emergency
+    }
+
+    /// Ends the emergency key roll when there are no more new keys waiting
+    /// to be certified or activated.
+    fn key_roll_emergency_check(&mut self) {
+        let waiting = self.resources.values().any(|rc| {
+            rc.key_roll_progress(None)
+                .map(|progress| progress.stage() != KeyRollStage::Old)
+                .unwrap_or(false)
+        });
+        if !waiting {
+            self.key_roll_emergency = false;
+        }
+    }
+
     fn keyroll_finish(
         &self,
         rcn: ResourceClassName,
@@ -1375,6 +1630,80 @@ impl<S: Signer> CertAuth<S> {
     }
 }
 
+/// # Key roll policy
+///
+impl<S: Signer> CertAuth<S> {
+    /// Returns the policy for automatic key rolls, if this CA has one.
+    pub fn key_roll_policy(&self) -> Option<&KeyRollPolicy> {
+        self.key_roll_policy.as_ref()
+    }
+
+    /// Returns the progress of key rolls in all resource classes of this CA.
+    pub fn key_roll_progress(&self) -> Vec<KeyRollProgress> {
+        let staging = self.key_roll_policy.map(|policy| policy.staging());
+        self.resources
+            .values()
+            .filter_map(|rc| rc.key_roll_progress(staging))
+            .collect()
+    }
+
+    /// Sets the policy for automatic key rolls, or removes it if None is
+    /// given so that keys are only rolled manually.
+    fn key_roll_policy_update(&self, policy: Option<KeyRollPolicy>) -> KrillResult<Vec<Evt>> {
+        if let Some(policy) = &policy {
+            policy
+                .verify()
+                .map_err(|reason| Error::CaKeyRollPolicyInvalid(self.handle.clone(), reason))?;
+        }
+
+        if policy == self.key_roll_policy {
+            Ok(vec![])
+        } else {
+            Ok(vec![StoredEvent::new(
+                &self.handle,
+                self.version,
+                EvtDet::KeyRollPolicyUpdated(policy),
+            )])
+        }
+    }
+}
+
+/// # Timing
+///
+impl<S: Signer> CertAuth<S> {
+    /// Returns the timing config of this CA, or the server wide timing config
+    /// if this CA does not have its own.
+    pub fn timing_config(&self) -> TimingConfig {
+        self.timing.unwrap_or_else(get_timing_config)
+    }
+
+    /// Returns true if this CA has its own timing config.
+    pub fn has_custom_timing(&self) -> bool {
+        self.timing.is_some()
+    }
+
+    /// Sets the timing config for this CA, or reverts to the server wide
+    /// timing config if None is given. The new timing is used the next time
+    /// that objects are published, or certificates are issued.
+    fn timing_update(&self, timing: Option<TimingConfig>) -> KrillResult<Vec<Evt>> {
+        if let Some(timing) = &timing {
+            timing
+                .verify()
+                .map_err(|reason| Error::CaTimingInvalid(self.handle.clone(), reason))?;
+        }
+
+        if timing == self.timing {
+            Ok(vec![])
+        } else {
+            Ok(vec![StoredEvent::new(
+                &self.handle,
+                self.version,
+                EvtDet::TimingUpdated(timing),
+            )])
+        }
+    }
+}
+
 /// # Publishing
 ///
 impl<S: Signer> CertAuth<S> {
@@ -1412,8 +1741,16 @@ impl<S: Signer> CertAuth<S> {
                     self.get_repository_contact()?.repo_info()
                 };
 
-                res.append(&mut rc.republish(auths.as_slice(), repo_info, mode, 
-                                             signer, &get_roa_prefix_grouping_strategy())?);
+                res.append(&mut rc.republish(
+                    auths.as_slice(),
+                    &self.aspas,
+                    &self.bgpsec_defs,
+                    repo_info,
+                    mode,
+                    &self.timing_config(),
+                    signer,
+                    &self.roa_prefix_grouping_strategy(),
+                )?);
             }
         }
 
@@ -1552,7 +1889,7 @@ impl<S: Signer> CertAuth<S> {
         // Update ROAs, and derive deltas and revocations for publishing.
         for (rcn, rc) in self.resources.iter() {
             let updates = rc.update_roas(current_auths.as_slice(), &mode, 
-                                         signer.deref(), &get_roa_prefix_grouping_strategy())?;
+                                         signer.deref(), &self.roa_prefix_grouping_strategy())?;
             if updates.contains_changes() {
                 let mut delta = ObjectsDelta::new(repo.repo_info().ca_repository(rc.name_space()));
 
@@ -1583,8 +1920,14 @@ impl<S: Signer> CertAuth<S> {
         for (rcn, (delta, revocations)) in deltas.into_iter() {
             let rc = self.resources.get(&rcn).unwrap();
 
-            let pub_detail =
-                rc.publish_objects(repo.repo_info(), delta, revocations, &mode, signer.deref())?;
+            let pub_detail = rc.publish_objects(
+                repo.repo_info(),
+                delta,
+                revocations,
+                &mode,
+                &self.timing_config(),
+                signer.deref(),
+            )?;
 
             res.push(StoredEvent::new(&self.handle, version, pub_detail));
             version += 1;
@@ -1594,6 +1937,343 @@ impl<S: Signer> CertAuth<S> {
     }
 }
 
+/// # ROA grouping
+///
+impl<S: Signer> CertAuth<S> {
+    /// Returns the ROA prefix grouping strategy of this CA, or the server
+    /// wide strategy if this CA does not have its own.
+    pub fn roa_prefix_grouping_strategy(&self) -> RoaPrefixGroupingStrategy {
+        self.roa_grouping
+            .unwrap_or_else(get_roa_prefix_grouping_strategy)
+    }
+
+    /// Returns true if this CA has its own ROA prefix grouping strategy.
+    pub fn has_custom_roa_grouping(&self) -> bool {
+        self.roa_grouping.is_some()
+    }
+
+    /// Sets the ROA prefix grouping strategy for this CA, or reverts to the
+    /// server wide strategy if None is given. Existing ROAs are re-issued in
+    /// the new grouping, if it differs from the current grouping.
+    fn roa_grouping_update(
+        &self,
+        strategy: Option<RoaPrefixGroupingStrategy>,
+        signer: Arc<RwLock<S>>,
+    ) -> KrillResult<Vec<Evt>> {
+        if strategy == self.roa_grouping {
+            return Ok(vec![]);
+        }
+
+        let evt_dets = vec![EvtDet::RoaGroupingUpdated(strategy)];
+        let new_strategy = strategy.unwrap_or_else(get_roa_prefix_grouping_strategy);
+        let signer = signer.read().unwrap();
+        self.roa_grouping_migration(evt_dets, &new_strategy, signer.deref())
+    }
+
+    /// Re-issues the ROAs if they are not grouped according to the strategy
+    /// used by this CA, e.g. because the server wide strategy was changed.
+    fn roa_grouping_migrate(&self, signer: Arc<RwLock<S>>) -> KrillResult<Vec<Evt>> {
+        let signer = signer.read().unwrap();
+        self.roa_grouping_migration(vec![], &self.roa_prefix_grouping_strategy(), signer.deref())
+    }
+
+    /// Returns the given events, followed by events for the ROAs which are
+    /// re-issued in all resource classes where they are not grouped according
+    /// to the strategy, and their publication. The old ROAs are withdrawn in
+    /// the same delta.
+    fn roa_grouping_migration(
+        &self,
+        evt_dets: Vec<EvtDet>,
+        strategy: &RoaPrefixGroupingStrategy,
+        signer: &S,
+    ) -> KrillResult<Vec<Evt>> {
+        let auths: Vec<RouteAuthorization> = self.routes.authorizations().cloned().collect();
+
+        self.publish_object_updates(
+            evt_dets,
+            |rc, _mode| rc.migrate_roas(auths.as_slice(), signer, strategy),
+            EvtDet::RoasUpdated,
+            signer,
+        )
+    }
+}
+
+/// # Managing ASPAs
+///
+impl<S: Signer> CertAuth<S> {
+    /// Adds or replaces ASPA definitions, and removes definitions for customer
+    /// ASNs, and updates the ASPA objects. Will return an error in case a
+    /// definition is added for a customer ASN not held by this CA.
+    fn aspas_update(
+        &self,
+        updates: AspaDefinitionUpdates,
+        signer: Arc<RwLock<S>>,
+    ) -> KrillResult<Vec<Evt>> {
+        let (add_or_replace, remove) = updates.unpack();
+        let all_resources = self.all_resources();
+
+        let mut definitions = self.aspas.clone();
+        let mut evt_dets = vec![];
+
+        for definition in add_or_replace {
+            let customer = definition.customer();
+            if definition.providers().is_empty() {
+                return Err(Error::CaAspaProvidersEmpty(self.handle.clone(), customer));
+            } else if definition.customer_used_as_provider() {
+                return Err(Error::CaAspaCustomerAsProvider(
+                    self.handle.clone(),
+                    customer,
+                ));
+            } else if !all_resources.contains_asn(customer) {
+                return Err(Error::CaAspaNotEntitled(self.handle.clone(), customer));
+            }
+
+            definitions.add_or_replace(definition.clone());
+            evt_dets.push(EvtDet::AspaConfigAdded(definition));
+        }
+
+        for customer in remove {
+            if !definitions.has(customer) {
+                return Err(Error::CaAspaUnknown(self.handle.clone(), customer));
+            }
+
+            definitions.remove(customer);
+            evt_dets.push(EvtDet::AspaConfigRemoved(customer));
+        }
+
+        self.aspa_objects_update(evt_dets, &definitions, signer)
+    }
+
+    /// Updates the providers for the ASPA definition of an existing customer
+    /// ASN, and updates the ASPA objects.
+    fn aspas_update_providers(
+        &self,
+        customer: AsNumber,
+        update: AspaProvidersUpdate,
+        signer: Arc<RwLock<S>>,
+    ) -> KrillResult<Vec<Evt>> {
+        let mut definition = self
+            .aspas
+            .get(customer)
+            .cloned()
+            .ok_or_else(|| Error::CaAspaUnknown(self.handle.clone(), customer))?;
+
+        if !definition.apply_update(&update) {
+            return Err(Error::CaAspaProvidersUpdateConflict(
+                self.handle.clone(),
+                customer,
+            ));
+        } else if definition.providers().is_empty() {
+            return Err(Error::CaAspaProvidersEmpty(self.handle.clone(), customer));
+        } else if definition.customer_used_as_provider() {
+            return Err(Error::CaAspaCustomerAsProvider(
+                self.handle.clone(),
+                customer,
+            ));
+        }
+
+        let mut definitions = self.aspas.clone();
+        definitions.add_or_replace(definition);
+
+        let evt_dets = vec![EvtDet::AspaConfigUpdated(customer, update)];
+
+        self.aspa_objects_update(evt_dets, &definitions, signer)
+    }
+
+    /// Returns the given configuration events, followed by events for the
+    /// updated ASPA objects in each resource class and their publication.
+    fn aspa_objects_update(
+        &self,
+        evt_dets: Vec<EvtDet>,
+        definitions: &AspaDefinitions,
+        signer: Arc<RwLock<S>>,
+    ) -> KrillResult<Vec<Evt>> {
+        let signer = signer.read().unwrap();
+
+        self.publish_object_updates(
+            evt_dets,
+            |rc, mode| rc.update_aspas(definitions, mode, signer.deref()),
+            EvtDet::AspaObjectsUpdated,
+            signer.deref(),
+        )
+    }
+}
+
+/// # Managing BGPsec router certificates
+///
+impl<S: Signer> CertAuth<S> {
+    /// Adds and removes BGPsec router certificate definitions, and issues or
+    /// revokes the router certificates. Will return an error in case the CSR
+    /// of an added definition is invalid, or its ASN is not held by this CA.
+    fn bgpsec_update_definitions(
+        &self,
+        updates: BgpSecDefinitionUpdates,
+        signer: Arc<RwLock<S>>,
+    ) -> KrillResult<Vec<Evt>> {
+        let (add, remove) = updates.unpack();
+        let all_resources = self.all_resources();
+
+        let mut definitions = self.bgpsec_defs.clone();
+        let mut evt_dets = vec![];
+
+        for definition in add {
+            if definition.csr().validate().is_err() {
+                return Err(Error::CaBgpSecCsrInvalid(
+                    self.handle.clone(),
+                    "invalid signature".to_string(),
+                ));
+            } else if !definition.has_router_key() {
+                return Err(Error::BgpSecRouterKeyUnsupported(definition.asn_key()));
+            } else if !all_resources.contains_asn(definition.asn()) {
+                return Err(Error::CaBgpSecNotEntitled(
+                    self.handle.clone(),
+                    definition.asn(),
+                ));
+            }
+
+            definitions.add(definition.clone());
+            evt_dets.push(EvtDet::BgpSecDefinitionAdded(definition));
+        }
+
+        for asn_key in remove {
+            if !definitions.has(&asn_key) {
+                return Err(Error::CaBgpSecUnknown(self.handle.clone(), asn_key));
+            }
+
+            definitions.remove(&asn_key);
+            evt_dets.push(EvtDet::BgpSecDefinitionRemoved(asn_key));
+        }
+
+        let signer = signer.read().unwrap();
+
+        self.publish_object_updates(
+            evt_dets,
+            |rc, mode| rc.update_bgpsec_certificates(&definitions, mode, signer.deref()),
+            EvtDet::BgpSecCertificatesUpdated,
+            signer.deref(),
+        )
+    }
+}
+
+/// # Publishing updated objects
+///
+impl<S: Signer> CertAuth<S> {
+    /// Updates objects, such as ROAs, ASPA objects or router certificates,
+    /// in each resource class with a current key, and publishes all changes
+    /// in a resource class as a single delta. Returns the given events,
+    /// followed by the events for the updated objects, and the events for
+    /// their publication.
+    fn publish_object_updates<U, F>(
+        &self,
+        mut evt_dets: Vec<EvtDet>,
+        update: F,
+        updated: fn(ResourceClassName, U) -> EvtDet,
+        signer: &S,
+    ) -> KrillResult<Vec<Evt>>
+    where
+        U: ObjectUpdates,
+        F: Fn(&ResourceClass, &PublishMode) -> KrillResult<U>,
+    {
+        let mode = PublishMode::Normal;
+
+        let mut deltas = HashMap::new();
+
+        // Update objects, and derive deltas and revocations for publishing.
+        // The repository is only needed if there are changes, so that CAs
+        // without repository can be checked for e.g. the ROA grouping.
+        for (rcn, rc) in self.resources.iter() {
+            if rc.current_key().is_none() {
+                continue;
+            }
+
+            let updates = update(rc, &mode)?;
+            if updates.contains_changes() {
+                let repo = self.get_repository_contact()?;
+                let mut delta = ObjectsDelta::new(repo.repo_info().ca_repository(rc.name_space()));
+
+                for added in updates.added().into_iter() {
+                    delta.add(added);
+                }
+                for update in updates.updated().into_iter() {
+                    delta.update(update);
+                }
+                for withdraw in updates.withdrawn().into_iter() {
+                    delta.withdraw(withdraw);
+                }
+
+                deltas.insert(rcn, (delta, updates.revocations()));
+
+                evt_dets.push(updated(rcn.clone(), updates));
+            }
+        }
+
+        // Create publication delta with all additions/updates/withdraws as a single delta
+        for (rcn, (delta, revocations)) in deltas.into_iter() {
+            let repo = self.get_repository_contact()?;
+            let rc = self.resources.get(&rcn).unwrap();
+            evt_dets.push(rc.publish_objects(
+                repo.repo_info(),
+                delta,
+                revocations,
+                &mode,
+                &self.timing_config(),
+                signer,
+            )?);
+        }
+
+        let mut version = self.version;
+        let mut res = vec![];
+        for evt_det in evt_dets.into_iter() {
+            res.push(StoredEvent::new(&self.handle, version, evt_det));
+            version += 1;
+        }
+        Ok(res)
+    }
+}
+
+/// # Signing Resource Signed Checklists
+///
+impl<S: Signer> CertAuth<S> {
+    /// Signs an RPKI Signed Checklist for the requested resources and file
+    /// digests, using a one-off EE certificate under the current key of the
+    /// resource class holding all requested resources. Signed checklists are
+    /// handed to the requester only, so there are no events or publication.
+    pub fn sign_checklist(&self, request: RscRequest, signer: &S) -> KrillResult<SignedChecklist> {
+        if request.resources().is_empty() {
+            return Err(Error::CaRscInvalid(
+                self.handle.clone(),
+                "no resources".to_string(),
+            ));
+        } else if request.digests().is_empty() {
+            return Err(Error::CaRscInvalid(
+                self.handle.clone(),
+                "no digests".to_string(),
+            ));
+        }
+
+        for digest in request.digests() {
+            digest
+                .validate()
+                .map_err(|e| Error::CaRscInvalid(self.handle.clone(), e.to_string()))?;
+        }
+
+        let key = self
+            .resources
+            .values()
+            .find(|rc| match rc.current_resources() {
+                Some(held) => held.contains(request.resources()),
+                None => false,
+            })
+            .and_then(|rc| rc.current_key())
+            .ok_or_else(|| {
+                Error::CaRscNotEntitled(self.handle.clone(), request.resources().clone())
+            })?;
+
+        let rsc = SignedChecklistBuilder::make_rsc(&request, key, signer)?;
+        Ok(SignedChecklist::new(Base64::from(&rsc)))
+    }
+}
+
 pub fn set_roa_prefix_grouping_strategy(roa_prefix_grouping_strategy: RoaPrefixGroupingStrategy) {
     *(ROA_PREFIX_GROUPING_STRATEGY.lock().unwrap()) = roa_prefix_grouping_strategy;
 }
@@ -1602,13 +2282,28 @@ fn get_roa_prefix_grouping_strategy() -> RoaPrefixGroupingStrategy {
     ROA_PREFIX_GROUPING_STRATEGY.lock().unwrap().deref().clone()
 }
 
+pub fn set_timing_config(timing: TimingConfig) {
+    *(TIMING_CONFIG.lock().unwrap()) = timing;
+}
+
+fn get_timing_config() -> TimingConfig {
+    *TIMING_CONFIG.lock().unwrap()
+}
+
 //------------ Tests ---------------------------------------------------------
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
+
+    use crate::commons::api::RepoInfo;
+    use crate::commons::eventsourcing::KeyStoreType;
     use crate::commons::util::softsigner::OpenSslSigner;
     use crate::commons::util::test;
+    use crate::constants::SNAPSHOT_EVENTS;
+    use crate::daemon::ca::CaServer;
+    use crate::daemon::mq::EventQueueListener;
 
     #[test]
     fn generate_id_cert() {
@@ -1618,4 +2313,149 @@ mod tests {
             id.cert.validate_ta().unwrap();
         });
     }
+
+    /// Returns an embedded trust anchor, and the objects it has published.
+    fn published_ta(d: &PathBuf) -> (Arc<CertAuth<OpenSslSigner>>, Vec<PublishElement>) {
+        let signer = Arc::new(RwLock::new(OpenSslSigner::build(d).unwrap()));
+        let event_queue = Arc::new(EventQueueListener::in_mem());
+
+        let server = CaServer::<OpenSslSigner>::build(
+            d,
+            event_queue,
+            signer,
+            RoaPrefixGroupingStrategy::RoaPerPrefix,
+            TimingConfig::default(),
+            SNAPSHOT_EVENTS,
+            KeyStoreType::Disk,
+        )
+        .unwrap();
+
+        let repo_info = {
+            let base_uri = test::rsync("rsync://localhost/repo/ta/");
+            let rrdp_uri = test::https("https://localhost/repo/notification.xml");
+            RepoInfo::new(base_uri, rrdp_uri)
+        };
+        let ta_uri = test::https("https://localhost/ta/ta.cer");
+        let ta_aia = test::rsync("rsync://localhost/repo/ta.cer");
+        server.init_ta(repo_info, ta_aia, vec![ta_uri]).unwrap();
+
+        let ta = server.get_trust_anchor().unwrap();
+        let objects = ta.all_objects();
+        (ta, objects)
+    }
+
+    fn crl_index(objects: &[PublishElement]) -> usize {
+        objects
+            .iter()
+            .position(|el| el.uri().to_string().ends_with(".crl"))
+            .unwrap()
+    }
+
+    fn has_issue(invalid: &[InvalidObject], uri: &uri::Rsync, reason: &str) -> bool {
+        invalid
+            .iter()
+            .any(|i| i.uri() == uri && i.reason() == reason)
+    }
+
+    #[test]
+    fn validate_published() {
+        test::test_under_tmp(|d| {
+            let (ta, _) = published_ta(&d);
+            assert_eq!(Vec::<InvalidObject>::new(), ta.validate_published());
+        });
+    }
+
+    #[test]
+    fn validate_published_tampered_object() {
+        test::test_under_tmp(|d| {
+            let (ta, mut objects) = published_ta(&d);
+
+            let idx = crl_index(&objects);
+            let crl_uri = objects[idx].uri().clone();
+            let mut bytes = objects[idx].base64().to_bytes().to_vec();
+            let last = bytes.len() - 1;
+            bytes[last] ^= 0xff;
+            objects[idx] = PublishElement::new(Base64::from_content(&bytes), crl_uri.clone());
+
+            let invalid = ta.validate_objects(&objects, Time::now());
+            assert!(has_issue(
+                &invalid,
+                &crl_uri,
+                "hash does not match manifest"
+            ));
+        });
+    }
+
+    #[test]
+    fn validate_published_tampered_signature() {
+        test::test_under_tmp(|d| {
+            let (ta, mut objects) = published_ta(&d);
+
+            // The manifest is not listed on a manifest, so only its own
+            // signature shows that it was changed. The CMS ends with it.
+            let idx = objects
+                .iter()
+                .position(|el| el.uri().to_string().ends_with(".mft"))
+                .unwrap();
+            let mft_uri = objects[idx].uri().clone();
+            let mut bytes = objects[idx].base64().to_bytes().to_vec();
+            let last = bytes.len() - 1;
+            bytes[last] ^= 0xff;
+            objects[idx] = PublishElement::new(Base64::from_content(&bytes), mft_uri.clone());
+
+            let invalid = ta.validate_objects(&objects, Time::now());
+            assert!(has_issue(
+                &invalid,
+                &mft_uri,
+                "signature does not match EE certificate"
+            ));
+        });
+    }
+
+    #[test]
+    fn validate_published_missing_crl() {
+        test::test_under_tmp(|d| {
+            let (ta, mut objects) = published_ta(&d);
+
+            let crl = objects.remove(crl_index(&objects));
+
+            let invalid = ta.validate_objects(&objects, Time::now());
+            assert!(has_issue(&invalid, crl.uri(), "CRL is not published"));
+        });
+    }
+
+    #[test]
+    fn validate_published_stale_crl() {
+        test::test_under_tmp(|d| {
+            let (ta, objects) = published_ta(&d);
+            let crl_uri = objects[crl_index(&objects)].uri().clone();
+
+            // The CRL is re-issued before it becomes stale, but this did not
+            // happen if it is validated a few days later.
+            let later = Time::now() + Duration::days(3);
+
+            let invalid = ta.validate_objects(&objects, later);
+            assert!(has_issue(&invalid, &crl_uri, "CRL is stale"));
+        });
+    }
+
+    #[test]
+    fn validate_published_not_on_manifest() {
+        test::test_under_tmp(|d| {
+            let (ta, mut objects) = published_ta(&d);
+
+            let uri = test::rsync("rsync://localhost/repo/ta/0/unlisted.roa");
+            objects.push(PublishElement::new(
+                Base64::from_content(b"unlisted"),
+                uri.clone(),
+            ));
+
+            let invalid = ta.validate_objects(&objects, Time::now());
+            assert!(has_issue(
+                &invalid,
+                &uri,
+                "published, but not on any manifest"
+            ));
+        });
+    }
 }
@@ -1,15 +1,14 @@
 use std::collections::HashMap;
 
-use chrono::Duration;
-
 use rpki::crypto::KeyIdentifier;
 use rpki::x509::Time;
 
-use crate::commons::api::{ChildCaInfo, ChildHandle, IssuedCert, ResourceClassName, ResourceSet};
+use crate::commons::api::{
+    ChildCaInfo, ChildHandle, IssuedCert, ResourceClassName, ResourceSet, TimingConfig,
+};
 use crate::commons::error::Error;
 use crate::commons::remote::id::IdCert;
 use crate::commons::KrillResult;
-use crate::constants::CHILD_CERTIFICATE_REISSUE_WEEKS;
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[allow(clippy::large_enum_variant)]
@@ -148,12 +147,11 @@ impl ChildCertificates {
         self.inner.values()
     }
 
-    pub fn expiring(&self) -> Vec<&IssuedCert> {
+    pub fn expiring(&self, timing: &TimingConfig) -> Vec<&IssuedCert> {
         self.inner
             .values()
             .filter(|issued| {
-                issued.validity().not_after()
-                    < Time::now() + Duration::weeks(CHILD_CERTIFICATE_REISSUE_WEEKS)
+                issued.validity().not_after() < Time::now() + timing.child_certificate_reissue()
             })
             .collect()
     }
@@ -6,9 +6,10 @@ use chrono::Duration;
 use rpki::uri;
 
 use crate::commons::api::{
-    ChildHandle, Entitlements, Handle, IssuanceRequest, ParentCaContact, ParentHandle, RcvdCert,
+    AsNumber, AspaDefinitionUpdates, AspaProvidersUpdate, BgpSecDefinitionUpdates, ChildHandle,
+    Entitlements, Handle, IssuanceRequest, KeyRollPolicy, ParentCaContact, ParentHandle, RcvdCert,
     RepositoryContact, ResourceClassName, ResourceSet, RevocationRequest, RevocationResponse,
-    UpdateChildRequest,
+    RoaPrefixGroupingStrategy, TimingConfig, UpdateChildRequest,
 };
 use crate::commons::eventsourcing;
 use crate::commons::remote::id::IdCert;
@@ -93,11 +94,51 @@ pub enum CmdDet<S: Signer> {
     // class has been revoked. I.e. remove the old key, and withdraw the crl and mft for it.
     KeyRollFinish(ResourceClassName, RevocationResponse),
 
+    // Set the policy for automatic key rolls, or roll keys manually only if None.
+    KeyRollPolicyUpdate(Option<KeyRollPolicy>),
+
+    // Roll all keys in an emergency, e.g. because they may be compromised, for the
+    // given reason. Keys which have a new key already are activated immediately,
+    // other new keys are activated as soon as they are certified. I.e. the staging
+    // period is not observed.
+    KeyRollEmergency(String, Arc<RwLock<S>>),
+
     // ------------------------------------------------------------
     // ROA Support
     // ------------------------------------------------------------
     RouteAuthorizationsUpdate(RouteAuthorizationUpdates, Arc<RwLock<S>>),
 
+    // Set the ROA grouping for this CA, or use the server strategy if None. ROAs
+    // are re-issued in the new grouping if needed.
+    RoaGroupingUpdate(Option<RoaPrefixGroupingStrategy>, Arc<RwLock<S>>),
+
+    // Re-issue ROAs if they are not grouped according to the strategy used by
+    // this CA, e.g. because the server strategy was changed.
+    RoaGroupingMigrate(Arc<RwLock<S>>),
+
+    // ------------------------------------------------------------
+    // ASPA Support
+    // ------------------------------------------------------------
+
+    // Add or replace ASPA definitions, and remove definitions for customer ASNs.
+    AspasUpdate(AspaDefinitionUpdates, Arc<RwLock<S>>),
+    // Update the providers in the ASPA definition for an existing customer ASN.
+    AspasUpdateProviders(AsNumber, AspaProvidersUpdate, Arc<RwLock<S>>),
+
+    // ------------------------------------------------------------
+    // BGPsec Router Certificate Support
+    // ------------------------------------------------------------
+
+    // Add and remove BGPsec router certificate definitions.
+    BgpSecUpdateDefinitions(BgpSecDefinitionUpdates, Arc<RwLock<S>>),
+
+    // ------------------------------------------------------------
+    // Timing
+    // ------------------------------------------------------------
+
+    // Set the timing for this CA, or use the server timing if None.
+    TimingUpdate(Option<TimingConfig>),
+
     // ------------------------------------------------------------
     // Publishing
     // ------------------------------------------------------------
@@ -182,11 +223,48 @@ impl<S: Signer> fmt::Display for CmdDet<S> {
             }
 
             CmdDet::KeyRollFinish(rcn, _) => write!(f, "Retire old revoked key in RC '{}'", rcn),
+            CmdDet::KeyRollPolicyUpdate(policy) => match policy {
+                Some(policy) => write!(f, "Update key roll policy to: {}", policy),
+                None => write!(f, "Remove key roll policy"),
+            },
+            CmdDet::KeyRollEmergency(reason, _) => {
+                write!(f, "Emergency key roll, reason: '{}'", reason)
+            }
 
             // ------------------------------------------------------------
             // ROA Support
             // ------------------------------------------------------------
             CmdDet::RouteAuthorizationsUpdate(updates, _) => write!(f, "Update ROAs '{}'", updates),
+            CmdDet::RoaGroupingUpdate(strategy, _) => match strategy {
+                Some(strategy) => write!(f, "Update ROA grouping to {}", strategy),
+                None => write!(f, "Use server ROA grouping"),
+            },
+            CmdDet::RoaGroupingMigrate(_) => write!(f, "Migrate ROAs to current grouping"),
+
+            // ------------------------------------------------------------
+            // ASPA Support
+            // ------------------------------------------------------------
+            CmdDet::AspasUpdate(updates, _) => write!(f, "Update ASPAs {}", updates),
+            CmdDet::AspasUpdateProviders(customer, update, _) => write!(
+                f,
+                "Update providers for ASPA for customer AS '{}': {}",
+                customer, update
+            ),
+
+            // ------------------------------------------------------------
+            // BGPsec Router Certificate Support
+            // ------------------------------------------------------------
+            CmdDet::BgpSecUpdateDefinitions(updates, _) => {
+                write!(f, "Update BGPsec router certificates {}", updates)
+            }
+
+            // ------------------------------------------------------------
+            // Timing
+            // ------------------------------------------------------------
+            CmdDet::TimingUpdate(timing) => match timing {
+                Some(timing) => write!(f, "Update timing to {}", timing),
+                None => write!(f, "Use server timing"),
+            },
 
             // ------------------------------------------------------------
             // Publishing
@@ -339,6 +417,15 @@ impl<S: Signer> CmdDet<S> {
         eventsourcing::SentCommand::new(handle, None, CmdDet::KeyRollFinish(rcn, res))
     }
 
+    /// Sets the policy for automatic key rolls, or removes it.
+    pub fn key_roll_policy_update(handle: &Handle, policy: Option<KeyRollPolicy>) -> Cmd<S> {
+        eventsourcing::SentCommand::new(handle, None, CmdDet::KeyRollPolicyUpdate(policy))
+    }
+
+    pub fn key_roll_emergency(handle: &Handle, reason: String, signer: Arc<RwLock<S>>) -> Cmd<S> {
+        eventsourcing::SentCommand::new(handle, None, CmdDet::KeyRollEmergency(reason, signer))
+    }
+
     pub fn publish(handle: &Handle, signer: Arc<RwLock<S>>) -> Cmd<S> {
         eventsourcing::SentCommand::new(handle, None, CmdDet::Republish(signer))
     }
@@ -369,4 +456,76 @@ impl<S: Signer> CmdDet<S> {
             CmdDet::RouteAuthorizationsUpdate(updates, signer),
         )
     }
+    /// Updates the route authorizations, provided that the CA is still at
+    /// the given version, i.e. it was not changed since the updates were
+    /// determined.
+    pub fn route_authorizations_update_at_version(
+        handle: &Handle,
+        version: u64,
+        updates: RouteAuthorizationUpdates,
+        signer: Arc<RwLock<S>>,
+    ) -> Cmd<S> {
+        eventsourcing::SentCommand::new(
+            handle,
+            Some(version),
+            CmdDet::RouteAuthorizationsUpdate(updates, signer),
+        )
+    }
+
+    /// Sets the ROA grouping for this CA, or reverts to the server strategy.
+    pub fn roa_grouping_update(
+        handle: &Handle,
+        strategy: Option<RoaPrefixGroupingStrategy>,
+        signer: Arc<RwLock<S>>,
+    ) -> Cmd<S> {
+        eventsourcing::SentCommand::new(handle, None, CmdDet::RoaGroupingUpdate(strategy, signer))
+    }
+
+    /// Re-issues the ROAs of this CA if they are not grouped according to its
+    /// current strategy.
+    pub fn roa_grouping_migrate(handle: &Handle, signer: Arc<RwLock<S>>) -> Cmd<S> {
+        eventsourcing::SentCommand::new(handle, None, CmdDet::RoaGroupingMigrate(signer))
+    }
+
+    /// Adds or replaces ASPA definitions, and removes definitions for
+    /// customer ASNs.
+    pub fn aspas_update(
+        handle: &Handle,
+        updates: AspaDefinitionUpdates,
+        signer: Arc<RwLock<S>>,
+    ) -> Cmd<S> {
+        eventsourcing::SentCommand::new(handle, None, CmdDet::AspasUpdate(updates, signer))
+    }
+
+    /// Updates the providers for the ASPA definition of a customer ASN.
+    pub fn aspas_update_providers(
+        handle: &Handle,
+        customer: AsNumber,
+        update: AspaProvidersUpdate,
+        signer: Arc<RwLock<S>>,
+    ) -> Cmd<S> {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CmdDet::AspasUpdateProviders(customer, update, signer),
+        )
+    }
+
+    /// Adds and removes BGPsec router certificate definitions.
+    pub fn bgpsec_update_definitions(
+        handle: &Handle,
+        updates: BgpSecDefinitionUpdates,
+        signer: Arc<RwLock<S>>,
+    ) -> Cmd<S> {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CmdDet::BgpSecUpdateDefinitions(updates, signer),
+        )
+    }
+
+    /// Sets the timing for this CA, or reverts to the server timing.
+    pub fn timing_update(handle: &Handle, timing: Option<TimingConfig>) -> Cmd<S> {
+        eventsourcing::SentCommand::new(handle, None, CmdDet::TimingUpdate(timing))
+    }
 }
@@ -6,18 +6,19 @@ use std::sync::{Arc, RwLock};
 use rpki::crypto::KeyIdentifier;
 
 use crate::commons::api::{
-    AddedObject, ChildHandle, Handle, IssuanceRequest, IssuedCert, ObjectName, ObjectsDelta,
+    AddedObject, AsNumber, AspaDefinition, AspaProvidersUpdate, BgpSecAsnKey, BgpSecDefinition,
+    ChildHandle, Handle, IssuanceRequest, IssuedCert, KeyRollPolicy, ObjectName, ObjectsDelta,
     ParentCaContact, ParentHandle, RcvdCert, RepoInfo, RepositoryContact, ResourceClassName,
-    ResourceSet, Revocation, RevocationRequest, RevokedObject, TaCertDetails, UpdatedObject,
-    WithdrawnObject,
+    ResourceSet, Revocation, RevocationRequest, RevokedObject, RoaPrefixGroupingStrategy,
+    TaCertDetails, TimingConfig, UpdatedObject, WithdrawnObject,
 };
 use crate::commons::eventsourcing::StoredEvent;
 use crate::commons::remote::id::IdCert;
 use crate::commons::KrillResult;
 use crate::daemon::ca::signing::Signer;
 use crate::daemon::ca::{
-    CertifiedKey, ChildDetails, CurrentObjectSetDelta, ResourceClass, Rfc8183Id, RoaInfo,
-    RouteAuthorization,
+    AspaInfo, BgpSecCertInfo, CertifiedKey, ChildDetails, CurrentObjectSetDelta, ResourceClass,
+    Rfc8183Id, RoaInfo, RouteAuthorization,
 };
 
 //------------ Ini -----------------------------------------------------------
@@ -71,6 +72,18 @@ impl fmt::Display for IniDet {
     }
 }
 
+//------------ ObjectUpdates -----------------------------------------------
+
+/// Describes the changes to the objects of a kind, e.g. ROAs, under a
+/// ResourceClass, so that they can be published in a single delta.
+pub trait ObjectUpdates {
+    fn contains_changes(&self) -> bool;
+    fn added(&self) -> Vec<AddedObject>;
+    fn updated(&self) -> Vec<UpdatedObject>;
+    fn withdrawn(&self) -> Vec<WithdrawnObject>;
+    fn revocations(&self) -> Vec<Revocation>;
+}
+
 //------------ RoaUpdates --------------------------------------------------
 
 /// Describes an update to the set of ROAs under a ResourceClass.
@@ -78,6 +91,8 @@ impl fmt::Display for IniDet {
 pub struct RoaUpdates {
     updated: HashMap<RouteAuthorization, RoaInfo>,
     removed: HashMap<RouteAuthorization, RevokedObject>,
+    #[serde(default)]
+    removed_names: HashMap<RouteAuthorization, ObjectName>, // ROAs per ASN have their own names
 }
 
 impl Default for RoaUpdates {
@@ -85,6 +100,7 @@ impl Default for RoaUpdates {
         RoaUpdates {
             updated: HashMap::new(),
             removed: HashMap::new(),
+            removed_names: HashMap::new(),
         }
     }
 }
@@ -94,26 +110,42 @@ impl RoaUpdates {
         updated: HashMap<RouteAuthorization, RoaInfo>,
         removed: HashMap<RouteAuthorization, RevokedObject>,
     ) -> Self {
-        RoaUpdates { updated, removed }
+        RoaUpdates {
+            updated,
+            removed,
+            removed_names: HashMap::new(),
+        }
     }
 
     pub fn is_empty(&self) -> bool {
         self.updated.is_empty() && self.removed.is_empty()
     }
 
-    pub fn contains_changes(&self) -> bool {
-        !self.is_empty()
-    }
-
     pub fn update(&mut self, auth: RouteAuthorization, roa: RoaInfo) {
         self.updated.insert(auth, roa);
     }
 
-    pub fn remove(&mut self, auth: RouteAuthorization, revoke: RevokedObject) {
+    pub fn remove(&mut self, auth: RouteAuthorization, name: ObjectName, revoke: RevokedObject) {
         self.removed.insert(auth, revoke);
+        self.removed_names.insert(auth, name);
     }
 
-    pub fn added(&self) -> Vec<AddedObject> {
+    pub fn unpack(
+        self,
+    ) -> (
+        HashMap<RouteAuthorization, RoaInfo>,
+        HashMap<RouteAuthorization, RevokedObject>,
+    ) {
+        (self.updated, self.removed)
+    }
+}
+
+impl ObjectUpdates for RoaUpdates {
+    fn contains_changes(&self) -> bool {
+        !self.is_empty()
+    }
+
+    fn added(&self) -> Vec<AddedObject> {
         let mut res = vec![];
         for (_auth, info) in self.updated.iter() {
             if info.replaces().is_none() {
@@ -125,7 +157,7 @@ impl RoaUpdates {
         res
     }
 
-    pub fn updated(&self) -> Vec<UpdatedObject> {
+    fn updated(&self) -> Vec<UpdatedObject> {
         let mut res = vec![];
         for (_auth, info) in self.updated.iter() {
             if let Some(replaced) = info.replaces() {
@@ -137,17 +169,20 @@ impl RoaUpdates {
         res
     }
 
-    pub fn withdrawn(&self) -> Vec<WithdrawnObject> {
+    fn withdrawn(&self) -> Vec<WithdrawnObject> {
         let mut res = vec![];
         for (auth, revoked) in self.removed.iter() {
-            let name = ObjectName::from(auth);
+            let name = match self.removed_names.get(auth) {
+                Some(name) => name.clone(),
+                None => ObjectName::from(auth),
+            };
             let hash = revoked.hash().clone();
             res.push(WithdrawnObject::new(name, hash));
         }
         res
     }
 
-    pub fn revocations(&self) -> Vec<Revocation> {
+    fn revocations(&self) -> Vec<Revocation> {
         let mut res = vec![];
         for info in self.updated.values() {
             if let Some(old) = info.replaces() {
@@ -161,17 +196,177 @@ impl RoaUpdates {
 
         res
     }
+}
+
+//------------ AspaObjectsUpdates ------------------------------------------
+
+/// Describes an update to the set of ASPA objects under a ResourceClass.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AspaObjectsUpdates {
+    updated: HashMap<AsNumber, AspaInfo>,
+    removed: HashMap<AsNumber, RevokedObject>,
+}
+
+impl AspaObjectsUpdates {
+    pub fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn update(&mut self, customer: AsNumber, aspa: AspaInfo) {
+        self.updated.insert(customer, aspa);
+    }
+
+    pub fn remove(&mut self, customer: AsNumber, revoke: RevokedObject) {
+        self.removed.insert(customer, revoke);
+    }
 
     pub fn unpack(
         self,
     ) -> (
-        HashMap<RouteAuthorization, RoaInfo>,
-        HashMap<RouteAuthorization, RevokedObject>,
+        HashMap<AsNumber, AspaInfo>,
+        HashMap<AsNumber, RevokedObject>,
+    ) {
+        (self.updated, self.removed)
+    }
+}
+
+impl ObjectUpdates for AspaObjectsUpdates {
+    fn contains_changes(&self) -> bool {
+        !self.is_empty()
+    }
+
+    fn added(&self) -> Vec<AddedObject> {
+        let mut res = vec![];
+        for info in self.updated.values() {
+            if info.replaces().is_none() {
+                res.push(AddedObject::new(info.name(), info.object().clone()));
+            }
+        }
+        res
+    }
+
+    fn updated(&self) -> Vec<UpdatedObject> {
+        let mut res = vec![];
+        for info in self.updated.values() {
+            if let Some(replaced) = info.replaces() {
+                let object = info.object().clone();
+                let name = info.name();
+                res.push(UpdatedObject::new(name, object, replaced.hash().clone()));
+            }
+        }
+        res
+    }
+
+    fn withdrawn(&self) -> Vec<WithdrawnObject> {
+        let mut res = vec![];
+        for (customer, revoked) in self.removed.iter() {
+            let name = ObjectName::from(*customer);
+            res.push(WithdrawnObject::new(name, revoked.hash().clone()));
+        }
+        res
+    }
+
+    fn revocations(&self) -> Vec<Revocation> {
+        let mut res = vec![];
+        for info in self.updated.values() {
+            if let Some(old) = info.replaces() {
+                res.push(old.revocation())
+            }
+        }
+
+        for revoked in self.removed.values() {
+            res.push(revoked.revocation())
+        }
+
+        res
+    }
+}
+
+//------------ BgpSecCertificateUpdates ------------------------------------
+
+/// Describes an update to the set of BGPsec router certificates under a
+/// ResourceClass.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BgpSecCertificateUpdates {
+    updated: HashMap<BgpSecAsnKey, BgpSecCertInfo>,
+    removed: HashMap<BgpSecAsnKey, RevokedObject>,
+}
+
+impl BgpSecCertificateUpdates {
+    pub fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn update(&mut self, asn_key: BgpSecAsnKey, cert: BgpSecCertInfo) {
+        self.updated.insert(asn_key, cert);
+    }
+
+    pub fn remove(&mut self, asn_key: BgpSecAsnKey, revoke: RevokedObject) {
+        self.removed.insert(asn_key, revoke);
+    }
+
+    pub fn unpack(
+        self,
+    ) -> (
+        HashMap<BgpSecAsnKey, BgpSecCertInfo>,
+        HashMap<BgpSecAsnKey, RevokedObject>,
     ) {
         (self.updated, self.removed)
     }
 }
 
+impl ObjectUpdates for BgpSecCertificateUpdates {
+    fn contains_changes(&self) -> bool {
+        !self.is_empty()
+    }
+
+    fn added(&self) -> Vec<AddedObject> {
+        let mut res = vec![];
+        for info in self.updated.values() {
+            if info.replaces().is_none() {
+                res.push(AddedObject::new(info.name(), info.object().clone()));
+            }
+        }
+        res
+    }
+
+    fn updated(&self) -> Vec<UpdatedObject> {
+        let mut res = vec![];
+        for info in self.updated.values() {
+            if let Some(replaced) = info.replaces() {
+                let object = info.object().clone();
+                let name = info.name();
+                res.push(UpdatedObject::new(name, object, replaced.hash().clone()));
+            }
+        }
+        res
+    }
+
+    fn withdrawn(&self) -> Vec<WithdrawnObject> {
+        let mut res = vec![];
+        for (asn_key, revoked) in self.removed.iter() {
+            let name = ObjectName::from(asn_key);
+            res.push(WithdrawnObject::new(name, revoked.hash().clone()));
+        }
+        res
+    }
+
+    fn revocations(&self) -> Vec<Revocation> {
+        let mut res = vec![];
+        for info in self.updated.values() {
+            if let Some(old) = info.replaces() {
+                res.push(old.revocation())
+            }
+        }
+
+        for revoked in self.removed.values() {
+            res.push(revoked.revocation())
+        }
+
+        res
+    }
+}
+
 //------------ ChildCertificateUpdates -------------------------------------
 
 /// Describes an update to the set of ROAs under a ResourceClass.
@@ -249,11 +444,34 @@ pub enum EvtDet {
     KeyRollFinished(ResourceClassName, ObjectsDelta),
     UnexpectedKeyFound(ResourceClassName, RevocationRequest),
 
+    // Key roll policy, None means that keys are only rolled manually
+    KeyRollPolicyUpdated(Option<KeyRollPolicy>),
+
+    // Emergency key roll, with the reason given by the operator
+    KeyRollEmergencyStarted(String),
+
     // Route Authorizations
     RouteAuthorizationAdded(RouteAuthorization),
     RouteAuthorizationRemoved(RouteAuthorization),
     RoasUpdated(ResourceClassName, RoaUpdates),
 
+    // ROA grouping, None means that the server wide strategy is used
+    RoaGroupingUpdated(Option<RoaPrefixGroupingStrategy>),
+
+    // ASPAs
+    AspaConfigAdded(AspaDefinition),
+    AspaConfigUpdated(AsNumber, AspaProvidersUpdate),
+    AspaConfigRemoved(AsNumber),
+    AspaObjectsUpdated(ResourceClassName, AspaObjectsUpdates),
+
+    // BGPsec router certificates
+    BgpSecDefinitionAdded(BgpSecDefinition),
+    BgpSecDefinitionRemoved(BgpSecAsnKey),
+    BgpSecCertificatesUpdated(ResourceClassName, BgpSecCertificateUpdates),
+
+    // Timing, None means that the server wide timing is used
+    TimingUpdated(Option<TimingConfig>),
+
     // Publishing
     ObjectSetUpdated(
         ResourceClassName,
@@ -494,6 +712,7 @@ impl fmt::Display for EvtDet {
                     ParentCaContact::Embedded => "embedded",
                     ParentCaContact::Ta(_) => "TA proxy",
                     ParentCaContact::Rfc6492(_) => "RFC6492",
+                    ParentCaContact::Offline(_) => "offline TA",
                 };
                 write!(f, "added {} parent '{}' ", contact_str, parent)
             }
@@ -502,6 +721,7 @@ impl fmt::Display for EvtDet {
                     ParentCaContact::Embedded => "embedded",
                     ParentCaContact::Ta(_) => "TA proxy",
                     ParentCaContact::Rfc6492(_) => "RFC6492",
+                    ParentCaContact::Offline(_) => "offline TA",
                 };
                 write!(f, "updated parent '{}' contact to '{}' ", parent,  contact_str)
             }
@@ -561,6 +781,15 @@ impl fmt::Display for EvtDet {
                 "Found unexpected key in resource class '{}', will try to revoke key id: '{}'",
                 rcn, revoke.key()
             ),
+            EvtDet::KeyRollPolicyUpdated(policy) => match policy {
+                Some(policy) => write!(f, "updated key roll policy to: {}", policy),
+                None => write!(f, "removed key roll policy"),
+            },
+            EvtDet::KeyRollEmergencyStarted(reason) => write!(
+                f,
+                "key roll: started EMERGENCY key roll, new keys are activated without staging, reason: '{}'",
+                reason
+            ),
 
             // Route Authorizations
             EvtDet::RouteAuthorizationAdded(route) => write!(
@@ -589,6 +818,81 @@ impl fmt::Display for EvtDet {
                 }
                 Ok(())
             },
+            EvtDet::RoaGroupingUpdated(strategy) => match strategy {
+                Some(strategy) => write!(f, "updated ROA grouping to {}", strategy),
+                None => write!(f, "reverted to server ROA grouping"),
+            },
+
+            // ASPAs
+            EvtDet::AspaConfigAdded(definition) => write!(
+                f,
+                "added ASPA: '{}'",
+                definition
+            ),
+            EvtDet::AspaConfigUpdated(customer, update) => write!(
+                f,
+                "updated providers for ASPA for customer AS '{}': {}",
+                customer, update
+            ),
+            EvtDet::AspaConfigRemoved(customer) => write!(
+                f,
+                "removed ASPA for customer AS '{}'",
+                customer
+            ),
+            EvtDet::AspaObjectsUpdated(rcn, updates) => {
+                write!(f, "updated ASPA objects under resource class '{}'", rcn)?;
+                if !updates.updated.is_empty() {
+                    write!(f, " updated:")?;
+                    for customer in updates.updated.keys() {
+                        write!(f, " {}", customer)?;
+                    }
+                }
+                if !updates.removed.is_empty() {
+                    write!(f, " removed:")?;
+                    for customer in updates.removed.keys() {
+                        write!(f, " {}", customer)?;
+                    }
+                }
+                Ok(())
+            }
+
+            // BGPsec router certificates
+            EvtDet::BgpSecDefinitionAdded(definition) => write!(
+                f,
+                "added BGPsec router certificate definition: '{}'",
+                definition
+            ),
+            EvtDet::BgpSecDefinitionRemoved(asn_key) => write!(
+                f,
+                "removed BGPsec router certificate definition: '{}'",
+                asn_key
+            ),
+            EvtDet::BgpSecCertificatesUpdated(rcn, updates) => {
+                write!(
+                    f,
+                    "updated BGPsec router certificates under resource class '{}'",
+                    rcn
+                )?;
+                if !updates.updated.is_empty() {
+                    write!(f, " updated:")?;
+                    for asn_key in updates.updated.keys() {
+                        write!(f, " {}", asn_key)?;
+                    }
+                }
+                if !updates.removed.is_empty() {
+                    write!(f, " removed:")?;
+                    for asn_key in updates.removed.keys() {
+                        write!(f, " {}", asn_key)?;
+                    }
+                }
+                Ok(())
+            }
+
+            // Timing
+            EvtDet::TimingUpdated(timing) => match timing {
+                Some(timing) => write!(f, "updated timing to {}", timing),
+                None => write!(f, "reverted to server timing"),
+            },
 
             // Publishing
             EvtDet::ObjectSetUpdated(rcn, key_objects_map) => {
@@ -1,6 +1,5 @@
 use std::ops::{Deref, DerefMut};
 
-use chrono::Duration;
 use serde::{Deserialize, Serialize};
 
 use rpki::crypto::{KeyIdentifier, PublicKeyFormat};
@@ -10,11 +9,10 @@ use rpki::x509::Time;
 use crate::commons::api::{
     ActiveInfo, CertifiedKeyInfo, EntitlementClass, IssuanceRequest, PendingInfo, PendingKeyInfo,
     RcvdCert, RepoInfo, RequestResourceLimit, ResourceClassKeysInfo, ResourceClassName,
-    ResourceSet, RevocationRequest, RollNewInfo, RollOldInfo, RollPendingInfo,
+    ResourceSet, RevocationRequest, RollNewInfo, RollOldInfo, RollPendingInfo, TimingConfig,
 };
 use crate::commons::error::Error;
 use crate::commons::KrillResult;
-use crate::constants::PUBLISH_THRESHOLD_HOURS;
 use crate::daemon::ca::{CurrentObjectSet, CurrentObjectSetDelta, EvtDet, Signer};
 
 //------------ CertifiedKey --------------------------------------------------
@@ -27,6 +25,8 @@ pub struct CertifiedKey {
     incoming_cert: RcvdCert,
     current_set: CurrentObjectSet,
     request: Option<IssuanceRequest>,
+    #[serde(default)]
+    certified: Option<Time>, // None for keys certified before this was kept
 }
 
 impl CertifiedKey {
@@ -34,16 +34,19 @@ impl CertifiedKey {
         incoming_cert: RcvdCert,
         repo_info: &RepoInfo,
         name_space: &str,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<Self> {
         let key_id = incoming_cert.cert().subject_key_identifier();
-        let current_set = CurrentObjectSet::create(&incoming_cert, repo_info, name_space, signer)?;
+        let current_set =
+            CurrentObjectSet::create(&incoming_cert, repo_info, name_space, timing, signer)?;
 
         Ok(CertifiedKey {
             key_id,
             incoming_cert,
             current_set,
             request: None,
+            certified: Some(Time::now()),
         })
     }
 
@@ -62,6 +65,11 @@ impl CertifiedKey {
         self.incoming_cert = incoming_cert;
     }
 
+    /// Returns the time when this key was first certified, if known.
+    pub fn certified(&self) -> Option<Time> {
+        self.certified
+    }
+
     pub fn current_set(&self) -> &CurrentObjectSet {
         &self.current_set
     }
@@ -123,8 +131,8 @@ impl CertifiedKey {
         }
     }
 
-    pub fn close_to_next_update(&self) -> bool {
-        self.current_set.next_update() < Time::now() + Duration::hours(PUBLISH_THRESHOLD_HOURS)
+    pub fn close_to_next_update(&self, timing: &TimingConfig) -> bool {
+        self.current_set.next_update() < Time::now() + timing.publish_threshold()
     }
 
     pub fn with_new_cert(mut self, cert: RcvdCert) -> Self {
@@ -264,6 +272,17 @@ impl KeyState {
         }
     }
 
+    /// Returns the identifiers of all keys in this state.
+    pub fn key_ids(&self) -> Vec<&KeyIdentifier> {
+        match self {
+            KeyState::Pending(pending) => vec![pending.key_id()],
+            KeyState::Active(current) => vec![current.key_id()],
+            KeyState::RollPending(pending, current) => vec![pending.key_id(), current.key_id()],
+            KeyState::RollNew(new, current) => vec![new.key_id(), current.key_id()],
+            KeyState::RollOld(current, old) => vec![current.key_id(), old.key_id()],
+        }
+    }
+
     /// Revoke all current keys
     pub fn revoke<S: Signer>(
         &self,
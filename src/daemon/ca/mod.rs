@@ -7,6 +7,7 @@ mod certauth;
 pub use self::certauth::CertAuth;
 pub use self::certauth::Rfc8183Id;
 pub use self::certauth::set_roa_prefix_grouping_strategy;
+pub use self::certauth::set_timing_config;
 
 mod child;
 pub use self::child::*;
@@ -23,6 +24,18 @@ pub use self::publishing::*;
 mod routes;
 pub use self::routes::*;
 
+mod aspa;
+pub use self::aspa::*;
+
+mod bgpsec;
+pub use self::bgpsec::*;
+
+mod rsc;
+pub use self::rsc::*;
+
+mod validation;
+pub use self::validation::*;
+
 mod commands;
 pub use self::commands::Cmd;
 pub use self::commands::CmdDet;
@@ -35,7 +48,7 @@ pub use self::events::IniDet;
 
 mod server;
 pub use self::server::CaServer;
-pub use self::server::RoaPrefixGroupingStrategy;
+pub use crate::commons::api::RoaPrefixGroupingStrategy;
 
 mod signing;
 pub use self::signing::SignSupport;
@@ -3,8 +3,6 @@
 use std::collections::HashMap;
 
 use bytes::Bytes;
-use chrono::Duration;
-
 use rpki::crl::{Crl, TbsCertList};
 use rpki::crypto::{DigestAlgorithm, KeyIdentifier};
 use rpki::manifest::{FileAndHash, Manifest, ManifestContent};
@@ -13,11 +11,11 @@ use rpki::x509::{Serial, Time, Validity};
 
 use crate::commons::api::{
     AddedObject, CurrentObject, HexEncodedHash, IssuedCert, ObjectName, ObjectsDelta, RcvdCert,
-    RepoInfo, Revocation, Revocations, RevocationsDelta, UpdatedObject, WithdrawnObject,
+    RepoInfo, Revocation, Revocations, RevocationsDelta, TimingConfig, UpdatedObject,
+    WithdrawnObject,
 };
 use crate::commons::KrillResult;
-use crate::constants::{PUBLISH_NEXT_HOURS, PUBLISH_VALID_DAYS};
-use crate::daemon::ca::{self, RoaInfo, RouteAuthorization, Signer};
+use crate::daemon::ca::{self, AspaInfo, BgpSecCertInfo, RoaInfo, RouteAuthorization, Signer};
 
 //------------ AddedOrUpdated ----------------------------------------------
 
@@ -167,6 +165,7 @@ impl CurrentObjectSet {
         signing_cert: &RcvdCert,
         repo_info: &RepoInfo,
         name_space: &str,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<Self> {
         let number = 1;
@@ -177,6 +176,7 @@ impl CurrentObjectSet {
             number,
             None,
             signing_cert,
+            timing,
             signer,
         )?;
 
@@ -186,6 +186,7 @@ impl CurrentObjectSet {
             name_space,
             number,
             None,
+            timing,
             signer,
         )?;
 
@@ -237,6 +238,7 @@ impl CrlBuilder {
         number: u64,
         old: Option<HexEncodedHash>,
         signing_cert: &RcvdCert,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<(CrlInfo, RevocationsDelta)> {
         let signing_key = signing_cert.cert().subject_public_key_info();
@@ -254,7 +256,7 @@ impl CrlBuilder {
         }
 
         let this_update = Time::five_minutes_ago();
-        let next_update = Time::now() + Duration::hours(PUBLISH_NEXT_HOURS);
+        let next_update = Time::now() + timing.publish_next();
         let serial_number = Serial::from(number);
 
         let mut crl = TbsCertList::new(
@@ -296,6 +298,8 @@ impl ManifestBuilder {
         crl_info: &CrlInfo,
         issued: impl Iterator<Item = &'a IssuedCert>,
         roas: impl Iterator<Item = (&'a RouteAuthorization, &'a RoaInfo)>,
+        aspas: impl Iterator<Item = &'a AspaInfo>,
+        bgpsec_certs: impl Iterator<Item = &'a BgpSecCertInfo>,
         delta: &ObjectsDelta,
     ) -> Self {
         let mut entries: HashMap<Bytes, Bytes> = HashMap::new();
@@ -323,6 +327,22 @@ impl ManifestBuilder {
             entries.insert(name.into(), hash);
         }
 
+        // Add all *current* ASPAs
+        for aspa_info in aspas {
+            let name = aspa_info.name();
+            let hash = Self::mft_hash(&aspa_info.object().content().to_bytes());
+
+            entries.insert(name.into(), hash);
+        }
+
+        // Add all *current* BGPsec router certificates
+        for bgpsec_info in bgpsec_certs {
+            let name = bgpsec_info.name();
+            let hash = Self::mft_hash(&bgpsec_info.object().content().to_bytes());
+
+            entries.insert(name.into(), hash);
+        }
+
         // Add all *new* objects
         for added in delta.added() {
             let name = added.name().clone();
@@ -357,6 +377,7 @@ impl ManifestBuilder {
         name_space: &str,
         number: u64,
         old: Option<HexEncodedHash>,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<ManifestInfo> {
         let signing_key = signing_cert.cert().subject_public_key_info();
@@ -372,8 +393,8 @@ impl ManifestBuilder {
 
         let this_update = Time::five_minutes_ago();
         let now = Time::now();
-        let next_update = Time::now() + Duration::hours(PUBLISH_NEXT_HOURS);
-        let valid_until = Time::now() + Duration::days(PUBLISH_VALID_DAYS);
+        let next_update = Time::now() + timing.publish_next();
+        let valid_until = Time::now() + timing.publish_valid();
 
         let entries = self.entries.iter().map(|(k, v)| FileAndHash::new(k, v));
 
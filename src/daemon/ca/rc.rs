@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::net::Ipv4Addr;
 use std::str::FromStr;
 
 use chrono::Duration;
@@ -13,17 +14,22 @@ use uuid::Uuid;
 use crate::commons::api::rrdp::PublishElement;
 use crate::commons::api::Base64;
 use crate::commons::api::{
-    AddedObject, AsNumber, CurrentObject, CurrentObjects, EntitlementClass, HexEncodedHash, IssuanceRequest,
-    IssuedCert, ObjectName, ObjectsDelta, ParentHandle, RcvdCert, ReplacedObject, RepoInfo,
-    RequestResourceLimit, ResourceClassInfo, ResourceClassName, ResourceSet, Revocation,
-    RevocationRequest, RevokedObject, RoaDefinition, TypedPrefix, UpdatedObject, WithdrawnObject,
+    AddedObject, AsNumber, CurrentObject, CurrentObjects, EntitlementClass, HexEncodedHash,
+    IssuanceRequest, IssuedCert, KeyRollProgress, KeyRollStage, ObjectName, ObjectsDelta,
+    ParentHandle, RcvdCert, ReplacedObject, RepoInfo, RequestResourceLimit, ResourceClassInfo,
+    ResourceClassName, ResourceSet, Revocation, RevocationRequest, RevokedObject, RoaDefinition,
+    TimingConfig, TypedPrefix, UpdatedObject, WithdrawnObject,
 };
 use crate::commons::error::Error;
 use crate::commons::KrillResult;
-use crate::daemon::ca::events::{ChildCertificateUpdates, RoaUpdates};
+use crate::daemon::ca::events::{
+    AspaObjectsUpdates, BgpSecCertificateUpdates, ChildCertificateUpdates, ObjectUpdates,
+    RoaUpdates,
+};
 use crate::daemon::ca::signing::CsrInfo;
 use crate::daemon::ca::{
-    self, ta_handle, AddedOrUpdated, CertifiedKey, ChildCertificates, CrlBuilder, CurrentKey,
+    self, ta_handle, AddedOrUpdated, AspaDefinitions, AspaInfo, AspaObjects, BgpSecCertInfo,
+    BgpSecCertificates, BgpSecDefinitions, CertifiedKey, ChildCertificates, CrlBuilder, CurrentKey,
     CurrentObjectSetDelta, EvtDet, KeyState, ManifestBuilder, NewKey, OldKey, PendingKey, RoaInfo,
     Roas, RouteAuthorization, SignSupport, Signer, RoaPrefixGroupingStrategy,
 };
@@ -50,6 +56,10 @@ pub struct ResourceClass {
     parent_rc_name: ResourceClassName,
 
     roas: Roas,
+    #[serde(default)]
+    aspas: AspaObjects,
+    #[serde(default)]
+    bgpsec_certs: BgpSecCertificates,
     certificates: ChildCertificates,
 
     last_key_change: Time,
@@ -73,6 +83,8 @@ impl ResourceClass {
             parent_handle,
             parent_rc_name,
             roas: Roas::default(),
+            aspas: AspaObjects::default(),
+            bgpsec_certs: BgpSecCertificates::default(),
             certificates: ChildCertificates::default(),
             last_key_change: Time::now(),
             key_state: KeyState::create(pending_key),
@@ -86,6 +98,8 @@ impl ResourceClass {
             parent_handle: ta_handle(),
             parent_rc_name,
             roas: Roas::default(),
+            aspas: AspaObjects::default(),
+            bgpsec_certs: BgpSecCertificates::default(),
             certificates: ChildCertificates::default(),
             last_key_change: Time::now(),
             key_state: KeyState::create(pending_key),
@@ -140,6 +154,23 @@ impl ResourceClass {
         self.current_key().ok_or_else(|| Error::KeyUseNoCurrentKey)
     }
 
+    /// Returns all keys for this RC that have a certificate, i.e. the keys
+    /// which publish a manifest and CRL.
+    pub fn certified_keys(&self) -> Vec<&CertifiedKey> {
+        match &self.key_state {
+            KeyState::Pending(_) => vec![],
+            KeyState::Active(current) | KeyState::RollPending(_, current) => vec![current],
+            KeyState::RollNew(new, current) => vec![new, current],
+            KeyState::RollOld(current, old) => vec![current, old.key()],
+        }
+    }
+
+    /// Returns the identifiers of all keys for this RC, including pending
+    /// keys.
+    pub fn key_ids(&self) -> Vec<&KeyIdentifier> {
+        self.key_state.key_ids()
+    }
+
     /// Gets the new key for a key roll, or returns an error if there is none.
     pub fn get_new_key(&self) -> KrillResult<&NewKey> {
         if let KeyState::RollNew(new_key, _) = &self.key_state {
@@ -156,6 +187,14 @@ impl ResourceClass {
             current_objects.insert(roa_info.name().clone(), roa_info.object().clone());
         }
 
+        for aspa_info in self.aspas.current() {
+            current_objects.insert(aspa_info.name(), aspa_info.object().clone());
+        }
+
+        for bgpsec_info in self.bgpsec_certs.current() {
+            current_objects.insert(bgpsec_info.name(), bgpsec_info.object().clone());
+        }
+
         for issued in self.certificates.current() {
             let cert = issued.cert();
             current_objects.insert(ObjectName::from(cert), CurrentObject::from(cert));
@@ -210,6 +249,7 @@ impl ResourceClass {
         &self,
         rcvd_cert: RcvdCert,
         repo_info: &RepoInfo,
+        timing: &TimingConfig,
         signer: &S,
         roa_prefix_grouping_strategy: &RoaPrefixGroupingStrategy, 
     ) -> KrillResult<Vec<EvtDet>> {
@@ -221,10 +261,12 @@ impl ResourceClass {
             rcvd_cert: RcvdCert,
             repo_info: &RepoInfo,
             name_space: &str,
+            timing: &TimingConfig,
             signer: &S,
         ) -> KrillResult<(CertifiedKey, ObjectsDelta)> {
             let mut delta = ObjectsDelta::new(rcvd_cert.ca_repository().clone());
-            let active_key = CertifiedKey::create(rcvd_cert, repo_info, name_space, signer)?;
+            let active_key =
+                CertifiedKey::create(rcvd_cert, repo_info, name_space, timing, signer)?;
 
             match active_key.current_set().manifest_info().added_or_updated() {
                 AddedOrUpdated::Added(added) => delta.add(added),
@@ -247,6 +289,7 @@ impl ResourceClass {
                         rcvd_cert,
                         repo_info,
                         self.name_space(),
+                        timing,
                         signer,
                     )?;
                     Ok(vec![EvtDet::KeyPendingToActive(
@@ -257,7 +300,7 @@ impl ResourceClass {
                 }
             }
             KeyState::Active(current) => {
-                self.update_rcvd_cert_current(current, rcvd_cert, repo_info, signer, roa_prefix_grouping_strategy)
+                self.update_rcvd_cert_current(current, rcvd_cert, repo_info, timing, signer, roa_prefix_grouping_strategy)
             }
             KeyState::RollPending(pending, current) => {
                 if rcvd_cert_ki == pending.key_id() {
@@ -265,6 +308,7 @@ impl ResourceClass {
                         rcvd_cert,
                         repo_info,
                         self.name_space(),
+                        timing,
                         signer,
                     )?;
                     Ok(vec![EvtDet::KeyPendingToNew(
@@ -273,7 +317,7 @@ impl ResourceClass {
                         delta,
                     )])
                 } else {
-                    self.update_rcvd_cert_current(current, rcvd_cert, repo_info, signer, roa_prefix_grouping_strategy)
+                    self.update_rcvd_cert_current(current, rcvd_cert, repo_info, timing, signer, roa_prefix_grouping_strategy)
                 }
             }
             KeyState::RollNew(new, current) => {
@@ -284,12 +328,12 @@ impl ResourceClass {
                         rcvd_cert,
                     )])
                 } else {
-                    self.update_rcvd_cert_current(current, rcvd_cert, repo_info, signer, roa_prefix_grouping_strategy)
+                    self.update_rcvd_cert_current(current, rcvd_cert, repo_info, timing, signer, roa_prefix_grouping_strategy)
                 }
             }
             KeyState::RollOld(current, _old) => {
                 // We will never request a new certificate for an old key
-                self.update_rcvd_cert_current(current, rcvd_cert, repo_info, signer, roa_prefix_grouping_strategy)
+                self.update_rcvd_cert_current(current, rcvd_cert, repo_info, timing, signer, roa_prefix_grouping_strategy)
             }
         }
     }
@@ -299,6 +343,7 @@ impl ResourceClass {
         current: &CurrentKey,
         rcvd_cert: RcvdCert,
         repo_info: &RepoInfo,
+        timing: &TimingConfig,
         signer: &S,
         roa_prefix_grouping_strategy: &RoaPrefixGroupingStrategy,
     ) -> KrillResult<Vec<EvtDet>> {
@@ -321,8 +366,11 @@ impl ResourceClass {
             let authorizations: Vec<RouteAuthorization> = self.get_authorizations(roa_prefix_grouping_strategy)?;
             res.append(&mut self.republish(
                 authorizations.as_slice(),
+                &self.aspas.definitions(),
+                &self.bgpsec_certs.definitions(),
                 repo_info,
                 &publish_mode,
+                timing,
                 signer,
                 roa_prefix_grouping_strategy,
             )?)
@@ -396,6 +444,7 @@ impl ResourceClass {
         objects_delta: ObjectsDelta,
         new_revocations: Vec<Revocation>,
         mode: &PublishMode,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<EvtDet> {
         let mut key_pub_map = HashMap::new();
@@ -423,6 +472,7 @@ impl ResourceClass {
                 repo_info,
                 objects_delta,
                 publish_key_revocations,
+                timing,
                 signer,
             )
             .map_err(Error::signer)?;
@@ -434,7 +484,14 @@ impl ResourceClass {
             let delta = ObjectsDelta::new(repo_info.ca_repository(ns));
 
             let other_delta = self
-                .make_current_set_delta(other_key, repo_info, delta, other_key_revocations, signer)
+                .make_current_set_delta(
+                    other_key,
+                    repo_info,
+                    delta,
+                    other_key_revocations,
+                    timing,
+                    signer,
+                )
                 .map_err(ca::Error::signer)?;
 
             key_pub_map.insert(other_key.key_id().clone(), other_delta);
@@ -443,9 +500,9 @@ impl ResourceClass {
         Ok(EvtDet::ObjectSetUpdated(self.name.clone(), key_pub_map))
     }
 
-    fn needs_publication(&self, mode: &PublishMode) -> bool {
+    fn needs_publication(&self, mode: &PublishMode, timing: &TimingConfig) -> bool {
         match mode {
-            PublishMode::Normal => self.get_current_key().unwrap().close_to_next_update(),
+            PublishMode::Normal => self.get_current_key().unwrap().close_to_next_update(timing),
             _ => true,
         }
     }
@@ -455,8 +512,11 @@ impl ResourceClass {
     pub fn republish<S: Signer>(
         &self,
         authorizations: &[RouteAuthorization],
+        aspas: &AspaDefinitions,
+        bgpsec_defs: &BgpSecDefinitions,
         repo_info: &RepoInfo,
         mode: &PublishMode,
+        timing: &TimingConfig,
         signer: &S,
         roa_prefix_grouping_strategy: &RoaPrefixGroupingStrategy,
     ) -> KrillResult<Vec<EvtDet>> {
@@ -484,7 +544,42 @@ impl ResourceClass {
             res.push(EvtDet::RoasUpdated(self.name.clone(), roa_updates));
         }
 
-        let child_cert_updates = self.update_child_certificates(mode, signer)?;
+        let aspa_updates = self.update_aspas(aspas, mode, signer)?;
+        if aspa_updates.contains_changes() {
+            for added in aspa_updates.added().into_iter() {
+                delta.add(added);
+            }
+            for update in aspa_updates.updated().into_iter() {
+                delta.update(update);
+            }
+            for withdraw in aspa_updates.withdrawn().into_iter() {
+                delta.withdraw(withdraw);
+            }
+            revocations.append(&mut aspa_updates.revocations());
+
+            res.push(EvtDet::AspaObjectsUpdated(self.name.clone(), aspa_updates));
+        }
+
+        let bgpsec_updates = self.update_bgpsec_certificates(bgpsec_defs, mode, signer)?;
+        if bgpsec_updates.contains_changes() {
+            for added in bgpsec_updates.added().into_iter() {
+                delta.add(added);
+            }
+            for update in bgpsec_updates.updated().into_iter() {
+                delta.update(update);
+            }
+            for withdraw in bgpsec_updates.withdrawn().into_iter() {
+                delta.withdraw(withdraw);
+            }
+            revocations.append(&mut bgpsec_updates.revocations());
+
+            res.push(EvtDet::BgpSecCertificatesUpdated(
+                self.name.clone(),
+                bgpsec_updates,
+            ));
+        }
+
+        let child_cert_updates = self.update_child_certificates(mode, timing, signer)?;
         if !child_cert_updates.is_empty() {
             for issued in child_cert_updates.issued() {
                 match issued.replaces() {
@@ -505,10 +600,10 @@ impl ResourceClass {
             ));
         }
 
-        if !delta.is_empty() || !revocations.is_empty() || self.needs_publication(mode) {
+        if !delta.is_empty() || !revocations.is_empty() || self.needs_publication(mode, timing) {
             debug!("Publishing delta: {:?}", delta);
             debug!("Publishing revocations: {:?}", revocations);
-            res.push(self.publish_objects(&repo_info, delta, revocations, mode, signer)?);
+            res.push(self.publish_objects(&repo_info, delta, revocations, mode, timing, signer)?);
         }
 
         Ok(res)
@@ -521,6 +616,7 @@ impl ResourceClass {
         issued_certs: &[&IssuedCert],
         removed_certs: &[&Cert],
         repo_info: &RepoInfo,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<HashMap<KeyIdentifier, CurrentObjectSetDelta>> {
         let issuing_key = self.get_current_key()?;
@@ -553,7 +649,14 @@ impl ResourceClass {
         }
 
         let set_delta = self
-            .make_current_set_delta(issuing_key, repo_info, objects_delta, revocations, signer)
+            .make_current_set_delta(
+                issuing_key,
+                repo_info,
+                objects_delta,
+                revocations,
+                timing,
+                signer,
+            )
             .map_err(Error::signer)?;
 
         let mut res = HashMap::new();
@@ -567,6 +670,7 @@ impl ResourceClass {
         repo_info: &RepoInfo,
         mut objects_delta: ObjectsDelta,
         mut new_revocations: Vec<Revocation>,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<CurrentObjectSetDelta> {
         let signing_cert = signing_key.incoming_cert();
@@ -588,6 +692,7 @@ impl ResourceClass {
             number,
             Some(current_crl_hash),
             signing_cert,
+            timing,
             signer,
         )?;
 
@@ -601,19 +706,26 @@ impl ResourceClass {
         // List all current files, i.e.
         //  - the new CRL
         //  - current ROAs
+        //  - current ASPAs
         //  - current Certs
+        //  - current BGPsec router Certs
         //  - applying the delta - which may update the current ROAs and Certs on the MFT
         let issued = self.certificates.current();
         let roas = self.roas.iter();
-
-        let manifest_info = ManifestBuilder::new(&crl_info, issued, roas, &objects_delta).build(
-            signing_cert,
-            repo_info,
-            self.name_space(),
-            number,
-            Some(current_mft_hash),
-            signer,
-        )?;
+        let aspas = self.aspas.current();
+        let bgpsec_certs = self.bgpsec_certs.current();
+
+        let manifest_info =
+            ManifestBuilder::new(&crl_info, issued, roas, aspas, bgpsec_certs, &objects_delta)
+                .build(
+                    signing_cert,
+                    repo_info,
+                    self.name_space(),
+                    number,
+                    Some(current_mft_hash),
+                    timing,
+                    signer,
+                )?;
 
         match manifest_info.added_or_updated() {
             AddedOrUpdated::Added(added) => objects_delta.add(added),
@@ -640,6 +752,18 @@ impl ResourceClass {
             let uri = base_repo.resolve(ns, object_name.as_str());
             res.push(PublishElement::new(base64, uri));
         }
+        // ASPAs
+        for info in self.aspas.current() {
+            let base64 = info.object().content().clone();
+            let uri = base_repo.resolve(ns, info.name().as_str());
+            res.push(PublishElement::new(base64, uri));
+        }
+        // BGPsec router Certs
+        for info in self.bgpsec_certs.current() {
+            let base64 = info.object().content().clone();
+            let uri = base_repo.resolve(ns, info.name().as_str());
+            res.push(PublishElement::new(base64, uri));
+        }
         // Certs
         for cert in self.certificates.current() {
             let base64 = Base64::from_content(cert.to_captured().as_slice());
@@ -783,7 +907,16 @@ impl ResourceClass {
         duration: Duration,
         signer: &mut S,
     ) -> KrillResult<Vec<EvtDet>> {
-        if self.last_key_change + duration > Time::now() {
+        let current_since = match self.current_key() {
+            Some(current) => current.certified().unwrap_or(self.last_key_change),
+            None => return Ok(vec![]),
+        };
+        // Keys can not be older than the earliest time that can be expressed
+        let old_since = match Time::now().checked_sub_signed(duration) {
+            Some(time) => Time::from(time),
+            None => return Ok(vec![]),
+        };
+        if current_since > old_since {
             return Ok(vec![]);
         }
 
@@ -801,11 +934,13 @@ impl ResourceClass {
         &self,
         repo_info: &RepoInfo,
         staging: Duration,
+        timing: &TimingConfig,
         signer: &S,
         roa_prefix_grouping_strategy: &RoaPrefixGroupingStrategy,
     ) -> KrillResult<Vec<EvtDet>> {
-        if !self.key_state.has_new_key() || self.last_key_change + staging > Time::now() {
-            return Ok(vec![]);
+        match self.new_key_activate_after(staging) {
+            Some(time) if time <= Time::now() => {}
+            _ => return Ok(vec![]),
         }
 
         let mut res = vec![];
@@ -820,8 +955,11 @@ impl ResourceClass {
 
         res.append(&mut self.republish(
             authorizations.as_slice(),
+            &self.aspas.definitions(),
+            &self.bgpsec_certs.definitions(),
             repo_info,
             &PublishMode::KeyRollActivation,
+            timing,
             signer,
             roa_prefix_grouping_strategy,
         )?);
@@ -829,6 +967,38 @@ impl ResourceClass {
         Ok(res)
     }
 
+    /// Returns the time after which the new key may be activated, given the
+    /// staging period, or None if there is no new key, or if the staging
+    /// period ends beyond the latest time that can be expressed.
+    fn new_key_activate_after(&self, staging: Duration) -> Option<Time> {
+        match &self.key_state {
+            KeyState::RollNew(new, _) => {
+                let certified = new.certified().unwrap_or(self.last_key_change);
+                certified.checked_add_signed(staging).map(Time::from)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the progress of a key roll in this resource class, if there
+    /// is one. The activation time of a new key is only known if a staging
+    /// period is given.
+    pub fn key_roll_progress(&self, staging: Option<Duration>) -> Option<KeyRollProgress> {
+        let stage = match &self.key_state {
+            KeyState::Pending(_) | KeyState::Active(_) => return None,
+            KeyState::RollPending(_, _) => KeyRollStage::Pending,
+            KeyState::RollNew(_, _) => KeyRollStage::New,
+            KeyState::RollOld(_, _) => KeyRollStage::Old,
+        };
+        let activate_after = staging.and_then(|staging| self.new_key_activate_after(staging));
+
+        Some(KeyRollProgress::new(
+            self.name.clone(),
+            stage,
+            activate_after,
+        ))
+    }
+
     /// Finish a key roll, withdraw the old key
     pub fn keyroll_finish(&self, base_repo: &RepoInfo) -> KrillResult<EvtDet> {
         match &self.key_state {
@@ -866,6 +1036,7 @@ impl ResourceClass {
         csr: CsrInfo,
         child_resources: &ResourceSet,
         limit: RequestResourceLimit,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<IssuedCert> {
         let signing_key = self.get_current_key()?;
@@ -876,8 +1047,15 @@ impl ResourceClass {
             .get(&csr.key_id())
             .map(ReplacedObject::from);
 
-        let issued =
-            SignSupport::make_issued_cert(csr, &resources, limit, replaces, signing_key, signer)?;
+        let issued = SignSupport::make_issued_cert(
+            csr,
+            &resources,
+            limit,
+            replaces,
+            signing_key,
+            timing,
+            signer,
+        )?;
 
         Ok(issued)
     }
@@ -888,6 +1066,7 @@ impl ResourceClass {
         updated_resources: Option<ResourceSet>,
         signing_key: &CertifiedKey,
         csr_info_opt: Option<CsrInfo>,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<IssuedCert> {
         let (_uri, limit, resource_set, cert) = previous.clone().unpack();
@@ -901,6 +1080,7 @@ impl ResourceClass {
             limit,
             Some(replaced),
             signing_key,
+            timing,
             signer,
         )?;
 
@@ -910,6 +1090,7 @@ impl ResourceClass {
     fn update_child_certificates<S: Signer>(
         &self,
         mode: &PublishMode,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<ChildCertificateUpdates> {
         let mut updates = ChildCertificateUpdates::default();
@@ -923,8 +1104,9 @@ impl ResourceClass {
             PublishMode::Normal => {
                 // re-issue: things about to expire
                 // revoke: nothing
-                for issued in self.certificates.expiring() {
-                    let re_issued = self.re_issue(issued, None, signing_key, None, signer)?;
+                for issued in self.certificates.expiring(timing) {
+                    let re_issued =
+                        self.re_issue(issued, None, signing_key, None, timing, signer)?;
                     updates.issue(re_issued);
                 }
             }
@@ -943,6 +1125,7 @@ impl ResourceClass {
                             Some(remaining_resources),
                             signing_key,
                             None,
+                            timing,
                             signer,
                         )?;
                         updates.issue(re_issued);
@@ -951,7 +1134,8 @@ impl ResourceClass {
             }
             PublishMode::KeyRollActivation => {
                 for issued in self.certificates.iter() {
-                    let re_issued = self.re_issue(issued, None, signing_key, None, signer)?;
+                    let re_issued =
+                        self.re_issue(issued, None, signing_key, None, timing, signer)?;
                     updates.issue(re_issued);
                 }
             }
@@ -964,8 +1148,14 @@ impl ResourceClass {
                         issued.subject_public_key_info().clone(),
                     );
 
-                    let re_issued =
-                        self.re_issue(issued, None, signing_key, Some(csr_info_update), signer)?;
+                    let re_issued = self.re_issue(
+                        issued,
+                        None,
+                        signing_key,
+                        Some(csr_info_update),
+                        timing,
+                        signer,
+                    )?;
                     updates.issue(re_issued);
                 }
             }
@@ -1027,7 +1217,8 @@ impl ResourceClass {
                 // Remove any ROAs no longer in auths, or no longer in resources.
                 for (current_auth, roa_info) in self.roas.iter() {
                     if !auths.contains(current_auth) || !resources.contains(&current_auth.prefix().into()) {
-                        updates.remove(*current_auth, RevokedObject::from(roa_info.object()));
+                        let name = roa_info.name().clone();
+                        updates.remove(*current_auth, name, RevokedObject::from(roa_info.object()));
                     }
                 }
 
@@ -1082,7 +1273,8 @@ impl ResourceClass {
 
                     if new_auths_for_asn.is_none() {
                         // remove roa
-                        updates.remove(*roa_auth, RevokedObject::from(roa_info.object()));
+                        let name = roa_info.name().clone();
+                        updates.remove(*roa_auth, name, RevokedObject::from(roa_info.object()));
                         continue;
                     }
 
@@ -1105,6 +1297,118 @@ impl ResourceClass {
                     updates.update(auth, RoaInfo::new_roa(&roa, (&roa_name).into()));
                 }
             }
+
+            RoaPrefixGroupingStrategy::RoaPerAsnBounded(max) => {
+                let max = *max;
+
+                let mut wanted_per_asn: HashMap<AsNumber, HashSet<RouteAuthorization>> =
+                    HashMap::new();
+                for auth in auths {
+                    // if the auth is not in this resource class, just skip it.
+                    if !resources.contains(&auth.prefix().into()) {
+                        continue;
+                    }
+                    wanted_per_asn
+                        .entry(auth.asn())
+                        .or_insert_with(HashSet::new)
+                        .insert(*auth);
+                }
+
+                // Prefixes stay in the ROA they are in, so that adding or removing
+                // a prefix only affects one ROA. Go over the ROAs in order of their
+                // name, so that duplicates are resolved in the same way every time.
+                let mut current = vec![];
+                for (roa_key, roa_info) in self.roas.iter() {
+                    let contained: HashSet<RouteAuthorization> = roa_info
+                        .retrieve_route_authorizations()?
+                        .into_iter()
+                        .collect();
+                    current.push((roa_key, roa_info, contained));
+                }
+                current.sort_by_key(|(_, roa_info, _)| roa_info.name().to_string());
+
+                let mut new_contents = Vec::with_capacity(current.len());
+                for (roa_key, _, contained) in current.iter() {
+                    let mut kept = HashSet::new();
+                    if let Some(wanted) = wanted_per_asn.get_mut(&roa_key.asn()) {
+                        for auth in contained {
+                            if kept.len() < max && wanted.remove(auth) {
+                                kept.insert(*auth);
+                            }
+                        }
+                    }
+                    new_contents.push(kept);
+                }
+
+                // Add new prefixes to existing ROAs for the ASN which have room,
+                // and put the remaining prefixes in new ROAs.
+                let mut unplaced: HashMap<AsNumber, Vec<RouteAuthorization>> = HashMap::new();
+                for (asn, wanted) in wanted_per_asn {
+                    let mut wanted: Vec<RouteAuthorization> = wanted.into_iter().collect();
+                    wanted.sort_by_key(|auth| auth.to_string());
+                    wanted.reverse();
+                    unplaced.insert(asn, wanted);
+                }
+
+                for ((roa_key, roa_info, contained), mut content) in
+                    current.into_iter().zip(new_contents.into_iter())
+                {
+                    if let Some(wanted) = unplaced.get_mut(&roa_key.asn()) {
+                        while content.len() < max {
+                            match wanted.pop() {
+                                Some(auth) => {
+                                    content.insert(auth);
+                                }
+                                None => break,
+                            }
+                        }
+                    }
+
+                    let name = roa_info.name().clone();
+                    if content.is_empty() {
+                        updates.remove(*roa_key, name, RevokedObject::from(roa_info.object()));
+                        continue;
+                    }
+
+                    let expiring = roa_info.object().expires() < Time::now() + Duration::weeks(4);
+                    let activating = mode == &PublishMode::KeyRollActivation;
+
+                    if content != contained || expiring || activating || new_repo.is_some() {
+                        let new_roa = Roas::make_roa_multi(
+                            &content,
+                            key,
+                            new_repo.as_ref(),
+                            signer,
+                            name.clone(),
+                        )?;
+                        updates.update(*roa_key, RoaInfo::updated_roa(roa_info, &new_roa, name));
+                    }
+                }
+
+                for (asn, mut wanted) in unplaced {
+                    wanted.reverse();
+                    let mut index = 0;
+                    for chunk in wanted.chunks(max) {
+                        let mut roa_key = bounded_roa_key(asn, index);
+                        while self.roas.get(&roa_key).is_some() {
+                            index += 1;
+                            roa_key = bounded_roa_key(asn, index);
+                        }
+                        index += 1;
+
+                        let group: HashSet<RouteAuthorization> = chunk.iter().cloned().collect();
+                        let roa_name = generate_uuid_roa_name();
+                        let roa = Roas::make_roa_multi(
+                            &group,
+                            key,
+                            new_repo.as_ref(),
+                            signer,
+                            &roa_name,
+                        )?;
+                        updates.update(roa_key, RoaInfo::new_roa(&roa, (&roa_name).into()));
+                    }
+                }
+            }
         }
 
         Ok(updates)
@@ -1115,13 +1419,170 @@ impl ResourceClass {
         self.roas.updated(updates);
     }
 
+    /// Re-issues all ROAs in case they are not grouped according to the
+    /// given strategy, e.g. because the strategy used by the CA changed. The
+    /// old ROAs are withdrawn, unless they are replaced by a ROA under the
+    /// same name, so that all updates can be published in a single delta.
+    /// Returns empty updates if the ROAs are grouped as expected already.
+    pub fn migrate_roas<S: Signer>(
+        &self,
+        auths: &[RouteAuthorization],
+        signer: &S,
+        roa_prefix_grouping_strategy: &RoaPrefixGroupingStrategy,
+    ) -> KrillResult<RoaUpdates> {
+        let mut updates = RoaUpdates::default();
+
+        let key = match self.current_key() {
+            Some(key) => key,
+            None => return Ok(updates),
+        };
+
+        if self.roas_grouped_by(roa_prefix_grouping_strategy)? {
+            return Ok(updates);
+        }
+
+        debug!(
+            "Re-issuing ROAs under resource class '{}' using {}",
+            self.name, roa_prefix_grouping_strategy
+        );
+
+        let resources = key.incoming_cert().resources();
+        let auths = auths
+            .iter()
+            .filter(|auth| resources.contains(&auth.prefix().into()));
+
+        // The key, the authorizations and the name for each new ROA
+        let mut groups: Vec<(RouteAuthorization, HashSet<RouteAuthorization>, ObjectName)> = vec![];
+
+        match roa_prefix_grouping_strategy {
+            RoaPrefixGroupingStrategy::RoaPerPrefix => {
+                for auth in auths {
+                    let mut group = HashSet::with_capacity(1);
+                    group.insert(*auth);
+                    groups.push((*auth, group, ObjectName::from(auth)));
+                }
+            }
+            RoaPrefixGroupingStrategy::RoaPerAsn => {
+                let mut per_asn: HashMap<AsNumber, HashSet<RouteAuthorization>> = HashMap::new();
+                for auth in auths {
+                    per_asn
+                        .entry(auth.asn())
+                        .or_insert_with(HashSet::new)
+                        .insert(*auth);
+                }
+                for (asn, group) in per_asn {
+                    // notice that the roa definition prefix is not relevant for this strategy
+                    let roa_key = RouteAuthorization::new(RoaDefinition::new(
+                        asn,
+                        TypedPrefix::from_str("0.0.0.0/0").unwrap(),
+                        None,
+                    ));
+                    groups.push((roa_key, group, ObjectName::from(&generate_uuid_roa_name())));
+                }
+            }
+            RoaPrefixGroupingStrategy::RoaPerAsnBounded(max) => {
+                let mut per_asn: HashMap<AsNumber, Vec<RouteAuthorization>> = HashMap::new();
+                for auth in auths {
+                    per_asn
+                        .entry(auth.asn())
+                        .or_insert_with(Vec::new)
+                        .push(*auth);
+                }
+                for (asn, mut asn_auths) in per_asn {
+                    asn_auths.sort_by_key(|auth| auth.to_string());
+                    for (index, chunk) in asn_auths.chunks(*max).enumerate() {
+                        let group = chunk.iter().cloned().collect();
+                        let roa_key = bounded_roa_key(asn, index as u32);
+                        groups.push((roa_key, group, ObjectName::from(&generate_uuid_roa_name())));
+                    }
+                }
+            }
+        }
+
+        let kept: HashSet<RouteAuthorization> =
+            groups.iter().map(|(roa_key, _, _)| *roa_key).collect();
+
+        for (roa_key, group, name) in groups {
+            match self.roas.get(&roa_key) {
+                Some(old) => {
+                    // Keep the name, so that the old ROA is replaced in the repository
+                    let name = old.name().clone();
+                    let roa = Roas::make_roa_multi(&group, key, None, signer, name.clone())?;
+                    updates.update(roa_key, RoaInfo::updated_roa(old, &roa, name));
+                }
+                None => {
+                    let roa = Roas::make_roa_multi(&group, key, None, signer, name.clone())?;
+                    updates.update(roa_key, RoaInfo::new_roa(&roa, name));
+                }
+            }
+        }
+
+        for (roa_key, info) in self.roas.iter() {
+            if !kept.contains(roa_key) {
+                let name = info.name().clone();
+                updates.remove(*roa_key, name, RevokedObject::from(info.object()));
+            }
+        }
+
+        Ok(updates)
+    }
+
+    /// Returns true if the ROAs are grouped according to the strategy, i.e.
+    /// if each ROA contains only the authorization it is kept under, if
+    /// there is only one ROA for each ASN, or if each ROA groups at most
+    /// the maximum number of prefixes.
+    fn roas_grouped_by(
+        &self,
+        roa_prefix_grouping_strategy: &RoaPrefixGroupingStrategy,
+    ) -> KrillResult<bool> {
+        fn only_key(roa_key: &RouteAuthorization, contained: &[RouteAuthorization]) -> bool {
+            contained.len() == 1
+                && contained[0].asn() == roa_key.asn()
+                && contained[0].prefix() == roa_key.prefix()
+                && contained[0].effective_max_length() == roa_key.effective_max_length()
+        }
+
+        match roa_prefix_grouping_strategy {
+            RoaPrefixGroupingStrategy::RoaPerPrefix => {
+                for (roa_key, roa_info) in self.roas.iter() {
+                    let contained = roa_info.retrieve_route_authorizations()?;
+                    if !only_key(roa_key, &contained) {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            RoaPrefixGroupingStrategy::RoaPerAsn => {
+                let mut asns = HashSet::new();
+                for roa_key in self.roas.authorizations() {
+                    if !asns.insert(roa_key.asn()) {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            RoaPrefixGroupingStrategy::RoaPerAsnBounded(max) => {
+                // ROAs issued per ASN are grouped as well, unless they hold
+                // more prefixes than allowed.
+                for (roa_key, roa_info) in self.roas.iter() {
+                    let contained = roa_info.retrieve_route_authorizations()?;
+                    if contained.len() > *max || only_key(roa_key, &contained) {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
     /// Retrieves the RC's authorizations based on the RoaPrefixGroupingStrategy currently being
     /// used.
     fn get_authorizations(&self, roa_prefix_grouping_strategy: &RoaPrefixGroupingStrategy)
             -> KrillResult<Vec<RouteAuthorization>> {
         match roa_prefix_grouping_strategy {
             RoaPrefixGroupingStrategy::RoaPerPrefix => Ok(self.roas.authorizations().cloned().collect()),
-            RoaPrefixGroupingStrategy::RoaPerAsn => {
+            RoaPrefixGroupingStrategy::RoaPerAsn
+            | RoaPrefixGroupingStrategy::RoaPerAsnBounded(_) => {
                 let mut res = vec![];
                 for (_, roa_info) in self.roas.iter() {
                     for auth in roa_info.retrieve_route_authorizations()? {
@@ -1134,10 +1595,190 @@ impl ResourceClass {
     }
 }
 
+/// # ASPAs
+///
+impl ResourceClass {
+    /// Updates the ASPA objects in accordance with the current definitions,
+    /// and the target resources and key determined by the PublishMode. ASPA
+    /// objects are only issued in this resource class for customer ASNs that
+    /// it holds.
+    pub fn update_aspas<S: Signer>(
+        &self,
+        definitions: &AspaDefinitions,
+        mode: &PublishMode,
+        signer: &S,
+    ) -> KrillResult<AspaObjectsUpdates> {
+        let mut updates = AspaObjectsUpdates::default();
+
+        let key = match mode {
+            PublishMode::KeyRollActivation => self.get_new_key()?,
+            _ => self.get_current_key()?,
+        };
+
+        let resources = match mode {
+            PublishMode::Normal | PublishMode::NewRepo(_) => key.incoming_cert().resources(),
+            PublishMode::UpdatedResources(resources) => resources,
+            PublishMode::KeyRollActivation => self.get_current_key()?.incoming_cert().resources(),
+        };
+
+        let new_repo = match &mode {
+            PublishMode::NewRepo(info) => Some(info.ca_repository(self.name_space())),
+            _ => None,
+        };
+
+        // Remove any ASPAs no longer defined, or for customers no longer held.
+        for (customer, info) in self.aspas.iter() {
+            if !definitions.has(*customer) || !resources.contains_asn(*customer) {
+                updates.remove(*customer, RevokedObject::from(info.object()));
+            }
+        }
+
+        for definition in definitions.all() {
+            let customer = definition.customer();
+
+            // if the customer is not in this resource class, just skip it.
+            if !resources.contains_asn(customer) {
+                continue;
+            }
+
+            match self.aspas.get(customer) {
+                None => {
+                    let aspa = AspaObjects::make_aspa(definition, key, new_repo.as_ref(), signer)?;
+                    updates.update(customer, AspaInfo::new_aspa(definition.clone(), &aspa));
+                }
+                Some(info) => {
+                    // Re-issue if the definition changed, if the ASPA is getting close to
+                    // its expiration time, or if we are activating the new key.
+                    let changed = info.definition() != definition;
+                    let expiring = info.object().expires() < Time::now() + Duration::weeks(4);
+                    let activating = mode == &PublishMode::KeyRollActivation;
+
+                    if changed || expiring || activating || new_repo.is_some() {
+                        let aspa =
+                            AspaObjects::make_aspa(definition, key, new_repo.as_ref(), signer)?;
+                        let info = AspaInfo::updated_aspa(info, definition.clone(), &aspa);
+                        updates.update(customer, info);
+                    }
+                }
+            }
+        }
+
+        Ok(updates)
+    }
+
+    /// Marks the ASPA objects as updated from an AspaObjectsUpdated event.
+    pub fn aspa_objects_updated(&mut self, updates: AspaObjectsUpdates) {
+        self.aspas.updated(updates);
+    }
+}
+
+/// # BGPsec router certificates
+///
+impl ResourceClass {
+    /// Updates the BGPsec router certificates in accordance with the current
+    /// definitions, and the target resources and key determined by the
+    /// PublishMode. Router certificates are only issued in this resource
+    /// class for ASNs that it holds.
+    pub fn update_bgpsec_certificates<S: Signer>(
+        &self,
+        definitions: &BgpSecDefinitions,
+        mode: &PublishMode,
+        signer: &S,
+    ) -> KrillResult<BgpSecCertificateUpdates> {
+        let mut updates = BgpSecCertificateUpdates::default();
+
+        let key = match mode {
+            PublishMode::KeyRollActivation => self.get_new_key()?,
+            _ => self.get_current_key()?,
+        };
+
+        let resources = match mode {
+            PublishMode::Normal | PublishMode::NewRepo(_) => key.incoming_cert().resources(),
+            PublishMode::UpdatedResources(resources) => resources,
+            PublishMode::KeyRollActivation => self.get_current_key()?.incoming_cert().resources(),
+        };
+
+        let new_repo = match &mode {
+            PublishMode::NewRepo(info) => Some(info.ca_repository(self.name_space())),
+            _ => None,
+        };
+
+        // Revoke any certificates no longer defined, or for ASNs no longer held.
+        // Also revoke certificates for keys that routers cannot use, which
+        // were accepted before such keys were refused.
+        for (asn_key, info) in self.bgpsec_certs.iter() {
+            if !definitions.has(asn_key)
+                || !resources.contains_asn(asn_key.asn())
+                || !info.definition().has_router_key()
+            {
+                updates.remove(*asn_key, RevokedObject::from(info.object()));
+            }
+        }
+
+        for definition in definitions.all() {
+            // if the ASN is not in this resource class, or the key cannot be
+            // used by a router, just skip it.
+            if !resources.contains_asn(definition.asn()) || !definition.has_router_key() {
+                continue;
+            }
+
+            let asn_key = definition.asn_key();
+
+            match self.bgpsec_certs.get(&asn_key) {
+                None => {
+                    let cert = BgpSecCertificates::make_router_cert(
+                        definition,
+                        key,
+                        new_repo.as_ref(),
+                        signer,
+                    )?;
+                    updates.update(asn_key, BgpSecCertInfo::new_cert(definition.clone(), &cert));
+                }
+                Some(info) => {
+                    // Re-issue if the certificate is getting close to its expiration
+                    // time, or if we are activating the new key.
+                    let expiring = info.object().expires() < Time::now() + Duration::weeks(4);
+                    let activating = mode == &PublishMode::KeyRollActivation;
+
+                    if expiring || activating || new_repo.is_some() {
+                        let cert = BgpSecCertificates::make_router_cert(
+                            definition,
+                            key,
+                            new_repo.as_ref(),
+                            signer,
+                        )?;
+                        updates.update(asn_key, BgpSecCertInfo::updated_cert(info, &cert));
+                    }
+                }
+            }
+        }
+
+        Ok(updates)
+    }
+
+    /// Marks the BGPsec router certificates as updated from a
+    /// BgpSecCertificatesUpdated event.
+    pub fn bgpsec_certificates_updated(&mut self, updates: BgpSecCertificateUpdates) {
+        self.bgpsec_certs.updated(updates);
+    }
+}
+
 fn generate_uuid_roa_name() -> String {
     format!("{}.roa", Uuid::new_v4())
 }
 
+/// Returns the key under which the ROA with the given index for an ASN is
+/// kept when using RoaPerAsnBounded. The prefix is not relevant for this
+/// strategy, it is only used to tell the ROAs for an ASN apart.
+fn bounded_roa_key(asn: AsNumber, index: u32) -> RouteAuthorization {
+    let prefix = format!("{}/32", Ipv4Addr::from(index));
+    RouteAuthorization::new(RoaDefinition::new(
+        asn,
+        TypedPrefix::from_str(&prefix).unwrap(),
+        None,
+    ))
+}
+
 //------------ PublishMode -------------------------------------------------
 
 /// Describes which kind of publication we're after:
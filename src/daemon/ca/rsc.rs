@@ -0,0 +1,168 @@
+use bcder::encode::{Constructed, Values};
+use bcder::{encode, Mode, OctetString, Oid, Tag};
+use bytes::Bytes;
+
+use rpki::resources::IpBlocks;
+use rpki::sigobj::{SignedObject, SignedObjectBuilder};
+use rpki::x509::{Serial, Time};
+
+use crate::commons::api::{ResourceSet, RscRequest};
+use crate::commons::KrillResult;
+use crate::daemon::ca::{self, CertifiedKey, SignSupport, Signer};
+
+/// The content type for RSC objects: id-ct-signedChecklist (1.2.840.113549.1.9.16.1.48)
+const RSC_CONTENT_TYPE: [u8; 11] = [42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 48];
+
+/// The digest algorithm for the checklist: id-sha256 (2.16.840.1.101.3.4.2.1)
+const SHA256_ALGORITHM: [u8; 9] = [96, 134, 72, 1, 101, 3, 4, 2, 1];
+
+const AFI_V4: [u8; 2] = [0, 1];
+const AFI_V6: [u8; 2] = [0, 2];
+
+/// The name used for the object in the EE certificate. Signed checklists are
+/// never published, so this is only needed to satisfy the object builder.
+const RSC_NAME: &str = "checklist.rsc";
+
+//------------ SignedChecklistBuilder --------------------------------------
+
+/// Makes RPKI Signed Checklists (RFC 9323) under a CA key, using a one-off
+/// EE certificate for exactly the requested resources.
+pub struct SignedChecklistBuilder;
+
+impl SignedChecklistBuilder {
+    /// Signs a checklist for the request, which is expected to have been
+    /// verified: i.e. the resources are held under the certified key, and
+    /// the digests are valid.
+    pub fn make_rsc<S: Signer>(
+        request: &RscRequest,
+        certified_key: &CertifiedKey,
+        signer: &S,
+    ) -> KrillResult<SignedObject> {
+        let incoming_cert = certified_key.incoming_cert();
+        let crl_uri = incoming_cert.crl_uri();
+        let aia = incoming_cert.uri();
+        let rsc_uri = incoming_cert.uri_for_object(RSC_NAME);
+
+        let signing_key = certified_key.key_id();
+        let resources = request.resources();
+        let (v4, v6) = Self::ip_blocks(resources)?;
+
+        let mut object_builder = SignedObjectBuilder::new(
+            Serial::random(signer).map_err(ca::Error::signer)?,
+            SignSupport::sign_validity_year(),
+            crl_uri,
+            aia.clone(),
+            rsc_uri,
+        );
+        // RFC 9323 forbids the SIA in the EE certificate, as the object
+        // does not live in a repository.
+        object_builder.set_signed_object(None);
+        object_builder.set_issuer(Some(incoming_cert.cert().subject().clone()));
+        object_builder.set_signing_time(Some(Time::now()));
+
+        if !resources.asn().is_empty() {
+            object_builder.set_as_resources(resources.to_as_resources());
+        }
+        if !v4.is_empty() {
+            object_builder.set_v4_resources(resources.to_ip_resources_v4());
+        }
+        if !v6.is_empty() {
+            object_builder.set_v6_resources(resources.to_ip_resources_v6());
+        }
+
+        object_builder
+            .finalize(
+                Oid(Bytes::from_static(&RSC_CONTENT_TYPE)),
+                Self::encode_content(request, &v4, &v6),
+                signer,
+                signing_key,
+            )
+            .map_err(ca::Error::signer)
+    }
+
+    /// Returns the IPv4 and IPv6 blocks of the resources. A ResourceSet
+    /// never inherits, so this only fails if that assumption is broken.
+    fn ip_blocks(resources: &ResourceSet) -> KrillResult<(IpBlocks, IpBlocks)> {
+        let inherited = |_| ca::Error::custom("Cannot sign checklist for inherited resources");
+        let v4 = resources
+            .to_ip_resources_v4()
+            .to_blocks()
+            .map_err(inherited)?;
+        let v6 = resources
+            .to_ip_resources_v6()
+            .to_blocks()
+            .map_err(inherited)?;
+        Ok((v4, v6))
+    }
+
+    /// Encodes the RpkiSignedChecklist content:
+    ///
+    /// ```text
+    /// RpkiSignedChecklist ::= SEQUENCE {
+    ///   version [0] INTEGER DEFAULT 0,
+    ///   resources ResourceBlock,
+    ///   digestAlgorithm DigestAlgorithmIdentifier,
+    ///   checkList SEQUENCE (SIZE(1..MAX)) OF FileNameAndHash }
+    ///
+    /// ResourceBlock ::= SEQUENCE {
+    ///   asID [0] ConstrainedASIdentifiers OPTIONAL,
+    ///   ipAddrBlocks [1] ConstrainedIPAddrBlocks OPTIONAL }
+    ///
+    /// FileNameAndHash ::= SEQUENCE {
+    ///   fileName IA5String OPTIONAL,
+    ///   hash Digest }
+    /// ```
+    ///
+    /// The version is left out, as it has the default value.
+    fn encode_content(request: &RscRequest, v4: &IpBlocks, v6: &IpBlocks) -> Bytes {
+        let resources = request.resources();
+
+        let as_id = if resources.asn().is_empty() {
+            None
+        } else {
+            Some(Constructed::new(
+                Tag::CTX_0,
+                encode::sequence(Constructed::new(Tag::CTX_0, resources.asn().encode())),
+            ))
+        };
+
+        let ip_addr_blocks = if v4.is_empty() && v6.is_empty() {
+            None
+        } else {
+            let v4 = if v4.is_empty() {
+                None
+            } else {
+                Some(v4.encode_family(AFI_V4))
+            };
+            let v6 = if v6.is_empty() {
+                None
+            } else {
+                Some(v6.encode_family(AFI_V6))
+            };
+            Some(Constructed::new(Tag::CTX_1, encode::sequence((v4, v6))))
+        };
+
+        let digests: Vec<(Option<Bytes>, Bytes)> = request
+            .digests()
+            .iter()
+            .map(|digest| {
+                let name = digest.file_name().map(|name| Bytes::from(name.as_str()));
+                (name, digest.digest_bytes())
+            })
+            .collect();
+
+        encode::sequence((
+            encode::sequence((as_id, ip_addr_blocks)),
+            encode::sequence(Oid(Bytes::from_static(&SHA256_ALGORITHM)).encode()),
+            encode::sequence(encode::slice(&digests, |(name, hash)| {
+                encode::sequence((
+                    name.as_ref()
+                        .map(|name| OctetString::encode_slice_as(name, Tag::IA5_STRING)),
+                    OctetString::encode_slice(hash),
+                ))
+            })),
+        ))
+        .to_captured(Mode::Der)
+        .into_bytes()
+    }
+}
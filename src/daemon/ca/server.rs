@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
@@ -8,16 +8,24 @@ use chrono::Duration;
 
 use rpki::crypto::KeyIdentifier;
 use rpki::uri;
+use rpki::x509::Time;
 
 use crate::commons::api::{
-    self, AddChildRequest, Base64, CertAuthHistory, CertAuthList, CertAuthSummary,
-    ChildAuthRequest, ChildCaInfo, ChildHandle, Entitlements, Handle, IssuanceRequest,
-    IssuanceResponse, IssuedCert, ListReply, ParentCaContact, ParentCaReq, ParentHandle,
-    PublishDelta, RcvdCert, RepoInfo, RepositoryContact, ResourceClassName, ResourceSet,
-    RevocationRequest, RevocationResponse, UpdateChildRequest,
+    self, AddChildRequest, AsNumber, AspaDefinitionUpdates, AspaProvidersUpdate, AuditEntry,
+    Base64, BgpSecDefinitionUpdates, CaKeyRollInfo, CaTimingInfo, CertAuthDiff, CertAuthHistory,
+    CertAuthHistoryPoint, CertAuthList, CertAuthSummary, ChildAuthRequest, ChildCaInfo,
+    ChildHandle, Entitlements, ErrorResponse, Handle, IssuanceRequest, IssuanceResponse,
+    IssuedCert, KeyRollEmergencyRequest, KeyRollPolicy, ListReply, ParentCaContact, ParentCaReq,
+    ParentHandle, PublishDelta, RcvdCert, RepoInfo, RepositoryContact, ResourceClassName,
+    ResourceSet, RevocationRequest, RevocationResponse, RoaDefinition, RoaDefinitionUpdates,
+    RoaGroupingInfo, RoaPrefixGroupingStrategy, RscRequest, SignedChecklist, TaCertDetails,
+    TaMessage, TaResponseBundle, TaResponses, TimingConfig, UpdateChildRequest,
 };
 use crate::commons::error::Error;
-use crate::commons::eventsourcing::{Aggregate, AggregateStore, DiskAggregateStore};
+use crate::commons::eventsourcing::{
+    Aggregate, AggregateStore, AggregateStoreError, DiskAggregateStore, KeyStoreType, StoredEffect,
+    DEFAULT_ACTOR,
+};
 use crate::commons::remote::builder::SignedMessageBuilder;
 use crate::commons::remote::cmslogger::CmsLogger;
 use crate::commons::remote::id::IdCert;
@@ -25,9 +33,10 @@ use crate::commons::remote::sigmsg::SignedMessage;
 use crate::commons::remote::{rfc6492, rfc8181, rfc8183};
 use crate::commons::util::httpclient;
 use crate::commons::KrillResult;
-use crate::constants::CASERVER_DIR;
+use crate::constants::{CASERVER_DIR, PUBLISHED_VALIDATION_MINUTES};
 use crate::daemon::ca::{
-    self, ta_handle, CertAuth, Cmd, CmdDet, IniDet, RouteAuthorizationUpdates, Signer,
+    self, ta_handle, CertAuth, Cmd, CmdDet, IniDet, InvalidObject, PublishedObjectsValidator,
+    RouteAuthorizationUpdates, Signer,
 };
 use crate::daemon::mq::EventQueueListener;
 
@@ -38,6 +47,8 @@ pub struct CaServer<S: Signer> {
     signer: Arc<RwLock<S>>,
     ca_store: Arc<DiskAggregateStore<CertAuth<S>>>,
     cms_logger_work_dir: PathBuf,
+    key_roll_failures: Arc<RwLock<HashMap<Handle, ErrorResponse>>>,
+    published_validations: Arc<RwLock<HashMap<Handle, PublishedValidation>>>,
 }
 
 impl<S: Signer> CaServer<S> {
@@ -48,15 +59,23 @@ impl<S: Signer> CaServer<S> {
         events_queue: Arc<EventQueueListener>,
         signer: Arc<RwLock<S>>,
         roa_prefix_grouping_strategy: RoaPrefixGroupingStrategy,
+        timing_config: TimingConfig,
+        snapshot_events: u64,
+        store_type: KeyStoreType,
     ) -> KrillResult<Self> {
-        let mut ca_store = DiskAggregateStore::<CertAuth<S>>::new(work_dir, CASERVER_DIR)?;
+        let mut ca_store =
+            DiskAggregateStore::<CertAuth<S>>::build(work_dir, CASERVER_DIR, store_type)?;
         ca_store.add_listener(events_queue);
+        ca_store.set_snapshot_events(snapshot_events);
         ca::set_roa_prefix_grouping_strategy(roa_prefix_grouping_strategy);
+        ca::set_timing_config(timing_config);
 
         Ok(CaServer {
             signer,
             ca_store: Arc::new(ca_store),
             cms_logger_work_dir: work_dir.clone(),
+            key_roll_failures: Arc::new(RwLock::new(HashMap::new())),
+            published_validations: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
@@ -80,7 +99,7 @@ impl<S: Signer> CaServer<S> {
         } else {
             // init normal CA
             let init = IniDet::init(&handle, self.signer.clone())?;
-            self.ca_store.add(init)?;
+            self.ca_store.add(init, DEFAULT_ACTOR)?;
 
             // add embedded repo
             let embedded = RepositoryContact::embedded(info);
@@ -135,8 +154,13 @@ impl<S: Signer> CaServer<S> {
     }
 
     /// Update repository where a CA publishes.
-    pub fn update_repo(&self, handle: Handle, new_contact: RepositoryContact) -> KrillResult<()> {
-        let cmd = CmdDet::update_repo(&handle, new_contact, self.signer.clone());
+    pub fn update_repo(
+        &self,
+        handle: Handle,
+        new_contact: RepositoryContact,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let cmd = CmdDet::update_repo(&handle, new_contact, self.signer.clone()).with_actor(actor);
         self.send_command(cmd)
     }
 
@@ -167,6 +191,7 @@ impl<S: Signer> CaServer<S> {
         parent: &ParentHandle,
         req: AddChildRequest,
         service_uri: &uri::Https,
+        actor: &str,
     ) -> KrillResult<ParentCaContact> {
         info!("CA '{}' process add child request: {}", &parent, &req);
         let (child_handle, child_res, child_auth) = req.unwrap();
@@ -176,7 +201,8 @@ impl<S: Signer> CaServer<S> {
             ChildAuthRequest::Rfc8183(req) => Some(req.id_cert().clone()),
         };
 
-        let add_child = CmdDet::child_add(&parent, child_handle.clone(), id_cert, child_res);
+        let add_child = CmdDet::child_add(&parent, child_handle.clone(), id_cert, child_res)
+            .with_actor(actor);
         self.ca_store.command(add_child)?;
 
         let tag = match child_auth {
@@ -245,14 +271,20 @@ impl<S: Signer> CaServer<S> {
         handle: &Handle,
         child: ChildHandle,
         req: UpdateChildRequest,
+        actor: &str,
     ) -> KrillResult<()> {
-        self.send_command(CmdDet::child_update(handle, child, req))
+        self.send_command(CmdDet::child_update(handle, child, req).with_actor(actor))
     }
 
     /// Update a child under this CA.
-    pub fn ca_child_remove(&self, handle: &Handle, child: ChildHandle) -> KrillResult<()> {
+    pub fn ca_child_remove(
+        &self,
+        handle: &Handle,
+        child: ChildHandle,
+        actor: &str,
+    ) -> KrillResult<()> {
         let signer = self.signer.clone();
-        self.send_command(CmdDet::child_remove(handle, child, signer))
+        self.send_command(CmdDet::child_remove(handle, child, signer).with_actor(actor))
     }
 }
 
@@ -275,6 +307,126 @@ impl<S: Signer> CaServer<S> {
             .map_err(|_| Error::CaUnknown(handle.clone()))
     }
 
+    /// Gets the commands processed by a CA, with the resulting events or
+    /// error, for the audit log.
+    pub fn get_ca_audit(&self, handle: &Handle) -> KrillResult<Vec<AuditEntry>> {
+        let unknown = |_| Error::CaUnknown(handle.clone());
+
+        let commands = self.ca_store.command_history(handle).map_err(unknown)?;
+        let (_init, events) = self.ca_store.history(handle).map_err(unknown)?.unpack();
+
+        let event_summary = |version: &u64| {
+            events
+                .get(*version as usize - 1)
+                .map(|evt| evt.details().to_string())
+                .unwrap_or_else(|| format!("unknown event version {}", version))
+        };
+
+        Ok(commands
+            .into_iter()
+            .map(|cmd| {
+                let (error, events) = match cmd.effect() {
+                    StoredEffect::Error(e) => (Some(e.clone()), vec![]),
+                    StoredEffect::Events(versions) => {
+                        (None, versions.iter().map(event_summary).collect())
+                    }
+                };
+                AuditEntry::new(
+                    cmd.time(),
+                    handle.clone(),
+                    cmd.actor().to_string(),
+                    cmd.version(),
+                    cmd.summary().to_string(),
+                    error,
+                    events,
+                )
+            })
+            .collect())
+    }
+
+    /// Gets a CA as it was at the given point in its history, by replaying
+    /// its events, including archived events, up to that point.
+    pub fn get_ca_at(
+        &self,
+        handle: &Handle,
+        point: CertAuthHistoryPoint,
+    ) -> KrillResult<CertAuth<S>> {
+        let unknown = |_| Error::CaUnknown(handle.clone());
+
+        let version = match point {
+            CertAuthHistoryPoint::Version(version) => version,
+            CertAuthHistoryPoint::Time(time) => {
+                // The version after the events of the last command processed
+                // at or before the time.
+                let at = (time.timestamp(), time.timestamp_subsec_micros());
+                let commands = self.ca_store.command_history(handle).map_err(unknown)?;
+
+                // The CA did not exist before the command that created it.
+                // CAs created by older versions of krill have no such
+                // command, and init events have no time, so use the time of
+                // their first command instead.
+                let created = commands
+                    .iter()
+                    .find(|cmd| cmd.version() == 0)
+                    .or_else(|| commands.first());
+                if let Some(created) = created {
+                    let created = (
+                        created.time().timestamp(),
+                        created.time().timestamp_subsec_micros(),
+                    );
+                    if at < created {
+                        return Err(Error::CaHistoryPointBeforeCreation(
+                            handle.clone(),
+                            time.to_rfc3339(),
+                        ));
+                    }
+                }
+
+                commands
+                    .iter()
+                    .filter(|cmd| {
+                        (cmd.time().timestamp(), cmd.time().timestamp_subsec_micros()) <= at
+                    })
+                    .filter_map(|cmd| match cmd.effect() {
+                        StoredEffect::Error(_) => None,
+                        StoredEffect::Events(versions) => versions.iter().max().cloned(),
+                    })
+                    .max()
+                    .map(|last| last + 1)
+                    .unwrap_or(1)
+            }
+        };
+
+        let (init, events) = self.ca_store.history(handle).map_err(unknown)?.unpack();
+        if version == 0 || version > events.len() as u64 + 1 {
+            return Err(Error::CaHistoryPointUnknown(handle.clone(), version));
+        }
+
+        let mut ca = CertAuth::init(init)?;
+        ca.apply_all(events.into_iter().take(version as usize - 1).collect());
+        Ok(ca)
+    }
+
+    /// Gets the differences between a CA at two points in its history.
+    pub fn get_ca_diff(
+        &self,
+        handle: &Handle,
+        from: CertAuthHistoryPoint,
+        to: CertAuthHistoryPoint,
+    ) -> KrillResult<CertAuthDiff> {
+        let from = self.get_ca_at(handle, from)?;
+        let to = self.get_ca_at(handle, to)?;
+
+        Ok(CertAuthDiff::new(
+            from.version(),
+            &from.as_ca_info(),
+            &from.roa_definitions(),
+            to.version(),
+            &to.as_ca_info(),
+            &to.roa_definitions(),
+        ))
+    }
+
     /// Checks whether a CA by the given handle exists.
     pub fn has_ca(&self, handle: &Handle) -> bool {
         self.ca_store.has(handle)
@@ -404,26 +556,110 @@ impl<S: Signer> CaServer<S> {
     }
 
     /// Initialises a CA without a repo, no parents, no children, no nothing
-    pub fn init_ca(&self, handle: &Handle) -> KrillResult<()> {
+    pub fn init_ca(&self, handle: &Handle, actor: &str) -> KrillResult<()> {
         if self.ca_store.has(handle) {
             Err(Error::CaDuplicate(handle.clone()))
         } else {
             let init = IniDet::init(handle, self.signer.clone())?;
-            self.ca_store.add(init)?;
+            self.ca_store.add(init, actor)?;
             Ok(())
         }
     }
 
-    pub fn ca_update_id(&self, handle: Handle) -> KrillResult<()> {
-        let cmd = CmdDet::update_id(&handle, self.signer.clone());
+    /// Removes all children of a CA, for when the CA is removed. Local CAs
+    /// which use this CA as their embedded parent are asked to remove it as
+    /// a parent first.
+    ///
+    /// All children are tried, even if some cannot be removed, so that a
+    /// retry only has to deal with the children that are left. Returns an
+    /// error listing those children, if any.
+    pub fn ca_remove_all_children(&self, handle: &Handle, actor: &str) -> KrillResult<()> {
+        let ca = self.get_ca(handle)?;
+        let children: Vec<ChildHandle> = ca.children().cloned().collect();
+
+        let mut not_removed = vec![];
+        for child in children {
+            if let Ok(child_ca) = self.get_ca(&child) {
+                if let Ok(ParentCaContact::Embedded) = child_ca.parent(handle) {
+                    if let Err(e) = self.ca_parent_remove(child.clone(), handle.clone(), actor) {
+                        warn!(
+                            "Could not remove parent '{}' from child '{}', error: {}",
+                            handle, child, e
+                        );
+                    }
+                }
+            }
+            if let Err(e) = self.ca_child_remove(handle, child.clone(), actor) {
+                warn!(
+                    "Could not remove child '{}' from CA '{}', error: {}",
+                    child, handle, e
+                );
+                not_removed.push(format!("'{}': {}", child, e));
+            }
+        }
+
+        if not_removed.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::CaChildrenNotRemoved(
+                handle.clone(),
+                not_removed.join(", "),
+            ))
+        }
+    }
+
+    /// Requests revocation of all keys of a CA at each of its parents, for
+    /// when the CA is removed. Not performed responses from remote parents
+    /// are only logged, because they may have revoked the keys already, but
+    /// an error is returned if the requests could not be sent at all, so
+    /// that the CA is not removed while its keys are still certified.
+    pub fn ca_revoke_all_keys(&self, handle: &Handle) -> KrillResult<()> {
+        let ca = self.get_ca(handle)?;
+
+        for parent in ca.parents() {
+            let requests = {
+                let signer = self.signer.read().unwrap();
+                ca.revoke_all_requests(parent, signer.deref())?
+            };
+
+            self.send_revoke_requests(handle, parent, requests)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes a CA, but keeps its history in an archive. The keys of the
+    /// CA are destroyed, because they can no longer be used.
+    pub fn ca_archive(&self, handle: &Handle) -> KrillResult<()> {
+        let keys = self.get_ca(handle)?.all_key_ids();
+
+        self.ca_store.archive(handle)?;
+
+        let mut signer = self.signer.write().unwrap();
+        for key in keys {
+            if let Err(e) = signer.destroy_key(&key) {
+                warn!("Could not destroy key '{}' of CA '{}': {}", key, handle, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn ca_update_id(&self, handle: Handle, actor: &str) -> KrillResult<()> {
+        let cmd = CmdDet::update_id(&handle, self.signer.clone()).with_actor(actor);
         self.send_command(cmd)
     }
 
     /// Adds a parent to a CA
-    pub fn ca_parent_add(&self, handle: Handle, parent: ParentCaReq) -> KrillResult<()> {
+    pub fn ca_parent_add(
+        &self,
+        handle: Handle,
+        parent: ParentCaReq,
+        actor: &str,
+    ) -> KrillResult<()> {
         let (parent_handle, parent_contact) = parent.unpack();
 
-        let add = CmdDet::add_parent(&handle, parent_handle, parent_contact);
+        let add = CmdDet::add_parent(&handle, parent_handle, parent_contact).with_actor(actor);
         self.send_command(add)
     }
 
@@ -433,20 +669,32 @@ impl<S: Signer> CaServer<S> {
         handle: Handle,
         parent: ParentHandle,
         contact: ParentCaContact,
+        actor: &str,
     ) -> KrillResult<()> {
-        let upd = CmdDet::update_parent(&handle, parent, contact);
+        let upd = CmdDet::update_parent(&handle, parent, contact).with_actor(actor);
         self.send_command(upd)
     }
 
     /// Removes a parent from a CA
-    pub fn ca_parent_remove(&self, handle: Handle, parent: ParentHandle) -> KrillResult<()> {
-        let upd = CmdDet::remove_parent(&handle, parent);
+    pub fn ca_parent_remove(
+        &self,
+        handle: Handle,
+        parent: ParentHandle,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let upd = CmdDet::remove_parent(&handle, parent).with_actor(actor);
         self.send_command(upd)
     }
 
     /// Perform a key roll for all active keys in a CA older than the specified duration.
-    pub fn ca_keyroll_init(&self, handle: Handle, max_age: Duration) -> KrillResult<()> {
-        let init_key_roll = CmdDet::key_roll_init(&handle, max_age, self.signer.clone());
+    pub fn ca_keyroll_init(
+        &self,
+        handle: Handle,
+        max_age: Duration,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let init_key_roll =
+            CmdDet::key_roll_init(&handle, max_age, self.signer.clone()).with_actor(actor);
         self.send_command(init_key_roll)
     }
 
@@ -454,11 +702,136 @@ impl<S: Signer> CaServer<S> {
     /// have an age equal to or greater than the staging period are promoted. The RFC mandates
     /// a staging period of 24 hours, but we may use a shorter period for testing and/or emergency
     /// manual key rolls.
-    pub fn ca_keyroll_activate(&self, handle: Handle, staging: Duration) -> KrillResult<()> {
-        let activate_cmd = CmdDet::key_roll_activate(&handle, staging, self.signer.clone());
+    pub fn ca_keyroll_activate(
+        &self,
+        handle: Handle,
+        staging: Duration,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let activate_cmd =
+            CmdDet::key_roll_activate(&handle, staging, self.signer.clone()).with_actor(actor);
         self.send_command(activate_cmd)
     }
 
+    /// Rolls all keys of a CA in an emergency, e.g. because they may have been
+    /// compromised. The request must be confirmed. New keys are activated,
+    /// and old keys revoked, as soon as the parent certifies the new keys.
+    pub fn ca_keyroll_emergency(
+        &self,
+        handle: Handle,
+        request: KeyRollEmergencyRequest,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let (reason, confirm) = request.unpack();
+        if !confirm {
+            return Err(Error::CaKeyRollEmergencyNotConfirmed(handle));
+        }
+
+        let cmd =
+            CmdDet::key_roll_emergency(&handle, reason, self.signer.clone()).with_actor(actor);
+        self.send_command(cmd)
+    }
+
+    /// Activates the new keys of a CA in an emergency key roll, without
+    /// observing the staging period.
+    pub fn ca_keyroll_emergency_activate(&self, handle: &Handle) -> KrillResult<()> {
+        if !self.get_ca(handle)?.key_roll_emergency() {
+            return Ok(()); // emergency key roll was completed in the mean time
+        }
+        self.ca_keyroll_activate(handle.clone(), Duration::zero(), DEFAULT_ACTOR)
+    }
+
+    /// Returns the key roll policy of a CA, and the progress of key rolls.
+    pub fn ca_key_roll_info(&self, handle: &Handle) -> KrillResult<CaKeyRollInfo> {
+        let ca = self.get_ca(handle)?;
+        Ok(CaKeyRollInfo::new(
+            ca.key_roll_policy().cloned(),
+            ca.key_roll_progress(),
+        ))
+    }
+
+    /// Sets the policy for automatic key rolls for a CA, or removes it if None.
+    pub fn ca_key_roll_policy_update(
+        &self,
+        handle: Handle,
+        policy: Option<KeyRollPolicy>,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let cmd = CmdDet::key_roll_policy_update(&handle, policy).with_actor(actor);
+        self.send_command(cmd)
+    }
+
+    /// Initiates key rolls for CAs which have a key roll policy and a key
+    /// that is older than the maximum key age, and activates new keys that
+    /// have been staged long enough. Failures are kept so that they can be
+    /// reported as issues for the CA, until the next attempt succeeds.
+    pub fn key_roll_all(&self) {
+        for handle in self.ca_store.list() {
+            let policy = match self.get_ca(&handle) {
+                Ok(ca) => match ca.key_roll_policy() {
+                    Some(policy) => *policy,
+                    None => continue,
+                },
+                Err(e) => {
+                    error!("Could not get CA '{}' for key roll: {}", handle, e);
+                    continue;
+                }
+            };
+
+            let res = self
+                .ca_keyroll_init(handle.clone(), policy.max_key_age(), DEFAULT_ACTOR)
+                .and_then(|_| {
+                    self.ca_keyroll_activate(handle.clone(), policy.staging(), DEFAULT_ACTOR)
+                });
+
+            let mut failures = self.key_roll_failures.write().unwrap();
+            match res {
+                Ok(()) => {
+                    failures.remove(&handle);
+                }
+                Err(e) => {
+                    error!("Automatic key roll failed for CA '{}': {}", handle, e);
+                    failures.insert(handle, e.to_error_response());
+                }
+            }
+        }
+    }
+
+    /// Returns the error of the last automatic key roll attempt for a CA, if
+    /// it failed.
+    pub fn key_roll_failure(&self, handle: &Handle) -> Option<ErrorResponse> {
+        self.key_roll_failures.read().unwrap().get(handle).cloned()
+    }
+
+    /// Returns the objects published by the CA which fail validation. The
+    /// result is kept until the CA changes, e.g. because it published new
+    /// objects, so that metrics and issues requests do not validate all
+    /// objects every time. The objects of an unchanged CA are validated
+    /// again after a while, because they may have become stale.
+    pub fn validate_published(&self, ca: &CertAuth<S>) -> Vec<InvalidObject> {
+        let now = Time::now();
+
+        if let Some(validation) = self.published_validations.read().unwrap().get(ca.handle()) {
+            let expires = validation.time + Duration::minutes(PUBLISHED_VALIDATION_MINUTES);
+            if validation.version == ca.version() && expires > now {
+                return validation.invalid.clone();
+            }
+        }
+
+        let invalid = ca.validate_published();
+        let validation = PublishedValidation {
+            version: ca.version(),
+            time: now,
+            invalid: invalid.clone(),
+        };
+        self.published_validations
+            .write()
+            .unwrap()
+            .insert(ca.handle().clone(), validation);
+
+        invalid
+    }
+
     /// Try to get updates for all embedded CAs, will skip the TA and/or CAs that
     /// have no parents. Will try to process all and log possible errors, i.e. do
     /// not bail out because of issues with one CA.
@@ -513,6 +886,9 @@ impl<S: Signer> CaServer<S> {
             if ca.get_repository_contact().is_err() {
                 // No repo set, yet. So, skip updating.
                 Ok(())
+            } else if ca.parent(parent)?.is_offline() {
+                // Entitlements are imported from the offline TA responses.
+                Ok(())
             } else {
                 let entitlements = self.get_entitlements_from_parent(handle, parent)?;
 
@@ -577,6 +953,8 @@ impl<S: Signer> CaServer<S> {
             ParentCaContact::Rfc6492(parent_res) => {
                 self.send_revoke_requests_rfc6492(revoke_requests, child.id_key(), parent_res)
             }
+            // Requests for an offline TA are exported, see ta_offline_requests.
+            ParentCaContact::Offline(_) => Ok(HashMap::new()),
         }
     }
 
@@ -674,6 +1052,8 @@ impl<S: Signer> CaServer<S> {
             ParentCaContact::Rfc6492(parent_res) => {
                 self.send_cert_requests_rfc6492(cert_requests, child.id_key(), &parent_res)
             }
+            // Requests for an offline TA are exported, see ta_offline_requests.
+            ParentCaContact::Offline(_) => Ok(HashMap::new()),
         }?;
 
         for (class_name, issued_certs) in issued_certs.into_iter() {
@@ -813,6 +1193,11 @@ impl<S: Signer> CaServer<S> {
             ParentCaContact::Ta(_) => Err(Error::TaNotAllowed),
             ParentCaContact::Embedded => self.get_entitlements_embedded(handle, parent),
             ParentCaContact::Rfc6492(res) => self.get_entitlements_rfc6492(handle, res),
+            ParentCaContact::Offline(_) => Err(Error::CaParentIssue(
+                handle.clone(),
+                parent.clone(),
+                "cannot contact offline Trust Anchor".to_string(),
+            )),
         }
     }
 
@@ -875,6 +1260,245 @@ impl<S: Signer> CaServer<S> {
     }
 }
 
+/// # Exchanging requests and responses with an offline Trust Anchor
+///
+impl<S: Signer> CaServer<S> {
+    /// Returns signed requests for all CAs which have an offline Trust Anchor
+    /// as a parent. There is always a list request, so that changes in
+    /// entitlements are picked up, followed by any open revocation and
+    /// certificate issuance requests.
+    pub fn ta_offline_requests(&self) -> KrillResult<Vec<TaMessage>> {
+        let mut res = vec![];
+
+        for handle in self.ca_store.list() {
+            let ca = self.get_ca(&handle)?;
+
+            for parent in ca.parents() {
+                let parent_res = match ca.parent(parent)? {
+                    ParentCaContact::Offline(parent_res) => parent_res,
+                    _ => continue,
+                };
+
+                let sender = parent_res.child_handle();
+                let recipient = parent_res.parent_handle();
+
+                let mut messages = vec![rfc6492::Message::list(sender.clone(), recipient.clone())];
+                for (_, requests) in ca.revoke_requests(parent).into_iter() {
+                    for req in requests.into_iter() {
+                        let revoke =
+                            rfc6492::Message::revoke(sender.clone(), recipient.clone(), req);
+                        messages.push(revoke);
+                    }
+                }
+                for (_, requests) in ca.cert_requests(parent).into_iter() {
+                    for req in requests.into_iter() {
+                        let issue = rfc6492::Message::issue(sender.clone(), recipient.clone(), req);
+                        messages.push(issue);
+                    }
+                }
+
+                let signer = self.signer.read().unwrap();
+                for msg in messages.into_iter() {
+                    let cms =
+                        SignedMessageBuilder::create(ca.id_key(), signer.deref(), msg.into_bytes())
+                            .map_err(Error::signer)?
+                            .as_bytes();
+                    res.push(TaMessage::new(handle.clone(), parent.clone(), cms));
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Lets the (offline) Trust Anchor process requests from its children,
+    /// and returns its signed responses. Requests which cannot be processed
+    /// are logged and skipped, so that they do not hold up other children.
+    pub fn ta_offline_sign(&self, requests: Vec<TaMessage>) -> KrillResult<Vec<TaMessage>> {
+        let ta_handle = ta_handle();
+        self.get_trust_anchor()?;
+
+        let mut res = vec![];
+        for request in requests.into_iter() {
+            match self.rfc6492(&ta_handle, request.to_bytes()) {
+                Ok(cms) => {
+                    let (ca, parent) = (request.ca().clone(), request.parent().clone());
+                    res.push(TaMessage::new(ca, parent, cms));
+                }
+                Err(e) => error!("Could not process offline TA request {}: {}", request, e),
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Signs the responses of the (offline) Trust Anchor, together with
+    /// everything that the online side needs to act on its behalf, with the
+    /// identity key of the Trust Anchor.
+    pub fn ta_offline_bundle(
+        &self,
+        ta: TaCertDetails,
+        responses: Vec<TaMessage>,
+    ) -> KrillResult<TaResponseBundle> {
+        let trust_anchor = self.get_trust_anchor()?;
+        let ta_uri = trust_anchor.trust_anchor_cert()?.uri().clone();
+
+        let responses = TaResponses::new(ta, ta_uri, responses, trust_anchor.all_objects());
+        let content = serde_json::to_vec(&responses).map_err(Error::JsonError)?;
+
+        let cms = SignedMessageBuilder::create(
+            trust_anchor.id_key(),
+            self.signer.read().unwrap().deref(),
+            Bytes::from(content),
+        )
+        .map_err(Error::signer)?
+        .as_bytes();
+
+        Ok(TaResponseBundle::new(cms))
+    }
+
+    /// Verifies a bundle signed by an offline Trust Anchor with the given
+    /// identity certificate, without processing it, and returns its content.
+    ///
+    /// The bundle must have responses, which must be valid replies to CAs
+    /// that have the Trust Anchor as their parent, and its objects must be
+    /// valid objects published under the Trust Anchor certificate.
+    pub fn ta_offline_responses_verify(
+        &self,
+        bundle: &TaResponseBundle,
+        id_cert: &IdCert,
+    ) -> KrillResult<TaResponses> {
+        let invalid = |msg: &str| Error::TaOfflineResponseInvalid(msg.to_string());
+
+        let msg = SignedMessage::decode(bundle.to_bytes(), false)
+            .map_err(|_| invalid("cannot decode bundle"))?;
+        msg.validate(id_cert)
+            .map_err(|_| invalid("bundle is not signed by the Trust Anchor"))?;
+        let responses: TaResponses = serde_json::from_slice(msg.content().to_bytes().as_ref())
+            .map_err(|e| Error::TaOfflineResponseInvalid(e.to_string()))?;
+
+        if responses.responses().is_empty() {
+            return Err(invalid("bundle has no responses"));
+        }
+        self.ta_offline_replies(responses.responses(), id_cert)?;
+
+        let ta = responses.ta();
+        if ta.cert().verify_ta_ref(false).is_err() {
+            return Err(invalid("Trust Anchor certificate is not self-signed"));
+        }
+
+        let ta_cert = RcvdCert::new(
+            ta.cert().clone(),
+            responses.ta_uri().clone(),
+            ta.resources().clone(),
+        );
+        let mut validator = PublishedObjectsValidator::new(responses.objects(), Time::now());
+        validator.validate_cert(&ta_cert);
+        if let Some(object) = validator.finish().first() {
+            return Err(Error::TaOfflineResponseInvalid(format!(
+                "invalid published object {}",
+                object
+            )));
+        }
+
+        Ok(responses)
+    }
+
+    /// Processes the responses by an offline Trust Anchor, in the same way
+    /// as responses to requests sent to a parent over HTTPS. All responses
+    /// are verified before any of them is processed, so that an invalid
+    /// bundle does not leave CAs partially updated. The bundle with these
+    /// responses must have been verified with ta_offline_responses_verify.
+    pub fn ta_offline_responses(
+        &self,
+        responses: &[TaMessage],
+        id_cert: &IdCert,
+    ) -> KrillResult<()> {
+        for (response, rcn, reply) in self.ta_offline_replies(responses, id_cert)? {
+            let handle = response.ca();
+            let parent = response.parent();
+
+            match reply {
+                rfc6492::Res::List(entitlements) => {
+                    self.update_resource_classes(handle, parent.clone(), entitlements)?;
+                }
+                rfc6492::Res::Issue(issue_response) => {
+                    let rcn = rcn.unwrap(); // resolved when verifying
+                    let (_, _, _, issued) = issue_response.unwrap();
+                    let received = RcvdCert::from(issued);
+
+                    let cmd = CmdDet::upd_received_cert(handle, rcn, received, self.signer.clone());
+                    self.send_command(cmd)?;
+                }
+                rfc6492::Res::Revoke(revoke_response) => {
+                    let rcn = rcn.unwrap(); // resolved when verifying
+                    let cmd = CmdDet::key_roll_finish(handle, rcn, revoke_response);
+                    self.send_command(cmd)?;
+                }
+                rfc6492::Res::NotPerformed(e) => {
+                    error!("Offline TA did not perform request for {}: {}", response, e)
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the responses by an offline Trust Anchor, and returns them
+    /// with their replies, and the resource classes that certificates and
+    /// revocations are for.
+    fn ta_offline_replies<'a>(
+        &self,
+        responses: &'a [TaMessage],
+        id_cert: &IdCert,
+    ) -> KrillResult<Vec<(&'a TaMessage, Option<ResourceClassName>, rfc6492::Res)>> {
+        let mut replies = vec![];
+
+        for response in responses.iter() {
+            let handle = response.ca();
+            let parent = response.parent();
+
+            let ca = self.get_ca(handle)?;
+            let parent_res = match ca.parent(parent)? {
+                ParentCaContact::Offline(parent_res) => parent_res,
+                _ => return Err(Error::CaParentNotOffline(handle.clone(), parent.clone())),
+            };
+
+            if parent_res.id_cert() != id_cert {
+                return Err(Error::TaOfflineResponseInvalid(format!(
+                    "identity certificate does not match parent '{}' of CA '{}'",
+                    parent, handle
+                )));
+            }
+
+            let msg = SignedMessage::decode(response.to_bytes(), false)
+                .map_err(|e| Error::TaOfflineResponseInvalid(e.to_string()))?;
+            msg.validate(parent_res.id_cert())
+                .map_err(|e| Error::TaOfflineResponseInvalid(e.to_string()))?;
+
+            let reply = rfc6492::Message::from_signed_message(&msg)
+                .map_err(|e| Error::TaOfflineResponseInvalid(e.to_string()))?
+                .into_reply()
+                .map_err(|e| Error::TaOfflineResponseInvalid(e.to_string()))?;
+
+            // Certificates and revocations must be for known resource classes
+            let rcn = match &reply {
+                rfc6492::Res::Issue(issue_response) => {
+                    Some(ca.rc_name_for_parent_class(parent, issue_response.class_name())?)
+                }
+                rfc6492::Res::Revoke(revoke_response) => {
+                    Some(ca.rc_name_for_parent_class(parent, revoke_response.class_name())?)
+                }
+                _ => None,
+            };
+
+            replies.push((response, rcn, reply));
+        }
+
+        Ok(replies)
+    }
+}
+
 /// # Support sending publication messages, and verifying responses.
 ///
 impl<S: Signer> CaServer<S> {
@@ -996,29 +1620,214 @@ impl<S: Signer> CaServer<S> {
         &self,
         handle: Handle,
         updates: RouteAuthorizationUpdates,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let cmd = CmdDet::route_authorizations_update(&handle, updates, self.signer.clone())
+            .with_actor(actor);
+        self.send_command(cmd)
+    }
+
+    /// Update the routes authorized by a CA so that they match the desired
+    /// definitions, and return the updates that this took. The updates are
+    /// applied as one command, and only if the CA was not changed by anyone
+    /// else in the meantime. Nothing is changed in case of a dry run.
+    pub fn ca_routes_sync(
+        &self,
+        handle: Handle,
+        desired: &HashSet<RoaDefinition>,
+        dry_run: bool,
+        actor: &str,
+    ) -> KrillResult<RoaDefinitionUpdates> {
+        let ca = self.get_ca(&handle)?;
+        let updates = RoaDefinitionUpdates::between(&ca.roa_definitions(), desired);
+
+        if !dry_run && !updates.is_empty() {
+            let cmd = CmdDet::route_authorizations_update_at_version(
+                &handle,
+                ca.version(),
+                updates.clone().into(),
+                self.signer.clone(),
+            )
+            .with_actor(actor);
+
+            self.send_command(cmd).map_err(|e| match e {
+                Error::AggregateStoreError(AggregateStoreError::ConcurrentModification(_)) => {
+                    Error::CaAuthorisationConcurrentUpdate(handle.clone())
+                }
+                e => e,
+            })?;
+        }
+
+        Ok(updates)
+    }
+}
+
+/// # Support ASPA functions
+///
+impl<S: Signer> CaServer<S> {
+    /// Add or replace ASPA definitions, and remove definitions for customer
+    /// ASNs, for a CA.
+    pub fn ca_aspas_update(
+        &self,
+        handle: Handle,
+        updates: AspaDefinitionUpdates,
+        actor: &str,
     ) -> KrillResult<()> {
-        let cmd = CmdDet::route_authorizations_update(&handle, updates, self.signer.clone());
+        let cmd = CmdDet::aspas_update(&handle, updates, self.signer.clone()).with_actor(actor);
+        self.send_command(cmd)
+    }
+
+    /// Update the providers for the ASPA definition of a customer ASN.
+    pub fn ca_aspas_update_providers(
+        &self,
+        handle: Handle,
+        customer: AsNumber,
+        update: AspaProvidersUpdate,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let cmd = CmdDet::aspas_update_providers(&handle, customer, update, self.signer.clone())
+            .with_actor(actor);
         self.send_command(cmd)
     }
 }
 
+/// # Support BGPsec router certificate functions
+///
+impl<S: Signer> CaServer<S> {
+    /// Add and remove BGPsec router certificate definitions for a CA.
+    pub fn ca_bgpsec_update_definitions(
+        &self,
+        handle: Handle,
+        updates: BgpSecDefinitionUpdates,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let cmd = CmdDet::bgpsec_update_definitions(&handle, updates, self.signer.clone())
+            .with_actor(actor);
+        self.send_command(cmd)
+    }
+}
 
-//------------ RoaPrefixGroupingStrategy -----------------------------------
+/// # Support Resource Signed Checklist functions
+///
+impl<S: Signer> CaServer<S> {
+    /// Signs an RPKI Signed Checklist for resources held by a CA. The signed
+    /// object is returned to the caller, and not published.
+    pub fn ca_sign_checklist(
+        &self,
+        handle: &Handle,
+        request: RscRequest,
+    ) -> KrillResult<SignedChecklist> {
+        let ca = self.get_ca(handle)?;
+        let signer = self.signer.read().unwrap();
+        ca.sign_checklist(request, signer.deref())
+    }
+}
 
-/// Defines the prefix grouping strategy to be used during ROA creation/update.
+/// # Timing of published objects and issued certificates
 ///
-/// RoaPerPrefix: One ROA per prefix (and ASN).
+impl<S: Signer> CaServer<S> {
+    /// Returns the timing used by a CA, and whether it is specific to the CA.
+    pub fn ca_timing(&self, handle: &Handle) -> KrillResult<CaTimingInfo> {
+        let ca = self.get_ca(handle)?;
+        Ok(CaTimingInfo::new(
+            ca.timing_config(),
+            ca.has_custom_timing(),
+        ))
+    }
+
+    /// Sets the timing for a CA, or reverts to the server timing if None.
+    pub fn ca_timing_update(
+        &self,
+        handle: Handle,
+        timing: Option<TimingConfig>,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let cmd = CmdDet::timing_update(&handle, timing).with_actor(actor);
+        self.send_command(cmd)
+    }
+
+    /// Returns how often all CAs should be republished (if needed), so that
+    /// this happens a number of times within the shortest republish threshold
+    /// used by any CA. This is one hour for the default threshold.
+    pub fn republish_interval(&self) -> std::time::Duration {
+        let shortest = self
+            .ca_store
+            .list()
+            .iter()
+            .filter_map(|handle| self.get_ca(handle).ok())
+            .map(|ca| ca.timing_config().publish_threshold())
+            .min()
+            .unwrap_or_else(|| TimingConfig::default().publish_threshold());
+
+        std::time::Duration::from_secs((shortest.num_seconds() / 8) as u64)
+    }
+}
+
+/// # ROA prefix grouping
 ///
-/// RoaPerAsn: Groups all prefixes authorisations for an ASN in a single ROA.
+impl<S: Signer> CaServer<S> {
+    /// Returns the ROA grouping used by a CA, and whether it is specific to
+    /// the CA.
+    pub fn ca_roa_grouping(&self, handle: &Handle) -> KrillResult<RoaGroupingInfo> {
+        let ca = self.get_ca(handle)?;
+        Ok(RoaGroupingInfo::new(
+            ca.roa_prefix_grouping_strategy(),
+            ca.has_custom_roa_grouping(),
+        ))
+    }
+
+    /// Sets the ROA grouping for a CA, or reverts to the server strategy if
+    /// None. The ROAs of the CA are re-issued in the new grouping.
+    pub fn ca_roa_grouping_update(
+        &self,
+        handle: Handle,
+        strategy: Option<RoaPrefixGroupingStrategy>,
+        actor: &str,
+    ) -> KrillResult<()> {
+        let cmd =
+            CmdDet::roa_grouping_update(&handle, strategy, self.signer.clone()).with_actor(actor);
+        self.send_command(cmd)
+    }
+
+    /// Re-issues the ROAs of all CAs which are not grouped according to the
+    /// strategy used by the CA, e.g. because the server strategy changed.
+    pub fn ca_roa_grouping_migrate_all(&self) -> KrillResult<()> {
+        for ca in self.ca_list().cas() {
+            let cmd = CmdDet::roa_grouping_migrate(ca.handle(), self.signer.clone());
+            if let Err(e) = self.send_command(cmd) {
+                error!("Could not migrate ROAs for CA '{}': {}", ca.handle(), e)
+            }
+        }
+        Ok(())
+    }
+}
+
+/// # Snapshots and archiving of events
 ///
-#[derive(Clone, Eq, PartialEq, Debug, Copy, Display, Deserialize, Serialize)]
-pub enum RoaPrefixGroupingStrategy {
-    RoaPerPrefix,
-    RoaPerAsn
+impl<S: Signer> CaServer<S> {
+    /// Saves a snapshot of the latest version of all CAs.
+    pub fn snapshot_all(&self) -> KrillResult<()> {
+        self.ca_store.snapshot_all()?;
+        Ok(())
+    }
+
+    /// Moves the commands of all CAs that were processed before the given
+    /// time, and their events, into compressed bundles. Returns the number
+    /// of archived commands.
+    pub fn compact(&self, before: Time) -> KrillResult<usize> {
+        let archived = self.ca_store.compact(before)?;
+        Ok(archived)
+    }
 }
 
-impl Default for RoaPrefixGroupingStrategy {
-    fn default() -> Self { Self::RoaPerPrefix }
+//------------ PublishedValidation -------------------------------------------
+
+/// The objects published by a CA which failed validation, at a version of
+/// the CA and at a time.
+struct PublishedValidation {
+    version: u64,
+    time: Time,
+    invalid: Vec<InvalidObject>,
 }
 
 //------------ Tests ---------------------------------------------------------
@@ -1030,9 +1839,10 @@ mod tests {
 
     use std::sync::{Arc, RwLock};
 
-    use crate::commons::api::RepoInfo;
+    use crate::commons::api::{RepoInfo, TimingConfig};
     use crate::commons::util::softsigner::OpenSslSigner;
     use crate::commons::util::test;
+    use crate::constants::SNAPSHOT_EVENTS;
 
     #[test]
     fn add_ta() {
@@ -1043,7 +1853,8 @@ mod tests {
             let event_queue = Arc::new(EventQueueListener::in_mem());
 
             let server = CaServer::<OpenSslSigner>::build(&d, event_queue, signer, 
-                    RoaPrefixGroupingStrategy::RoaPerPrefix).unwrap();
+                    RoaPrefixGroupingStrategy::RoaPerPrefix, TimingConfig::default(),
+                    SNAPSHOT_EVENTS, KeyStoreType::Disk).unwrap();
 
             let repo_info = {
                 let base_uri = test::rsync("rsync://localhost/repo/ta/");
@@ -12,7 +12,9 @@ use rpki::manifest::FileAndHash;
 use rpki::uri;
 use rpki::x509::{Name, Serial, Time, Validity};
 
-use crate::commons::api::{IssuedCert, ReplacedObject, RequestResourceLimit, ResourceSet};
+use crate::commons::api::{
+    IssuedCert, ReplacedObject, RequestResourceLimit, ResourceSet, TimingConfig,
+};
 use crate::commons::error::Error;
 use crate::commons::KrillResult;
 use crate::daemon::ca::{self, CertifiedKey};
@@ -140,6 +142,7 @@ impl SignSupport {
         limit: RequestResourceLimit,
         replaces: Option<ReplacedObject>,
         signing_key: &CertifiedKey,
+        timing: &TimingConfig,
         signer: &S,
     ) -> KrillResult<IssuedCert> {
         let (ca_repository, rpki_manifest, rpki_notify, pub_key) = csr.unpack();
@@ -155,7 +158,8 @@ impl SignSupport {
         let serial = { Serial::random(signer).map_err(ca::Error::signer)? };
         let issuer = signing_cert.cert().subject().clone();
 
-        let validity = Validity::new(Time::five_minutes_ago(), Time::next_year());
+        let not_after = Time::now() + timing.child_certificate_valid();
+        let validity = Validity::new(Time::five_minutes_ago(), not_after);
 
         let subject = Some(Name::from_pub_key(&pub_key));
 
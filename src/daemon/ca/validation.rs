@@ -0,0 +1,374 @@
+//! Relying party style validation of the objects that a CA publishes.
+//!
+//! This helps to find broken output before relying parties do. Validation
+//! starts from the certificates that the CA received from its parents for
+//! its keys, and uses the objects that the CA believes it has published.
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+
+use bytes::Bytes;
+
+use bcder::string::OctetString;
+use bcder::{decode, Mode, Oid, Tag};
+
+use rpki::cert::Cert;
+use rpki::crl::Crl;
+use rpki::crypto::{DigestAlgorithm, KeyIdentifier, PublicKey, Signature, SignatureAlgorithm};
+use rpki::manifest::Manifest;
+use rpki::oid;
+use rpki::sigobj::{MessageDigest, SignedAttrs, SignedObject};
+use rpki::uri;
+use rpki::x509::{Time, ValidationError};
+
+use crate::commons::api::rrdp::PublishElement;
+use crate::commons::api::{RcvdCert, ResourceSet};
+use crate::daemon::ca::CertifiedKey;
+
+//------------ InvalidObject -------------------------------------------------
+
+/// A published object that fails validation, or an object that should have
+/// been published but was not.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvalidObject {
+    uri: uri::Rsync,
+    reason: String,
+}
+
+impl InvalidObject {
+    fn new(uri: &uri::Rsync, reason: impl fmt::Display) -> Self {
+        InvalidObject {
+            uri: uri.clone(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn uri(&self) -> &uri::Rsync {
+        &self.uri
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for InvalidObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.uri, self.reason)
+    }
+}
+
+//------------ PublishedObjectsValidator -------------------------------------
+
+/// Validates published objects for each key of a CA. For each key:
+///
+/// - the manifest and CRL must be published where the certificate for the
+///   key says, and must not be stale;
+/// - the CRL must be signed by the key;
+/// - every manifest entry must be published with the listed hash;
+/// - signed objects, including the manifest, must be signed with their EE
+///   certificate;
+/// - certificates, including the EE certificates of signed objects, must
+///   be signed by the key, must refer to the CRL and certificate of the key,
+///   must not be revoked, must only hold resources certified to the key, and
+///   must be valid now.
+///
+/// Finally, every published object must be on a manifest.
+pub struct PublishedObjectsValidator {
+    objects: HashMap<String, (uri::Rsync, Bytes)>,
+    on_manifest: HashSet<String>,
+    invalid: Vec<InvalidObject>,
+    now: Time,
+}
+
+impl PublishedObjectsValidator {
+    /// Creates a validator for the published objects, which checks whether
+    /// they are valid at the given time.
+    pub fn new(published: &[PublishElement], now: Time) -> Self {
+        let objects = published
+            .iter()
+            .map(|el| {
+                let uri = el.uri().clone();
+                (uri.to_string(), (uri, el.base64().to_bytes()))
+            })
+            .collect();
+
+        PublishedObjectsValidator {
+            objects,
+            on_manifest: HashSet::new(),
+            invalid: vec![],
+            now,
+        }
+    }
+
+    /// Validates the manifest and CRL for the key, and all objects listed
+    /// on the manifest.
+    pub fn validate_key(&mut self, key: &CertifiedKey) {
+        self.validate_cert(key.incoming_cert())
+    }
+
+    /// Validates the manifest and CRL published under the certificate, and
+    /// all objects listed on the manifest.
+    pub fn validate_cert(&mut self, incoming: &RcvdCert) {
+        self.check_validity(incoming.uri(), incoming.cert());
+
+        let crl = self.validate_crl(incoming);
+
+        let mft_uri = match incoming.cert().rpki_manifest() {
+            Some(uri) => uri.clone(),
+            None => {
+                self.add(incoming.uri(), "certificate has no manifest URI");
+                return;
+            }
+        };
+        self.on_manifest.insert(mft_uri.to_string());
+
+        let mft = match self.object(&mft_uri, "manifest") {
+            None => return,
+            Some(bytes) => match Manifest::decode(bytes.clone(), false) {
+                Ok(mft) => {
+                    self.check_signed_object(&mft_uri, bytes, mft.cert());
+                    mft
+                }
+                Err(_) => {
+                    self.add(&mft_uri, "cannot decode manifest");
+                    return;
+                }
+            },
+        };
+
+        self.check_issued(&mft_uri, mft.cert(), incoming, crl.as_ref(), true);
+        if mft.content().next_update() < self.now {
+            self.add(&mft_uri, "manifest is stale");
+        }
+
+        for entry in mft.content().iter() {
+            let uri = incoming.ca_repository().join(entry.file());
+            self.on_manifest.insert(uri.to_string());
+
+            let bytes = match self.object(&uri, "manifest entry") {
+                Some(bytes) => bytes,
+                None => continue,
+            };
+
+            let digest = DigestAlgorithm::default().digest(&bytes);
+            if digest.as_ref() != entry.hash().as_ref() {
+                self.add(&uri, "hash does not match manifest");
+            }
+
+            let name = uri.to_string();
+            if name.ends_with(".cer") {
+                match Cert::decode(bytes) {
+                    Ok(cert) => self.check_issued(&uri, &cert, incoming, crl.as_ref(), false),
+                    Err(_) => self.add(&uri, "cannot decode certificate"),
+                }
+            } else if name.ends_with(".roa") || name.ends_with(".asa") {
+                match SignedObject::decode(bytes.clone(), false) {
+                    Ok(object) => {
+                        self.check_signed_object(&uri, bytes, object.cert());
+                        self.check_issued(&uri, object.cert(), incoming, crl.as_ref(), true)
+                    }
+                    Err(_) => self.add(&uri, "cannot decode signed object"),
+                }
+            }
+        }
+    }
+
+    /// Returns all invalid objects, including published objects which are
+    /// not on any manifest.
+    pub fn finish(mut self) -> Vec<InvalidObject> {
+        let mut not_on_manifest: Vec<InvalidObject> = self
+            .objects
+            .iter()
+            .filter(|(name, _)| !self.on_manifest.contains(*name))
+            .map(|(_, (uri, _))| InvalidObject::new(uri, "published, but not on any manifest"))
+            .collect();
+
+        self.invalid.append(&mut not_on_manifest);
+        self.invalid
+            .sort_by(|a, b| a.uri.to_string().cmp(&b.uri.to_string()));
+        self.invalid
+    }
+
+    fn validate_crl(&mut self, incoming: &RcvdCert) -> Option<Crl> {
+        let crl_uri = incoming.crl_uri();
+        let crl = match Crl::decode(self.object(&crl_uri, "CRL")?) {
+            Ok(crl) => crl,
+            Err(_) => {
+                self.add(&crl_uri, "cannot decode CRL");
+                return None;
+            }
+        };
+
+        if crl
+            .validate(incoming.cert().subject_public_key_info())
+            .is_err()
+        {
+            self.add(&crl_uri, "CRL is not signed by the key of the CA");
+        }
+        if crl.next_update() < self.now {
+            self.add(&crl_uri, "CRL is stale");
+        }
+
+        Some(crl)
+    }
+
+    /// Checks a certificate issued under the key, where 'ee' indicates that
+    /// this is the EE certificate of a signed object published at the uri.
+    fn check_issued(
+        &mut self,
+        uri: &uri::Rsync,
+        cert: &Cert,
+        incoming: &RcvdCert,
+        crl: Option<&Crl>,
+        ee: bool,
+    ) {
+        self.check_validity(uri, cert);
+
+        if cert.verify_signature(incoming.cert(), false).is_err() {
+            self.add(uri, "certificate is not signed by the key of the CA");
+        }
+
+        let crl_uri = incoming.crl_uri();
+        if cert.crl_uri() != Some(&crl_uri) {
+            self.add(uri, format!("CRL URI does not match '{}'", crl_uri));
+        }
+        if cert.ca_issuer() != Some(incoming.uri()) {
+            let msg = format!("issuer URI does not match '{}'", incoming.uri());
+            self.add(uri, msg);
+        }
+        if ee && cert.signed_object() != Some(uri) {
+            self.add(uri, "signed object URI does not match");
+        }
+
+        if let Some(crl) = crl {
+            if crl.contains(cert.serial_number()) {
+                self.add(uri, "certificate is revoked");
+            }
+        }
+
+        // Inherited resources are always held.
+        if let Ok(resources) = ResourceSet::try_from(cert) {
+            if !incoming.resources().contains(&resources) {
+                let msg = format!("resources '{}' are not held by the CA", resources);
+                self.add(uri, msg);
+            }
+        }
+    }
+
+    /// Checks that the signed object published at the uri is signed with
+    /// the key of its EE certificate.
+    fn check_signed_object(&mut self, uri: &uri::Rsync, bytes: Bytes, ee: &Cert) {
+        let verified = CmsSignature::decode(bytes)
+            .map_err(|_| ValidationError)
+            .and_then(|cms| cms.verify(ee.subject_public_key_info()));
+        if verified.is_err() {
+            self.add(uri, "signature does not match EE certificate");
+        }
+    }
+
+    fn check_validity(&mut self, uri: &uri::Rsync, cert: &Cert) {
+        let validity = cert.validity();
+        if validity.not_before() > self.now {
+            self.add(uri, "certificate is not yet valid");
+        } else if validity.not_after() < self.now {
+            self.add(uri, "certificate has expired");
+        }
+    }
+
+    fn object(&mut self, uri: &uri::Rsync, kind: &str) -> Option<Bytes> {
+        match self.objects.get(&uri.to_string()) {
+            Some((_, bytes)) => Some(bytes.clone()),
+            None => {
+                self.add(uri, format!("{} is not published", kind));
+                None
+            }
+        }
+    }
+
+    fn add(&mut self, uri: &uri::Rsync, reason: impl fmt::Display) {
+        self.invalid.push(InvalidObject::new(uri, reason));
+    }
+}
+
+//------------ CmsSignature --------------------------------------------------
+
+/// The signature of the CMS of a signed object, e.g. a manifest or ROA.
+///
+/// The rpki crate only verifies this signature when it validates a signed
+/// object under a resource certificate that was validated all the way from
+/// a trust anchor, but a CA only has the certificates for its own keys.
+struct CmsSignature {
+    digest_algorithm: DigestAlgorithm,
+    content: OctetString,
+    signed_attrs: SignedAttrs,
+    message_digest: MessageDigest,
+    signature: Signature,
+}
+
+impl CmsSignature {
+    fn decode(bytes: Bytes) -> Result<Self, decode::Error> {
+        Mode::Ber.decode(bytes, Self::take_from)
+    }
+
+    fn take_from<S: decode::Source>(cons: &mut decode::Constructed<S>) -> Result<Self, S::Err> {
+        cons.take_sequence(|cons| {
+            oid::SIGNED_DATA.skip_if(cons)?; // contentType
+            cons.take_constructed_if(Tag::CTX_0, |cons| {
+                cons.take_sequence(|cons| {
+                    cons.skip_u8_if(3)?; // version -- must be 3
+                    let digest_algorithm = DigestAlgorithm::take_set_from(cons)?;
+                    let (content_type, content) = cons.take_sequence(|cons| {
+                        // encapContentInfo
+                        Ok((
+                            Oid::take_from(cons)?,
+                            cons.take_constructed_if(Tag::CTX_0, OctetString::take_from)?,
+                        ))
+                    })?;
+                    cons.take_constructed_if(Tag::CTX_0, Cert::take_from)?; // certificates
+
+                    cons.take_set(|cons| {
+                        // signerInfos
+                        cons.take_sequence(|cons| {
+                            cons.skip_u8_if(3)?;
+                            cons.take_value_if(Tag::CTX_0, KeyIdentifier::from_content)?;
+                            if DigestAlgorithm::take_from(cons)? != digest_algorithm {
+                                return Err(decode::Malformed.into());
+                            }
+                            let attrs = SignedAttrs::take_from(cons)?;
+                            if attrs.2 != content_type {
+                                return Err(decode::Malformed.into());
+                            }
+                            let signature = Signature::new(
+                                SignatureAlgorithm::cms_take_from(cons)?,
+                                OctetString::take_from(cons)?.into_bytes(),
+                            );
+                            Ok(CmsSignature {
+                                digest_algorithm,
+                                content,
+                                signed_attrs: attrs.0,
+                                message_digest: attrs.1,
+                                signature,
+                            })
+                        })
+                    })
+                })
+            })
+        })
+    }
+
+    /// Verifies the digest of the content, and the signature over the
+    /// signed attributes, in the same way as for RFC 6492 messages.
+    fn verify(&self, key: &PublicKey) -> Result<(), ValidationError> {
+        let digest = {
+            let mut context = self.digest_algorithm.start();
+            self.content.iter().for_each(|x| context.update(x));
+            context.finish()
+        };
+        if digest.as_ref() != self.message_digest.as_ref() {
+            return Err(ValidationError);
+        }
+        let msg = self.signed_attrs.encode_verify();
+        key.verify(&msg, &self.signature).map_err(Into::into)
+    }
+}
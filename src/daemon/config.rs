@@ -6,7 +6,8 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use clap::{App, Arg};
+use bytes::Bytes;
+use clap::{App, Arg, ArgMatches};
 use log::{error, LevelFilter};
 use serde::de;
 use serde::{Deserialize, Deserializer};
@@ -16,11 +17,15 @@ use toml;
 
 use rpki::uri;
 
-use crate::commons::api::Token;
+use crate::commons::api::{ApiToken, TimingConfig, Token};
+use crate::commons::eventsourcing::KeyStoreType;
+use crate::commons::remote::id::IdCert;
 use crate::commons::util::ext_serde;
 use crate::constants::*;
+use crate::daemon::auth::{Authorizer, ADMIN_TOKEN_ACTOR};
 use crate::daemon::http::ssl;
 use crate::daemon::ca::RoaPrefixGroupingStrategy;
+use crate::daemon::oidc::OidcRoleMapping;
 //------------ ConfigDefaults ------------------------------------------------
 
 pub struct ConfigDefaults;
@@ -97,6 +102,70 @@ impl ConfigDefaults {
     fn roa_prefix_grouping_strategy() -> RoaPrefixGroupingStrategy {
         RoaPrefixGroupingStrategy::RoaPerPrefix
     }
+
+    fn timing_publish_next_hours() -> u32 {
+        PUBLISH_NEXT_HOURS
+    }
+
+    fn timing_publish_valid_days() -> u32 {
+        PUBLISH_VALID_DAYS
+    }
+
+    fn timing_publish_threshold_hours() -> u32 {
+        PUBLISH_THRESHOLD_HOURS
+    }
+
+    fn timing_child_certificate_valid_weeks() -> u32 {
+        CHILD_CERTIFICATE_VALIDITY_WEEKS
+    }
+
+    fn timing_child_certificate_reissue_weeks() -> u32 {
+        CHILD_CERTIFICATE_REISSUE_WEEKS
+    }
+
+    fn snapshot_events() -> u64 {
+        SNAPSHOT_EVENTS
+    }
+
+    fn store_type() -> KeyStoreType {
+        KeyStoreType::Disk
+    }
+
+    fn signer_type() -> SignerType {
+        SignerType::OpenSsl
+    }
+
+    fn pkcs11_pin() -> Option<String> {
+        env::var("KRILL_PKCS11_PIN").ok()
+    }
+
+    fn encrypt_keys() -> bool {
+        false
+    }
+
+    fn keys_passphrase() -> Option<String> {
+        env::var("KRILL_KEYS_PASSPHRASE").ok()
+    }
+
+    fn oidc_client_secret() -> Option<String> {
+        env::var("KRILL_OIDC_CLIENT_SECRET").ok()
+    }
+
+    fn oidc_scope() -> String {
+        "openid email".to_string()
+    }
+
+    fn oidc_name_claim() -> String {
+        "email".to_string()
+    }
+
+    fn oidc_role_claim() -> String {
+        "groups".to_string()
+    }
+
+    fn oidc_session_seconds() -> i64 {
+        8 * 3600
+    }
 }
 
 //------------ Config --------------------------------------------------------
@@ -155,6 +224,9 @@ pub struct Config {
     #[serde(default = "ConfigDefaults::auth_token")]
     pub auth_token: Token,
 
+    #[serde(default)]
+    pub api_tokens: Vec<ApiToken>,
+
     #[serde(default = "ConfigDefaults::ca_refresh")]
     pub ca_refresh: u32,
 
@@ -169,6 +241,75 @@ pub struct Config {
 
     #[serde(default = "ConfigDefaults::roa_prefix_grouping_strategy")]
     pub roa_prefix_grouping_strategy: RoaPrefixGroupingStrategy,
+
+    #[serde(default = "ConfigDefaults::timing_publish_next_hours")]
+    timing_publish_next_hours: u32,
+
+    #[serde(default = "ConfigDefaults::timing_publish_valid_days")]
+    timing_publish_valid_days: u32,
+
+    #[serde(default = "ConfigDefaults::timing_publish_threshold_hours")]
+    timing_publish_threshold_hours: u32,
+
+    #[serde(default = "ConfigDefaults::timing_child_certificate_valid_weeks")]
+    timing_child_certificate_valid_weeks: u32,
+
+    #[serde(default = "ConfigDefaults::timing_child_certificate_reissue_weeks")]
+    timing_child_certificate_reissue_weeks: u32,
+
+    #[serde(default = "ConfigDefaults::snapshot_events")]
+    pub snapshot_events: u64,
+
+    pub archive_after_days: Option<u32>,
+
+    #[serde(default = "ConfigDefaults::store_type")]
+    pub store_type: KeyStoreType,
+
+    pub bgp_dump_file: Option<PathBuf>,
+
+    pub ta_offline_id_cert: Option<PathBuf>,
+
+    #[serde(default = "ConfigDefaults::signer_type")]
+    pub signer_type: SignerType,
+
+    pub pkcs11_lib: Option<PathBuf>,
+
+    pub pkcs11_slot: Option<u64>,
+
+    pub pkcs11_token_label: Option<String>,
+
+    #[serde(default = "ConfigDefaults::pkcs11_pin")]
+    pub pkcs11_pin: Option<String>,
+
+    #[serde(default = "ConfigDefaults::encrypt_keys")]
+    pub encrypt_keys: bool,
+
+    #[serde(default = "ConfigDefaults::keys_passphrase")]
+    keys_passphrase: Option<String>,
+
+    keys_passphrase_file: Option<PathBuf>,
+
+    pub oidc_issuer: Option<String>,
+
+    pub oidc_client_id: Option<String>,
+
+    #[serde(default = "ConfigDefaults::oidc_client_secret")]
+    pub oidc_client_secret: Option<String>,
+
+    #[serde(default = "ConfigDefaults::oidc_scope")]
+    pub oidc_scope: String,
+
+    #[serde(default = "ConfigDefaults::oidc_name_claim")]
+    pub oidc_name_claim: String,
+
+    #[serde(default = "ConfigDefaults::oidc_role_claim")]
+    pub oidc_role_claim: String,
+
+    #[serde(default)]
+    pub oidc_role_mappings: Vec<OidcRoleMapping>,
+
+    #[serde(default = "ConfigDefaults::oidc_session_seconds")]
+    pub oidc_session_seconds: i64,
 }
 
 /// # Accessors
@@ -213,6 +354,84 @@ impl Config {
     pub fn use_ta(&self) -> bool {
         self.use_ta
     }
+
+    /// Returns the timing used by all CAs, unless overridden for a CA.
+    pub fn timing_config(&self) -> TimingConfig {
+        TimingConfig::new(
+            self.timing_publish_next_hours,
+            self.timing_publish_valid_days,
+            self.timing_publish_threshold_hours,
+            self.timing_child_certificate_valid_weeks,
+            self.timing_child_certificate_reissue_weeks,
+        )
+    }
+
+    /// Returns the passphrase used to encrypt the openssl signer keys, if
+    /// key encryption is enabled. The passphrase is read from the
+    /// keys_passphrase_file, if set, or else keys_passphrase.
+    pub fn keys_passphrase(&self) -> Result<Option<String>, ConfigError> {
+        if !self.encrypt_keys {
+            return Ok(None);
+        }
+
+        let passphrase = match &self.keys_passphrase_file {
+            Some(path) => {
+                let mut passphrase = String::new();
+                File::open(path)
+                    .and_then(|mut f| f.read_to_string(&mut passphrase))
+                    .map_err(|e| {
+                        ConfigError::Other(format!(
+                            "Cannot read keys_passphrase_file '{}': {}",
+                            path.display(),
+                            e
+                        ))
+                    })?;
+                passphrase.trim().to_string()
+            }
+            None => self.keys_passphrase.clone().unwrap_or_default(),
+        };
+
+        if passphrase.is_empty() {
+            Err(ConfigError::other(
+                "encrypt_keys is set, but no passphrase was found: set keys_passphrase_file, keys_passphrase or KRILL_KEYS_PASSPHRASE",
+            ))
+        } else {
+            Ok(Some(passphrase))
+        }
+    }
+
+    /// Returns the identity certificate of the offline Trust Anchor, if
+    /// ta_offline_id_cert is set. The file has the certificate in PEM, as
+    /// shown by 'krillc show --ca ta' on the offline server.
+    pub fn ta_offline_id_cert(&self) -> Result<Option<IdCert>, ConfigError> {
+        let path = match &self.ta_offline_id_cert {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let err = |e: String| {
+            ConfigError::Other(format!(
+                "Cannot read ta_offline_id_cert '{}': {}",
+                path.display(),
+                e
+            ))
+        };
+
+        let mut pem = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut pem))
+            .map_err(|e| err(e.to_string()))?;
+
+        let base64: String = pem
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let der = base64::decode(&base64).map_err(|e| err(e.to_string()))?;
+
+        IdCert::decode(Bytes::from(der))
+            .map(Some)
+            .map_err(|_| err("not an identity certificate".to_string()))
+    }
 }
 
 /// # Create
@@ -234,11 +453,40 @@ impl Config {
         log_file.push("krill.log");
         let syslog_facility = ConfigDefaults::syslog_facility();
         let auth_token = Token::from("secret");
+        let api_tokens = vec![];
         let ca_refresh = 3600;
         let post_limit_api = ConfigDefaults::post_limit_api();
         let post_limit_rfc8181 = ConfigDefaults::post_limit_rfc8181();
         let post_limit_rfc6492 = ConfigDefaults::post_limit_rfc6492();
         let roa_prefix_grouping_strategy = ConfigDefaults::roa_prefix_grouping_strategy();
+        let timing_publish_next_hours = ConfigDefaults::timing_publish_next_hours();
+        let timing_publish_valid_days = ConfigDefaults::timing_publish_valid_days();
+        let timing_publish_threshold_hours = ConfigDefaults::timing_publish_threshold_hours();
+        let timing_child_certificate_valid_weeks =
+            ConfigDefaults::timing_child_certificate_valid_weeks();
+        let timing_child_certificate_reissue_weeks =
+            ConfigDefaults::timing_child_certificate_reissue_weeks();
+        let snapshot_events = ConfigDefaults::snapshot_events();
+        let archive_after_days = None;
+        let store_type = ConfigDefaults::store_type();
+        let bgp_dump_file = None;
+        let ta_offline_id_cert = None;
+        let signer_type = ConfigDefaults::signer_type();
+        let pkcs11_lib = None;
+        let pkcs11_slot = None;
+        let pkcs11_token_label = None;
+        let pkcs11_pin = None;
+        let encrypt_keys = false;
+        let keys_passphrase = None;
+        let keys_passphrase_file = None;
+        let oidc_issuer = None;
+        let oidc_client_id = None;
+        let oidc_client_secret = None;
+        let oidc_scope = ConfigDefaults::oidc_scope();
+        let oidc_name_claim = ConfigDefaults::oidc_name_claim();
+        let oidc_role_claim = ConfigDefaults::oidc_role_claim();
+        let oidc_role_mappings = vec![];
+        let oidc_session_seconds = ConfigDefaults::oidc_session_seconds();
 
         Config {
             ip,
@@ -256,11 +504,38 @@ impl Config {
             log_file,
             syslog_facility,
             auth_token,
+            api_tokens,
             ca_refresh,
             post_limit_api,
             post_limit_rfc8181,
             post_limit_rfc6492,
             roa_prefix_grouping_strategy,
+            timing_publish_next_hours,
+            timing_publish_valid_days,
+            timing_publish_threshold_hours,
+            timing_child_certificate_valid_weeks,
+            timing_child_certificate_reissue_weeks,
+            snapshot_events,
+            archive_after_days,
+            store_type,
+            bgp_dump_file,
+            ta_offline_id_cert,
+            signer_type,
+            pkcs11_lib,
+            pkcs11_slot,
+            pkcs11_token_label,
+            pkcs11_pin,
+            encrypt_keys,
+            keys_passphrase,
+            keys_passphrase_file,
+            oidc_issuer,
+            oidc_client_id,
+            oidc_client_secret,
+            oidc_scope,
+            oidc_name_claim,
+            oidc_role_claim,
+            oidc_role_mappings,
+            oidc_session_seconds,
         }
     }
 
@@ -280,8 +555,8 @@ impl Config {
         config
     }
 
-    pub fn get_config_filename() -> String {
-        let matches = App::new(KRILL_SERVER_APP)
+    fn arg_matches<'a>() -> ArgMatches<'a> {
+        App::new(KRILL_SERVER_APP)
             .version(KRILL_VERSION)
             .arg(
                 Arg::with_name("config")
@@ -297,8 +572,33 @@ impl Config {
                     )
                     .required(false),
             )
-            .get_matches();
+            .arg(
+                Arg::with_name("encrypt-keys")
+                    .long("encrypt-keys")
+                    .help(
+                        "Encrypt all unencrypted keys in place, using the passphrase \
+                         from the config, and exit. Set 'encrypt_keys = true' in the \
+                         config before using this.",
+                    )
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("migrate-store")
+                    .long("migrate-store")
+                    .value_name("TYPE")
+                    .possible_values(&["disk", "sqlite"])
+                    .help(
+                        "Copy all CAs and the repository server from the other type of \
+                         store into a store of this type, and exit. Set 'store_type' \
+                         in the config to this type after using this.",
+                    )
+                    .required(false),
+            )
+            .get_matches()
+    }
 
+    pub fn get_config_filename() -> String {
+        let matches = Self::arg_matches();
         let config_file = matches
             .value_of("config")
             .unwrap_or(KRILL_DEFAULT_CONFIG_FILE);
@@ -306,6 +606,21 @@ impl Config {
         config_file.to_string()
     }
 
+    /// Returns true if the one-shot migration to encrypt existing keys was
+    /// requested, rather than starting the server.
+    pub fn encrypt_keys_requested() -> bool {
+        Self::arg_matches().is_present("encrypt-keys")
+    }
+
+    /// Returns the type of store to migrate to, if the one-shot migration of
+    /// the CAs and repository server between types of store was requested,
+    /// rather than starting the server.
+    pub fn migrate_store_requested() -> Option<KeyStoreType> {
+        Self::arg_matches()
+            .value_of("migrate-store")
+            .and_then(|s| KeyStoreType::from_str(s).ok())
+    }
+
     /// Creates the config (at startup). Panics in case of issues.
     pub fn create() -> Result<Self, ConfigError> {
         let config_file = Self::get_config_filename();
@@ -397,6 +712,119 @@ impl Config {
             ));
         }
 
+        self.timing_config()
+            .verify()
+            .map_err(|reason| ConfigError::Other(format!("Invalid timing config: {}", reason)))?;
+
+        if self.snapshot_events == 0 {
+            return Err(ConfigError::other("snapshot_events must be at least 1"));
+        }
+
+        if let Some(days) = self.archive_after_days {
+            let max = TIMING_MAX_YEARS * 365;
+            if days == 0 {
+                return Err(ConfigError::other("archive_after_days must be at least 1"));
+            } else if days > max {
+                return Err(ConfigError::Other(format!(
+                    "archive_after_days must not exceed {}",
+                    max
+                )));
+            }
+        }
+
+        for (idx, api_token) in self.api_tokens.iter().enumerate() {
+            Authorizer::verify_definition(&api_token.definition()).map_err(|reason| {
+                ConfigError::Other(format!(
+                    "Invalid api token '{}': {}",
+                    api_token.name(),
+                    reason
+                ))
+            })?;
+            if api_token.name() == ADMIN_TOKEN_ACTOR
+                || self.api_tokens[..idx]
+                    .iter()
+                    .any(|other| other.name() == api_token.name())
+            {
+                return Err(ConfigError::Other(format!(
+                    "Duplicate api token name '{}'",
+                    api_token.name()
+                )));
+            }
+            // The token identifies the actor, so it must be unique. Do not
+            // include the token itself in the error.
+            if api_token.token() == &self.auth_token
+                || self.api_tokens[..idx]
+                    .iter()
+                    .any(|other| other.token() == api_token.token())
+            {
+                return Err(ConfigError::Other(format!(
+                    "The token of api token '{}' is already used by another token",
+                    api_token.name()
+                )));
+            }
+        }
+
+        if self.signer_type == SignerType::Pkcs11 {
+            if !cfg!(feature = "hsm") {
+                return Err(ConfigError::other(
+                    "Cannot use pkcs11 signer, krill was built without the 'hsm' feature",
+                ));
+            }
+            if self.pkcs11_lib.is_none() {
+                return Err(ConfigError::other(
+                    "pkcs11_lib must be set when using the pkcs11 signer",
+                ));
+            }
+            if self.pkcs11_slot.is_none() && self.pkcs11_token_label.is_none() {
+                return Err(ConfigError::other(
+                    "pkcs11_slot or pkcs11_token_label must be set when using the pkcs11 signer",
+                ));
+            }
+            if self.pkcs11_pin.is_none() {
+                return Err(ConfigError::other(
+                    "pkcs11_pin (or KRILL_PKCS11_PIN) must be set when using the pkcs11 signer",
+                ));
+            }
+        }
+
+        if self.encrypt_keys {
+            if self.signer_type != SignerType::OpenSsl {
+                return Err(ConfigError::other(
+                    "encrypt_keys can only be used with the openssl signer",
+                ));
+            }
+            self.keys_passphrase()?;
+        }
+
+        self.ta_offline_id_cert()?;
+
+        if let Some(issuer) = &self.oidc_issuer {
+            if !self.test_mode && !issuer.to_lowercase().starts_with("https://") {
+                return Err(ConfigError::other(
+                    "oidc_issuer must use https unless test mode is used (KRILL_TEST)",
+                ));
+            }
+            if self.oidc_client_id.is_none() {
+                return Err(ConfigError::other(
+                    "oidc_client_id must be set when using oidc_issuer",
+                ));
+            }
+            if self.oidc_session_seconds <= 0 {
+                return Err(ConfigError::other(
+                    "oidc_session_seconds must be a positive number",
+                ));
+            }
+            for mapping in &self.oidc_role_mappings {
+                Authorizer::verify_role(mapping.role(), mapping.cas()).map_err(|reason| {
+                    ConfigError::Other(format!(
+                        "Invalid oidc role mapping for '{}': {}",
+                        mapping.claim_value(),
+                        reason
+                    ))
+                })?;
+            }
+        }
+
         Ok(())
     }
 
@@ -620,6 +1048,32 @@ impl<'de> Deserialize<'de> for HttpsMode {
     }
 }
 
+//------------ SignerType ----------------------------------------------------
+
+/// The implementation used for keeping keys and signing.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SignerType {
+    OpenSsl,
+    Pkcs11,
+}
+
+impl<'de> Deserialize<'de> for SignerType {
+    fn deserialize<D>(d: D) -> Result<SignerType, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(d)?;
+        match string.as_str() {
+            "openssl" => Ok(SignerType::OpenSsl),
+            "pkcs11" => Ok(SignerType::Pkcs11),
+            _ => Err(de::Error::custom(format!(
+                "expected \"openssl\", or \"pkcs11\", found: \"{}\"",
+                string
+            ))),
+        }
+    }
+}
+
 //------------ Tests ---------------------------------------------------------
 
 #[cfg(test)]
@@ -640,4 +1094,39 @@ mod tests {
         assert_eq!(c.socket_addr(), expected_socket_addr);
     }
 
+    #[test]
+    fn verify_archive_after_days() {
+        let mut config = Config::test_config(&PathBuf::from("work"));
+
+        config.archive_after_days = Some(90);
+        config.verify().unwrap();
+
+        config.archive_after_days = Some(0);
+        config.verify().unwrap_err();
+
+        config.archive_after_days = Some(u32::MAX);
+        config.verify().unwrap_err();
+    }
+
+    #[test]
+    fn verify_api_tokens() {
+        use crate::commons::api::{ApiTokenDefinition, Role};
+
+        let api_token = |name: &str, token: &str| {
+            let definition = ApiTokenDefinition::new(name.to_string(), Role::ReadOnly, vec![]);
+            ApiToken::new(definition, Token::from(token))
+        };
+
+        let mut config = Config::test_config(&PathBuf::from("work"));
+
+        config.api_tokens = vec![api_token("alice", "a"), api_token("bob", "b")];
+        config.verify().unwrap();
+
+        config.api_tokens = vec![api_token("alice", "a"), api_token("bob", "a")];
+        config.verify().unwrap_err();
+
+        config.api_tokens = vec![api_token("alice", config.auth_token.as_ref())];
+        config.verify().unwrap_err();
+    }
+
 }
@@ -1,18 +1,27 @@
 //! Process requests received, delegate, and wrap up the responses.
+use std::str::FromStr;
+
+use actix_web::http::cookie::{Cookie, SameSite};
 use actix_web::http::StatusCode;
-use actix_web::web::{self, Json, Path};
+use actix_web::web::{self, Json, Path, Query};
 use actix_web::HttpResponse;
 use bytes::Bytes;
 use serde::Serialize;
 
 use crate::commons::api::{
-    AddChildRequest, CertAuthInit, Handle, ParentCaContact, ParentCaReq, ParentHandle,
-    PublisherHandle, PublisherList, RepositoryUpdate, RoaDefinitionUpdates, UpdateChildRequest,
+    AddChildRequest, ApiTokenDefinition, AsNumber, AspaDefinitionUpdates, AspaProvidersUpdate,
+    AuditCriteria, BgpSecDefinitionUpdates, CertAuthHistoryPoint, CertAuthInit, Handle,
+    KeyRollEmergencyRequest, KeyRollPolicy, ParentCaContact, ParentCaReq, ParentHandle,
+    PublisherHandle, PublisherList, RepositoryUpdate, RoaDefinition, RoaDefinitionUpdates,
+    RoaFileOptions, RoaPrefixGroupingStrategy, RoaSyncOptions, RscRequest, TaRequestBundle,
+    TaRequestOptions, TaResponseBundle, TimingConfig, UpdateChildRequest,
 };
+use crate::commons::bgp::Announcements;
 use crate::commons::error::Error;
 use crate::commons::remote::{rfc6492, rfc8181, rfc8183};
-use crate::daemon::auth::Auth;
+use crate::daemon::auth::{Auth, Permission};
 use crate::daemon::http::server::AppServer;
+use crate::daemon::oidc::{OidcCallback, CSRF_COOKIE, SESSION_COOKIE};
 
 //------------ Support Functions ---------------------------------------------
 
@@ -73,28 +82,27 @@ pub fn health() -> HttpResponse {
 
 /// Returns the server health.
 pub fn api_authorized(server: web::Data<AppServer>, auth: Auth) -> HttpResponse {
-    if_api_allowed(&server, &auth, api_ok)
+    if_api_allowed(&server, &auth, Permission::Read, |_| api_ok())
 }
 
-fn if_allowed<F>(allowed: bool, op: F) -> HttpResponse
+/// Performs the operation if the token used has the permission needed, and
+/// passes on the actor (name of the token) for changes that are recorded.
+fn if_api_allowed<F>(
+    server: &web::Data<AppServer>,
+    auth: &Auth,
+    permission: Permission,
+    op: F,
+) -> HttpResponse
 where
-    F: FnOnce() -> HttpResponse,
+    F: FnOnce(&str) -> HttpResponse,
 {
-    if allowed {
-        op()
-    } else {
-        HttpResponse::Forbidden().finish()
+    let actor = server.read().actor(auth, &permission);
+    match actor {
+        Some(actor) => op(&actor),
+        None => HttpResponse::Forbidden().finish(),
     }
 }
 
-fn if_api_allowed<F>(server: &web::Data<AppServer>, auth: &Auth, op: F) -> HttpResponse
-where
-    F: FnOnce() -> HttpResponse,
-{
-    let allowed = server.read().is_api_allowed(auth);
-    if_allowed(allowed, op)
-}
-
 /// Produce prometheus style metrics
 pub fn metrics(server: web::Data<AppServer>) -> HttpResponse {
     let mut res = String::new();
@@ -197,6 +205,17 @@ pub fn metrics(server: web::Data<AppServer>) -> HttpResponse {
         ));
     }
 
+    res.push_str("\n");
+    res.push_str("# HELP krill_cas_invalid_objects number of invalid published objects for CA\n");
+    res.push_str("# TYPE krill_cas_invalid_objects gauge\n");
+    for (ca, status) in cas_status.iter() {
+        res.push_str(&format!(
+            "krill_cas_invalid_objects{{ca=\"{}\"}} {}\n",
+            ca,
+            status.invalid_object_count()
+        ));
+    }
+
     HttpResponse::Ok().body(res)
 }
 
@@ -205,6 +224,105 @@ pub fn server_info(server: web::Data<AppServer>) -> HttpResponse {
     render_json(server.read().server_info())
 }
 
+//------------ Admin: API Tokens ---------------------------------------------
+
+/// Returns the definitions of all named API tokens, without the tokens.
+pub fn api_tokens(server: web::Data<AppServer>, auth: Auth) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |_| {
+        render_json(server.read().api_tokens())
+    })
+}
+
+/// Adds a named API token, and returns it including the generated token.
+pub fn api_token_add(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    definition: Json<ApiTokenDefinition>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |_| {
+        render_json_res(server.write().api_token_add(definition.into_inner()))
+    })
+}
+
+/// Removes a named API token that was added through the API.
+#[allow(clippy::needless_pass_by_value)]
+pub fn api_token_remove(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    name: Path<String>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |_| {
+        render_empty_res(server.write().api_token_remove(&name))
+    })
+}
+
+//------------ Login: OpenID Connect -----------------------------------------
+
+/// Tells the UI whether users can log in using OpenID Connect.
+pub fn oidc_enabled(server: web::Data<AppServer>) -> HttpResponse {
+    render_json(json!({ "enabled": server.read().oidc_enabled() }))
+}
+
+/// Starts a login by sending the user to the identity provider.
+pub fn oidc_login(server: web::Data<AppServer>) -> HttpResponse {
+    match server.read().oidc_login_uri() {
+        Ok(uri) => HttpResponse::Found().header("location", uri).finish(),
+        Err(e) => server_error(e),
+    }
+}
+
+/// Completes a login when the identity provider sends the user back, sets
+/// the session and CSRF token cookies and sends the user to the UI.
+pub fn oidc_callback(server: web::Data<AppServer>, callback: Query<OidcCallback>) -> HttpResponse {
+    match server.read().oidc_login(callback.into_inner()) {
+        Ok((session, csrf_token)) => {
+            let session = Cookie::build(SESSION_COOKIE, session)
+                .path("/")
+                .secure(true)
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .finish();
+            let csrf_token = Cookie::build(CSRF_COOKIE, csrf_token)
+                .path("/")
+                .secure(true)
+                .same_site(SameSite::Strict)
+                .finish();
+            HttpResponse::Found()
+                .header("location", "/")
+                .cookie(session)
+                .cookie(csrf_token)
+                .finish()
+        }
+        Err(e) => server_error(e),
+    }
+}
+
+/// Ends the session and removes the session and CSRF token cookies. The
+/// request must include the CSRF token of the session, so that other sites
+/// cannot log the user out.
+#[allow(clippy::needless_pass_by_value)]
+pub fn oidc_logout(server: web::Data<AppServer>, auth: Auth) -> HttpResponse {
+    let (session, csrf_token) = match auth {
+        Auth::Session(session, Some(csrf_token)) => (session, csrf_token),
+        _ => return HttpResponse::Forbidden().finish(),
+    };
+
+    match server.read().oidc_logout(&session, &csrf_token) {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::Forbidden().finish(),
+        Err(e) => return server_error(e),
+    }
+
+    // The cookies were set for the whole site, so they must be removed
+    // using the same path.
+    let session = Cookie::build(SESSION_COOKIE, "").path("/").finish();
+    let csrf_token = Cookie::build(CSRF_COOKIE, "").path("/").finish();
+    HttpResponse::Ok()
+        .del_cookie(&session)
+        .del_cookie(&csrf_token)
+        .finish()
+}
+
 //------------ Admin: Publishers ---------------------------------------------
 
 pub fn repo_stats(server: web::Data<AppServer>) -> HttpResponse {
@@ -224,7 +342,7 @@ pub fn stale_publishers(server: web::Data<AppServer>, seconds: web::Path<i64>) -
 
 /// Returns a json structure with all publishers in it.
 pub fn list_pbl(server: web::Data<AppServer>, auth: Auth) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         render_json_res(
             server
                 .read()
@@ -240,8 +358,8 @@ pub fn add_pbl(
     auth: Auth,
     pbl: Json<rfc8183::PublisherRequest>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
-        render_json_res(server.write().add_publisher(pbl.into_inner()))
+    if_api_allowed(&server, &auth, Permission::PublisherAdmin, |actor| {
+        render_json_res(server.write().add_publisher(pbl.into_inner(), actor))
     })
 }
 
@@ -253,15 +371,19 @@ pub fn remove_pbl(
     auth: Auth,
     publisher: Path<Handle>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.write().remove_publisher(publisher.into_inner()))
+    if_api_allowed(&server, &auth, Permission::PublisherAdmin, |actor| {
+        render_empty_res(
+            server
+                .write()
+                .remove_publisher(publisher.into_inner(), actor),
+        )
     })
 }
 
 /// Returns a json structure with publisher details
 #[allow(clippy::needless_pass_by_value)]
 pub fn show_pbl(server: web::Data<AppServer>, auth: Auth, publisher: Path<Handle>) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         render_json_res(server.read().get_publisher(&publisher.into_inner()))
     })
 }
@@ -289,7 +411,7 @@ pub fn repository_response_xml(
     auth: Auth,
     publisher: Path<Handle>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         match repository_response(&server, &publisher.into_inner()) {
             Ok(res) => HttpResponse::Ok()
                 .content_type("application/xml")
@@ -305,7 +427,7 @@ pub fn repository_response_json(
     auth: Auth,
     publisher: Path<Handle>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         match repository_response(&server, &publisher.into_inner()) {
             Ok(res) => render_json(res),
             Err(e) => server_error(e),
@@ -344,11 +466,11 @@ pub fn ca_add_child(
     req: Json<AddChildRequest>,
     auth: Auth,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
         render_json_res(
             server
                 .read()
-                .ca_add_child(&parent.into_inner(), req.into_inner()),
+                .ca_add_child(&parent.into_inner(), req.into_inner(), actor),
         )
     })
 }
@@ -363,8 +485,12 @@ pub fn ca_child_update(
     let ca = ca_and_child.0;
     let child = ca_and_child.1;
 
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.read().ca_child_update(&ca, child, req.into_inner()))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(
+            server
+                .read()
+                .ca_child_update(&ca, child, req.into_inner(), actor),
+        )
     })
 }
 
@@ -377,8 +503,8 @@ pub fn ca_child_remove(
     let ca = ca_and_child.0;
     let child = ca_and_child.1;
 
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.read().ca_child_remove(&ca, child))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_child_remove(&ca, child, actor))
     })
 }
 
@@ -391,7 +517,7 @@ pub fn ca_show_child(
     let ca = ca_and_child.0;
     let child = ca_and_child.1;
 
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         render_json_res(server.read().ca_show_child(&ca, &child))
     })
 }
@@ -405,7 +531,7 @@ pub fn ca_parent_contact(
     let ca = ca_and_child.0;
     let child = ca_and_child.1;
 
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         render_json_res(server.read().ca_parent_contact(&ca, child.clone()))
     })
 }
@@ -419,7 +545,7 @@ pub fn ca_parent_res_json(
     let ca = ca_and_child.0;
     let child = ca_and_child.1;
 
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         render_json_res(server.read().ca_parent_response(&ca, child.clone()))
     })
 }
@@ -433,7 +559,7 @@ pub fn ca_parent_res_xml(
     let ca = ca_and_child.0;
     let child = ca_and_child.1;
 
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         match server.read().ca_parent_response(&ca, child.clone()) {
             Ok(res) => HttpResponse::Ok()
                 .content_type("application/xml")
@@ -460,7 +586,7 @@ pub fn cas_stats(server: web::Data<AppServer>) -> HttpResponse {
 }
 
 pub fn cas(server: web::Data<AppServer>, auth: Auth) -> HttpResponse {
-    if_api_allowed(&server, &auth, || render_json(server.read().cas()))
+    if_api_allowed(&server, &auth, Permission::Read, |_| render_json(server.read().cas()))
 }
 
 pub fn ca_init(
@@ -468,8 +594,15 @@ pub fn ca_init(
     auth: Auth,
     ca_init: Json<CertAuthInit>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.write().ca_init(ca_init.into_inner()))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.write().ca_init(ca_init.into_inner(), actor))
+    })
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn ca_delete(server: web::Data<AppServer>, auth: Auth, handle: Path<Handle>) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_delete(&handle.into_inner(), actor))
     })
 }
 
@@ -478,13 +611,13 @@ pub fn ca_regenerate_id(
     auth: Auth,
     handle: Path<Handle>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.read().ca_update_id(handle.into_inner()))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_update_id(handle.into_inner(), actor))
     })
 }
 
 pub fn ca_info(server: web::Data<AppServer>, auth: Auth, handle: Path<Handle>) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         render_json_res(server.read().ca_info(&handle.into_inner()))
     })
 }
@@ -495,13 +628,13 @@ pub fn ca_my_parent_contact(
     ca_and_parent: Path<(Handle, Handle)>,
 ) -> HttpResponse {
     let (ca, parent) = ca_and_parent.into_inner();
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         render_json_res(server.read().ca_my_parent_contact(&ca, &parent))
     })
 }
 
 pub fn ca_history(server: web::Data<AppServer>, auth: Auth, handle: Path<Handle>) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         match server.read().ca_history(&handle.into_inner()) {
             Some(history) => render_json(history),
             None => api_not_found(),
@@ -509,13 +642,59 @@ pub fn ca_history(server: web::Data<AppServer>, auth: Auth, handle: Path<Handle>
     })
 }
 
+/// Returns the CA as it was at a version, or time (RFC 3339), in its history.
+pub fn ca_info_at(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    ca_and_point: Path<(Handle, String)>,
+) -> HttpResponse {
+    let (ca, point) = ca_and_point.into_inner();
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        render_json_res(
+            parse_history_point(&ca, &point).and_then(|point| server.read().ca_info_at(&ca, point)),
+        )
+    })
+}
+
+/// Returns the differences between the CA at two versions, or times, in its
+/// history.
+pub fn ca_diff(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    ca_and_points: Path<(Handle, String, String)>,
+) -> HttpResponse {
+    let (ca, from, to) = ca_and_points.into_inner();
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        render_json_res(parse_history_point(&ca, &from).and_then(|from| {
+            parse_history_point(&ca, &to).and_then(|to| server.read().ca_diff(&ca, from, to))
+        }))
+    })
+}
+
+fn parse_history_point(ca: &Handle, point: &str) -> Result<CertAuthHistoryPoint, Error> {
+    CertAuthHistoryPoint::from_str(point).map_err(|e| Error::CaHistoryPointInvalid(ca.clone(), e))
+}
+
+/// Returns the commands processed by CAs, and who sent them. The query may
+/// select commands 'since' a time (seconds since the epoch), for a 'ca', or
+/// by an 'actor'.
+pub fn audit(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    criteria: Query<AuditCriteria>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        render_json_res(server.read().audit(&criteria))
+    })
+}
+
 pub fn ca_child_req_xml(
     server: web::Data<AppServer>,
     auth: Auth,
     handle: Path<Handle>,
 ) -> HttpResponse {
     let handle = handle.into_inner();
-    if_api_allowed(&server, &auth, || match ca_child_req(&server, &handle) {
+    if_api_allowed(&server, &auth, Permission::Read, |_| match ca_child_req(&server, &handle) {
         Ok(req) => HttpResponse::Ok()
             .content_type("application/xml")
             .body(req.encode_vec()),
@@ -529,7 +708,7 @@ pub fn ca_child_req_json(
     handle: Path<Handle>,
 ) -> HttpResponse {
     let handle = handle.into_inner();
-    if_api_allowed(&server, &auth, || match ca_child_req(&server, &handle) {
+    if_api_allowed(&server, &auth, Permission::Read, |_| match ca_child_req(&server, &handle) {
         Ok(req) => render_json(req),
         Err(e) => server_error(e),
     })
@@ -548,7 +727,7 @@ pub fn ca_publisher_req_json(
     handle: Path<Handle>,
 ) -> HttpResponse {
     let handle = handle.into_inner();
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         match server.read().ca_publisher_req(&handle) {
             Some(req) => render_json(req),
             None => api_not_found(),
@@ -562,7 +741,7 @@ pub fn ca_publisher_req_xml(
     handle: Path<Handle>,
 ) -> HttpResponse {
     let handle = handle.into_inner();
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         match server.read().ca_publisher_req(&handle) {
             Some(req) => HttpResponse::Ok()
                 .content_type("application/xml")
@@ -578,7 +757,7 @@ pub fn ca_repo_details(
     handle: Path<Handle>,
 ) -> HttpResponse {
     let handle = handle.into_inner();
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         render_json_res(server.read().ca_repo_details(&handle))
     })
 }
@@ -589,7 +768,7 @@ pub fn ca_repo_state(
     handle: Path<Handle>,
 ) -> HttpResponse {
     let handle = handle.into_inner();
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         render_json_res(server.read().ca_repo_state(&handle))
     })
 }
@@ -623,8 +802,8 @@ pub fn ca_repo_update(
         Err(e) => return server_error(e),
     };
 
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.read().ca_update_repo(handle, update))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_update_repo(handle, update, actor))
     })
 }
 
@@ -634,11 +813,11 @@ pub fn ca_add_parent(
     ca: Path<Handle>,
     req: Json<ParentCaReq>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
         render_empty_res(
             server
                 .read()
-                .ca_parent_add(ca.into_inner(), req.into_inner()),
+                .ca_parent_add(ca.into_inner(), req.into_inner(), actor),
         )
     })
 }
@@ -669,8 +848,8 @@ pub fn ca_add_parent_xml(
         ParentCaReq::new(parent, contact)
     };
 
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.read().ca_parent_add(ca, req))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_parent_add(ca, req, actor))
     })
 }
 
@@ -702,8 +881,8 @@ pub fn ca_update_parent(
         Ok(contact) => contact,
         Err(e) => return server_error(e),
     };
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.read().ca_parent_update(ca, parent, contact))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_parent_update(ca, parent, contact, actor))
     })
 }
 
@@ -713,15 +892,15 @@ pub fn ca_remove_parent(
     ca_and_parent: Path<(Handle, Handle)>,
 ) -> HttpResponse {
     let (ca, parent) = ca_and_parent.into_inner();
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.read().ca_parent_remove(ca, parent))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_parent_remove(ca, parent, actor))
     })
 }
 
 /// Force a key roll for a CA, i.e. use a max key age of 0 seconds.
 pub fn ca_kr_init(server: web::Data<AppServer>, auth: Auth, handle: Path<Handle>) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.read().ca_keyroll_init(handle.into_inner()))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_keyroll_init(handle.into_inner(), actor))
     })
 }
 
@@ -731,8 +910,155 @@ pub fn ca_kr_activate(
     auth: Auth,
     handle: Path<Handle>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
-        render_empty_res(server.read().ca_keyroll_activate(handle.into_inner()))
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(
+            server
+                .read()
+                .ca_keyroll_activate(handle.into_inner(), actor),
+        )
+    })
+}
+
+/// Roll all keys of this CA in an emergency, without observing the staging
+/// period. The request must be confirmed.
+pub fn ca_kr_emergency(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    request: Json<KeyRollEmergencyRequest>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_keyroll_emergency(
+            handle.into_inner(),
+            request.into_inner(),
+            actor,
+        ))
+    })
+}
+
+/// Show the key roll policy of this CA, and the progress of key rolls
+pub fn ca_kr_policy_show(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        render_json_res(server.read().ca_key_roll_info(&handle.into_inner()))
+    })
+}
+
+/// Set the policy for automatic key rolls for this CA
+pub fn ca_kr_policy_update(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    policy: Json<KeyRollPolicy>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_key_roll_policy_update(
+            handle.into_inner(),
+            Some(policy.into_inner()),
+            actor,
+        ))
+    })
+}
+
+/// Remove the key roll policy for this CA, so that keys are rolled manually
+pub fn ca_kr_policy_remove(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(
+            server
+                .read()
+                .ca_key_roll_policy_update(handle.into_inner(), None, actor),
+        )
+    })
+}
+
+//------------ Admin: Offline Trust Anchor -----------------------------------
+
+/// Export the requests for an offline Trust Anchor parent
+#[allow(clippy::needless_pass_by_value)]
+pub fn ta_offline_requests(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    options: Query<TaRequestOptions>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |_| {
+        render_json_res(server.read().ta_offline_requests(*options))
+    })
+}
+
+/// Let the Trust Anchor of this offline server process exported requests
+pub fn ta_offline_sign(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    bundle: Json<TaRequestBundle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |_| {
+        render_json_res(server.read().ta_offline_sign(bundle.into_inner()))
+    })
+}
+
+/// Import the responses of an offline Trust Anchor
+pub fn ta_offline_responses(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    bundle: Json<TaResponseBundle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(
+            server
+                .read()
+                .ta_offline_responses(bundle.into_inner(), actor),
+        )
+    })
+}
+
+//------------ Admin: Timing -------------------------------------------------
+
+/// Show the timing used by this CA
+pub fn ca_timing_show(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        render_json_res(server.read().ca_timing(&handle.into_inner()))
+    })
+}
+
+/// Set the timing for this CA, overriding the server timing
+pub fn ca_timing_update(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    timing: Json<TimingConfig>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_timing_update(
+            handle.into_inner(),
+            Some(timing.into_inner()),
+            actor,
+        ))
+    })
+}
+
+/// Revert to the server timing for this CA
+pub fn ca_timing_reset(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(
+            server
+                .read()
+                .ca_timing_update(handle.into_inner(), None, actor),
+        )
     })
 }
 
@@ -745,22 +1071,67 @@ pub fn ca_routes_update(
     handle: Path<Handle>,
     updates: Json<RoaDefinitionUpdates>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    let handle = handle.into_inner();
+    let permission = Permission::RoaUpdate(handle.clone());
+    if_api_allowed(&server, &auth, permission, |actor| {
         render_empty_res(
             server
                 .read()
-                .ca_routes_update(handle.into_inner(), updates.into_inner()),
+                .ca_routes_update(handle, updates.into_inner(), actor),
         )
     })
 }
 
+/// Show which announcements would change validation state if the updates
+/// were applied, without applying them
+#[allow(clippy::needless_pass_by_value)]
+pub fn ca_routes_dry_run(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    updates: Json<RoaDefinitionUpdates>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        render_json_res(server.read().ca_routes_dry_run(&handle, &updates))
+    })
+}
+
+/// Show an analysis of the route authorizations for this CA: redundant and
+/// overly permissive authorizations, authorizations for prefixes no longer
+/// held, and suggestions for uncovered announcements
+pub fn ca_routes_analysis(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        let handle = handle.into_inner();
+        render_json_res(server.read().ca_routes_analyse(&handle, None))
+    })
+}
+
+/// Show an analysis of the route authorizations for this CA, using the
+/// announcements posted rather than the BGP dump configured on the server
+pub fn ca_routes_analysis_announcements(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    announcements: Json<Announcements>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        let handle = handle.into_inner();
+        let announcements = Some(announcements.into_inner());
+        render_json_res(server.read().ca_routes_analyse(&handle, announcements))
+    })
+}
+
 /// show the route authorizations for this CA
 pub fn ca_routes_show(
     server: web::Data<AppServer>,
     auth: Auth,
     handle: Path<Handle>,
 ) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
         let handle = handle.into_inner();
         match server.read().ca_routes_show(&handle) {
             Ok(roas) => render_json(roas),
@@ -769,23 +1140,234 @@ pub fn ca_routes_show(
     })
 }
 
+/// Replace the route authorizations for this CA with the desired ones, and
+/// show the updates that this took
+pub fn ca_routes_sync(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    options: Query<RoaSyncOptions>,
+    desired: Json<Vec<RoaDefinition>>,
+) -> HttpResponse {
+    let handle = handle.into_inner();
+    let permission = Permission::RoaUpdate(handle.clone());
+    if_api_allowed(&server, &auth, permission, |actor| {
+        render_json_res(
+            server
+                .read()
+                .ca_routes_sync(handle, desired.into_inner(), *options, actor),
+        )
+    })
+}
+
+/// Import the complete set of route authorizations for this CA from a file,
+/// and show the updates that this took
+#[allow(clippy::needless_pass_by_value)]
+pub fn ca_routes_import(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    options: Query<RoaFileOptions>,
+    body: Bytes,
+) -> HttpResponse {
+    let handle = handle.into_inner();
+    let permission = Permission::RoaUpdate(handle.clone());
+    if_api_allowed(&server, &auth, permission, |actor| {
+        let file = String::from_utf8_lossy(&body);
+        render_json_res(
+            server
+                .read()
+                .ca_routes_import(handle, *options, &file, actor),
+        )
+    })
+}
+
+/// Export the route authorizations for this CA as a file
+#[allow(clippy::needless_pass_by_value)]
+pub fn ca_routes_export(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    options: Query<RoaFileOptions>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        let format = options.format();
+        match server.read().ca_routes_export(&handle, format) {
+            Ok(file) => HttpResponse::Ok()
+                .content_type(format.content_type())
+                .body(file),
+            Err(e) => server_error(e),
+        }
+    })
+}
+
+/// Show the ROA grouping used by this CA
+pub fn ca_roa_grouping_show(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        render_json_res(server.read().ca_roa_grouping(&handle.into_inner()))
+    })
+}
+
+/// Set the ROA grouping for this CA, overriding the server strategy
+pub fn ca_roa_grouping_update(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    strategy: Json<RoaPrefixGroupingStrategy>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(server.read().ca_roa_grouping_update(
+            handle.into_inner(),
+            Some(strategy.into_inner()),
+            actor,
+        ))
+    })
+}
+
+/// Revert to the server ROA grouping for this CA
+pub fn ca_roa_grouping_reset(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        render_empty_res(
+            server
+                .read()
+                .ca_roa_grouping_update(handle.into_inner(), None, actor),
+        )
+    })
+}
+
+//------------ Admin: ASPAs --------------------------------------------------
+
+/// Show the ASPA definitions for this CA
+pub fn ca_aspas_show(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        let handle = handle.into_inner();
+        match server.read().ca_aspas_show(&handle) {
+            Ok(aspas) => render_json(aspas),
+            Err(e) => server_error(e),
+        }
+    })
+}
+
+/// Add or replace ASPA definitions, and remove definitions for customer ASNs
+pub fn ca_aspas_update(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    updates: Json<AspaDefinitionUpdates>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        let handle = handle.into_inner();
+        render_empty_res(
+            server
+                .read()
+                .ca_aspas_update(handle, updates.into_inner(), actor),
+        )
+    })
+}
+
+/// Update the providers for the ASPA definition of a customer ASN
+pub fn ca_aspas_update_providers(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    params: Path<(Handle, u32)>,
+    update: Json<AspaProvidersUpdate>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        let (handle, customer) = params.into_inner();
+        let customer = AsNumber::new(customer);
+        render_empty_res(server.read().ca_aspas_update_providers(
+            handle,
+            customer,
+            update.into_inner(),
+            actor,
+        ))
+    })
+}
+
+//------------ Admin: BGPsec router certificates -----------------------------
+
+/// Show the BGPsec router certificates configured for this CA
+pub fn ca_bgpsec_show(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Read, |_| {
+        let handle = handle.into_inner();
+        match server.read().ca_bgpsec_show(&handle) {
+            Ok(definitions) => render_json(definitions),
+            Err(e) => server_error(e),
+        }
+    })
+}
+
+/// Add and remove BGPsec router certificates
+pub fn ca_bgpsec_update(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    updates: Json<BgpSecDefinitionUpdates>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |actor| {
+        let handle = handle.into_inner();
+        render_empty_res(
+            server
+                .read()
+                .ca_bgpsec_update(handle, updates.into_inner(), actor),
+        )
+    })
+}
+
+//------------ Admin: Resource Signed Checklists -----------------------------
+
+/// Sign an RPKI Signed Checklist for resources held by this CA
+pub fn ca_sign_checklist(
+    server: web::Data<AppServer>,
+    auth: Auth,
+    handle: Path<Handle>,
+    request: Json<RscRequest>,
+) -> HttpResponse {
+    if_api_allowed(&server, &auth, Permission::Admin, |_| {
+        let handle = handle.into_inner();
+        match server
+            .read()
+            .ca_sign_checklist(&handle, request.into_inner())
+        {
+            Ok(rsc) => render_json(rsc),
+            Err(e) => server_error(e),
+        }
+    })
+}
+
 //------------ Admin: Force republish ----------------------------------------
 
 pub fn republish_all(server: web::Data<AppServer>, auth: Auth) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Admin, |_| {
         render_empty_res(server.read().republish_all())
     })
 }
 
 pub fn resync_all(server: web::Data<AppServer>, auth: Auth) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Admin, |_| {
         render_empty_res(server.read().resync_all())
     })
 }
 
 /// Refresh all CAs
 pub fn refresh_all(server: web::Data<AppServer>, auth: Auth) -> HttpResponse {
-    if_api_allowed(&server, &auth, || {
+    if_api_allowed(&server, &auth, Permission::Admin, |_| {
         render_empty_res(server.read().refresh_all())
     })
 }
@@ -7,7 +7,7 @@ use std::fs::File;
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use actix_web::http::StatusCode;
-use actix_web::web::{delete, get, post, scope, Path};
+use actix_web::web::{delete, get, post, put, scope, Path};
 use actix_web::{guard, middleware, web, Resource};
 use actix_web::{App, HttpResponse, HttpServer};
 use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
@@ -47,6 +47,8 @@ pub fn start(config: &Config) -> Result<(), Error> {
     let post_limit_rfc8181 = config.post_limit_rfc8181;
     let post_limit_rfc6492 = config.post_limit_rfc6492;
 
+    let krill = server.clone();
+
     HttpServer::new(move || {
         App::new()
             .data(server.clone())
@@ -56,12 +58,24 @@ pub fn start(config: &Config) -> Result<(), Error> {
             .route("/stats/info", get().to(server_info))
             .route("/stats/repo", get().to(repo_stats))
             .route("/stats/cas", get().to(cas_stats))
+            // Login using OpenID Connect
+            .route("/auth/enabled", get().to(oidc_enabled))
+            .route("/auth/login", get().to(oidc_login))
+            .route("/auth/callback", get().to(oidc_callback))
+            .route("/auth/logout", post().to(oidc_logout))
             // API end-points
             .service(
                 scope("/api/v1")
                     .data(web::JsonConfig::default().limit(post_limit_api))
+                    .data(web::PayloadConfig::default().limit(post_limit_api))
                     // Let the UI check if it's authorized
                     .route("/authorized", get().to(api_authorized))
+                    // Named API tokens
+                    .route("/tokens", get().to(api_tokens))
+                    .route("/tokens", post().to(api_token_add))
+                    .route("/tokens/{name}", delete().to(api_token_remove))
+                    // Audit log of commands processed by all CAs
+                    .route("/audit", get().to(audit))
                     // Repositories and their publishers (both embedded and remote)
                     .route("/publishers", get().to(list_pbl))
                     .route("/publishers", post().to(add_pbl))
@@ -82,8 +96,11 @@ pub fn start(config: &Config) -> Result<(), Error> {
                     .route("/cas/issues", get().to(endpoints::all_ca_issues))
                     .route("/cas/issues/{ca}", get().to(endpoints::ca_issues))
                     .route("/cas/{ca}", get().to(ca_info))
+                    .route("/cas/{ca}", delete().to(ca_delete))
                     .route("/cas/{ca}/id", post().to(ca_regenerate_id))
                     .route("/cas/{ca}/history", get().to(ca_history))
+                    .route("/cas/{ca}/at/{point}", get().to(ca_info_at))
+                    .route("/cas/{ca}/diff/{from}/{to}", get().to(ca_diff))
                     .route("/cas/{ca}/child_request.xml", get().to(ca_child_req_xml))
                     .route("/cas/{ca}/child_request.json", get().to(ca_child_req_json))
                     .route("/cas/{ca}/repo", get().to(ca_repo_details))
@@ -120,8 +137,52 @@ pub fn start(config: &Config) -> Result<(), Error> {
                     .route("/cas/{ca}/children/{child}", delete().to(ca_child_remove))
                     .route("/cas/{ca}/keys/roll_init", post().to(ca_kr_init))
                     .route("/cas/{ca}/keys/roll_activate", post().to(ca_kr_activate))
+                    .route("/cas/{ca}/keys/roll_emergency", post().to(ca_kr_emergency))
+                    .route("/cas/{ca}/keys/policy", get().to(ca_kr_policy_show))
+                    .route("/cas/{ca}/keys/policy", post().to(ca_kr_policy_update))
+                    .route("/cas/{ca}/keys/policy", delete().to(ca_kr_policy_remove))
                     .route("/cas/{ca}/routes", post().to(ca_routes_update))
                     .route("/cas/{ca}/routes", get().to(ca_routes_show))
+                    .route("/cas/{ca}/routes", put().to(ca_routes_sync))
+                    .route(
+                        "/cas/{ca}/routes/analysis/dryrun",
+                        post().to(ca_routes_dry_run),
+                    )
+                    .route(
+                        "/cas/{ca}/routes/analysis/full",
+                        get().to(ca_routes_analysis),
+                    )
+                    .route(
+                        "/cas/{ca}/routes/analysis/full",
+                        post().to(ca_routes_analysis_announcements),
+                    )
+                    .route("/cas/{ca}/routes/import", post().to(ca_routes_import))
+                    .route("/cas/{ca}/routes/export", get().to(ca_routes_export))
+                    .route("/cas/{ca}/routes/grouping", get().to(ca_roa_grouping_show))
+                    .route(
+                        "/cas/{ca}/routes/grouping",
+                        post().to(ca_roa_grouping_update),
+                    )
+                    .route(
+                        "/cas/{ca}/routes/grouping",
+                        delete().to(ca_roa_grouping_reset),
+                    )
+                    .route("/cas/{ca}/aspas", get().to(ca_aspas_show))
+                    .route("/cas/{ca}/aspas", post().to(ca_aspas_update))
+                    .route(
+                        "/cas/{ca}/aspas/as/{customer}",
+                        post().to(ca_aspas_update_providers),
+                    )
+                    .route("/cas/{ca}/bgpsec", get().to(ca_bgpsec_show))
+                    .route("/cas/{ca}/bgpsec", post().to(ca_bgpsec_update))
+                    .route("/cas/{ca}/rsc/sign", post().to(ca_sign_checklist))
+                    .route("/cas/{ca}/timing", get().to(ca_timing_show))
+                    .route("/cas/{ca}/timing", post().to(ca_timing_update))
+                    .route("/cas/{ca}/timing", delete().to(ca_timing_reset))
+                    // Exchange requests and responses with an offline TA
+                    .route("/ta/offline/requests", get().to(ta_offline_requests))
+                    .route("/ta/offline/sign", post().to(ta_offline_sign))
+                    .route("/ta/offline/responses", post().to(ta_offline_responses))
                     // Republish ALL CAs
                     .route("/cas/republish_all", post().to(republish_all))
                     // Force resyncing of all CAs at repo servers
@@ -174,6 +235,12 @@ pub fn start(config: &Config) -> Result<(), Error> {
     .bind_ssl(config.socket_addr(), https_builder)?
     .run()?;
 
+    // The server was stopped, save snapshots so that the next start is quick.
+    info!("Saving snapshots before shutting down");
+    if let Err(e) = krill.read().snapshot_all() {
+        error!("Could not save snapshots on shutdown: {}", e);
+    }
+
     Ok(())
 }
 
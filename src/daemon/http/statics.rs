@@ -43,6 +43,7 @@ where
             .add_static(&FAVICON)
             .add_static(&APP_JS)
             .add_static(&APP_JS_MAP)
+            .add_static(&OIDC_JS)
             .add_static(&APP_CSS)
             .add_static(&IMG_KRILL_LOG)
             .add_static(&IMG_ROUTE_LEFT)
@@ -125,6 +126,11 @@ static APP_JS_MAP: StaticContent = StaticContent {
     content: include_bytes!("../../../lagosta/js/app.js.map"),
     ctype: JS,
 };
+static OIDC_JS: StaticContent = StaticContent {
+    web_path: "/js/oidc.js",
+    content: include_bytes!("../../../lagosta/js/oidc.js"),
+    ctype: JS,
+};
 static APP_CSS: StaticContent = StaticContent {
     web_path: "/css/app.css",
     content: include_bytes!("../../../lagosta/css/app.css"),
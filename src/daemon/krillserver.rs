@@ -12,25 +12,40 @@ use rpki::uri;
 use rpki::x509::Time;
 
 use crate::commons::api::{
-    AddChildRequest, AllCertAuthIssues, CaRepoDetails, CertAuthHistory, CertAuthInfo, CertAuthInit,
-    CertAuthIssues, CertAuthList, CertAuthStats, ChildCaInfo, ChildHandle, CurrentRepoState,
-    Handle, ListReply, ParentCaContact, ParentCaReq, ParentHandle, PublishDelta, PublisherDetails,
-    PublisherHandle, RepoInfo, RepositoryContact, RepositoryUpdate, RoaDefinition,
-    RoaDefinitionUpdates, ServerInfo, TaCertDetails, UpdateChildRequest,
+    AddChildRequest, AllCertAuthIssues, ApiToken, ApiTokenDefinition, AsNumber, AspaDefinitionList,
+    AspaDefinitionUpdates, AspaProvidersUpdate, AuditCriteria, AuditLog, BgpSecDefinitionList,
+    BgpSecDefinitionUpdates, CaKeyRollInfo, CaRepoDetails, CaTimingInfo, CertAuthDiff,
+    CertAuthHistory, CertAuthHistoryPoint, CertAuthInfo, CertAuthInit, CertAuthIssues,
+    CertAuthList, CertAuthStats, ChildCaInfo, ChildHandle, CurrentRepoState, Handle,
+    KeyRollEmergencyRequest, KeyRollPolicy, ListReply, ParentCaContact, ParentCaReq, ParentHandle,
+    PublishDelta, PublisherDetails, PublisherHandle, RepoInfo, RepositoryContact, RepositoryUpdate,
+    RoaDefinition, RoaDefinitionUpdates, RoaFileFormat, RoaFileOptions, RoaGroupingInfo,
+    RoaPrefixGroupingStrategy, RoaSyncOptions, RscRequest, ServerInfo, SignedChecklist,
+    TaCertDetails, TaRequestBundle, TaRequestOptions, TaResponseBundle, TimingConfig,
+    UpdateChildRequest,
 };
+use crate::commons::bgp::{Announcements, AnnouncementsCache, RoaAnalysis, RoaUpdatesImpact};
 use crate::commons::error::Error;
+use crate::commons::eventsourcing::DEFAULT_ACTOR;
+use crate::commons::remote::id::IdCert;
 use crate::commons::remote::rfc8183;
-use crate::commons::util::softsigner::OpenSslSigner;
+use crate::commons::util::file;
+use crate::commons::util::signer::KrillSigner;
 use crate::commons::{KrillEmptyResult, KrillResult};
 use crate::constants::*;
-use crate::daemon::auth::{Auth, Authorizer};
+use crate::daemon::auth::{Auth, Authorizer, Permission};
 use crate::daemon::ca::{self, ta_handle};
 use crate::daemon::config::Config;
 use crate::daemon::mq::EventQueueListener;
+use crate::daemon::oidc::{OidcAuthenticator, OidcCallback};
 use crate::daemon::scheduler::Scheduler;
 use crate::pubd::{PubServer, RepoStats};
 use crate::publish::CaPublisher;
 
+/// The file in the work directory where the details of an offline Trust
+/// Anchor are kept, when its responses are imported.
+const TA_OFFLINE_FILE: &str = "ta_offline.json";
+
 //------------ KrillServer ---------------------------------------------------
 
 /// This is the master krill server that is doing all the orchestration
@@ -59,7 +74,16 @@ pub struct KrillServer {
     pubserver: Option<Arc<PubServer>>,
 
     // Handles the internal TA and/or CAs
-    caserver: Arc<ca::CaServer<OpenSslSigner>>,
+    caserver: Arc<ca::CaServer<KrillSigner>>,
+
+    // Dump of BGP announcements, used to analyse ROA updates
+    bgp_dump: Option<AnnouncementsCache>,
+
+    // Identity of the offline TA, used to verify its response bundles
+    ta_offline_id_cert: Option<IdCert>,
+
+    // Queue of events for triggered background processing
+    event_queue: Arc<EventQueueListener>,
 
     // Responsible for background tasks, e.g. re-publishing
     #[allow(dead_code)] // just need to keep this in scope
@@ -81,6 +105,10 @@ impl KrillServer {
         let token = &config.auth_token;
         let ca_refresh_rate = config.ca_refresh;
         let roa_prefix_grouping_strategy = config.roa_prefix_grouping_strategy;
+        let timing_config = config.timing_config();
+        let snapshot_events = config.snapshot_events;
+        let store_type = config.store_type;
+        let ta_offline_id_cert = config.ta_offline_id_cert().map_err(Error::custom)?;
 
         info!("Starting {} v{}", KRILL_SERVER_APP, KRILL_VERSION);
         info!("{} uses service uri: {}", KRILL_SERVER_APP, service_uri);
@@ -88,10 +116,13 @@ impl KrillServer {
         let mut repo_dir = work_dir.clone();
         repo_dir.push("repo");
 
-        let signer = OpenSslSigner::build(work_dir)?;
+        let signer = KrillSigner::build(config)?;
         let signer = Arc::new(RwLock::new(signer));
 
-        let authorizer = Authorizer::new(token);
+        let mut authorizer = Authorizer::build(token, &config.api_tokens, work_dir)?;
+        if let Some(oidc) = OidcAuthenticator::build(config) {
+            authorizer = authorizer.with_oidc(oidc);
+        }
 
         let pubserver = {
             if config.repo_enabled {
@@ -100,6 +131,8 @@ impl KrillServer {
                     rrdp_base_uri.clone(),
                     work_dir,
                     signer.clone(),
+                    snapshot_events,
+                    store_type,
                 )?)
             } else {
                 PubServer::remove_if_empty(
@@ -107,14 +140,18 @@ impl KrillServer {
                     rrdp_base_uri.clone(),
                     work_dir,
                     signer.clone(),
+                    snapshot_events,
+                    store_type,
                 )?
             }
         };
         let pubserver: Option<Arc<PubServer>> = pubserver.map(Arc::new);
 
-        let event_queue = Arc::new(EventQueueListener::in_mem());
+        let event_queue = Arc::new(EventQueueListener::disk(work_dir)?);
         let caserver = Arc::new(ca::CaServer::build(work_dir, event_queue.clone(), 
-                                                    signer, roa_prefix_grouping_strategy)?);
+                                                    signer, roa_prefix_grouping_strategy,
+                                                    timing_config, snapshot_events,
+                                                    store_type)?);
 
         if config.use_ta() {
             let ta_handle = ta_handle();
@@ -140,18 +177,23 @@ impl KrillServer {
                 let req =
                     rfc8183::PublisherRequest::new(None, ta_handle.clone(), ta.id_cert().clone());
 
-                pubserver.create_publisher(req)?;
+                pubserver.create_publisher(req, DEFAULT_ACTOR)?;
 
                 // Force initial  publication
                 caserver.republish(&ta_handle)?;
             }
         }
 
+        // Re-issue ROAs in case the server ROA grouping strategy was changed
+        // in the config, for CAs which do not have their own strategy.
+        caserver.ca_roa_grouping_migrate_all()?;
+
         let scheduler = Scheduler::build(
-            event_queue,
+            event_queue.clone(),
             caserver.clone(),
             pubserver.clone(),
             ca_refresh_rate,
+            config.archive_after_days,
         );
 
         Ok(KrillServer {
@@ -160,6 +202,9 @@ impl KrillServer {
             authorizer,
             pubserver,
             caserver,
+            bgp_dump: config.bgp_dump_file.clone().map(AnnouncementsCache::new),
+            ta_offline_id_cert,
+            event_queue,
             scheduler,
             started: Time::now(),
         })
@@ -176,8 +221,45 @@ impl KrillServer {
 
 /// # Authentication
 impl KrillServer {
-    pub fn is_api_allowed(&self, auth: &Auth) -> bool {
-        self.authorizer.is_api_allowed(auth)
+    /// Returns the actor (token or user name) if the token or session has
+    /// the permission.
+    pub fn actor(&self, auth: &Auth, permission: &Permission) -> Option<String> {
+        self.authorizer.actor(auth, permission)
+    }
+
+    /// Returns the definitions of all named API tokens.
+    pub fn api_tokens(&self) -> Vec<ApiTokenDefinition> {
+        self.authorizer.tokens()
+    }
+
+    /// Adds a named API token, and returns it including the token itself.
+    pub fn api_token_add(&mut self, definition: ApiTokenDefinition) -> KrillResult<ApiToken> {
+        self.authorizer.add_token(definition)
+    }
+
+    /// Removes a named API token that was added through the API.
+    pub fn api_token_remove(&mut self, name: &str) -> KrillEmptyResult {
+        self.authorizer.remove_token(name)
+    }
+
+    /// Starts an OpenID Connect login, returns the URI to redirect to.
+    pub fn oidc_login_uri(&self) -> KrillResult<String> {
+        self.authorizer.oidc_login_uri()
+    }
+
+    /// Completes an OpenID Connect login, returns the session id and its
+    /// CSRF token.
+    pub fn oidc_login(&self, callback: OidcCallback) -> KrillResult<(String, String)> {
+        self.authorizer.oidc_login(callback)
+    }
+
+    pub fn oidc_logout(&self, session: &str, csrf_token: &str) -> KrillResult<bool> {
+        self.authorizer.oidc_logout(session, csrf_token)
+    }
+
+    /// Returns whether users can log in using OpenID Connect.
+    pub fn oidc_enabled(&self) -> bool {
+        self.authorizer.oidc_enabled()
     }
 }
 
@@ -203,17 +285,22 @@ impl KrillServer {
     pub fn add_publisher(
         &self,
         req: rfc8183::PublisherRequest,
+        actor: &str,
     ) -> KrillResult<rfc8183::RepositoryResponse> {
         let publisher_handle = req.publisher_handle().clone();
 
-        self.get_embedded()?.create_publisher(req)?;
+        self.get_embedded()?.create_publisher(req, actor)?;
 
         self.repository_response(&publisher_handle)
     }
 
     /// Removes a publisher, blows up if it didn't exist.
-    pub fn remove_publisher(&mut self, publisher: PublisherHandle) -> KrillEmptyResult {
-        self.get_embedded()?.remove_publisher(publisher)
+    pub fn remove_publisher(
+        &mut self,
+        publisher: PublisherHandle,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        self.get_embedded()?.remove_publisher(publisher, actor)
     }
 
     /// Returns a publisher.
@@ -250,7 +337,14 @@ impl KrillServer {
 /// # Being a parent
 ///
 impl KrillServer {
+    /// Returns the details of the embedded Trust Anchor or, if there is no
+    /// embedded Trust Anchor, the offline Trust Anchor as last imported.
     pub fn ta(&self) -> KrillResult<TaCertDetails> {
+        let offline_ta = file::file_path(&self.work_dir, TA_OFFLINE_FILE);
+        if !self.caserver.has_ca(&ta_handle()) && offline_ta.exists() {
+            return Ok(file::load_json(&offline_ta)?);
+        }
+
         let ta = self.caserver.get_ca(&ta_handle())?;
         if let ParentCaContact::Ta(ta) = ta.parent(&ta_handle()).unwrap() {
             Ok(ta.clone())
@@ -269,8 +363,11 @@ impl KrillServer {
         &self,
         parent: &ParentHandle,
         req: AddChildRequest,
+        actor: &str,
     ) -> KrillResult<ParentCaContact> {
-        let contact = self.caserver.ca_add_child(parent, req, &self.service_uri)?;
+        let contact = self
+            .caserver
+            .ca_add_child(parent, req, &self.service_uri, actor)?;
         Ok(contact)
     }
 
@@ -304,14 +401,20 @@ impl KrillServer {
         parent: &ParentHandle,
         child: ChildHandle,
         req: UpdateChildRequest,
+        actor: &str,
     ) -> KrillEmptyResult {
-        self.caserver.ca_child_update(parent, child, req)?;
+        self.caserver.ca_child_update(parent, child, req, actor)?;
         Ok(())
     }
 
     /// Update IdCert or resources of a child.
-    pub fn ca_child_remove(&self, handle: &Handle, child: ChildHandle) -> KrillEmptyResult {
-        self.caserver.ca_child_remove(handle, child)?;
+    pub fn ca_child_remove(
+        &self,
+        handle: &Handle,
+        child: ChildHandle,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        self.caserver.ca_child_remove(handle, child, actor)?;
         Ok(())
     }
 
@@ -335,9 +438,14 @@ impl KrillServer {
     }
 
     /// Adds a parent to a CA, will check first if the parent can be reached.
-    pub fn ca_parent_add(&self, handle: Handle, parent: ParentCaReq) -> KrillEmptyResult {
+    pub fn ca_parent_add(
+        &self,
+        handle: Handle,
+        parent: ParentCaReq,
+        actor: &str,
+    ) -> KrillEmptyResult {
         self.ca_parent_reachable(&handle, parent.handle(), parent.contact())?;
-        Ok(self.caserver.ca_parent_add(handle, parent)?)
+        Ok(self.caserver.ca_parent_add(handle, parent, actor)?)
     }
 
     /// Updates a parent contact for a CA
@@ -346,9 +454,12 @@ impl KrillServer {
         handle: Handle,
         parent: ParentHandle,
         contact: ParentCaContact,
+        actor: &str,
     ) -> KrillEmptyResult {
         self.ca_parent_reachable(&handle, &parent, &contact)?;
-        Ok(self.caserver.ca_parent_update(handle, parent, contact)?)
+        Ok(self
+            .caserver
+            .ca_parent_update(handle, parent, contact, actor)?)
     }
 
     fn ca_parent_reachable(
@@ -357,13 +468,21 @@ impl KrillServer {
         parent: &ParentHandle,
         contact: &ParentCaContact,
     ) -> KrillEmptyResult {
+        if contact.is_offline() {
+            return Ok(()); // Cannot be reached, by design.
+        }
         self.caserver
             .get_entitlements_from_parent_and_contact(handle, parent, contact)?;
         Ok(())
     }
 
-    pub fn ca_parent_remove(&self, handle: Handle, parent: ParentHandle) -> KrillEmptyResult {
-        Ok(self.caserver.ca_parent_remove(handle, parent)?)
+    pub fn ca_parent_remove(
+        &self,
+        handle: Handle,
+        parent: ParentHandle,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        Ok(self.caserver.ca_parent_remove(handle, parent, actor)?)
     }
 }
 
@@ -378,10 +497,11 @@ impl KrillServer {
             if let Ok(ca) = self.caserver.get_ca(ca.handle()) {
                 let roa_count = ca.roa_definitions().len();
                 let child_count = ca.children().count();
+                let invalid_object_count = self.caserver.validate_published(&ca).len();
 
                 res.insert(
                     ca.handle().clone(),
-                    CertAuthStats::new(roa_count, child_count),
+                    CertAuthStats::new(roa_count, child_count, invalid_object_count),
                 );
             }
         }
@@ -418,6 +538,25 @@ impl KrillServer {
             }
         }
 
+        for retry in self.event_queue.retry_info(ca_handle) {
+            issues.add_retry_info(retry);
+        }
+
+        for invalid in self.caserver.validate_published(&ca) {
+            let uri = invalid.uri().clone();
+            let reason = invalid.reason().to_string();
+            let e = Error::CaPublishedObjectInvalid(ca_handle.clone(), uri, reason);
+            issues.add_published_issue(e.to_error_response());
+        }
+
+        for progress in ca.key_roll_progress() {
+            issues.add_key_roll_progress(progress);
+        }
+
+        if let Some(failure) = self.caserver.key_roll_failure(ca_handle) {
+            issues.add_key_roll_failure(failure);
+        }
+
         Ok(issues)
     }
 }
@@ -454,6 +593,20 @@ impl KrillServer {
     }
 }
 
+/// # Snapshots of CAs and the repository
+///
+impl KrillServer {
+    /// Saves a snapshot of all CAs and the repository, so that no events need
+    /// to be replayed when the server starts again.
+    pub fn snapshot_all(&self) -> KrillEmptyResult {
+        self.caserver.snapshot_all()?;
+        if let Some(pubserver) = &self.pubserver {
+            pubserver.snapshot_all()?;
+        }
+        Ok(())
+    }
+}
+
 /// # Admin CAS
 ///
 impl KrillServer {
@@ -481,6 +634,51 @@ impl KrillServer {
         self.caserver.get_ca_history(handle).ok()
     }
 
+    /// Returns the public CA info for a CA as it was at the given point in
+    /// its history.
+    pub fn ca_info_at(
+        &self,
+        handle: &Handle,
+        point: CertAuthHistoryPoint,
+    ) -> KrillResult<CertAuthInfo> {
+        self.caserver
+            .get_ca_at(handle, point)
+            .map(|ca| ca.as_ca_info())
+    }
+
+    /// Returns the differences between a CA at two points in its history.
+    pub fn ca_diff(
+        &self,
+        handle: &Handle,
+        from: CertAuthHistoryPoint,
+        to: CertAuthHistoryPoint,
+    ) -> KrillResult<CertAuthDiff> {
+        self.caserver.get_ca_diff(handle, from, to)
+    }
+
+    /// Returns the commands processed by all CAs, or by the CA in the
+    /// criteria, which match the criteria.
+    pub fn audit(&self, criteria: &AuditCriteria) -> KrillResult<AuditLog> {
+        let handles = match criteria.ca() {
+            Some(ca) => vec![ca.clone()],
+            None => self
+                .caserver
+                .ca_list()
+                .cas()
+                .iter()
+                .map(|ca| ca.handle().clone())
+                .collect(),
+        };
+
+        let mut entries = vec![];
+        for handle in handles {
+            let ca_entries = self.caserver.get_ca_audit(&handle)?;
+            entries.extend(ca_entries.into_iter().filter(|e| criteria.matches(e)));
+        }
+
+        Ok(AuditLog::new(entries))
+    }
+
     /// Returns the publisher request for a CA, or NONE of the CA cannot be found.
     pub fn ca_publisher_req(&self, handle: &Handle) -> Option<rfc8183::PublisherRequest> {
         self.caserver
@@ -489,12 +687,48 @@ impl KrillServer {
             .ok()
     }
 
-    pub fn ca_init(&mut self, init: CertAuthInit) -> KrillEmptyResult {
+    pub fn ca_init(&mut self, init: CertAuthInit, actor: &str) -> KrillEmptyResult {
         let handle = init.unpack();
 
         // Create CA
-        self.caserver.init_ca(&handle)?;
+        self.caserver.init_ca(&handle, actor)?;
+
+        Ok(())
+    }
+
+    /// Deletes a CA. Its children are removed, its keys are revoked by its
+    /// parents and its objects are withdrawn from its repository. The history
+    /// of the CA is archived, rather than deleted, but only after all these
+    /// steps succeeded. Finally any queued events for the CA are dropped and
+    /// its keys are destroyed.
+    pub fn ca_delete(&self, handle: &Handle, actor: &str) -> KrillEmptyResult {
+        if handle == &ta_handle() {
+            return Err(Error::TaNotAllowed);
+        }
+
+        let ca = self.caserver.get_ca(handle)?;
+
+        // Remove children, revoke keys and withdraw objects first. If any
+        // of these fail the CA is kept, so that the delete can be retried.
+        self.caserver.ca_remove_all_children(handle, actor)?;
+        self.caserver.ca_revoke_all_keys(handle)?;
+
+        let publisher = CaPublisher::new(self.caserver.clone(), self.pubserver.clone());
+        if let Err(e) = publisher.clean_up(handle) {
+            warn!("Could not clean up old repo for '{}': {}", handle, e);
+        }
+        publisher.withdraw_all(handle)?;
 
+        self.caserver.ca_archive(handle)?;
+        self.event_queue.remove_ca(handle);
+
+        if let Ok(RepositoryContact::Embedded(_)) = ca.get_repository_contact() {
+            if let Err(e) = self.get_embedded()?.remove_publisher(handle.clone(), actor) {
+                warn!("Could not remove embedded publisher '{}': {}", handle, e);
+            }
+        }
+
+        info!("Deleted CA '{}'", handle);
         Ok(())
     }
 
@@ -514,7 +748,12 @@ impl KrillServer {
     }
 
     /// Update the repository for a CA, or return an error. (see `CertAuth::repo_update`)
-    pub fn ca_update_repo(&self, handle: Handle, update: RepositoryUpdate) -> KrillEmptyResult {
+    pub fn ca_update_repo(
+        &self,
+        handle: Handle,
+        update: RepositoryUpdate,
+        actor: &str,
+    ) -> KrillEmptyResult {
         let contact = match update {
             RepositoryUpdate::Embedded => {
                 // Add to embedded publication server if not present
@@ -524,7 +763,7 @@ impl KrillServer {
 
                     // Add publisher
                     let req = rfc8183::PublisherRequest::new(None, handle.clone(), id_cert);
-                    self.add_publisher(req)?;
+                    self.add_publisher(req, actor)?;
                 }
 
                 RepositoryContact::embedded(self.get_embedded()?.repo_info_for(&handle)?)
@@ -539,7 +778,7 @@ impl KrillServer {
             }
         };
 
-        Ok(self.caserver.update_repo(handle, contact)?)
+        Ok(self.caserver.update_repo(handle, contact, actor)?)
     }
 
     fn repo_state(
@@ -562,20 +801,29 @@ impl KrillServer {
         }
     }
 
-    pub fn ca_update_id(&self, handle: Handle) -> KrillEmptyResult {
-        Ok(self.caserver.ca_update_id(handle)?)
+    pub fn ca_update_id(&self, handle: Handle, actor: &str) -> KrillEmptyResult {
+        Ok(self.caserver.ca_update_id(handle, actor)?)
     }
 
-    pub fn ca_keyroll_init(&self, handle: Handle) -> KrillEmptyResult {
+    pub fn ca_keyroll_init(&self, handle: Handle, actor: &str) -> KrillEmptyResult {
         Ok(self
             .caserver
-            .ca_keyroll_init(handle, Duration::seconds(0))?)
+            .ca_keyroll_init(handle, Duration::seconds(0), actor)?)
     }
 
-    pub fn ca_keyroll_activate(&self, handle: Handle) -> KrillEmptyResult {
+    pub fn ca_keyroll_activate(&self, handle: Handle, actor: &str) -> KrillEmptyResult {
         Ok(self
             .caserver
-            .ca_keyroll_activate(handle, Duration::seconds(0))?)
+            .ca_keyroll_activate(handle, Duration::seconds(0), actor)?)
+    }
+
+    pub fn ca_keyroll_emergency(
+        &self,
+        handle: Handle,
+        request: KeyRollEmergencyRequest,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        self.caserver.ca_keyroll_emergency(handle, request, actor)
     }
 
     pub fn rfc6492(&self, handle: Handle, msg_bytes: Bytes) -> KrillResult<Bytes> {
@@ -590,14 +838,281 @@ impl KrillServer {
         &self,
         handle: Handle,
         updates: RoaDefinitionUpdates,
+        actor: &str,
     ) -> KrillEmptyResult {
-        Ok(self.caserver.ca_routes_update(handle, updates.into())?)
+        Ok(self
+            .caserver
+            .ca_routes_update(handle, updates.into(), actor)?)
     }
 
     pub fn ca_routes_show(&self, handle: &Handle) -> KrillResult<Vec<RoaDefinition>> {
         let ca = self.caserver.get_ca(handle)?;
         Ok(ca.roa_definitions())
     }
+
+    /// Shows which announcements in the configured BGP dump would change
+    /// validation state, if the updates were applied. Nothing is changed.
+    pub fn ca_routes_dry_run(
+        &self,
+        handle: &Handle,
+        updates: &RoaDefinitionUpdates,
+    ) -> KrillResult<RoaUpdatesImpact> {
+        let current = self.ca_routes_show(handle)?;
+
+        let announcements = self
+            .bgp_dump
+            .as_ref()
+            .ok_or_else(|| Error::BgpDumpNotConfigured)?
+            .get()?;
+
+        Ok(RoaUpdatesImpact::analyse(&current, updates, &announcements))
+    }
+
+    /// Analyses the ROA definitions of a CA, using the given announcements,
+    /// or else the configured BGP dump if there is one.
+    pub fn ca_routes_analyse(
+        &self,
+        handle: &Handle,
+        announcements: Option<Announcements>,
+    ) -> KrillResult<RoaAnalysis> {
+        let ca = self.caserver.get_ca(handle)?;
+        let definitions = ca.roa_definitions();
+        let resources = ca.all_resources();
+
+        let announcements = match (announcements, &self.bgp_dump) {
+            (Some(announcements), _) => Some(Arc::new(announcements)),
+            (None, Some(dump)) => Some(dump.get()?),
+            (None, None) => None,
+        };
+
+        Ok(RoaAnalysis::analyse(
+            &definitions,
+            &resources,
+            announcements.as_ref().map(Arc::as_ref),
+        ))
+    }
+
+    /// Replaces the ROA definitions for a CA with the desired definitions,
+    /// and returns the updates that this took, or would take for a dry run.
+    pub fn ca_routes_sync(
+        &self,
+        handle: Handle,
+        desired: Vec<RoaDefinition>,
+        options: RoaSyncOptions,
+        actor: &str,
+    ) -> KrillResult<RoaDefinitionUpdates> {
+        let desired = desired.into_iter().collect();
+        self.caserver
+            .ca_routes_sync(handle, &desired, options.dry_run(), actor)
+    }
+
+    /// Imports the complete set of ROA definitions for a CA from a file, and
+    /// returns the updates that this took, or would take for a dry run.
+    pub fn ca_routes_import(
+        &self,
+        handle: Handle,
+        options: RoaFileOptions,
+        file: &str,
+        actor: &str,
+    ) -> KrillResult<RoaDefinitionUpdates> {
+        let desired = options
+            .format()
+            .parse(file)
+            .map_err(|e| Error::CaAuthorisationFileInvalid(handle.clone(), e))?;
+
+        self.caserver
+            .ca_routes_sync(handle, &desired, options.dry_run(), actor)
+    }
+
+    /// Exports the complete set of ROA definitions for a CA as a file.
+    pub fn ca_routes_export(&self, handle: &Handle, format: RoaFileFormat) -> KrillResult<String> {
+        let definitions = self.ca_routes_show(handle)?;
+        Ok(format.format(&definitions))
+    }
+}
+
+/// # Handle ASPA requests
+///
+impl KrillServer {
+    pub fn ca_aspas_show(&self, handle: &Handle) -> KrillResult<AspaDefinitionList> {
+        let ca = self.caserver.get_ca(handle)?;
+        Ok(ca.aspa_definitions())
+    }
+
+    pub fn ca_aspas_update(
+        &self,
+        handle: Handle,
+        updates: AspaDefinitionUpdates,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        self.caserver.ca_aspas_update(handle, updates, actor)
+    }
+
+    pub fn ca_aspas_update_providers(
+        &self,
+        handle: Handle,
+        customer: AsNumber,
+        update: AspaProvidersUpdate,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        self.caserver
+            .ca_aspas_update_providers(handle, customer, update, actor)
+    }
+}
+
+/// # Handle BGPsec router certificate requests
+///
+impl KrillServer {
+    pub fn ca_bgpsec_show(&self, handle: &Handle) -> KrillResult<BgpSecDefinitionList> {
+        let ca = self.caserver.get_ca(handle)?;
+        Ok(ca.bgpsec_definitions())
+    }
+
+    pub fn ca_bgpsec_update(
+        &self,
+        handle: Handle,
+        updates: BgpSecDefinitionUpdates,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        self.caserver
+            .ca_bgpsec_update_definitions(handle, updates, actor)
+    }
+}
+
+/// # Handle Resource Signed Checklist requests
+///
+impl KrillServer {
+    pub fn ca_sign_checklist(
+        &self,
+        handle: &Handle,
+        request: RscRequest,
+    ) -> KrillResult<SignedChecklist> {
+        self.caserver.ca_sign_checklist(handle, request)
+    }
+}
+
+/// # Key roll policy
+///
+impl KrillServer {
+    pub fn ca_key_roll_info(&self, handle: &Handle) -> KrillResult<CaKeyRollInfo> {
+        self.caserver.ca_key_roll_info(handle)
+    }
+
+    /// Sets the policy for automatic key rolls for a CA, or removes it if None.
+    pub fn ca_key_roll_policy_update(
+        &self,
+        handle: Handle,
+        policy: Option<KeyRollPolicy>,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        self.caserver
+            .ca_key_roll_policy_update(handle, policy, actor)
+    }
+}
+
+/// # Timing of published objects and issued certificates
+///
+impl KrillServer {
+    pub fn ca_timing(&self, handle: &Handle) -> KrillResult<CaTimingInfo> {
+        self.caserver.ca_timing(handle)
+    }
+
+    /// Sets the timing for a CA, or reverts it to the server timing if None.
+    pub fn ca_timing_update(
+        &self,
+        handle: Handle,
+        timing: Option<TimingConfig>,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        self.caserver.ca_timing_update(handle, timing, actor)
+    }
+}
+
+/// # ROA prefix grouping
+///
+impl KrillServer {
+    pub fn ca_roa_grouping(&self, handle: &Handle) -> KrillResult<RoaGroupingInfo> {
+        self.caserver.ca_roa_grouping(handle)
+    }
+
+    /// Sets the ROA grouping for a CA, or reverts it to the server strategy
+    /// if None.
+    pub fn ca_roa_grouping_update(
+        &self,
+        handle: Handle,
+        strategy: Option<RoaPrefixGroupingStrategy>,
+        actor: &str,
+    ) -> KrillEmptyResult {
+        self.caserver
+            .ca_roa_grouping_update(handle, strategy, actor)
+    }
+}
+
+/// # Offline Trust Anchor
+///
+impl KrillServer {
+    /// Exports the requests of all CAs which have an offline Trust Anchor as
+    /// their parent, so that they can be taken to the Trust Anchor.
+    pub fn ta_offline_requests(&self, options: TaRequestOptions) -> KrillResult<TaRequestBundle> {
+        let requests = self.caserver.ta_offline_requests()?;
+        Ok(TaRequestBundle::new(requests, options.republish()))
+    }
+
+    /// Lets the Trust Anchor of this, offline, server process the requests.
+    /// Returns its responses, along with all objects that it publishes.
+    pub fn ta_offline_sign(&self, bundle: TaRequestBundle) -> KrillResult<TaResponseBundle> {
+        let (requests, republish) = bundle.unpack();
+
+        let responses = self.caserver.ta_offline_sign(requests)?;
+        if republish {
+            self.caserver.republish(&ta_handle())?;
+        }
+
+        self.caserver.ta_offline_bundle(self.ta()?, responses)
+    }
+
+    /// Imports the responses of an offline Trust Anchor. Its objects are
+    /// published in the embedded repository, adding the Trust Anchor as a
+    /// publisher if needed, and its certificate is kept so that this server
+    /// can serve the TAL. Then the responses are processed by the CAs.
+    ///
+    /// The bundle must be signed by the Trust Anchor of this server, or
+    /// else by the offline Trust Anchor configured in ta_offline_id_cert.
+    pub fn ta_offline_responses(&self, bundle: TaResponseBundle, actor: &str) -> KrillEmptyResult {
+        let ta_handle = ta_handle();
+
+        let id_cert = if self.caserver.has_ca(&ta_handle) {
+            self.caserver.get_trust_anchor()?.id_cert().clone()
+        } else {
+            self.ta_offline_id_cert
+                .clone()
+                .ok_or_else(|| Error::TaOfflineIdCertMissing)?
+        };
+
+        // Verify the whole bundle before acting on any of it.
+        let responses = self
+            .caserver
+            .ta_offline_responses_verify(&bundle, &id_cert)?;
+
+        // If this server has the TA itself, e.g. when testing, then the TA
+        // already publishes its own objects here.
+        if !self.caserver.has_ca(&ta_handle) {
+            let pubserver = self.get_embedded()?;
+            if !pubserver.publishers()?.contains(&ta_handle) {
+                let req = rfc8183::PublisherRequest::new(None, ta_handle.clone(), id_cert.clone());
+                pubserver.create_publisher(req, actor)?;
+            }
+
+            let publisher = CaPublisher::new(self.caserver.clone(), self.pubserver.clone());
+            publisher.publish_objects(&ta_handle, responses.objects().to_vec())?;
+
+            let path = file::file_path(&self.work_dir, TA_OFFLINE_FILE);
+            file::save_json(responses.ta(), &path)?;
+        }
+
+        self.caserver
+            .ta_offline_responses(responses.responses(), &id_cert)
+    }
 }
 
 /// # Handle publication requests
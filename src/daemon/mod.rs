@@ -4,6 +4,7 @@ pub mod config;
 pub mod endpoints;
 pub mod http;
 pub mod krillserver;
+pub mod oidc;
 pub mod scheduler;
 pub mod test;
 
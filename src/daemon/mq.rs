@@ -3,20 +3,38 @@
 //! signed material, or asking a newly added parent for resource
 //! entitlements.
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::sync::RwLock;
 
-use crate::commons::api::{Handle, ParentHandle, ResourceClassName, RevocationRequest};
+use chrono::Duration;
+use rand::{thread_rng, Rng};
+use serde_json;
+
+use rpki::x509::Time;
+
+use crate::commons::api::{
+    Handle, ParentHandle, QueueRetryInfo, ResourceClassName, RevocationRequest,
+};
+use crate::commons::error::Error;
 use crate::commons::eventsourcing::{self, Event};
+use crate::commons::util::file;
+use crate::constants::{
+    EVENT_QUEUE_DIR, QUEUE_RETRY_BASE_SECONDS, QUEUE_RETRY_MAX_ATTEMPTS,
+    QUEUE_RETRY_MAX_SECONDS,
+};
 use crate::daemon::ca::{CertAuth, Evt, EvtDet, Signer};
 
 //------------ QueueEvent ----------------------------------------------------
 
 /// This type contains all the events of interest for a KrillServer, with
 /// the details needed for triggered processing.
-#[derive(Clone, Debug, Display, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Display, Eq, PartialEq, Serialize)]
 #[allow(clippy::large_enum_variant)]
+#[serde(rename_all = "snake_case")]
 pub enum QueueEvent {
     #[display(fmt = "delta for '{}' version '{}'", _0, _1)]
     Delta(Handle, u64),
@@ -48,26 +66,178 @@ pub enum QueueEvent {
 
     #[display(fmt = "clean up old repo *if it exists* for '{}' version '{}'", _0, _1)]
     CleanOldRepo(Handle, u64),
+
+    #[display(
+        fmt = "activate new keys in emergency key roll for '{}' version '{}'",
+        _0,
+        _1
+    )]
+    KeyRollEmergency(Handle, u64),
+}
+
+impl QueueEvent {
+    /// The CA this event applies to.
+    pub fn handle(&self) -> &Handle {
+        match self {
+            QueueEvent::Delta(handle, _)
+            | QueueEvent::ParentAdded(handle, _, _)
+            | QueueEvent::RepositoryConfigured(handle, _)
+            | QueueEvent::RequestsPending(handle, _)
+            | QueueEvent::ResourceClassRemoved(handle, _, _, _)
+            | QueueEvent::UnexpectedKey(handle, _, _, _)
+            | QueueEvent::CleanOldRepo(handle, _)
+            | QueueEvent::KeyRollEmergency(handle, _) => handle,
+        }
+    }
 }
 
+//------------ QueueEventId --------------------------------------------------
+
+/// Identifies an event popped from the queue, so that it can be acknowledged
+/// after it has been processed successfully.
+pub type QueueEventId = u64;
+
+//------------ CaRetryState --------------------------------------------------
+
+/// The retry state for a CA. The delay before retrying is based on the
+/// number of failures for the CA since its last successful event, so that
+/// a CA whose parent or repository is unavailable does not get a fresh
+/// backoff for every new event.
+#[derive(Debug, Default)]
+struct CaRetryState {
+    failures: u32,
+    events: BTreeMap<QueueEventId, QueueRetryInfo>,
+}
+
+//------------ EventQueueListener --------------------------------------------
+
 #[derive(Debug)]
 pub struct EventQueueListener {
     q: RwLock<Box<dyn EventQueueStore>>,
+    retries: RwLock<HashMap<Handle, CaRetryState>>,
 }
 
 impl EventQueueListener {
     pub fn in_mem() -> Self {
         EventQueueListener {
             q: RwLock::new(Box::new(MemoryEventQueue::new())),
+            retries: RwLock::new(HashMap::new()),
         }
     }
+
+    /// Creates a listener which keeps its queue on disk under the work_dir,
+    /// so that events which were not yet acknowledged when the server
+    /// stopped are replayed when it is started again.
+    pub fn disk(work_dir: &PathBuf) -> Result<Self, io::Error> {
+        let q = DiskEventQueue::build(work_dir)?;
+        Ok(EventQueueListener {
+            q: RwLock::new(Box::new(q)),
+            retries: RwLock::new(HashMap::new()),
+        })
+    }
 }
 
 impl EventQueueListener {
-    pub fn pop(&self) -> Option<QueueEvent> {
+    /// Pops the next event. The event should be acknowledged using its id
+    /// once it has been processed. Un-acknowledged events are delivered
+    /// again when the server is restarted.
+    pub fn pop(&self) -> Option<(QueueEventId, QueueEvent)> {
         self.q.write().unwrap().pop()
     }
 
+    /// Acknowledges that the event with this id was processed successfully,
+    /// so it will not be delivered again. This resets the backoff for the
+    /// CA, and clears the state for its events which were given up on. Other
+    /// events of the CA which are waiting to be retried keep their state.
+    pub fn ack(&self, id: QueueEventId, handle: &Handle) {
+        self.q.write().unwrap().ack(id);
+
+        let mut retries = self.retries.write().unwrap();
+        let clear = match retries.get_mut(handle) {
+            Some(state) => {
+                state.failures = 0;
+                state.events.remove(&id);
+                state.events.retain(|_, info| !info.is_given_up());
+                state.events.is_empty()
+            }
+            None => false,
+        };
+        if clear {
+            retries.remove(handle);
+        }
+    }
+
+    /// Drops all queued events and retry state for a CA, e.g. because it
+    /// was removed.
+    pub fn remove_ca(&self, handle: &Handle) {
+        self.q.write().unwrap().remove_ca(handle);
+        self.retries.write().unwrap().remove(handle);
+    }
+
+    /// Reschedules an event which could not be processed, using exponential
+    /// backoff (with jitter) based on the number of failures for the CA
+    /// since its last success. Gives up on the event after it failed
+    /// QUEUE_RETRY_MAX_ATTEMPTS times.
+    ///
+    /// Note that the retry state is kept in memory only. Un-acknowledged
+    /// events are delivered again straight away after a restart, and events
+    /// which were given up on are no longer reported.
+    pub fn retry(&self, id: QueueEventId, evt: QueueEvent, error: &Error) {
+        let handle = evt.handle().clone();
+        let mut retries = self.retries.write().unwrap();
+        let state = retries.entry(handle.clone()).or_default();
+
+        state.failures += 1;
+        let attempts = state.events.get(&id).map(|r| r.attempts()).unwrap_or(0) + 1;
+
+        if attempts >= QUEUE_RETRY_MAX_ATTEMPTS {
+            error!(
+                "Giving up on event '{}' after {} failed attempts for CA '{}'",
+                evt, attempts, handle
+            );
+            self.q.write().unwrap().ack(id);
+            let info =
+                QueueRetryInfo::given_up(evt.to_string(), attempts, error.to_error_response());
+            state.events.insert(id, info);
+        } else {
+            let next = Time::now() + Self::retry_delay(state.failures);
+            info!(
+                "Will retry event '{}' at {} (attempt {})",
+                evt,
+                next.to_rfc3339(),
+                attempts + 1
+            );
+            let info = QueueRetryInfo::scheduled(
+                evt.to_string(),
+                attempts,
+                error.to_error_response(),
+                next,
+            );
+            self.q.write().unwrap().delay(id, evt, next);
+            state.events.insert(id, info);
+        }
+    }
+
+    /// Returns the retry state for each event of the CA which failed, and
+    /// which is being retried, or was given up on, ordered by event.
+    pub fn retry_info(&self, handle: &Handle) -> Vec<QueueRetryInfo> {
+        match self.retries.read().unwrap().get(handle) {
+            Some(state) => state.events.values().cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    /// Doubles the delay for every failed attempt up to a maximum, and then
+    /// picks a random delay between half and the full value, so that retries
+    /// for many CAs affected by the same outage are spread out.
+    fn retry_delay(attempts: u32) -> Duration {
+        let exp = attempts.saturating_sub(1).min(20);
+        let secs = QUEUE_RETRY_BASE_SECONDS
+            .saturating_mul(1 << exp)
+            .min(QUEUE_RETRY_MAX_SECONDS);
+        Duration::seconds(thread_rng().gen_range(secs / 2, secs + 1))
+    }
+
     fn push_back(&self, evt: QueueEvent) {
         self.q.write().unwrap().push_back(evt)
     }
@@ -79,7 +249,7 @@ unsafe impl Sync for EventQueueListener {}
 
 /// Implement listening for CertAuth Published events.
 impl<S: Signer> eventsourcing::EventListener<CertAuth<S>> for EventQueueListener {
-    fn listen(&self, _ca: &CertAuth<S>, event: &Evt) {
+    fn listen(&self, ca: &CertAuth<S>, event: &Evt) {
         trace!("Seen CertAuth event '{}'", event);
 
         let handle = event.handle();
@@ -87,12 +257,20 @@ impl<S: Signer> eventsourcing::EventListener<CertAuth<S>> for EventQueueListener
         match event.details() {
             EvtDet::ObjectSetUpdated(_, _)
             | EvtDet::ParentRemoved(_, _)
-            | EvtDet::KeyPendingToNew(_, _, _)
             | EvtDet::KeyPendingToActive(_, _, _)
             | EvtDet::KeyRollFinished(_, _) => {
                 let evt = QueueEvent::Delta(handle.clone(), version);
                 self.push_back(evt);
             }
+            EvtDet::KeyPendingToNew(_, _, _) => {
+                self.push_back(QueueEvent::Delta(handle.clone(), version));
+
+                // In an emergency key roll new keys are activated as soon
+                // as they are certified.
+                if ca.key_roll_emergency() {
+                    self.push_back(QueueEvent::KeyRollEmergency(handle.clone(), version));
+                }
+            }
             EvtDet::ResourceClassRemoved(class_name, _delta, parent, revocations) => {
                 self.push_back(QueueEvent::Delta(handle.clone(), version));
 
@@ -149,39 +327,389 @@ impl<S: Signer> eventsourcing::EventListener<CertAuth<S>> for EventQueueListener
 ///
 /// The EventQueue should implement Eventlistener
 trait EventQueueStore: fmt::Debug {
-    fn pop(&self) -> Option<QueueEvent>;
+    fn pop(&self) -> Option<(QueueEventId, QueueEvent)>;
+    fn ack(&self, id: QueueEventId);
     fn push_back(&self, evt: QueueEvent);
+
+    /// Removes all pending and delayed events for the CA.
+    fn remove_ca(&self, handle: &Handle);
+
+    /// Puts a popped, but not acknowledged, event back on the queue. It will
+    /// not be popped again before the given time.
+    fn delay(&self, id: QueueEventId, evt: QueueEvent, until: Time);
 }
 
 //------------ MemoryEventQueue ----------------------------------------------
 
-/// In memory event queue implementation.
+/// In memory event queue implementation. Events are considered delivered
+/// as soon as they are popped, so acknowledging them is a no-op.
 #[derive(Debug)]
 struct MemoryEventQueue {
-    q: RwLock<VecDeque<QueueEvent>>,
+    q: RwLock<QueueState>,
 }
 
 impl MemoryEventQueue {
     pub fn new() -> Self {
         MemoryEventQueue {
-            q: RwLock::new(VecDeque::new()),
+            q: RwLock::new(QueueState::default()),
         }
     }
 }
 
 impl EventQueueStore for MemoryEventQueue {
-    fn pop(&self) -> Option<QueueEvent> {
-        let res = self.q.write().unwrap().pop_front();
+    fn pop(&self) -> Option<(QueueEventId, QueueEvent)> {
+        let res = self.q.write().unwrap().pop();
 
-        if let Some(evt) = res.as_ref() {
+        if let Some((_, evt)) = res.as_ref() {
             trace!("Popping evt from schedule queue: {}", evt)
         }
 
         res
     }
 
+    fn ack(&self, id: QueueEventId) {
+        self.q.write().unwrap().remove(id);
+    }
+
     fn push_back(&self, evt: QueueEvent) {
         trace!("Pushing event to schedule queue: {}", evt);
-        self.q.write().unwrap().push_back(evt);
+        let mut state = self.q.write().unwrap();
+        let id = state.next_id();
+        state.pending.push_back((id, evt));
+    }
+
+    fn delay(&self, id: QueueEventId, evt: QueueEvent, until: Time) {
+        self.q.write().unwrap().delayed.push((until, id, evt));
+    }
+
+    fn remove_ca(&self, handle: &Handle) {
+        self.q.write().unwrap().remove_ca(handle);
+    }
+}
+
+//------------ QueueState ----------------------------------------------------
+
+/// The pending and delayed events, and the id to use for the next event,
+/// shared by the queue implementations.
+#[derive(Debug, Default)]
+struct QueueState {
+    next: QueueEventId,
+    pending: VecDeque<(QueueEventId, QueueEvent)>,
+    delayed: Vec<(Time, QueueEventId, QueueEvent)>,
+}
+
+impl QueueState {
+    fn next_id(&mut self) -> QueueEventId {
+        let id = self.next;
+        self.next += 1;
+        id
+    }
+
+    /// Removes an acknowledged event, so that it will not be delivered
+    /// again if it was also delayed for a retry.
+    fn remove(&mut self, id: QueueEventId) {
+        self.pending.retain(|(pending, _)| *pending != id);
+        self.delayed.retain(|(_, delayed, _)| *delayed != id);
+    }
+
+    /// Removes all pending and delayed events for the CA, and returns their
+    /// ids.
+    fn remove_ca(&mut self, handle: &Handle) -> Vec<QueueEventId> {
+        let mut removed = vec![];
+        self.pending.retain(|(id, evt)| {
+            let keep = evt.handle() != handle;
+            if !keep {
+                removed.push(*id);
+            }
+            keep
+        });
+        self.delayed.retain(|(_, id, evt)| {
+            let keep = evt.handle() != handle;
+            if !keep {
+                removed.push(*id);
+            }
+            keep
+        });
+        removed
+    }
+
+    /// Moves delayed events which are due to the pending queue, and then
+    /// pops the first pending event.
+    fn pop(&mut self) -> Option<(QueueEventId, QueueEvent)> {
+        if !self.delayed.is_empty() {
+            let now = Time::now();
+            let (due, delayed) = self
+                .delayed
+                .drain(..)
+                .partition::<Vec<_>, _>(|(until, _, _)| *until <= now);
+            self.delayed = delayed;
+            for (_, id, evt) in due {
+                self.pending.push_back((id, evt));
+            }
+        }
+        self.pending.pop_front()
+    }
+}
+
+//------------ DiskEventQueue ------------------------------------------------
+
+/// Event queue which saves each event as a json file in a directory under
+/// the work_dir, before making it available for processing. The file is
+/// only removed when the event is acknowledged, so any events which were
+/// pending or being processed when the server stopped (or crashed) are
+/// picked up again when the queue is built on startup. This gives us
+/// at-least-once delivery, which is fine because all triggered processes
+/// are idempotent.
+#[derive(Debug)]
+struct DiskEventQueue {
+    dir: PathBuf,
+    q: RwLock<QueueState>,
+}
+
+impl DiskEventQueue {
+    fn build(work_dir: &PathBuf) -> Result<Self, io::Error> {
+        let dir = file::sub_dir(work_dir, EVENT_QUEUE_DIR)?;
+
+        let mut saved: Vec<(QueueEventId, QueueEvent)> = vec![];
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            match Self::id_for_path(&path) {
+                Some(id) => match file::load_json::<QueueEvent>(&path) {
+                    Ok(evt) => saved.push((id, evt)),
+                    Err(e) => {
+                        warn!(
+                            "Could not load queued event from '{}', removing it. Error: {}",
+                            path.to_string_lossy(),
+                            e
+                        );
+                        file::delete(&path)?;
+                    }
+                },
+                None => {
+                    // Left-over from an incomplete write, the event was never
+                    // pushed to the queue, so we can safely remove it.
+                    file::delete(&path)?;
+                }
+            }
+        }
+
+        saved.sort_by_key(|(id, _)| *id);
+
+        let mut state = QueueState::default();
+        if let Some((id, _)) = saved.last() {
+            state.next = id + 1;
+        }
+        if !saved.is_empty() {
+            info!("Replaying {} un-acknowledged queued events", saved.len());
+        }
+        state.pending = saved.into_iter().collect();
+
+        Ok(DiskEventQueue {
+            dir,
+            q: RwLock::new(state),
+        })
+    }
+
+    fn path_for_id(&self, id: QueueEventId) -> PathBuf {
+        file::file_path(&self.dir, &format!("{:020}.json", id))
+    }
+
+    fn id_for_path(path: &PathBuf) -> Option<QueueEventId> {
+        let name = path.file_name()?.to_str()?;
+        if name.ends_with(".json") {
+            name.trim_end_matches(".json").parse::<QueueEventId>().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Writes the event to a temporary file first, and then renames it, so
+    /// that we never end up with a half-written event.
+    fn save(&self, id: QueueEventId, evt: &QueueEvent) -> Result<(), io::Error> {
+        let path = self.path_for_id(id);
+        let tmp_path = file::file_path(&self.dir, &format!("{:020}.tmp", id));
+
+        let json = serde_json::to_string(evt)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+        let mut f = fs::File::create(&tmp_path)?;
+        f.write_all(json.as_bytes())?;
+        f.sync_all()?;
+
+        fs::rename(&tmp_path, &path)
+    }
+}
+
+impl EventQueueStore for DiskEventQueue {
+    fn pop(&self) -> Option<(QueueEventId, QueueEvent)> {
+        let res = self.q.write().unwrap().pop();
+
+        if let Some((id, evt)) = res.as_ref() {
+            trace!("Popping evt '{}' from schedule queue: {}", id, evt)
+        }
+
+        res
+    }
+
+    fn ack(&self, id: QueueEventId) {
+        self.q.write().unwrap().remove(id);
+
+        let path = self.path_for_id(id);
+        if path.exists() {
+            if let Err(e) = file::delete(&path) {
+                error!("Could not remove acknowledged event '{}': {}", id, e);
+            }
+        }
+    }
+
+    fn push_back(&self, evt: QueueEvent) {
+        trace!("Pushing event to schedule queue: {}", evt);
+        let mut state = self.q.write().unwrap();
+        let id = state.next_id();
+        if let Err(e) = self.save(id, &evt) {
+            // We can still process the event, but it will not survive a restart.
+            error!("Could not save queued event '{}' to disk: {}", evt, e);
+        }
+        state.pending.push_back((id, evt));
+    }
+
+    fn delay(&self, id: QueueEventId, evt: QueueEvent, until: Time) {
+        // The event is still saved on disk, because it was not acknowledged.
+        self.q.write().unwrap().delayed.push((until, id, evt));
+    }
+
+    fn remove_ca(&self, handle: &Handle) {
+        for id in self.q.write().unwrap().remove_ca(handle) {
+            let path = self.path_for_id(id);
+            if let Err(e) = file::delete(&path) {
+                error!("Could not remove event '{}' for removed CA: {}", id, e);
+            }
+        }
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::commons::util::test;
+
+    #[test]
+    fn disk_queue_replays_unacknowledged_events() {
+        test::test_under_tmp(|d| {
+            let ca = Handle::from_str_unsafe("ca");
+
+            let first = QueueEvent::Delta(ca.clone(), 1);
+            let second = QueueEvent::RequestsPending(ca.clone(), 2);
+            let third = QueueEvent::CleanOldRepo(ca, 3);
+
+            {
+                let q = EventQueueListener::disk(&d).unwrap();
+                q.push_back(first.clone());
+                q.push_back(second.clone());
+                q.push_back(third.clone());
+
+                let (id, evt) = q.pop().unwrap();
+                assert_eq!(first, evt);
+                q.ack(id);
+
+                // popped, but not acknowledged
+                let (_, evt) = q.pop().unwrap();
+                assert_eq!(second, evt);
+            }
+
+            let q = EventQueueListener::disk(&d).unwrap();
+            assert_eq!(second, q.pop().unwrap().1);
+            assert_eq!(third, q.pop().unwrap().1);
+            assert!(q.pop().is_none());
+
+            // new events must not re-use ids of replayed events
+            q.push_back(first.clone());
+            let (id, _) = q.pop().unwrap();
+            assert_eq!(3, id);
+        })
+    }
+
+    #[test]
+    fn acknowledged_delayed_event_is_not_delivered_again() {
+        let evt = QueueEvent::Delta(Handle::from_str_unsafe("ca"), 1);
+
+        let mut state = QueueState::default();
+        let id = state.next_id();
+        state.delayed.push((Time::now(), id, evt.clone()));
+        state.remove(id);
+        assert!(state.pop().is_none());
+
+        let other = state.next_id();
+        state.delayed.push((Time::now(), id, evt.clone()));
+        state.pending.push_back((other, evt.clone()));
+        state.remove(other);
+        assert_eq!(Some((id, evt)), state.pop());
+    }
+
+    #[test]
+    fn retry_with_backoff_and_give_up() {
+        let ca = Handle::from_str_unsafe("ca");
+        let evt = QueueEvent::Delta(ca.clone(), 1);
+        let err = Error::custom("repository unavailable");
+
+        let q = EventQueueListener::in_mem();
+        q.push_back(evt.clone());
+
+        let (id, popped) = q.pop().unwrap();
+        q.retry(id, popped, &err);
+
+        // the event is delayed, so it is not available straight away
+        assert!(q.pop().is_none());
+        let info = &q.retry_info(&ca)[0];
+        assert_eq!(1, info.attempts());
+        assert!(info.next_attempt().unwrap() > Time::now());
+
+        // a later success for the event clears its retry state
+        q.ack(id, &ca);
+        assert!(q.retry_info(&ca).is_empty());
+
+        // give up after max attempts
+        q.push_back(evt.clone());
+        let (id, popped) = q.pop().unwrap();
+        for _ in 1..QUEUE_RETRY_MAX_ATTEMPTS {
+            q.retry(id, popped.clone(), &err);
+        }
+        q.retry(id, popped, &err);
+        assert!(q.retry_info(&ca)[0].is_given_up());
+
+        // another event of the CA has its own attempts, but shares the
+        // backoff of the CA
+        q.push_back(QueueEvent::RequestsPending(ca.clone(), 2));
+        let (other_id, other) = q.pop().unwrap();
+        q.retry(other_id, other, &err);
+        let infos = q.retry_info(&ca);
+        assert_eq!(2, infos.len());
+        assert!(infos[0].is_given_up());
+        assert_eq!(1, infos[1].attempts());
+        let delay = infos[1].next_attempt().unwrap().timestamp() - Time::now().timestamp();
+        assert!(delay >= QUEUE_RETRY_MAX_SECONDS / 2 - 1);
+
+        // a success for the CA clears the given up event, and resets the
+        // backoff for the CA
+        q.push_back(QueueEvent::CleanOldRepo(ca.clone(), 3));
+        let (third_id, _) = q.pop().unwrap();
+        q.ack(third_id, &ca);
+        let infos = q.retry_info(&ca);
+        assert_eq!(1, infos.len());
+        assert!(!infos[0].is_given_up());
+
+        q.ack(other_id, &ca);
+        assert!(q.retry_info(&ca).is_empty());
+        assert!(q.retries.read().unwrap().is_empty());
+
+        for attempts in 1..30 {
+            let delay = EventQueueListener::retry_delay(attempts).num_seconds();
+            assert!(delay <= QUEUE_RETRY_MAX_SECONDS);
+            assert!(delay >= QUEUE_RETRY_BASE_SECONDS / 2);
+        }
     }
 }
@@ -0,0 +1,527 @@
+//! Support for logging in to the UI and API using an OpenID Connect
+//! identity provider (IdP), using the authorization code flow.
+//!
+//! Users are sent to the IdP to log in, and are sent back to krill with a
+//! code. Krill exchanges the code for an ID token at the IdP, verifies it,
+//! and maps the claims in it to a krill role. It then creates a session,
+//! which is identified by a cookie.
+//!
+//! Because browsers send the session cookie with any request to krill,
+//! requests that make changes must also include the CSRF token of the
+//! session in a header. The UI reads this token from a separate cookie,
+//! which other sites cannot read.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+use openssl::bn::BigNum;
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use openssl::sign::Verifier;
+use reqwest::Url;
+use serde_json::Value;
+
+use rpki::x509::Time;
+
+use crate::commons::api::{Handle, Role};
+use crate::commons::error::Error;
+use crate::commons::util::httpclient;
+use crate::commons::KrillResult;
+use crate::daemon::config::Config;
+
+/// The name of the cookie used to identify a session.
+pub const SESSION_COOKIE: &str = "krill_session";
+
+/// The name of the cookie that holds the CSRF token of a session. Unlike
+/// the session cookie, this cookie can be read by the UI.
+pub const CSRF_COOKIE: &str = "krill_csrf";
+
+/// The header that must hold the CSRF token in requests that use a session
+/// to make changes.
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// Logins that are not completed within this time are forgotten.
+const PENDING_LOGIN_SECONDS: i64 = 600;
+
+/// The maximum number of logins that can be pending at the same time. When
+/// more logins are started, the oldest pending login is forgotten.
+const MAX_PENDING_LOGINS: usize = 1000;
+
+/// Allowed clock skew when checking the expiry of ID tokens.
+const CLOCK_SKEW_SECONDS: i64 = 60;
+
+//------------ OidcRoleMapping -----------------------------------------------
+
+/// Maps a value of the role claim in the ID token to a krill role.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct OidcRoleMapping {
+    claim_value: String,
+    role: Role,
+    #[serde(default)]
+    cas: Vec<Handle>,
+}
+
+impl OidcRoleMapping {
+    pub fn new(claim_value: &str, role: Role, cas: Vec<Handle>) -> Self {
+        OidcRoleMapping {
+            claim_value: claim_value.to_string(),
+            role,
+            cas,
+        }
+    }
+
+    pub fn claim_value(&self) -> &str {
+        &self.claim_value
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn cas(&self) -> &Vec<Handle> {
+        &self.cas
+    }
+}
+
+//------------ OidcCallback --------------------------------------------------
+
+/// The query parameters of the redirect from the IdP back to krill.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OidcCallback {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+//------------ Session -------------------------------------------------------
+
+/// A logged in user.
+#[derive(Clone, Debug)]
+pub struct Session {
+    name: String,
+    role: Role,
+    cas: Vec<Handle>,
+    expires: Time,
+    csrf_token: String,
+}
+
+impl Session {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn cas(&self) -> &Vec<Handle> {
+        &self.cas
+    }
+
+    pub fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+
+    /// Checks the CSRF token sent with a request, in constant time.
+    pub fn csrf_token_matches(&self, token: &str) -> bool {
+        token.len() == self.csrf_token.len()
+            && openssl::memcmp::eq(token.as_bytes(), self.csrf_token.as_bytes())
+    }
+}
+
+//------------ OidcAuthenticator ---------------------------------------------
+
+/// Takes care of the login flow, and keeps the sessions of logged in users.
+///
+/// The IdP configuration is discovered when the first login starts, so that
+/// krill can start even if the IdP is not reachable.
+pub struct OidcAuthenticator {
+    issuer: String,
+    client_id: String,
+    client_secret: String,
+    scope: String,
+    name_claim: String,
+    role_claim: String,
+    role_mappings: Vec<OidcRoleMapping>,
+    session_seconds: i64,
+    redirect_uri: String,
+
+    provider: RwLock<Option<Provider>>,
+    pending: RwLock<HashMap<String, PendingLogin>>,
+    sessions: RwLock<HashMap<String, Session>>,
+}
+
+impl OidcAuthenticator {
+    /// Builds the authenticator if an OIDC issuer is configured. The presence
+    /// of the other settings is checked in Config::verify.
+    pub fn build(config: &Config) -> Option<Self> {
+        let issuer = config.oidc_issuer.as_ref()?;
+
+        Some(OidcAuthenticator {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id: config.oidc_client_id.clone().unwrap_or_default(),
+            client_secret: config.oidc_client_secret.clone().unwrap_or_default(),
+            scope: config.oidc_scope.clone(),
+            name_claim: config.oidc_name_claim.clone(),
+            role_claim: config.oidc_role_claim.clone(),
+            role_mappings: config.oidc_role_mappings.clone(),
+            session_seconds: config.oidc_session_seconds,
+            redirect_uri: format!("{}auth/callback", config.service_uri()),
+            provider: RwLock::new(None),
+            pending: RwLock::new(HashMap::new()),
+            sessions: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Starts a login, returns the URI at the IdP to redirect the user to.
+    pub fn login_uri(&self) -> KrillResult<String> {
+        let provider = self.provider(false)?;
+
+        let state = random_string()?;
+        let nonce = random_string()?;
+
+        let uri = Url::parse_with_params(
+            &provider.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("scope", self.scope.as_str()),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+            ],
+        )
+        .map_err(|e| Error::OidcLogin(format!("invalid authorization endpoint: {}", e)))?;
+
+        let mut pending = self.pending.write().unwrap();
+        let now = Time::now().timestamp();
+        pending.retain(|_, login| now - login.started < PENDING_LOGIN_SECONDS);
+        while pending.len() >= MAX_PENDING_LOGINS {
+            let oldest = pending
+                .iter()
+                .min_by_key(|(_, login)| login.started)
+                .map(|(state, _)| state.clone())
+                .unwrap();
+            pending.remove(&oldest);
+        }
+        pending.insert(
+            state,
+            PendingLogin {
+                nonce,
+                started: now,
+            },
+        );
+
+        Ok(uri.to_string())
+    }
+
+    /// Completes a login, returns the id and the CSRF token of the new
+    /// session.
+    pub fn callback(&self, callback: OidcCallback) -> KrillResult<(String, String)> {
+        if let Some(error) = callback.error {
+            return Err(Error::OidcLogin(format!(
+                "identity provider says: {}",
+                error
+            )));
+        }
+
+        let code = callback
+            .code
+            .ok_or_else(|| Error::OidcLogin("no code in callback".to_string()))?;
+        let state = callback
+            .state
+            .ok_or_else(|| Error::OidcLogin("no state in callback".to_string()))?;
+
+        let login = self
+            .pending
+            .write()
+            .unwrap()
+            .remove(&state)
+            .ok_or_else(|| Error::OidcLogin("unknown or expired login".to_string()))?;
+
+        let provider = self.provider(false)?;
+        let response: TokenResponse = httpclient::post_form_with_response(
+            &provider.token_endpoint,
+            &[
+                ("grant_type", "authorization_code"),
+                ("code", code.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+            ],
+        )
+        .map_err(|e| Error::OidcLogin(format!("cannot get token: {}", e)))?;
+
+        let claims = self.verify_id_token(&response.id_token, &login.nonce)?;
+        let session = self.session_for_claims(&claims)?;
+
+        info!(
+            "User '{}' logged in with role '{}'",
+            session.name, session.role
+        );
+
+        let id = random_string()?;
+        let csrf_token = session.csrf_token.clone();
+        let mut sessions = self.sessions.write().unwrap();
+        let now = Time::now();
+        sessions.retain(|_, session| session.expires > now);
+        sessions.insert(id.clone(), session);
+
+        Ok((id, csrf_token))
+    }
+
+    /// Returns the session for the id, unless it is unknown or expired.
+    pub fn session(&self, id: &str) -> Option<Session> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(id)
+            .filter(|session| session.expires > Time::now())
+            .cloned()
+    }
+
+    /// Ends a session, if the CSRF token is the one of the session. Returns
+    /// whether the session was ended.
+    pub fn logout(&self, id: &str, csrf_token: &str) -> bool {
+        let mut sessions = self.sessions.write().unwrap();
+        let matches = sessions
+            .get(id)
+            .map(|session| session.csrf_token_matches(csrf_token))
+            .unwrap_or(false);
+        if matches {
+            sessions.remove(id);
+        }
+        matches
+    }
+
+    /// Returns the provider configuration, discovers it if needed, or if
+    /// a refresh is asked for, e.g. because the IdP rolled its keys.
+    fn provider(&self, refresh: bool) -> KrillResult<Provider> {
+        if !refresh {
+            if let Some(provider) = self.provider.read().unwrap().as_ref() {
+                return Ok(provider.clone());
+            }
+        }
+
+        let discovery_uri = format!("{}/.well-known/openid-configuration", self.issuer);
+        let discovery: Discovery = httpclient::get_json(&discovery_uri, None)
+            .map_err(|e| Error::OidcLogin(format!("cannot discover provider: {}", e)))?;
+
+        if discovery.issuer.trim_end_matches('/') != self.issuer {
+            return Err(Error::OidcLogin(format!(
+                "provider uses issuer '{}', expected '{}'",
+                discovery.issuer, self.issuer
+            )));
+        }
+
+        let jwks: Jwks = httpclient::get_json(&discovery.jwks_uri, None)
+            .map_err(|e| Error::OidcLogin(format!("cannot get provider keys: {}", e)))?;
+
+        let provider = Provider {
+            issuer: discovery.issuer,
+            authorization_endpoint: discovery.authorization_endpoint,
+            token_endpoint: discovery.token_endpoint,
+            keys: jwks.keys,
+        };
+
+        *self.provider.write().unwrap() = Some(provider.clone());
+        Ok(provider)
+    }
+
+    /// Verifies the signature and claims of the ID token, and returns the
+    /// claims.
+    fn verify_id_token(&self, id_token: &str, nonce: &str) -> KrillResult<Value> {
+        let invalid = |msg: &str| Error::OidcLogin(format!("invalid ID token: {}", msg));
+
+        let parts: Vec<&str> = id_token.split('.').collect();
+        if parts.len() != 3 {
+            return Err(invalid("not a JWT"));
+        }
+
+        let header: JwtHeader = serde_json::from_slice(&base64url(parts[0])?)
+            .map_err(|_| invalid("cannot parse header"))?;
+        if header.alg != "RS256" {
+            return Err(invalid("only RS256 signatures are supported"));
+        }
+
+        let mut provider = self.provider(false)?;
+        if provider.key(header.kid.as_ref()).is_none() {
+            provider = self.provider(true)?;
+        }
+        let key = provider
+            .key(header.kid.as_ref())
+            .ok_or_else(|| invalid("signed with unknown key"))?;
+
+        let signed = format!("{}.{}", parts[0], parts[1]);
+        let signature = base64url(parts[2])?;
+        if !key.verify(signed.as_bytes(), &signature)? {
+            return Err(invalid("bad signature"));
+        }
+
+        let claims: Value = serde_json::from_slice(&base64url(parts[1])?)
+            .map_err(|_| invalid("cannot parse claims"))?;
+
+        if claims["iss"].as_str() != Some(provider.issuer.as_str()) {
+            return Err(invalid("wrong issuer"));
+        }
+
+        let audience_ok = match &claims["aud"] {
+            Value::String(aud) => aud == &self.client_id,
+            Value::Array(auds) => auds.iter().any(|aud| aud.as_str() == Some(&self.client_id)),
+            _ => false,
+        };
+        if !audience_ok {
+            return Err(invalid("wrong audience"));
+        }
+
+        match claims["exp"].as_i64() {
+            Some(exp) if exp + CLOCK_SKEW_SECONDS > Time::now().timestamp() => {}
+            _ => return Err(invalid("expired")),
+        }
+
+        if claims["nonce"].as_str() != Some(nonce) {
+            return Err(invalid("wrong nonce"));
+        }
+
+        Ok(claims)
+    }
+
+    /// Maps the claims to a session. The first role mapping that matches
+    /// a value of the role claim is used. Users without a matching role
+    /// cannot log in.
+    fn session_for_claims(&self, claims: &Value) -> KrillResult<Session> {
+        let name = claims[self.name_claim.as_str()]
+            .as_str()
+            .or_else(|| claims["sub"].as_str())
+            .ok_or_else(|| Error::OidcLogin("no name or subject in ID token".to_string()))?
+            .to_string();
+
+        let values: Vec<&str> = match &claims[self.role_claim.as_str()] {
+            Value::String(value) => vec![value.as_str()],
+            Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+
+        let mapping = self
+            .role_mappings
+            .iter()
+            .find(|mapping| values.contains(&mapping.claim_value()))
+            .ok_or_else(|| Error::OidcLogin(format!("no krill role for user '{}'", name)))?;
+
+        let expires = Time::now() + chrono::Duration::seconds(self.session_seconds);
+
+        Ok(Session {
+            name,
+            role: mapping.role(),
+            cas: mapping.cas().clone(),
+            expires,
+            csrf_token: random_string()?,
+        })
+    }
+}
+
+/// Does not show the client secret or sessions.
+impl fmt::Debug for OidcAuthenticator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OidcAuthenticator")
+            .field("issuer", &self.issuer)
+            .field("client_id", &self.client_id)
+            .finish()
+    }
+}
+
+//------------ Provider ------------------------------------------------------
+
+#[derive(Clone, Debug)]
+struct Provider {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    keys: Vec<Jwk>,
+}
+
+impl Provider {
+    /// Finds the key with the given key id. If the token does not specify
+    /// a key id, then the provider must have exactly one key.
+    fn key(&self, kid: Option<&String>) -> Option<&Jwk> {
+        match kid {
+            Some(kid) => self.keys.iter().find(|k| k.kid.as_ref() == Some(kid)),
+            None if self.keys.len() == 1 => self.keys.first(),
+            None => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PendingLogin {
+    nonce: String,
+    started: i64,
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+impl Jwk {
+    fn verify(&self, data: &[u8], signature: &[u8]) -> KrillResult<bool> {
+        let invalid = || Error::OidcLogin("provider uses unsupported key".to_string());
+        if self.kty != "RSA" {
+            return Err(invalid());
+        }
+        let n = base64url(self.n.as_ref().ok_or_else(invalid)?)?;
+        let e = base64url(self.e.as_ref().ok_or_else(invalid)?)?;
+
+        let verify = || -> Result<bool, ErrorStack> {
+            let rsa =
+                Rsa::from_public_components(BigNum::from_slice(&n)?, BigNum::from_slice(&e)?)?;
+            let pkey = PKey::from_rsa(rsa)?;
+
+            let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey)?;
+            verifier.update(data)?;
+            verifier.verify(signature)
+        };
+
+        verify().map_err(|e| Error::OidcLogin(format!("cannot verify ID token: {}", e)))
+    }
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+fn base64url(s: &str) -> KrillResult<Vec<u8>> {
+    base64::decode_config(s, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| Error::OidcLogin("invalid base64url encoding".to_string()))
+}
+
+fn random_string() -> KrillResult<String> {
+    let mut bytes = [0_u8; 32];
+    openssl::rand::rand_bytes(&mut bytes).map_err(Error::signer)?;
+    Ok(hex::encode(bytes))
+}
@@ -2,12 +2,14 @@
 //! event that occurred, or planned (e.g. re-publishing).
 
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use clokwerk::{self, ScheduleHandle, TimeUnits};
 
-use crate::commons::util::softsigner::OpenSslSigner;
-use crate::daemon::ca::CaServer;
+use rpki::x509::Time;
+
+use crate::commons::KrillEmptyResult;
+use crate::daemon::ca::{CaServer, Signer};
 use crate::daemon::mq::{EventQueueListener, QueueEvent};
 use crate::pubd::PubServer;
 use crate::publish::CaPublisher;
@@ -27,127 +29,196 @@ pub struct Scheduler {
     /// they are not renewed within the configured grace period.
     #[allow(dead_code)] // just need to keep this in scope
     ca_refresh_sh: ScheduleHandle,
+
+    /// Responsible for initiating and activating key rolls for CAs which have
+    /// a key roll policy.
+    #[allow(dead_code)] // just need to keep this in scope
+    key_roll_sh: ScheduleHandle,
+
+    /// Responsible for archiving old commands and events, if configured.
+    #[allow(dead_code)] // just need to keep this in scope
+    archive_sh: Option<ScheduleHandle>,
 }
 
 impl Scheduler {
-    pub fn build(
+    pub fn build<S: Signer>(
         event_queue: Arc<EventQueueListener>,
-        caserver: Arc<CaServer<OpenSslSigner>>,
+        caserver: Arc<CaServer<S>>,
         pubserver: Option<Arc<PubServer>>,
         ca_refresh_rate: u32,
+        archive_after_days: Option<u32>,
     ) -> Self {
-        let event_sh = make_event_sh(event_queue, caserver.clone(), pubserver);
+        let event_sh = make_event_sh(event_queue, caserver.clone(), pubserver.clone());
         let republish_sh = make_republish_sh(caserver.clone());
-        let ca_refresh_sh = make_ca_refresh_sh(caserver, ca_refresh_rate);
+        let ca_refresh_sh = make_ca_refresh_sh(caserver.clone(), ca_refresh_rate);
+        let key_roll_sh = make_key_roll_sh(caserver.clone());
+        let archive_sh = archive_after_days.map(|days| make_archive_sh(caserver, pubserver, days));
 
         Scheduler {
             event_sh,
             republish_sh,
             ca_refresh_sh,
+            key_roll_sh,
+            archive_sh,
         }
     }
 }
 
-fn make_event_sh(
+fn make_event_sh<S: Signer>(
     event_queue: Arc<EventQueueListener>,
-    caserver: Arc<CaServer<OpenSslSigner>>,
+    caserver: Arc<CaServer<S>>,
     pubserver: Option<Arc<PubServer>>,
 ) -> ScheduleHandle {
     let mut scheduler = clokwerk::Scheduler::new();
     scheduler.every(1.seconds()).run(move || {
-        while let Some(evt) = event_queue.pop() {
-            match evt {
-                QueueEvent::Delta(handle, version) => {
-                    info!("Trigger publication for '{}' version '{}'", handle, version);
-                    let publisher = CaPublisher::new(caserver.clone(), pubserver.clone());
-
-                    if let Err(e) = publisher.publish(&handle) {
-                        error!("Failed to publish for CA: {}, error: {}", handle, e);
-                    }
-                }
-                QueueEvent::ResourceClassRemoved(handle, _, parent, revocations) => {
-                    info!(
-                        "Trigger send revoke requests for removed RC for '{}' under '{}'",
-                        handle,
-                        parent
-                    );
-                    if caserver
-                        .send_revoke_requests(&handle, &parent, revocations)
-                        .is_err()
-                    {
-                        warn!("Could not revoke key for removed resource class. This is not \
-                        an issue, because typically the parent will revoke our keys pro-actively, \
-                        just before removing the resource class entitlements.");
-                    }
-                }
-                QueueEvent::UnexpectedKey(handle, _, rcn, revocation) => {
-                    info!(
-                        "Trigger sending revocation requests for unexpected key with id '{}' in RC '{}'",
-                        revocation.key(),
-                        rcn
-                    );
-                    if let Err(e) = caserver
-                        .send_revoke_unexpected_key(&handle, rcn, revocation)
-                    {
-                        error!("Could not revoke unexpected surplus key at parent: {}", e);
-                    }
-                }
-                QueueEvent::ParentAdded(handle, _, parent) => {
-                    info!(
-                        "Get updates for '{}' from added parent '{}'.",
-                        handle,
-                        parent
-                    );
-                    if let Err(e) = caserver.get_updates_from_parent(&handle, &parent) {
-                        error!(
-                            "Error getting updates for '{}', from parent '{}',  error: '{}'",
-                            &handle, &parent, e
-                        )
-                    }
-                }
-                QueueEvent::RepositoryConfigured(ca, _) => {
-                    info!("Repository configured for '{}'", ca);
-                    if let Err(e) = caserver.get_delayed_updates(&ca) {
-                        error!(
-                            "Error getting updates after configuring repository for '{}',  error: '{}'",
-                            &ca, e
-                        )
-                    }
-                }
-
-                QueueEvent::RequestsPending(handle, _) => {
-                    info!("Get updates for pending requests for '{}'.", handle);
-                    if let Err(e) = caserver.send_all_requests(&handle) {
-                        error!(
-                            "Failed to send pending requests for '{}', error '{}'",
-                            &handle, e
-                        );
-                    }
-                }
-                QueueEvent::CleanOldRepo(handle, _) => {
-                    let publisher = CaPublisher::new(caserver.clone(), pubserver.clone());
-                    if let Err(e) = publisher.clean_up(&handle) {
-                        info!(
-                            "Could not clean up old repo for '{}', it may be that it's no longer available. Got error '{}'",
-                            &handle, e
-                        );
-                    }
-                    if let Err(e) = caserver.remove_old_repo(&handle) {
-                        error!(
-                            "Failed to remove old repo from ca '{}', error '{}'",
-                            &handle, e
-                        );
-                    }
-                }
+        while let Some((id, evt)) = event_queue.pop() {
+            // Events for CAs which were removed since can be dropped.
+            if !caserver.has_ca(evt.handle()) {
+                info!("Dropping event '{}' for removed CA", evt);
+                event_queue.ack(id, evt.handle());
+                continue;
+            }
+
+            // Only acknowledge events which were processed successfully,
+            // failed events are retried with an increasing delay.
+            match process_event(evt.clone(), &caserver, &pubserver) {
+                Ok(()) => event_queue.ack(id, evt.handle()),
+                Err(e) => event_queue.retry(id, evt, &e),
             }
         }
     });
     scheduler.watch_thread(Duration::from_millis(100))
 }
 
-fn make_republish_sh(caserver: Arc<CaServer<OpenSslSigner>>) -> ScheduleHandle {
+/// Processes an event from the queue.
+fn process_event<S: Signer>(
+    evt: QueueEvent,
+    caserver: &Arc<CaServer<S>>,
+    pubserver: &Option<Arc<PubServer>>,
+) -> KrillEmptyResult {
+    match evt {
+        QueueEvent::Delta(handle, version) => {
+            info!("Trigger publication for '{}' version '{}'", handle, version);
+            let publisher = CaPublisher::new(caserver.clone(), pubserver.clone());
+
+            publisher.publish(&handle).map_err(|e| {
+                error!("Failed to publish for CA: {}, error: {}", handle, e);
+                e
+            })
+        }
+        QueueEvent::ResourceClassRemoved(handle, _, parent, revocations) => {
+            info!(
+                "Trigger send revoke requests for removed RC for '{}' under '{}'",
+                handle,
+                parent
+            );
+            if caserver
+                .send_revoke_requests(&handle, &parent, revocations)
+                .is_err()
+            {
+                warn!("Could not revoke key for removed resource class. This is not \
+                an issue, because typically the parent will revoke our keys pro-actively, \
+                just before removing the resource class entitlements.");
+            }
+            Ok(())
+        }
+        QueueEvent::UnexpectedKey(handle, _, rcn, revocation) => {
+            info!(
+                "Trigger sending revocation requests for unexpected key with id '{}' in RC '{}'",
+                revocation.key(),
+                rcn
+            );
+            caserver
+                .send_revoke_unexpected_key(&handle, rcn, revocation)
+                .map(|_| ())
+                .map_err(|e| {
+                    error!("Could not revoke unexpected surplus key at parent: {}", e);
+                    e
+                })
+        }
+        QueueEvent::ParentAdded(handle, _, parent) => {
+            info!(
+                "Get updates for '{}' from added parent '{}'.",
+                handle,
+                parent
+            );
+            caserver
+                .get_updates_from_parent(&handle, &parent)
+                .map_err(|e| {
+                    error!(
+                        "Error getting updates for '{}', from parent '{}',  error: '{}'",
+                        &handle, &parent, e
+                    );
+                    e
+                })
+        }
+        QueueEvent::RepositoryConfigured(ca, _) => {
+            info!("Repository configured for '{}'", ca);
+            caserver.get_delayed_updates(&ca).map_err(|e| {
+                error!(
+                    "Error getting updates after configuring repository for '{}',  error: '{}'",
+                    &ca, e
+                );
+                e
+            })
+        }
+
+        QueueEvent::RequestsPending(handle, _) => {
+            info!("Get updates for pending requests for '{}'.", handle);
+            caserver.send_all_requests(&handle).map_err(|e| {
+                error!(
+                    "Failed to send pending requests for '{}', error '{}'",
+                    &handle, e
+                );
+                e
+            })
+        }
+        QueueEvent::KeyRollEmergency(handle, _) => {
+            info!("Activate new keys in emergency key roll for '{}'", handle);
+            caserver
+                .ca_keyroll_emergency_activate(&handle)
+                .map_err(|e| {
+                    error!(
+                        "Failed to activate new keys in emergency key roll for '{}', error '{}'",
+                        &handle, e
+                    );
+                    e
+                })
+        }
+        QueueEvent::CleanOldRepo(handle, _) => {
+            let publisher = CaPublisher::new(caserver.clone(), pubserver.clone());
+            if let Err(e) = publisher.clean_up(&handle) {
+                info!(
+                    "Could not clean up old repo for '{}', it may be that it's no longer available. Got error '{}'",
+                    &handle, e
+                );
+            }
+            caserver.remove_old_repo(&handle).map_err(|e| {
+                error!(
+                    "Failed to remove old repo from ca '{}', error '{}'",
+                    &handle, e
+                );
+                e
+            })
+        }
+    }
+}
+
+/// Checks every minute whether it is time to republish. The interval follows
+/// the shortest republish threshold of all CAs, so that CAs with a custom
+/// timing do not go stale.
+fn make_republish_sh<S: Signer>(caserver: Arc<CaServer<S>>) -> ScheduleHandle {
     let mut scheduler = clokwerk::Scheduler::new();
-    scheduler.every(1.hours()).run(move || {
+    let mut last_run: Option<Instant> = None;
+    scheduler.every(1.minutes()).run(move || {
+        let interval = caserver.republish_interval();
+        if let Some(last) = last_run {
+            if last.elapsed() < interval {
+                return;
+            }
+        }
+        last_run = Some(Instant::now());
+
         info!("Triggering background republication for all CAs");
         if let Err(e) = caserver.republish_all() {
             error!("Background republishing failed: {}", e);
@@ -156,7 +227,7 @@ fn make_republish_sh(caserver: Arc<CaServer<OpenSslSigner>>) -> ScheduleHandle {
     scheduler.watch_thread(Duration::from_millis(100))
 }
 
-fn make_ca_refresh_sh(caserver: Arc<CaServer<OpenSslSigner>>, refresh_rate: u32) -> ScheduleHandle {
+fn make_ca_refresh_sh<S: Signer>(caserver: Arc<CaServer<S>>, refresh_rate: u32) -> ScheduleHandle {
     let mut scheduler = clokwerk::Scheduler::new();
     scheduler.every(refresh_rate.seconds()).run(move || {
         info!("Triggering background refresh for all CAs");
@@ -164,3 +235,57 @@ fn make_ca_refresh_sh(caserver: Arc<CaServer<OpenSslSigner>>, refresh_rate: u32)
     });
     scheduler.watch_thread(Duration::from_millis(100))
 }
+
+/// Checks every hour whether keys need to be rolled, or new keys can be
+/// activated, for CAs with a key roll policy.
+fn make_key_roll_sh<S: Signer>(caserver: Arc<CaServer<S>>) -> ScheduleHandle {
+    let mut scheduler = clokwerk::Scheduler::new();
+    scheduler.every(1.hours()).run(move || {
+        info!("Triggering background key rolls for CAs with a key roll policy");
+        caserver.key_roll_all()
+    });
+    scheduler.watch_thread(Duration::from_millis(100))
+}
+
+/// Archives the commands and events older than the given number of days,
+/// once when the server starts and then every day.
+fn make_archive_sh<S: Signer>(
+    caserver: Arc<CaServer<S>>,
+    pubserver: Option<Arc<PubServer>>,
+    days: u32,
+) -> ScheduleHandle {
+    archive_events(&caserver, &pubserver, days);
+
+    let mut scheduler = clokwerk::Scheduler::new();
+    scheduler.every(1.days()).run(move || {
+        archive_events(&caserver, &pubserver, days);
+    });
+    scheduler.watch_thread(Duration::from_millis(100))
+}
+
+fn archive_events<S: Signer>(
+    caserver: &Arc<CaServer<S>>,
+    pubserver: &Option<Arc<PubServer>>,
+    days: u32,
+) {
+    let before = match Time::now().checked_sub_signed(chrono::Duration::days(i64::from(days))) {
+        Some(before) => Time::from(before),
+        None => return, // no commands can be that old
+    };
+
+    info!(
+        "Archiving commands and events from before {}",
+        before.to_rfc3339()
+    );
+    if let Err(e) = caserver.compact(before) {
+        error!("Could not archive commands and events for CAs: {}", e);
+    }
+    if let Some(pubserver) = pubserver {
+        if let Err(e) = pubserver.compact(before) {
+            error!(
+                "Could not archive commands and events for the repository: {}",
+                e
+            );
+        }
+    }
+}
@@ -1,22 +1,35 @@
 //! Support for tests in other modules using a running krill server
 
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::{thread, time};
 
+use rpki::manifest::Manifest;
 use rpki::uri::Rsync;
 use rpki::roa::Roa;
+use rpki::x509::Time;
 
-use crate::cli::options::{BulkCaCommand, CaCommand, Command, Options, PublishersCommand};
+use crate::cli::options::{
+    BulkCaCommand, CaCommand, Command, Options, PublishersCommand, TaOfflineCommand,
+};
 use crate::cli::report::{ApiResponse, ReportFormat};
 use crate::cli::{Error, KrillClient};
 use crate::commons::api::{
-    AddChildRequest, AsNumber, Base64, CertAuthInfo, CertAuthInit, CertifiedKeyInfo, ChildAuthRequest, ChildHandle,
-    Handle, ParentCaContact, ParentCaReq, ParentHandle, Publish, PublisherDetails, PublisherHandle,
-    RepositoryUpdate, ResourceClassKeysInfo, ResourceClassName, ResourceSet, RoaDefinition, RoaDefinitionUpdates,
+    AddChildRequest, AsNumber, AspaDefinition, AspaDefinitionList, AspaProvidersUpdate,
+    AuditCriteria, AuditLog, Base64, BgpSecAsnKey, BgpSecDefinition, BgpSecDefinitionList,
+    CaKeyRollInfo, CaTimingInfo, CertAuthDiff, CertAuthHistory, CertAuthHistoryPoint, CertAuthInfo,
+    CertAuthInit, CertAuthIssues, CertifiedKeyInfo, ChildAuthRequest, ChildHandle, Handle,
+    KeyRollEmergencyRequest, KeyRollPolicy, ParentCaContact, ParentCaReq, ParentHandle, Publish,
+    PublisherDetails, PublisherHandle, RepositoryUpdate, ResourceClassKeysInfo, ResourceClassName,
+    ResourceSet, RoaDefinition, RoaDefinitionUpdates, RoaFileFormat, RoaFileOptions,
+    RoaGroupingInfo, RoaPrefixGroupingStrategy, RoaSyncOptions, RscRequest, SignedChecklist,
+    TaRequestBundle, TaRequestOptions, TaResponseBundle, TaResponses, TimingConfig,
     UpdateChildRequest,
 };
+use crate::commons::bgp::{Announcements, RoaAnalysis, RoaUpdatesImpact};
 use crate::commons::remote::rfc8183;
 use crate::commons::remote::rfc8183::ChildRequest;
+use crate::commons::remote::sigmsg::SignedMessage;
 use crate::commons::util::test;
 use crate::daemon::ca::{convert_friendly_roa_ip_addr_to_typed_prefix, ta_handle};
 use crate::daemon::config::Config;
@@ -30,13 +43,26 @@ pub enum PubdTestContext {
 pub fn test_with_krill_server<F>(op: F)
 where
     F: FnOnce(PathBuf) -> (),
+{
+    test_with_configured_krill_server(|_| {}, op)
+}
+
+/// Like test_with_krill_server, but lets the test change the config
+/// before the server is started, e.g. to enable login with OpenID Connect.
+pub fn test_with_configured_krill_server<C, F>(configure: C, op: F)
+where
+    C: FnOnce(&mut Config),
+    F: FnOnce(PathBuf) -> (),
 {
     test::test_under_tmp(|dir| {
         // Set up a test PubServer Config
         let server_conf = {
             // Use a data dir for the storage
             let data_dir = test::sub_dir(&dir);
-            Config::test(&data_dir)
+            let mut config = Config::test(&data_dir);
+            configure(&mut config);
+            config.verify().unwrap();
+            config
         };
 
         // Start the server
@@ -60,8 +86,19 @@ where
 }
 
 pub fn start_krill_pubd_server() -> PathBuf {
+    start_configured_krill_pubd_server(|_| {})
+}
+
+/// Like start_krill_pubd_server, but lets the test change the config before
+/// the server is started.
+pub fn start_configured_krill_pubd_server<C>(configure: C) -> PathBuf
+where
+    C: FnOnce(&mut Config),
+{
     let data_dir = test::sub_dir(&PathBuf::from("work"));
-    let server_conf = Config::pubd_test(&data_dir);
+    let mut server_conf = Config::pubd_test(&data_dir);
+    configure(&mut server_conf);
+    server_conf.verify().unwrap();
 
     // Start the server
     thread::spawn(move || server::start(&server_conf).unwrap());
@@ -166,6 +203,10 @@ pub fn init_child_with_embedded_repo(handle: &Handle) {
     )));
 }
 
+pub fn delete_ca(handle: &Handle) {
+    krill_admin(Command::CertAuth(CaCommand::Delete(handle.clone())));
+}
+
 pub fn generate_new_id(handle: &Handle) {
     krill_admin(Command::CertAuth(CaCommand::UpdateId(handle.clone())));
 }
@@ -213,6 +254,21 @@ pub fn add_child_to_ta_rfc6492(
     }
 }
 
+pub fn add_child_embedded(
+    parent: &ParentHandle,
+    child: &ChildHandle,
+    resources: ResourceSet,
+) -> ParentCaContact {
+    let auth = ChildAuthRequest::Embedded;
+    let req = AddChildRequest::new(child.clone(), resources, auth);
+    let res = krill_admin(Command::CertAuth(CaCommand::ChildAdd(parent.clone(), req)));
+
+    match res {
+        ApiResponse::ParentCaContact(info) => info,
+        _ => panic!("Expected ParentCaInfo response"),
+    }
+}
+
 pub fn add_child_rfc6492(
     parent: &ParentHandle,
     child: &ChildHandle,
@@ -288,6 +344,66 @@ pub fn ca_roll_activate(handle: &Handle) {
     )));
 }
 
+pub fn ca_roll_emergency(handle: &Handle, reason: &str) {
+    let request = KeyRollEmergencyRequest::new(reason.to_string(), true);
+    krill_admin(Command::CertAuth(CaCommand::KeyRollEmergency(
+        handle.clone(),
+        request,
+    )));
+}
+
+pub fn ca_roll_emergency_not_possible(handle: &Handle, reason: &str) {
+    let request = KeyRollEmergencyRequest::new(reason.to_string(), true);
+    krill_admin_expect_error(Command::CertAuth(CaCommand::KeyRollEmergency(
+        handle.clone(),
+        request,
+    )));
+}
+
+pub fn ca_roll_emergency_unconfirmed(handle: &Handle, reason: &str) {
+    let request = KeyRollEmergencyRequest::new(reason.to_string(), false);
+    krill_admin_expect_error(Command::CertAuth(CaCommand::KeyRollEmergency(
+        handle.clone(),
+        request,
+    )));
+}
+
+pub fn ca_history(handle: &Handle) -> CertAuthHistory {
+    match krill_admin(Command::CertAuth(CaCommand::ShowHistory(handle.clone()))) {
+        ApiResponse::CertAuthHistory(history) => history,
+        _ => panic!("Expected CA history"),
+    }
+}
+
+pub fn audit(criteria: AuditCriteria) -> AuditLog {
+    match krill_admin(Command::Audit(criteria)) {
+        ApiResponse::AuditLog(log) => log,
+        _ => panic!("Expected audit log"),
+    }
+}
+
+pub fn ca_details_at(handle: &Handle, point: CertAuthHistoryPoint) -> CertAuthInfo {
+    match krill_admin(Command::CertAuth(CaCommand::ShowAt(handle.clone(), point))) {
+        ApiResponse::CertAuthInfo(inf) => inf,
+        _ => panic!("Expected cert auth info"),
+    }
+}
+
+pub fn ca_details_at_unknown(handle: &Handle, point: CertAuthHistoryPoint) {
+    krill_admin_expect_error(Command::CertAuth(CaCommand::ShowAt(handle.clone(), point)));
+}
+
+pub fn ca_diff(
+    handle: &Handle,
+    from: CertAuthHistoryPoint,
+    to: CertAuthHistoryPoint,
+) -> CertAuthDiff {
+    match krill_admin(Command::CertAuth(CaCommand::Diff(handle.clone(), from, to))) {
+        ApiResponse::CertAuthDiff(diff) => diff,
+        _ => panic!("Expected CA diff"),
+    }
+}
+
 pub fn ca_route_authorizations_update(handle: &Handle, updates: RoaDefinitionUpdates) {
     krill_admin(Command::CertAuth(CaCommand::RouteAuthorizationsUpdate(
         handle.clone(),
@@ -302,6 +418,277 @@ pub fn ca_route_authorizations_update_expect_error(handle: &Handle, updates: Roa
     )));
 }
 
+pub fn ca_roa_grouping(handle: &Handle) -> RoaGroupingInfo {
+    match krill_admin(Command::CertAuth(CaCommand::RoaGroupingShow(
+        handle.clone(),
+    ))) {
+        ApiResponse::RoaGroupingInfo(grouping) => grouping,
+        _ => panic!("Expected ROA grouping"),
+    }
+}
+
+pub fn ca_roa_grouping_update(handle: &Handle, strategy: RoaPrefixGroupingStrategy) {
+    krill_admin(Command::CertAuth(CaCommand::RoaGroupingUpdate(
+        handle.clone(),
+        strategy,
+    )));
+}
+
+pub fn ca_roa_grouping_reset(handle: &Handle) {
+    krill_admin(Command::CertAuth(CaCommand::RoaGroupingReset(
+        handle.clone(),
+    )));
+}
+
+pub fn ca_route_authorizations_dry_run(
+    handle: &Handle,
+    updates: RoaDefinitionUpdates,
+) -> RoaUpdatesImpact {
+    match krill_admin(Command::CertAuth(CaCommand::RouteAuthorizationsDryRun(
+        handle.clone(),
+        updates,
+    ))) {
+        ApiResponse::RouteAuthorizationsImpact(impact) => impact,
+        _ => panic!("Expected route authorizations impact"),
+    }
+}
+
+pub fn ca_route_authorizations_analysis(handle: &Handle) -> RoaAnalysis {
+    ca_route_authorizations_analysis_with(handle, None)
+}
+
+pub fn ca_route_authorizations_analysis_with(
+    handle: &Handle,
+    announcements: Option<Announcements>,
+) -> RoaAnalysis {
+    match krill_admin(Command::CertAuth(CaCommand::RouteAuthorizationsAnalysis(
+        handle.clone(),
+        announcements,
+    ))) {
+        ApiResponse::RouteAuthorizationsAnalysis(analysis) => analysis,
+        _ => panic!("Expected route authorizations analysis"),
+    }
+}
+
+pub fn ca_route_authorizations_sync(
+    handle: &Handle,
+    desired: Vec<RoaDefinition>,
+    options: RoaSyncOptions,
+) -> RoaDefinitionUpdates {
+    match krill_admin(Command::CertAuth(CaCommand::RouteAuthorizationsSync(
+        handle.clone(),
+        desired,
+        options,
+    ))) {
+        ApiResponse::RouteAuthorizationUpdates(updates) => updates,
+        _ => panic!("Expected route authorization updates"),
+    }
+}
+
+pub fn ca_route_authorizations_import(
+    handle: &Handle,
+    options: RoaFileOptions,
+    file: &str,
+) -> RoaDefinitionUpdates {
+    match krill_admin(Command::CertAuth(CaCommand::RouteAuthorizationsImport(
+        handle.clone(),
+        options,
+        file.to_string(),
+    ))) {
+        ApiResponse::RouteAuthorizationUpdates(updates) => updates,
+        _ => panic!("Expected route authorization updates"),
+    }
+}
+
+pub fn ca_route_authorizations_export(handle: &Handle, format: RoaFileFormat) -> String {
+    match krill_admin(Command::CertAuth(CaCommand::RouteAuthorizationsExport(
+        handle.clone(),
+        format,
+    ))) {
+        ApiResponse::GenericBody(file) => file,
+        _ => panic!("Expected exported file"),
+    }
+}
+
+pub fn ca_aspas(handle: &Handle) -> AspaDefinitionList {
+    match krill_admin(Command::CertAuth(CaCommand::AspasList(handle.clone()))) {
+        ApiResponse::AspaDefinitions(aspas) => aspas,
+        _ => panic!("Expected ASPA definitions"),
+    }
+}
+
+pub fn ca_aspas_add(handle: &Handle, aspa: AspaDefinition) {
+    krill_admin(Command::CertAuth(CaCommand::AspasAddOrReplace(
+        handle.clone(),
+        aspa,
+    )));
+}
+
+pub fn ca_aspas_add_expect_error(handle: &Handle, aspa: AspaDefinition) {
+    krill_admin_expect_error(Command::CertAuth(CaCommand::AspasAddOrReplace(
+        handle.clone(),
+        aspa,
+    )));
+}
+
+pub fn ca_aspas_remove(handle: &Handle, customer: AsNumber) {
+    krill_admin(Command::CertAuth(CaCommand::AspasRemove(
+        handle.clone(),
+        customer,
+    )));
+}
+
+pub fn ca_aspas_update(handle: &Handle, customer: AsNumber, update: AspaProvidersUpdate) {
+    krill_admin(Command::CertAuth(CaCommand::AspasUpdate(
+        handle.clone(),
+        customer,
+        update,
+    )));
+}
+
+pub fn ca_aspas_update_expect_error(
+    handle: &Handle,
+    customer: AsNumber,
+    update: AspaProvidersUpdate,
+) {
+    krill_admin_expect_error(Command::CertAuth(CaCommand::AspasUpdate(
+        handle.clone(),
+        customer,
+        update,
+    )));
+}
+
+pub fn ca_bgpsec_list(handle: &Handle) -> BgpSecDefinitionList {
+    match krill_admin(Command::CertAuth(CaCommand::BgpSecList(handle.clone()))) {
+        ApiResponse::BgpSecDefinitions(definitions) => definitions,
+        _ => panic!("Expected BGPsec definitions"),
+    }
+}
+
+pub fn ca_bgpsec_add(handle: &Handle, definition: BgpSecDefinition) {
+    krill_admin(Command::CertAuth(CaCommand::BgpSecAdd(
+        handle.clone(),
+        definition,
+    )));
+}
+
+pub fn ca_bgpsec_add_expect_error(handle: &Handle, definition: BgpSecDefinition) {
+    krill_admin_expect_error(Command::CertAuth(CaCommand::BgpSecAdd(
+        handle.clone(),
+        definition,
+    )));
+}
+
+pub fn ca_bgpsec_remove(handle: &Handle, router: BgpSecAsnKey) {
+    krill_admin(Command::CertAuth(CaCommand::BgpSecRemove(
+        handle.clone(),
+        router,
+    )));
+}
+
+pub fn ca_sign_checklist(handle: &Handle, request: RscRequest) -> SignedChecklist {
+    match krill_admin(Command::CertAuth(CaCommand::SignChecklist(
+        handle.clone(),
+        request,
+    ))) {
+        ApiResponse::SignedChecklist(rsc) => rsc,
+        _ => panic!("Expected signed checklist"),
+    }
+}
+
+pub fn ca_sign_checklist_expect_error(handle: &Handle, request: RscRequest) {
+    krill_admin_expect_error(Command::CertAuth(CaCommand::SignChecklist(
+        handle.clone(),
+        request,
+    )));
+}
+
+pub fn ca_timing(handle: &Handle) -> CaTimingInfo {
+    match krill_admin(Command::CertAuth(CaCommand::TimingShow(handle.clone()))) {
+        ApiResponse::CaTimingInfo(timing) => timing,
+        _ => panic!("Expected CA timing"),
+    }
+}
+
+pub fn ca_timing_update(handle: &Handle, timing: TimingConfig) {
+    krill_admin(Command::CertAuth(CaCommand::TimingUpdate(
+        handle.clone(),
+        timing,
+    )));
+}
+
+pub fn ca_timing_update_expect_error(handle: &Handle, timing: TimingConfig) {
+    krill_admin_expect_error(Command::CertAuth(CaCommand::TimingUpdate(
+        handle.clone(),
+        timing,
+    )));
+}
+
+pub fn ca_timing_reset(handle: &Handle) {
+    krill_admin(Command::CertAuth(CaCommand::TimingReset(handle.clone())));
+}
+
+pub fn ca_key_roll_info(handle: &Handle) -> CaKeyRollInfo {
+    match krill_admin(Command::CertAuth(CaCommand::KeyRollPolicyShow(
+        handle.clone(),
+    ))) {
+        ApiResponse::CaKeyRollInfo(info) => info,
+        _ => panic!("Expected CA key roll info"),
+    }
+}
+
+pub fn ca_key_roll_policy_update(handle: &Handle, policy: KeyRollPolicy) {
+    krill_admin(Command::CertAuth(CaCommand::KeyRollPolicyUpdate(
+        handle.clone(),
+        policy,
+    )));
+}
+
+pub fn ca_key_roll_policy_update_expect_error(handle: &Handle, policy: KeyRollPolicy) {
+    krill_admin_expect_error(Command::CertAuth(CaCommand::KeyRollPolicyUpdate(
+        handle.clone(),
+        policy,
+    )));
+}
+
+pub fn ca_key_roll_policy_remove(handle: &Handle) {
+    krill_admin(Command::CertAuth(CaCommand::KeyRollPolicyRemove(
+        handle.clone(),
+    )));
+}
+
+pub fn ta_offline_requests(republish: bool) -> TaRequestBundle {
+    let options = TaRequestOptions::new(republish);
+    match krill_admin(Command::TaOffline(TaOfflineCommand::Requests(options))) {
+        ApiResponse::TaRequestBundle(bundle) => bundle,
+        _ => panic!("Expected offline TA requests"),
+    }
+}
+
+pub fn ta_offline_sign(requests: TaRequestBundle) -> TaResponseBundle {
+    match krill_admin(Command::TaOffline(TaOfflineCommand::Sign(requests))) {
+        ApiResponse::TaResponseBundle(bundle) => bundle,
+        _ => panic!("Expected offline TA responses"),
+    }
+}
+
+pub fn ta_offline_import(responses: TaResponseBundle) {
+    krill_admin(Command::TaOffline(TaOfflineCommand::Import(responses)));
+}
+
+/// Returns the content of a bundle, without verifying its signature.
+pub fn ta_offline_responses_content(bundle: &TaResponseBundle) -> TaResponses {
+    let msg = SignedMessage::decode(bundle.to_bytes(), false).unwrap();
+    serde_json::from_slice(msg.content().to_bytes().as_ref()).unwrap()
+}
+
+pub fn ca_exists(handle: &Handle) -> bool {
+    match krill_admin(Command::CertAuth(CaCommand::List)) {
+        ApiResponse::CertAuths(list) => list.cas().iter().any(|ca| ca.handle() == handle),
+        _ => panic!("Expected list of CAs"),
+    }
+}
+
 pub fn ca_details(handle: &Handle) -> CertAuthInfo {
     match krill_admin(Command::CertAuth(CaCommand::Show(handle.clone()))) {
         ApiResponse::CertAuthInfo(inf) => inf,
@@ -309,6 +696,13 @@ pub fn ca_details(handle: &Handle) -> CertAuthInfo {
     }
 }
 
+pub fn ca_issues(handle: &Handle) -> CertAuthIssues {
+    match krill_admin(Command::CertAuth(CaCommand::Issues(Some(handle.clone())))) {
+        ApiResponse::CertAuthIssues(issues) => issues,
+        _ => panic!("Expected CA issues"),
+    }
+}
+
 pub fn ca_key_for_rcn(handle: &Handle, rcn: &ResourceClassName) -> CertifiedKeyInfo {
     ca_details(handle)
         .resource_classes()
@@ -418,6 +812,15 @@ pub fn publisher_details(publisher: &PublisherHandle) -> PublisherDetails {
     }
 }
 
+pub fn publisher_exists(publisher: &PublisherHandle) -> bool {
+    match krill_admin(Command::Publishers(PublishersCommand::PublisherList)) {
+        ApiResponse::PublisherList(list) => {
+            list.publishers().iter().any(|p| p.handle() == publisher)
+        }
+        _ => panic!("Expected publisher list"),
+    }
+}
+
 pub fn wait_for_published_objects(publisher: &PublisherHandle, objects: &[&str]) {
     let mut details = publisher_details(publisher);
 
@@ -464,6 +867,39 @@ pub fn count_roa_files(publisher: &PublisherHandle) -> u32 {
     current_files.iter().filter(|uri| uri.ends_with(".roa")).count() as u32
 }
 
+/// Returns the currently published ROAs by their URI.
+pub fn published_roas(publisher: &PublisherHandle) -> HashMap<Rsync, Base64> {
+    let details = publisher_details(publisher);
+    details
+        .current_files()
+        .iter()
+        .filter(|p| p.uri().ends_with(".roa"))
+        .map(|p| (p.uri().clone(), p.base64().clone()))
+        .collect()
+}
+
+pub fn count_aspa_files(publisher: &PublisherHandle) -> u32 {
+    let details = publisher_details(publisher);
+    let current_files = details.current_files();
+
+    let current_files: Vec<&Rsync> = current_files.iter().map(|p| p.uri()).collect();
+    current_files
+        .iter()
+        .filter(|uri| uri.ends_with(".asa"))
+        .count() as u32
+}
+
+pub fn count_router_cert_files(publisher: &PublisherHandle) -> u32 {
+    let details = publisher_details(publisher);
+    let current_files = details.current_files();
+
+    let current_files: Vec<&Rsync> = current_files.iter().map(|p| p.uri()).collect();
+    current_files
+        .iter()
+        .filter(|uri| uri.to_string().contains("/ROUTER-"))
+        .count() as u32
+}
+
 pub fn get_published_objects(publisher: &PublisherHandle) -> Vec<Rsync> {
     let details = publisher_details(publisher);
     details.current_files().iter().map(|p| p.uri()).map(|u| u.clone()).collect()
@@ -488,6 +924,18 @@ pub fn wait_for_updated_published_objects(publisher: &PublisherHandle, previous_
     panic!("Publishing did not happened after 90 seconds.");
 }
 
+/// Returns the next update time of the current manifest of the publisher.
+pub fn manifest_next_update(publisher: &PublisherHandle) -> Time {
+    let details = publisher_details(publisher);
+    let mft = details
+        .current_files()
+        .iter()
+        .find(|p| p.uri().ends_with(".mft"))
+        .map(|p| Manifest::decode(p.base64().to_bytes(), false).unwrap())
+        .unwrap();
+    mft.content().next_update()
+}
+
 pub fn roas_contain_route(publisher: &PublisherHandle, roa_def: &RoaDefinition) -> bool {
     let details = publisher_details(publisher);
     let current_files = details.current_files();
@@ -18,9 +18,13 @@ extern crate log;
 extern crate actix_service;
 extern crate actix_web;
 extern crate clokwerk;
+extern crate flate2;
 extern crate openssl;
 extern crate rand;
 extern crate reqwest;
+#[macro_use]
+extern crate rusqlite;
+#[macro_use]
 extern crate serde_json;
 extern crate syslog;
 extern crate tokio;
@@ -1,22 +1,25 @@
-use std::fs;
 use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
 use bytes::Bytes;
 use rpki::uri;
+use rpki::x509::Time;
 
 use crate::commons::api::{
     Handle, ListReply, PublishDelta, PublisherDetails, PublisherHandle, RepoInfo, RepositoryHandle,
 };
 use crate::commons::error::Error;
-use crate::commons::eventsourcing::{AggregateStore, AggregateStoreError, DiskAggregateStore};
+use crate::commons::eventsourcing::{
+    AggregateStore, AggregateStoreError, DiskAggregateStore, KeyStoreType, KrillKeyStore,
+    DEFAULT_ACTOR,
+};
 use crate::commons::remote::builder::SignedMessageBuilder;
 use crate::commons::remote::cmslogger::CmsLogger;
 use crate::commons::remote::rfc8181;
 use crate::commons::remote::rfc8183;
 use crate::commons::remote::sigmsg::SignedMessage;
-use crate::commons::util::softsigner::OpenSslSigner;
+use crate::commons::util::signer::KrillSigner;
 use crate::commons::KrillResult;
 use crate::constants::*;
 use crate::pubd::{self, CmdDet, RepoStats, Repository};
@@ -36,7 +39,7 @@ use crate::pubd::{self, CmdDet, RepoStats, Repository};
 ///
 pub struct PubServer {
     store: Arc<DiskAggregateStore<Repository>>,
-    signer: Arc<RwLock<OpenSslSigner>>,
+    signer: Arc<RwLock<KrillSigner>>,
     cms_logger_work_dir: PathBuf,
 }
 
@@ -47,14 +50,24 @@ impl PubServer {
         rsync_base: &uri::Rsync,
         rrdp_base_uri: uri::Https, // for the RRDP files
         work_dir: &PathBuf,        // for the aggregate stores
-        signer: Arc<RwLock<OpenSslSigner>>,
+        signer: Arc<RwLock<KrillSigner>>,
+        snapshot_events: u64,
+        store_type: KeyStoreType,
     ) -> Result<Option<Self>, Error> {
-        let mut pub_server_dir = work_dir.clone();
-        pub_server_dir.push(PUBSERVER_DIR);
-        if pub_server_dir.exists() {
-            let server = PubServer::build(rsync_base, rrdp_base_uri, work_dir, signer)?;
+        if KrillKeyStore::has_name_space(work_dir, PUBSERVER_DIR, store_type)
+            .map_err(AggregateStoreError::KeyStoreError)?
+        {
+            let server = PubServer::build(
+                rsync_base,
+                rrdp_base_uri,
+                work_dir,
+                signer,
+                snapshot_events,
+                store_type,
+            )?;
             if server.publishers()?.is_empty() {
-                let _result = fs::remove_dir_all(pub_server_dir);
+                let _result = KrillKeyStore::build(work_dir, PUBSERVER_DIR, store_type)
+                    .and_then(|store| store.remove_name_space());
                 Ok(None)
             } else {
                 Ok(Some(server))
@@ -68,14 +81,16 @@ impl PubServer {
         rsync_base: &uri::Rsync,
         rrdp_base_uri: uri::Https, // for the RRDP files
         work_dir: &PathBuf,        // for the aggregate stores
-        signer: Arc<RwLock<OpenSslSigner>>,
+        signer: Arc<RwLock<KrillSigner>>,
+        snapshot_events: u64,
+        store_type: KeyStoreType,
     ) -> Result<Self, Error> {
         let default = Self::repository_handle();
 
-        let store = Arc::new(DiskAggregateStore::<Repository>::new(
-            work_dir,
-            PUBSERVER_DIR,
-        )?);
+        let mut store =
+            DiskAggregateStore::<Repository>::build(work_dir, PUBSERVER_DIR, store_type)?;
+        store.set_snapshot_events(snapshot_events);
+        let store = Arc::new(store);
 
         if !store.has(&default) {
             info!("Creating default repository");
@@ -88,7 +103,7 @@ impl PubServer {
                 work_dir,
                 signer.deref_mut(),
             )?;
-            store.add(ini)?;
+            store.add(ini, DEFAULT_ACTOR)?;
         }
 
         let cms_logger_work_dir = work_dir.clone();
@@ -101,6 +116,24 @@ impl PubServer {
     }
 }
 
+/// # Snapshots and archiving of events
+///
+impl PubServer {
+    /// Saves a snapshot of the latest version of the repository.
+    pub fn snapshot_all(&self) -> Result<(), Error> {
+        self.store.snapshot_all()?;
+        Ok(())
+    }
+
+    /// Moves the commands that were processed before the given time, and
+    /// their events, into compressed bundles. Returns the number of archived
+    /// commands.
+    pub fn compact(&self, before: Time) -> Result<usize, Error> {
+        let archived = self.store.compact(before)?;
+        Ok(archived)
+    }
+}
+
 /// # Publication Protocol support
 ///
 impl PubServer {
@@ -232,9 +265,9 @@ impl PubServer {
 
     /// Adds a publisher. Will complain if a publisher already exists for this
     /// handle. Will also verify that the base_uri is allowed.
-    pub fn create_publisher(&self, req: rfc8183::PublisherRequest) -> KrillResult<()> {
+    pub fn create_publisher(&self, req: rfc8183::PublisherRequest, actor: &str) -> KrillResult<()> {
         let repository_handle = Self::repository_handle();
-        let cmd = CmdDet::add_publisher(&repository_handle, req);
+        let cmd = CmdDet::add_publisher(&repository_handle, req).with_actor(actor);
         self.store.command(cmd)?;
         Ok(())
     }
@@ -243,9 +276,9 @@ impl PubServer {
     /// re-activation in future. Reason is that we never forget the history
     /// of the old publisher, and if handles are re-used by different
     /// entities that would get confusing.
-    pub fn remove_publisher(&self, publisher: PublisherHandle) -> KrillResult<()> {
+    pub fn remove_publisher(&self, publisher: PublisherHandle, actor: &str) -> KrillResult<()> {
         let repository_handle = Self::repository_handle();
-        let cmd = CmdDet::remove_publisher(&repository_handle, publisher);
+        let cmd = CmdDet::remove_publisher(&repository_handle, publisher).with_actor(actor);
         self.store.command(cmd)?;
         self.write_repository()
     }
@@ -277,6 +310,7 @@ mod tests {
     use crate::commons::remote::builder::IdCertBuilder;
     use crate::commons::remote::id::IdCert;
     use crate::commons::util::file::CurrentFile;
+    use crate::commons::util::softsigner::OpenSslSigner;
     use crate::commons::util::test;
     use crate::pubd::Publisher;
 
@@ -308,10 +342,18 @@ mod tests {
     }
 
     fn make_server(work_dir: &PathBuf) -> PubServer {
-        let signer = OpenSslSigner::build(work_dir).unwrap();
+        let signer = KrillSigner::from(OpenSslSigner::build(work_dir).unwrap());
         let signer = Arc::new(RwLock::new(signer));
 
-        PubServer::build(&server_base_uri(), server_base_http_uri(), work_dir, signer).unwrap()
+        PubServer::build(
+            &server_base_uri(),
+            server_base_http_uri(),
+            work_dir,
+            signer,
+            SNAPSHOT_EVENTS,
+            KeyStoreType::Disk,
+        )
+        .unwrap()
     }
 
     #[test]
@@ -324,7 +366,7 @@ mod tests {
             let alice_handle = Handle::from_str_unsafe("alice");
             let publisher_req = make_publisher_req(alice_handle.as_str(), alice.id_cert());
 
-            server.create_publisher(publisher_req).unwrap();
+            server.create_publisher(publisher_req, DEFAULT_ACTOR).unwrap();
 
             let alice_found = server.get_publisher_details(&alice_handle).unwrap();
 
@@ -344,9 +386,9 @@ mod tests {
             let alice_handle = Handle::from_str_unsafe("alice");
             let publisher_req = make_publisher_req(alice_handle.as_str(), alice.id_cert());
 
-            server.create_publisher(publisher_req.clone()).unwrap();
+            server.create_publisher(publisher_req.clone(), DEFAULT_ACTOR).unwrap();
 
-            match server.create_publisher(publisher_req) {
+            match server.create_publisher(publisher_req, DEFAULT_ACTOR) {
                 Err(Error::PublisherDuplicate(name)) => assert_eq!(name, alice_handle),
                 _ => panic!("Expected error"),
             }
@@ -362,7 +404,7 @@ mod tests {
             let alice_handle = Handle::from_str_unsafe("alice");
             let publisher_req = make_publisher_req(alice_handle.as_str(), alice.id_cert());
 
-            server.create_publisher(publisher_req).unwrap();
+            server.create_publisher(publisher_req, DEFAULT_ACTOR).unwrap();
 
             let list_reply = server.list(&alice_handle).unwrap();
             assert_eq!(0, list_reply.elements().len());
@@ -379,7 +421,7 @@ mod tests {
             let alice_handle = Handle::from_str_unsafe("alice");
             let publisher_req = make_publisher_req(alice_handle.as_str(), alice.id_cert());
 
-            server.create_publisher(publisher_req).unwrap();
+            server.create_publisher(publisher_req, DEFAULT_ACTOR).unwrap();
 
             // get the file out of a list_reply
             fn find_in_reply<'a>(
@@ -2,26 +2,26 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::commons::api::Handle;
+use crate::commons::api::rrdp::PublishElement;
+use crate::commons::api::{Handle, ListReply};
 use crate::commons::api::{Publish, PublishDelta, RepositoryContact, Update, Withdraw};
 use crate::commons::error::Error;
-use crate::commons::util::softsigner::OpenSslSigner;
-use crate::daemon::ca::CaServer;
+use crate::daemon::ca::{CaServer, Signer};
 use crate::pubd::PubServer;
 
 //------------ CaPublisher ---------------------------------------------------
 
 /// A helper which orchestrates publishing by CAs at either local, or
 /// remote, repositories.
-pub struct CaPublisher {
-    caserver: Arc<CaServer<OpenSslSigner>>,
+pub struct CaPublisher<S: Signer> {
+    caserver: Arc<CaServer<S>>,
     pubserver: Option<Arc<PubServer>>,
 }
 
 /// # Construct
 ///
-impl CaPublisher {
-    pub fn new(caserver: Arc<CaServer<OpenSslSigner>>, pubserver: Option<Arc<PubServer>>) -> Self {
+impl<S: Signer> CaPublisher<S> {
+    pub fn new(caserver: Arc<CaServer<S>>, pubserver: Option<Arc<PubServer>>) -> Self {
         CaPublisher {
             caserver,
             pubserver,
@@ -29,7 +29,7 @@ impl CaPublisher {
     }
 }
 
-impl CaPublisher {
+impl<S: Signer> CaPublisher<S> {
     fn get_embedded(&self) -> Result<&Arc<PubServer>, Error> {
         self.pubserver
             .as_ref()
@@ -51,35 +51,7 @@ impl CaPublisher {
             RepositoryContact::Rfc8181(repo) => self.caserver.send_rfc8181_list(ca_handle, repo)?,
         };
 
-        let delta = {
-            let elements: HashMap<_, _> = list_reply
-                .into_elements()
-                .into_iter()
-                .map(|el| el.unpack())
-                .collect();
-
-            let mut all_objects: HashMap<_, _> =
-                ca.all_objects().into_iter().map(|el| el.unpack()).collect();
-
-            let mut withdraws = vec![];
-            let mut updates = vec![];
-            for (uri, hash) in elements.into_iter() {
-                match all_objects.remove(&uri) {
-                    Some(base64) => {
-                        if base64.to_encoded_hash() != hash {
-                            updates.push(Update::new(None, uri, base64, hash))
-                        }
-                    }
-                    None => withdraws.push(Withdraw::new(None, uri, hash)),
-                }
-            }
-            let publishes = all_objects
-                .into_iter()
-                .map(|(uri, base64)| Publish::new(None, uri, base64))
-                .collect();
-
-            PublishDelta::new(publishes, updates, withdraws)
-        };
+        let delta = Self::make_delta(list_reply, ca.all_objects());
 
         match &repo_contact {
             RepositoryContact::Embedded(_) => {
@@ -93,6 +65,50 @@ impl CaPublisher {
         Ok(())
     }
 
+    /// Publishes objects on behalf of a publisher at the embedded repository,
+    /// e.g. for an offline Trust Anchor. Any objects which the publisher has
+    /// in the repository, but which are not included, are withdrawn.
+    pub fn publish_objects(
+        &self,
+        publisher: &Handle,
+        objects: Vec<PublishElement>,
+    ) -> Result<(), Error> {
+        let pubserver = self.get_embedded()?;
+        let delta = Self::make_delta(pubserver.list(publisher)?, objects);
+        pubserver.publish(publisher.clone(), delta)
+    }
+
+    /// Makes a delta to get from the currently published objects, as listed
+    /// by the repository, to the given objects.
+    fn make_delta(list_reply: ListReply, objects: Vec<PublishElement>) -> PublishDelta {
+        let elements: HashMap<_, _> = list_reply
+            .into_elements()
+            .into_iter()
+            .map(|el| el.unpack())
+            .collect();
+
+        let mut all_objects: HashMap<_, _> = objects.into_iter().map(|el| el.unpack()).collect();
+
+        let mut withdraws = vec![];
+        let mut updates = vec![];
+        for (uri, hash) in elements.into_iter() {
+            match all_objects.remove(&uri) {
+                Some(base64) => {
+                    if base64.to_encoded_hash() != hash {
+                        updates.push(Update::new(None, uri, base64, hash))
+                    }
+                }
+                None => withdraws.push(Withdraw::new(None, uri, hash)),
+            }
+        }
+        let publishes = all_objects
+            .into_iter()
+            .map(|(uri, base64)| Publish::new(None, uri, base64))
+            .collect();
+
+        PublishDelta::new(publishes, updates, withdraws)
+    }
+
     pub fn clean_up(&self, ca_handle: &Handle) -> Result<(), Error> {
         let ca = self.caserver.get_ca(ca_handle)?;
 
@@ -106,6 +122,29 @@ impl CaPublisher {
             repo
         );
 
+        self.withdraw_all_at(ca_handle, repo)
+    }
+
+    /// Withdraws all objects published by the CA at its current repository,
+    /// for when the CA is removed.
+    pub fn withdraw_all(&self, ca_handle: &Handle) -> Result<(), Error> {
+        let ca = self.caserver.get_ca(ca_handle)?;
+
+        // Nothing was published if no repository was ever configured.
+        let repo = match ca.get_repository_contact() {
+            Ok(repo) => repo,
+            Err(_) => return Ok(()),
+        };
+
+        info!(
+            "Will withdraw all objects for '{}' from repository: {}",
+            ca_handle, repo
+        );
+
+        self.withdraw_all_at(ca_handle, repo)
+    }
+
+    fn withdraw_all_at(&self, ca_handle: &Handle, repo: &RepositoryContact) -> Result<(), Error> {
         let list_reply = match repo {
             RepositoryContact::Embedded(_) => self.get_embedded()?.list(ca_handle)?,
             RepositoryContact::Rfc8181(repo) => self.caserver.send_rfc8181_list(ca_handle, repo)?,
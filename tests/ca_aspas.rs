@@ -0,0 +1,79 @@
+extern crate krill;
+#[macro_use]
+extern crate serde_json;
+
+use std::str::FromStr;
+
+use krill::commons::api::{
+    AsNumber, AspaDefinition, AspaProvidersUpdate, Handle, ParentCaReq, ResourceSet, Token,
+};
+use krill::commons::util::httpclient;
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that ASPA definitions can be added, updated and removed, that the
+/// CA publishes an ASPA object for each, and that definitions for customer
+/// ASNs not held by the CA are rejected.
+fn ca_aspas() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("AS65000", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let customer = AsNumber::new(65000);
+
+        // Add an ASPA
+        let aspa = AspaDefinition::from_str("65000 => 65001, 65002").unwrap();
+        ca_aspas_add(&child, aspa.clone());
+        assert_eq!(&[aspa.clone()], ca_aspas(&child).definitions());
+        wait_for(30, "ASPA was not published", || {
+            count_aspa_files(&child) == 1
+        });
+
+        // Providers sent through the API in any order, and with duplicates,
+        // are kept sorted and unique.
+        let uri = format!("https://localhost:3000/api/v1/cas/{}/aspas", child);
+        let updates = json!({
+            "add_or_replace": [ { "customer": 65000, "providers": [65002, 65001, 65002] } ],
+            "remove": []
+        });
+        httpclient::post_json(&uri, updates, Some(&Token::from("secret"))).unwrap();
+        assert_eq!(&[aspa], ca_aspas(&child).definitions());
+
+        // Customer ASN not held, customer as provider, or no providers
+        ca_aspas_add_expect_error(&child, AspaDefinition::from_str("65003 => 65001").unwrap());
+        ca_aspas_add_expect_error(&child, AspaDefinition::from_str("65000 => 65000").unwrap());
+        ca_aspas_add_expect_error(&child, AspaDefinition::new(customer, vec![]));
+
+        // Update the providers
+        let update =
+            AspaProvidersUpdate::new(vec![AsNumber::new(65003)], vec![AsNumber::new(65001)]);
+        ca_aspas_update(&child, customer, update);
+        let updated = AspaDefinition::from_str("65000 => 65002, 65003").unwrap();
+        assert_eq!(&[updated], ca_aspas(&child).definitions());
+
+        // Adding an existing provider, or removing the last provider fails
+        let update = AspaProvidersUpdate::new(vec![AsNumber::new(65002)], vec![]);
+        ca_aspas_update_expect_error(&child, customer, update);
+        let update =
+            AspaProvidersUpdate::new(vec![], vec![AsNumber::new(65002), AsNumber::new(65003)]);
+        ca_aspas_update_expect_error(&child, customer, update);
+
+        // Remove the ASPA
+        ca_aspas_remove(&child, customer);
+        assert!(ca_aspas(&child).definitions().is_empty());
+        wait_for(30, "ASPA was not withdrawn", || {
+            count_aspa_files(&child) == 0
+        });
+    });
+}
@@ -0,0 +1,52 @@
+extern crate krill;
+
+use krill::cli::options::Command;
+use krill::commons::api::{AuditCriteria, Handle};
+use krill::daemon::test::*;
+
+#[test]
+/// Test that the audit log can be limited to the commands for one CA.
+fn ca_audit() {
+    test_with_krill_server(|_d| {
+        let ca_1 = Handle::from_str_unsafe("ca1");
+        let ca_2 = Handle::from_str_unsafe("ca2");
+
+        init_child_with_embedded_repo(&ca_1);
+        init_child_with_embedded_repo(&ca_2);
+
+        let ca_criteria = |ca: &Handle| AuditCriteria::new(None, Some(ca.clone()), None);
+
+        let all = audit(AuditCriteria::default());
+        let log_1 = audit(ca_criteria(&ca_1));
+        let log_2 = audit(ca_criteria(&ca_2));
+
+        // Each CA was created and then set up to use the embedded repository
+        assert!(log_1.entries().len() >= 2);
+        assert!(log_2.entries().len() >= 2);
+
+        assert!(log_1.entries().iter().all(|entry| entry.ca() == &ca_1));
+        assert!(log_2.entries().iter().all(|entry| entry.ca() == &ca_2));
+
+        // The log for all CAs includes both, as well as the TA
+        for entry in log_1.entries().iter().chain(log_2.entries().iter()) {
+            assert!(all.entries().contains(entry));
+        }
+        assert!(all.entries().len() > log_1.entries().len() + log_2.entries().len());
+
+        // The other criteria still apply
+        let admin = AuditCriteria::new(None, Some(ca_1.clone()), Some("admin".to_string()));
+        let admin_log = audit(admin);
+        assert!(!admin_log.entries().is_empty());
+        assert!(admin_log
+            .entries()
+            .iter()
+            .all(|entry| entry.ca() == &ca_1 && entry.actor() == "admin"));
+
+        let nobody = AuditCriteria::new(None, Some(ca_1), Some("nobody".to_string()));
+        assert!(audit(nobody).entries().is_empty());
+
+        // An unknown CA is an error, rather than an empty log
+        let unknown = Handle::from_str_unsafe("unknown");
+        krill_admin_expect_error(Command::Audit(ca_criteria(&unknown)));
+    });
+}
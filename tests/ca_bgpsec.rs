@@ -0,0 +1,86 @@
+extern crate krill;
+extern crate openssl;
+extern crate rpki;
+
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::x509::{X509NameBuilder, X509ReqBuilder};
+
+use rpki::csr::Csr;
+
+use krill::commons::api::{AsNumber, BgpSecDefinition, Handle, ParentCaReq, ResourceSet};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+/// Makes a CSR for a router key, like a router would.
+fn router_csr(key: &PKey<Private>) -> Csr {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, "router").unwrap();
+
+    let mut req = X509ReqBuilder::new().unwrap();
+    req.set_subject_name(&name.build()).unwrap();
+    req.set_pubkey(key).unwrap();
+    req.sign(key, MessageDigest::sha256()).unwrap();
+
+    Csr::decode(req.build().to_der().unwrap().as_slice()).unwrap()
+}
+
+fn ecdsa_p256_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+fn rsa_key() -> PKey<Private> {
+    PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap()
+}
+
+#[test]
+/// Test that BGPsec router certificates are issued and published for ASNs
+/// held by the CA, revoked when removed, and refused for ASNs not held or
+/// for keys that are not ECDSA P-256 keys.
+fn ca_bgpsec() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("AS65000", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        // RSA keys cannot be used by routers
+        let rsa = BgpSecDefinition::new(AsNumber::new(65000), router_csr(&rsa_key()));
+        ca_bgpsec_add_expect_error(&child, rsa);
+        assert!(ca_bgpsec_list(&child).asn_keys().is_empty());
+
+        let csr = router_csr(&ecdsa_p256_key());
+
+        // ASN not held by the CA
+        let not_held = BgpSecDefinition::new(AsNumber::new(65001), csr.clone());
+        ca_bgpsec_add_expect_error(&child, not_held);
+
+        // Issue a router certificate
+        let definition = BgpSecDefinition::new(AsNumber::new(65000), csr);
+        let router = definition.asn_key();
+        ca_bgpsec_add(&child, definition);
+        assert_eq!(&[router], ca_bgpsec_list(&child).asn_keys());
+        wait_for(30, "Router certificate was not published", || {
+            count_router_cert_files(&child) == 1
+        });
+
+        // Revoke it
+        ca_bgpsec_remove(&child, router);
+        assert!(ca_bgpsec_list(&child).asn_keys().is_empty());
+        wait_for(30, "Router certificate was not withdrawn", || {
+            count_router_cert_files(&child) == 0
+        });
+    });
+}
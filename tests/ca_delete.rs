@@ -0,0 +1,65 @@
+extern crate krill;
+
+use std::str::FromStr;
+
+use krill::commons::api::{Handle, ParentCaReq, ResourceSet, RoaDefinition, RoaDefinitionUpdates};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that a CA can be deleted: its key is revoked by its parent, its
+/// publisher is removed from the embedded repository, and its embedded child
+/// no longer has it as a parent.
+///
+///                   TA
+///                   |
+///                  CA1 (deleted)
+///                   |
+///                  CA2
+fn ca_delete() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+
+        let ca1 = Handle::from_str_unsafe("CA1");
+        let ca1_res = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&ca1);
+        let parent = {
+            let contact = add_child_to_ta_embedded(&ca1, ca1_res.clone());
+            ParentCaReq::new(ta_handle.clone(), contact)
+        };
+        add_parent_to_ca(&ca1, parent);
+        wait_for_current_resources(&ca1, &ca1_res);
+        wait_for_ta_to_have_number_of_issued_certs(1);
+
+        let ca2 = Handle::from_str_unsafe("CA2");
+        let ca2_res = ResourceSet::from_strs("", "10.0.0.0/24", "").unwrap();
+
+        init_child_with_embedded_repo(&ca2);
+        let parent = {
+            let contact = add_child_embedded(&ca1, &ca2, ca2_res.clone());
+            ParentCaReq::new(ca1.clone(), contact)
+        };
+        add_parent_to_ca(&ca2, parent);
+        wait_for_current_resources(&ca2, &ca2_res);
+
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(RoaDefinition::from_str("10.0.1.0/24 => 64496").unwrap());
+        ca_route_authorizations_update(&ca1, updates);
+        wait_for(30, "ROA was not published", || count_roa_files(&ca1) == 1);
+
+        delete_ca(&ca1);
+
+        assert!(!ca_exists(&ca1));
+        assert!(!publisher_exists(&ca1));
+
+        // The TA revoked the key of CA1
+        wait_for_ta_to_have_number_of_issued_certs(0);
+
+        // CA2 no longer has CA1 as its parent
+        assert!(ca_details(&ca2).parents().is_empty());
+        wait_for(30, "CA2 did not lose its resources", || {
+            ca_current_resources(&ca2).is_empty()
+        });
+    });
+}
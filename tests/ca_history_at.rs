@@ -0,0 +1,119 @@
+extern crate chrono;
+extern crate krill;
+extern crate rpki;
+
+use std::str::FromStr;
+
+use chrono::Duration;
+
+use rpki::x509::Time;
+
+use krill::cli::options::{CaCommand, Command};
+use krill::cli::Error;
+use krill::commons::api::{
+    CertAuthHistoryPoint, CertAuthInfo, Handle, ParentCaReq, ResourceSet, RoaDefinition,
+    RoaDefinitionUpdates,
+};
+use krill::commons::util::httpclient;
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+fn current_version(handle: &Handle) -> u64 {
+    ca_history(handle).events().len() as u64 + 1
+}
+
+fn roa_objects(info: &CertAuthInfo) -> usize {
+    info.published_objects()
+        .iter()
+        .filter(|p| p.uri().ends_with(".roa"))
+        .count()
+}
+
+#[test]
+/// Test that a CA can be shown as it was at an earlier version, or time,
+/// and that the differences between two versions can be shown.
+fn ca_history_at() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let before = current_version(&child);
+
+        let route_1 = RoaDefinition::from_str("10.0.0.0/24 => 64496").unwrap();
+        let route_2 = RoaDefinition::from_str("10.0.1.0/24 => 64496").unwrap();
+
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(route_1);
+        updates.add(route_2);
+        ca_route_authorizations_update(&child, updates);
+
+        wait_for(30, "ROAs not published", || count_roa_files(&child) == 2);
+
+        let after = current_version(&child);
+        assert!(after > before);
+
+        // The CA as it was before the ROAs were added, and after
+        let info_before = ca_details_at(&child, CertAuthHistoryPoint::Version(before));
+        assert_eq!(1, info_before.parents().len());
+        assert_eq!(0, roa_objects(&info_before));
+
+        let info_after = ca_details_at(&child, CertAuthHistoryPoint::Version(after));
+        assert_eq!(2, roa_objects(&info_after));
+
+        let info_now = ca_details_at(&child, CertAuthHistoryPoint::Time(Time::now()));
+        assert_eq!(2, roa_objects(&info_now));
+
+        // The routes and ROA objects show up as added in the diff
+        let diff = ca_diff(
+            &child,
+            CertAuthHistoryPoint::Version(before),
+            CertAuthHistoryPoint::Version(after),
+        );
+        assert_eq!(before, diff.from());
+        assert_eq!(after, diff.to());
+        assert_eq!(&vec![route_1, route_2], diff.routes_added());
+        assert!(diff.routes_removed().is_empty());
+        assert!(diff.parents_added().is_empty());
+        assert_eq!(
+            2,
+            diff.objects_added()
+                .iter()
+                .filter(|uri| uri.ends_with(".roa"))
+                .count()
+        );
+        assert!(diff.objects_removed().is_empty());
+
+        // The reverse diff shows them as removed
+        let diff = ca_diff(
+            &child,
+            CertAuthHistoryPoint::Version(after),
+            CertAuthHistoryPoint::Version(before),
+        );
+        assert_eq!(&vec![route_1, route_2], diff.routes_removed());
+        assert!(diff.routes_added().is_empty());
+
+        // Versions beyond the current one are unknown
+        ca_details_at_unknown(&child, CertAuthHistoryPoint::Version(0));
+        ca_details_at_unknown(&child, CertAuthHistoryPoint::Version(after + 1_000_000));
+
+        // The CA did not exist before it was created
+        let before = CertAuthHistoryPoint::Time(Time::now() - Duration::days(1));
+        let command = Command::CertAuth(CaCommand::ShowAt(child.clone(), before));
+        match krill_admin_expect_error(command) {
+            Error::HttpClientError(httpclient::Error::ErrorWithJson(_, res)) => {
+                assert_eq!("ca-history-point-before-creation", res.label())
+            }
+            e => panic!("Expected error response, got: {}", e),
+        }
+    });
+}
@@ -0,0 +1,45 @@
+extern crate krill;
+
+use krill::commons::api::{Handle, ParentCaReq, ResourceClassName, ResourceSet};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that an emergency key roll must be confirmed, and that it rolls the
+/// key and revokes the old key without further action by the operator.
+/// A CA without keys to roll refuses the roll.
+fn ca_keyroll_emergency() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+        let rcn = ResourceClassName::default();
+
+        init_child_with_embedded_repo(&child);
+
+        // Without a parent there are no keys to roll
+        ca_roll_emergency_not_possible(&child, "key may be compromised");
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let old_key = ca_key_for_rcn(&child, &rcn);
+
+        ca_roll_emergency_unconfirmed(&child, "key may be compromised");
+        assert!(ca_issues(&child).key_roll_progress().is_empty());
+
+        ca_roll_emergency(&child, "key may be compromised");
+        wait_for_key_roll_complete(&child);
+
+        let new_key = ca_key_for_rcn(&child, &rcn);
+        assert_ne!(old_key.key_id(), new_key.key_id());
+
+        let history = ca_history(&child).to_string();
+        assert!(history.contains("EMERGENCY"));
+        assert!(history.contains("key may be compromised"));
+    });
+}
@@ -0,0 +1,59 @@
+extern crate krill;
+extern crate rpki;
+
+use rpki::x509::Time;
+
+use krill::commons::api::{Handle, KeyRollPolicy, KeyRollStage, ParentCaReq, ResourceSet};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that a key roll policy can be set for a CA, that policies with a
+/// staging period shorter than RFC 6489 allows are rejected, and that the
+/// progress of a key roll is reported in the CA issues.
+fn ca_keyroll_policy() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let info = ca_key_roll_info(&child);
+        assert!(info.policy().is_none());
+        assert!(info.progress().is_empty());
+
+        // Staging period must be at least 24 hours
+        ca_key_roll_policy_update_expect_error(&child, KeyRollPolicy::new(365, 12));
+        assert!(ca_key_roll_info(&child).policy().is_none());
+
+        let policy = KeyRollPolicy::new(365, 24);
+        ca_key_roll_policy_update(&child, policy);
+        assert_eq!(Some(&policy), ca_key_roll_info(&child).policy());
+
+        // The key is new, so the policy does not roll it, but the progress
+        // of a manual key roll is reported.
+        ca_roll_init(&child);
+        wait_for_new_key(&child);
+
+        let issues = ca_issues(&child);
+        assert_eq!(1, issues.key_roll_progress().len());
+        let progress = &issues.key_roll_progress()[0];
+        assert_eq!(KeyRollStage::New, progress.stage());
+        assert!(progress.activate_after().unwrap() > Time::now());
+
+        ca_roll_activate(&child);
+        wait_for_key_roll_complete(&child);
+        assert!(ca_issues(&child).key_roll_progress().is_empty());
+
+        ca_key_roll_policy_remove(&child);
+        assert!(ca_key_roll_info(&child).policy().is_none());
+    });
+}
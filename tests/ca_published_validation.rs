@@ -0,0 +1,43 @@
+extern crate krill;
+
+use std::str::FromStr;
+
+use krill::commons::api::{Handle, ParentCaReq, ResourceSet, RoaDefinition, RoaDefinitionUpdates};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that the objects published by CAs pass validation, when ROAs are
+/// published, and while a key roll is in progress.
+fn ca_published_validation() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "2001:DB8::/32").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle.clone(), parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        assert!(ca_issues(&ta_handle).published_issues().is_empty());
+        assert!(ca_issues(&child).published_issues().is_empty());
+
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(RoaDefinition::from_str("10.0.0.0/24 => 64496").unwrap());
+        updates.add(RoaDefinition::from_str("2001:DB8::/32-48 => 64496").unwrap());
+        ca_route_authorizations_update(&child, updates);
+
+        assert!(ca_issues(&child).published_issues().is_empty());
+
+        ca_roll_init(&child);
+        wait_for_new_key(&child);
+
+        assert!(ca_issues(&ta_handle).published_issues().is_empty());
+        assert!(ca_issues(&child).published_issues().is_empty());
+    });
+}
@@ -0,0 +1,76 @@
+extern crate krill;
+
+use std::str::FromStr;
+
+use krill::commons::api::{
+    Handle, ParentCaReq, ResourceSet, RoaDefinition, RoaDefinitionUpdates,
+    RoaPrefixGroupingStrategy,
+};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that a CA can use its own ROA grouping, that its ROAs are re-issued
+/// in the new grouping when it is changed, and that the CA can revert to the
+/// server grouping.
+fn ca_roa_grouping() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "2001:DB8::/32").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let grouping = ca_roa_grouping(&child);
+        assert!(!grouping.custom());
+        assert_eq!(RoaPrefixGroupingStrategy::RoaPerPrefix, grouping.strategy());
+
+        let route_1 = RoaDefinition::from_str("10.0.0.0/24 => 64496").unwrap();
+        let route_2 = RoaDefinition::from_str("10.0.1.0/24 => 64496").unwrap();
+        let route_3 = RoaDefinition::from_str("2001:DB8::/32-48 => 64496").unwrap();
+        let route_4 = RoaDefinition::from_str("10.0.2.0/24 => 64497").unwrap();
+
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(route_1);
+        updates.add(route_2);
+        updates.add(route_3);
+        updates.add(route_4);
+        ca_route_authorizations_update(&child, updates);
+
+        wait_for(30, "ROAs not published per prefix", || {
+            count_roa_files(&child) == 4
+        });
+
+        // Group the ROAs per ASN, the ROAs per prefix are withdrawn
+        ca_roa_grouping_update(&child, RoaPrefixGroupingStrategy::RoaPerAsn);
+
+        let grouping = ca_roa_grouping(&child);
+        assert!(grouping.custom());
+        assert_eq!(RoaPrefixGroupingStrategy::RoaPerAsn, grouping.strategy());
+
+        wait_for(30, "ROAs not published per ASN", || {
+            count_roa_files(&child) == 2
+        });
+        for route in &[route_1, route_2, route_3, route_4] {
+            assert!(roas_contain_route(&child, route));
+        }
+
+        // Revert to the server grouping, i.e. one ROA per prefix
+        ca_roa_grouping_reset(&child);
+        assert!(!ca_roa_grouping(&child).custom());
+
+        wait_for(30, "ROAs not published per prefix again", || {
+            count_roa_files(&child) == 4
+        });
+        for route in &[route_1, route_2, route_3, route_4] {
+            assert!(roas_contain_route(&child, route));
+        }
+    });
+}
@@ -0,0 +1,127 @@
+extern crate krill;
+extern crate rpki;
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use rpki::uri::Rsync;
+
+use krill::commons::api::{
+    Base64, Handle, ParentCaReq, ResourceSet, RoaDefinition, RoaDefinitionUpdates,
+    RoaPrefixGroupingStrategy,
+};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+/// Returns the number of ROA files which were added, updated or removed.
+fn roas_changed(before: &HashMap<Rsync, Base64>, after: &HashMap<Rsync, Base64>) -> usize {
+    let added_or_updated = after
+        .iter()
+        .filter(|(uri, base64)| before.get(uri) != Some(base64))
+        .count();
+    let removed = before.keys().filter(|uri| !after.contains_key(uri)).count();
+    added_or_updated + removed
+}
+
+#[test]
+/// Test that ROAs are grouped per ASN in ROAs of a bounded size, and that
+/// adding or removing a prefix only affects a single ROA.
+fn ca_roa_grouping_bounded() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let strategy = RoaPrefixGroupingStrategy::RoaPerAsnBounded(2);
+        ca_roa_grouping_update(&child, strategy);
+        assert_eq!(strategy, ca_roa_grouping(&child).strategy());
+
+        let routes: Vec<RoaDefinition> = (0..5)
+            .map(|i| RoaDefinition::from_str(&format!("10.0.{}.0/24 => 64496", i)).unwrap())
+            .collect();
+
+        let mut updates = RoaDefinitionUpdates::empty();
+        for route in &routes {
+            updates.add(*route);
+        }
+        ca_route_authorizations_update(&child, updates);
+
+        wait_for(30, "ROAs not published in groups of 2", || {
+            count_roa_files(&child) == 3
+        });
+        for route in &routes {
+            assert!(roas_contain_route(&child, route));
+        }
+
+        // Adding a prefix fills up the ROA which has room for it
+        let before = published_roas(&child);
+        let route_6 = RoaDefinition::from_str("10.0.5.0/24 => 64496").unwrap();
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(route_6);
+        ca_route_authorizations_update(&child, updates);
+
+        wait_for(30, "ROA not updated for added prefix", || {
+            roas_changed(&before, &published_roas(&child)) == 1
+        });
+        assert_eq!(3, count_roa_files(&child));
+        assert!(roas_contain_route(&child, &route_6));
+
+        // All ROAs are full, so adding a prefix results in a new ROA
+        let before = published_roas(&child);
+        let route_7 = RoaDefinition::from_str("10.0.6.0/24 => 64496").unwrap();
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(route_7);
+        ca_route_authorizations_update(&child, updates);
+
+        wait_for(30, "ROA not added for added prefix", || {
+            count_roa_files(&child) == 4
+        });
+        assert_eq!(1, roas_changed(&before, &published_roas(&child)));
+        assert!(roas_contain_route(&child, &route_7));
+
+        // Removing a prefix only updates the ROA which contained it
+        let before = published_roas(&child);
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.remove(routes[0]);
+        ca_route_authorizations_update(&child, updates);
+
+        wait_for(30, "ROA not updated for removed prefix", || {
+            roas_changed(&before, &published_roas(&child)) == 1
+        });
+        assert_eq!(4, count_roa_files(&child));
+        assert!(!roas_contain_route(&child, &routes[0]));
+        for route in routes.iter().skip(1) {
+            assert!(roas_contain_route(&child, route));
+        }
+
+        // A ROA per ASN is kept if it holds no more prefixes than allowed,
+        // and re-issued in groups otherwise.
+        ca_roa_grouping_update(&child, RoaPrefixGroupingStrategy::RoaPerAsn);
+        wait_for(30, "ROAs not published per ASN", || {
+            count_roa_files(&child) == 1
+        });
+
+        let before = published_roas(&child);
+        ca_roa_grouping_update(&child, RoaPrefixGroupingStrategy::RoaPerAsnBounded(6));
+        assert_eq!(0, roas_changed(&before, &published_roas(&child)));
+
+        ca_roa_grouping_update(&child, strategy);
+        wait_for(30, "ROA per ASN not re-issued in groups of 2", || {
+            count_roa_files(&child) == 3
+        });
+        for route in routes.iter().skip(1) {
+            assert!(roas_contain_route(&child, route));
+        }
+        assert!(roas_contain_route(&child, &route_6));
+        assert!(roas_contain_route(&child, &route_7));
+    });
+}
@@ -0,0 +1,87 @@
+extern crate krill;
+
+use std::fs;
+use std::str::FromStr;
+
+use krill::commons::api::{Handle, ParentCaReq, ResourceSet, RoaDefinition, RoaDefinitionUpdates};
+use krill::commons::bgp::Announcements;
+use krill::daemon::ca::ta_handle;
+use krill::daemon::config::Config;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that the analysis of ROA definitions flags redundant and overly
+/// permissive definitions, and suggests definitions for announcements in the
+/// configured BGP dump, or sent with the request, which are not covered.
+fn ca_roas_analysis() {
+    let configure = |config: &mut Config| {
+        let mut dump_file = config.data_dir.clone();
+        dump_file.push("riswhoisdump.txt");
+        fs::create_dir_all(&config.data_dir).unwrap();
+        fs::write(
+            &dump_file,
+            "64496\t10.0.0.0/24\t100\n64497\t10.0.1.0/24\t100\n64498\t10.0.2.0/24\t100\n",
+        )
+        .unwrap();
+        config.bgp_dump_file = Some(dump_file);
+    };
+
+    test_with_configured_krill_server(configure, |_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let analysis = ca_route_authorizations_analysis(&child);
+        assert_eq!(Some(3), analysis.announcements());
+        assert_eq!(3, analysis.suggested().len());
+
+        let wide = RoaDefinition::from_str("10.0.0.0/16-24 => 64496").unwrap();
+        let specific = RoaDefinition::from_str("10.0.0.0/24 => 64496").unwrap();
+        let other = RoaDefinition::from_str("10.0.1.0/24 => 64497").unwrap();
+
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(wide);
+        updates.add(specific);
+        updates.add(other);
+        ca_route_authorizations_update(&child, updates);
+
+        let analysis = ca_route_authorizations_analysis(&child);
+
+        assert_eq!(1, analysis.redundant().len());
+        assert_eq!(&specific, analysis.redundant()[0].definition());
+        assert_eq!(&wide, analysis.redundant()[0].covered_by());
+
+        assert_eq!(1, analysis.permissive().len());
+        assert_eq!(&wide, analysis.permissive()[0].definition());
+        assert_eq!(
+            vec![specific.prefix()],
+            analysis.permissive()[0].announced().to_vec()
+        );
+
+        assert!(analysis.not_held().is_empty());
+
+        let suggested = RoaDefinition::from_str("10.0.2.0/24 => 64498").unwrap();
+        assert_eq!(vec![suggested], analysis.suggested().to_vec());
+
+        // Announcements can also be sent with the request, in which case the
+        // configured BGP dump is not used.
+        let announcements = Announcements::from_str("64496 10.0.3.0/24\n").unwrap();
+        let analysis = ca_route_authorizations_analysis_with(&child, Some(announcements));
+        assert_eq!(Some(1), analysis.announcements());
+        let announced = RoaDefinition::from_str("10.0.3.0/24 => 64496").unwrap();
+        assert_eq!(
+            vec![announced.prefix()],
+            analysis.permissive()[0].announced().to_vec()
+        );
+        assert!(analysis.suggested().is_empty());
+    });
+}
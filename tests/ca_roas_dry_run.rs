@@ -0,0 +1,84 @@
+extern crate krill;
+
+use std::fs;
+use std::str::FromStr;
+
+use krill::commons::api::{
+    Handle, ParentCaReq, ResourceSet, RoaDefinition, RoaDefinitionUpdates, RoaFileFormat,
+};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::config::Config;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that a dry run of ROA updates shows which announcements in the
+/// configured BGP dump would change validation state.
+fn ca_roas_dry_run() {
+    let configure = |config: &mut Config| {
+        let mut dump_file = config.data_dir.clone();
+        dump_file.push("riswhoisdump.txt");
+        fs::create_dir_all(&config.data_dir).unwrap();
+        fs::write(
+            &dump_file,
+            "64496\t10.0.0.0/24\t100\n64497\t10.0.1.0/24\t100\n",
+        )
+        .unwrap();
+        config.bgp_dump_file = Some(dump_file);
+    };
+
+    test_with_configured_krill_server(configure, |_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let current = RoaDefinition::from_str("10.0.0.0/24 => 64496").unwrap();
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(current);
+        ca_route_authorizations_update(&child, updates);
+
+        let exported = ca_route_authorizations_export(&child, RoaFileFormat::Csv);
+
+        let desired = RoaDefinition::from_str("10.0.0.0/16-24 => 64497").unwrap();
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(desired);
+        updates.remove(current);
+
+        let impact = ca_route_authorizations_dry_run(&child, updates);
+        assert_eq!(2, impact.announcements());
+
+        let changes: Vec<String> = impact.added()[0]
+            .changes()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            vec![
+                "10.0.0.0/24 => 64496: valid -> invalid",
+                "10.0.1.0/24 => 64497: not found -> valid",
+            ],
+            changes
+        );
+
+        let changes: Vec<String> = impact.removed()[0]
+            .changes()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(vec!["10.0.0.0/24 => 64496: valid -> invalid"], changes);
+
+        // Nothing was changed
+        assert_eq!(
+            exported,
+            ca_route_authorizations_export(&child, RoaFileFormat::Csv)
+        );
+    });
+}
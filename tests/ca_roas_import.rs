@@ -0,0 +1,87 @@
+extern crate krill;
+
+use std::str::FromStr;
+
+use krill::commons::api::{
+    Handle, ObjectName, ParentCaReq, ResourceSet, RoaDefinition, RoaDefinitionUpdates,
+    RoaFileFormat, RoaFileOptions,
+};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that the complete set of ROAs for a CA can be imported from, and
+/// exported to, CSV and SLURM files.
+fn ca_roas_import() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "2001:DB8::/32").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let route_1 = RoaDefinition::from_str("10.0.0.0/24 => 64496").unwrap();
+        let route_2 = RoaDefinition::from_str("10.0.1.0/24 => 64496").unwrap();
+        let route_3 = RoaDefinition::from_str("2001:DB8::/32-48 => 64496").unwrap();
+
+        let crl_file = ".crl";
+        let mft_file = ".mft";
+        let route1_file = ObjectName::from(&route_1).to_string();
+        let route1_file = route1_file.as_str();
+        let route2_file = ObjectName::from(&route_2).to_string();
+        let route2_file = route2_file.as_str();
+        let route3_file = ObjectName::from(&route_3).to_string();
+        let route3_file = route3_file.as_str();
+
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(route_1);
+        updates.add(route_2);
+        ca_route_authorizations_update(&child, updates);
+        wait_for_published_objects(&child, &[crl_file, mft_file, route1_file, route2_file]);
+
+        // A dry run shows the changes, but does not apply them
+        let csv = "ASN,IP Prefix,Max Length\nAS64496,10.0.0.0/24,\nAS64496,2001:db8::/32,48\n";
+        let csv_options = RoaFileOptions::new(RoaFileFormat::Csv, true);
+
+        let mut expected = RoaDefinitionUpdates::empty();
+        expected.add(route_3);
+        expected.remove(route_2);
+
+        assert_eq!(
+            expected,
+            ca_route_authorizations_import(&child, csv_options, csv)
+        );
+        wait_for_published_objects(&child, &[crl_file, mft_file, route1_file, route2_file]);
+
+        // Now apply the changes
+        let csv_options = RoaFileOptions::new(RoaFileFormat::Csv, false);
+        assert_eq!(
+            expected,
+            ca_route_authorizations_import(&child, csv_options, csv)
+        );
+        wait_for_published_objects(&child, &[crl_file, mft_file, route1_file, route3_file]);
+
+        // Importing the same file again changes nothing
+        let updates = ca_route_authorizations_import(&child, csv_options, csv);
+        assert!(updates.is_empty());
+
+        // The export matches the import
+        assert_eq!(
+            csv,
+            ca_route_authorizations_export(&child, RoaFileFormat::Csv)
+        );
+
+        // And the SLURM export can be imported again, without changes
+        let slurm = ca_route_authorizations_export(&child, RoaFileFormat::Slurm);
+        let slurm_options = RoaFileOptions::new(RoaFileFormat::Slurm, false);
+        let updates = ca_route_authorizations_import(&child, slurm_options, &slurm);
+        assert!(updates.is_empty());
+    });
+}
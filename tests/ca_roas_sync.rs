@@ -0,0 +1,68 @@
+extern crate krill;
+
+use std::str::FromStr;
+
+use krill::commons::api::{
+    Handle, ObjectName, ParentCaReq, ResourceSet, RoaDefinition, RoaDefinitionUpdates,
+    RoaSyncOptions,
+};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that the ROAs for a CA can be replaced with a complete desired set,
+/// and that krill works out the changes itself.
+fn ca_roas_sync() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let route_1 = RoaDefinition::from_str("10.0.0.0/24 => 64496").unwrap();
+        let route_2 = RoaDefinition::from_str("10.0.1.0/24 => 64496").unwrap();
+
+        let crl_file = ".crl";
+        let mft_file = ".mft";
+        let route1_file = ObjectName::from(&route_1).to_string();
+        let route1_file = route1_file.as_str();
+        let route2_file = ObjectName::from(&route_2).to_string();
+        let route2_file = route2_file.as_str();
+
+        // A dry run shows the changes, but does not apply them
+        let mut expected = RoaDefinitionUpdates::empty();
+        expected.add(route_1);
+        expected.add(route_2);
+
+        let dry_run = RoaSyncOptions::new(true);
+        let updates = ca_route_authorizations_sync(&child, vec![route_1, route_2], dry_run);
+        assert_eq!(expected, updates);
+        wait_for_published_objects(&child, &[crl_file, mft_file]);
+
+        // Apply the changes
+        let apply = RoaSyncOptions::new(false);
+        let updates = ca_route_authorizations_sync(&child, vec![route_1, route_2], apply);
+        assert_eq!(expected, updates);
+        wait_for_published_objects(&child, &[crl_file, mft_file, route1_file, route2_file]);
+
+        // Syncing the same set again changes nothing
+        let updates = ca_route_authorizations_sync(&child, vec![route_2, route_1], apply);
+        assert!(updates.is_empty());
+
+        // Only the difference is applied
+        let mut expected = RoaDefinitionUpdates::empty();
+        expected.remove(route_1);
+
+        let updates = ca_route_authorizations_sync(&child, vec![route_2], apply);
+        assert_eq!(expected, updates);
+        wait_for_published_objects(&child, &[crl_file, mft_file, route2_file]);
+    });
+}
@@ -0,0 +1,48 @@
+extern crate krill;
+extern crate rpki;
+
+use rpki::sigobj::SignedObject;
+
+use krill::commons::api::{Handle, ParentCaReq, ResourceSet, RscFileDigest, RscRequest};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that a CA can sign checklists for resources it holds, that these
+/// are not published, and that checklists for other resources are refused.
+fn ca_rsc() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("AS65000", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let digests = vec![RscFileDigest::for_content(
+            Some("loa.pdf".to_string()),
+            b"letter of authority",
+        )];
+
+        // Sign for a subset of the held resources
+        let resources = ResourceSet::from_strs("AS65000", "10.0.0.0/24", "").unwrap();
+        let rsc = ca_sign_checklist(&child, RscRequest::new(resources, digests.clone()));
+        assert!(SignedObject::decode(rsc.to_bytes(), true).is_ok());
+        let published = get_published_objects(&child);
+        assert!(!published.iter().any(|uri| uri.ends_with(".rsc")));
+
+        // Resources not held, no resources, or no digests
+        let not_held = ResourceSet::from_strs("", "192.168.0.0/24", "").unwrap();
+        ca_sign_checklist_expect_error(&child, RscRequest::new(not_held, digests.clone()));
+        ca_sign_checklist_expect_error(&child, RscRequest::new(ResourceSet::default(), digests));
+
+        let resources = ResourceSet::from_strs("", "10.0.0.0/24", "").unwrap();
+        ca_sign_checklist_expect_error(&child, RscRequest::new(resources, vec![]));
+    });
+}
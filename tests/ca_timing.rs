@@ -0,0 +1,65 @@
+extern crate chrono;
+extern crate krill;
+extern crate rpki;
+
+use std::str::FromStr;
+
+use chrono::Duration;
+
+use rpki::x509::Time;
+
+use krill::commons::api::{
+    Handle, ParentCaReq, ResourceSet, RoaDefinition, RoaDefinitionUpdates, TimingConfig,
+};
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+#[test]
+/// Test that a CA can use its own timing for the objects it publishes, that
+/// invalid timing is rejected, and that the CA can revert to the server timing.
+fn ca_timing() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+        let child = Handle::from_str_unsafe("child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+
+        let parent = {
+            let parent_contact = add_child_to_ta_embedded(&child, child_resources.clone());
+            ParentCaReq::new(ta_handle, parent_contact)
+        };
+        add_parent_to_ca(&child, parent);
+        wait_for_current_resources(&child, &child_resources);
+
+        let timing = ca_timing(&child);
+        assert!(!timing.custom());
+        assert_eq!(&TimingConfig::default(), timing.timing());
+
+        // Next update must be before the end of the validity
+        ca_timing_update_expect_error(&child, TimingConfig::new(48, 1, 8, 52, 4));
+        assert!(!ca_timing(&child).custom());
+
+        let custom = TimingConfig::new(2, 1, 1, 8, 2);
+        ca_timing_update(&child, custom);
+
+        let timing = ca_timing(&child);
+        assert!(timing.custom());
+        assert_eq!(&custom, timing.timing());
+
+        // Publish a ROA, so that a new manifest is published using the
+        // custom timing.
+        let mut updates = RoaDefinitionUpdates::empty();
+        updates.add(RoaDefinition::from_str("10.0.0.0/24 => 64496").unwrap());
+        ca_route_authorizations_update(&child, updates);
+
+        wait_for(30, "Manifest not published with custom timing", || {
+            manifest_next_update(&child) < Time::now() + Duration::hours(3)
+        });
+
+        ca_timing_reset(&child);
+        let timing = ca_timing(&child);
+        assert!(!timing.custom());
+        assert_eq!(&TimingConfig::default(), timing.timing());
+    });
+}
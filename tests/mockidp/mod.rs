@@ -0,0 +1,300 @@
+//! A minimal OpenID Connect identity provider, for testing the login flow
+//! against a running krill server.
+//!
+//! Every user that is sent to the authorization endpoint is logged in
+//! straight away, and receives an ID token with the claims given when the
+//! mock was started, or set later.
+//!
+//! Include this using `mod mockidp;` in tests that need it.
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::{thread, time};
+
+use actix_web::web::{self, Form, Query};
+use actix_web::{App, HttpResponse, HttpServer};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::sign::Signer;
+use reqwest::header::{COOKIE, LOCATION, SET_COOKIE};
+use reqwest::{Client, RedirectPolicy, RequestBuilder, StatusCode, Url};
+use serde_json::{Map, Value};
+
+use rpki::x509::Time;
+
+use krill::daemon::oidc::{CSRF_COOKIE, CSRF_HEADER, SESSION_COOKIE};
+
+const KEY_ID: &str = "mock-key";
+
+//------------ MockIdp -------------------------------------------------------
+
+#[derive(Clone)]
+struct MockIdp {
+    issuer: String,
+    key: PKey<Private>,
+    claims: Arc<RwLock<Map<String, Value>>>,
+    codes: Arc<RwLock<HashMap<String, Map<String, Value>>>>,
+}
+
+impl MockIdp {
+    fn id_token(&self, claims: &Map<String, Value>) -> String {
+        let header = json!({ "alg": "RS256", "typ": "JWT", "kid": KEY_ID });
+        let signed = format!(
+            "{}.{}",
+            base64url(header.to_string().as_bytes()),
+            base64url(Value::Object(claims.clone()).to_string().as_bytes())
+        );
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key).unwrap();
+        signer.update(signed.as_bytes()).unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        format!("{}.{}", signed, base64url(&signature))
+    }
+}
+
+/// A handle to a running mock identity provider.
+pub struct MockIdpHandle {
+    pub issuer: String,
+    claims: Arc<RwLock<Map<String, Value>>>,
+}
+
+impl MockIdpHandle {
+    /// Sets the claims for the ID tokens of the next logins.
+    pub fn set_claims(&self, claims: Value) {
+        *self.claims.write().unwrap() = claims_object(claims);
+    }
+}
+
+fn claims_object(claims: Value) -> Map<String, Value> {
+    match claims {
+        Value::Object(claims) => claims,
+        _ => panic!("claims must be a JSON object"),
+    }
+}
+
+/// Starts the mock identity provider on the given port. All ID tokens will
+/// include the given claims, e.g. the email and groups of the user, until
+/// other claims are set using the returned handle.
+pub fn start_mock_idp(port: u16, claims: Value) -> MockIdpHandle {
+    let issuer = format!("http://127.0.0.1:{}", port);
+    let claims = Arc::new(RwLock::new(claims_object(claims)));
+
+    let idp = MockIdp {
+        issuer: issuer.clone(),
+        key: PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+        claims: claims.clone(),
+        codes: Arc::new(RwLock::new(HashMap::new())),
+    };
+
+    thread::spawn(move || {
+        HttpServer::new(move || {
+            App::new()
+                .data(idp.clone())
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(discovery),
+                )
+                .route("/jwks", web::get().to(jwks))
+                .route("/authorize", web::get().to(authorize))
+                .route("/token", web::post().to(token))
+        })
+        .bind(("127.0.0.1", port))
+        .unwrap()
+        .run()
+        .unwrap()
+    });
+
+    let discovery_uri = format!("{}/.well-known/openid-configuration", issuer);
+    let mut tries = 0;
+    while reqwest::get(&discovery_uri).is_err() {
+        tries += 1;
+        if tries > 20 {
+            panic!("Mock identity provider is not coming up")
+        }
+        thread::sleep(time::Duration::from_millis(100));
+    }
+
+    MockIdpHandle { issuer, claims }
+}
+
+fn discovery(idp: web::Data<MockIdp>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "issuer": idp.issuer,
+        "authorization_endpoint": format!("{}/authorize", idp.issuer),
+        "token_endpoint": format!("{}/token", idp.issuer),
+        "jwks_uri": format!("{}/jwks", idp.issuer),
+        "response_types_supported": ["code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["RS256"]
+    }))
+}
+
+fn jwks(idp: web::Data<MockIdp>) -> HttpResponse {
+    let rsa = idp.key.rsa().unwrap();
+    HttpResponse::Ok().json(json!({
+        "keys": [{
+            "kty": "RSA",
+            "alg": "RS256",
+            "use": "sig",
+            "kid": KEY_ID,
+            "n": base64url(&rsa.n().to_vec()),
+            "e": base64url(&rsa.e().to_vec())
+        }]
+    }))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn authorize(idp: web::Data<MockIdp>, params: Query<HashMap<String, String>>) -> HttpResponse {
+    let param = |name: &str| params.get(name).cloned().unwrap_or_default();
+
+    let now = Time::now().timestamp();
+    let mut claims = idp.claims.read().unwrap().clone();
+    claims.insert("iss".to_string(), json!(idp.issuer));
+    claims.insert("sub".to_string(), json!("mock-user"));
+    claims.insert("aud".to_string(), json!(param("client_id")));
+    claims.insert("iat".to_string(), json!(now));
+    claims.insert("exp".to_string(), json!(now + 300));
+    claims.insert("nonce".to_string(), json!(param("nonce")));
+
+    let mut code = [0_u8; 16];
+    openssl::rand::rand_bytes(&mut code).unwrap();
+    let code = hex::encode(code);
+    idp.codes.write().unwrap().insert(code.clone(), claims);
+
+    let redirect = Url::parse_with_params(
+        &param("redirect_uri"),
+        &[("code", code), ("state", param("state"))],
+    )
+    .unwrap();
+
+    HttpResponse::Found()
+        .header("location", redirect.to_string())
+        .finish()
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn token(idp: web::Data<MockIdp>, form: Form<HashMap<String, String>>) -> HttpResponse {
+    let claims = form
+        .get("code")
+        .and_then(|code| idp.codes.write().unwrap().remove(code));
+
+    match claims {
+        Some(claims) => HttpResponse::Ok().json(json!({
+            "access_token": "mock-access-token",
+            "token_type": "Bearer",
+            "id_token": idp.id_token(&claims)
+        })),
+        None => HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" })),
+    }
+}
+
+fn base64url(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+//------------ Logging in ----------------------------------------------------
+
+fn browser() -> Client {
+    Client::builder()
+        .redirect(RedirectPolicy::none())
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+}
+
+/// Follows one redirect, and returns the response.
+fn follow(client: &Client, uri: &str) -> reqwest::Response {
+    let res = client.get(uri).send().unwrap();
+    assert_eq!(
+        StatusCode::FOUND,
+        res.status(),
+        "expected redirect from {}",
+        uri
+    );
+    res
+}
+
+fn location(res: &reqwest::Response) -> String {
+    res.headers()[LOCATION].to_str().unwrap().to_string()
+}
+
+/// A session of a user that logged in, as a browser would keep it.
+pub struct LoginSession {
+    pub id: String,
+    pub csrf_token: String,
+}
+
+impl LoginSession {
+    /// A session that krill does not know, with a made up CSRF token.
+    pub fn unknown() -> Self {
+        LoginSession {
+            id: "unknown".to_string(),
+            csrf_token: "unknown".to_string(),
+        }
+    }
+}
+
+/// Logs in to the krill server at the service URI, like a browser would,
+/// and returns the session id and CSRF token from the cookies that krill
+/// sets.
+pub fn login_with_mock_idp(service_uri: &str) -> LoginSession {
+    let client = browser();
+
+    let to_idp = follow(&client, &format!("{}auth/login", service_uri));
+    let to_krill = follow(&client, &location(&to_idp));
+    let callback = follow(&client, &location(&to_krill));
+
+    let cookie = |name: &str| {
+        let prefix = format!("{}=", name);
+        callback
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|cookie| cookie.to_str().ok())
+            .find(|cookie| cookie.starts_with(&prefix))
+            .map(|cookie| {
+                cookie[prefix.len()..]
+                    .split(';')
+                    .next()
+                    .unwrap()
+                    .to_string()
+            })
+            .unwrap_or_else(|| panic!("no {} cookie set", name))
+    };
+
+    LoginSession {
+        id: cookie(SESSION_COOKIE),
+        csrf_token: cookie(CSRF_COOKIE),
+    }
+}
+
+/// Gets the URI using the session, and returns the status code.
+pub fn get_with_session(uri: &str, session: &LoginSession) -> StatusCode {
+    send_with_session(browser().get(uri), session)
+}
+
+/// Posts the JSON body to the URI using the session, including the CSRF
+/// token like the UI does, and returns the status code.
+pub fn post_with_session(uri: &str, body: &Value, session: &LoginSession) -> StatusCode {
+    send_with_session(
+        browser()
+            .post(uri)
+            .header(CSRF_HEADER, session.csrf_token.as_str())
+            .json(body),
+        session,
+    )
+}
+
+/// Posts the JSON body to the URI using only the session cookie, like a
+/// form on another site could, and returns the status code.
+pub fn post_without_csrf_token(uri: &str, body: &Value, session: &LoginSession) -> StatusCode {
+    send_with_session(browser().post(uri).json(body), session)
+}
+
+fn send_with_session(req: RequestBuilder, session: &LoginSession) -> StatusCode {
+    req.header(COOKIE, format!("{}={}", SESSION_COOKIE, session.id))
+        .send()
+        .unwrap()
+        .status()
+}
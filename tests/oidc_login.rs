@@ -0,0 +1,81 @@
+extern crate actix_web;
+extern crate base64;
+extern crate hex;
+extern crate krill;
+extern crate openssl;
+extern crate reqwest;
+extern crate rpki;
+#[macro_use]
+extern crate serde_json;
+
+mod mockidp;
+
+use krill::commons::api::Role;
+use krill::daemon::config::Config;
+use krill::daemon::oidc::OidcRoleMapping;
+use krill::daemon::test::*;
+
+use mockidp::*;
+
+#[test]
+fn oidc_login() {
+    let idp = start_mock_idp(
+        3100,
+        json!({ "email": "reader@example.com", "groups": ["staff", "krill-readers"] }),
+    );
+
+    let issuer = idp.issuer.clone();
+    let configure = move |config: &mut Config| {
+        config.oidc_issuer = Some(issuer);
+        config.oidc_client_id = Some("krill".to_string());
+        config.oidc_client_secret = Some("secret".to_string());
+        config.oidc_role_mappings = vec![
+            OidcRoleMapping::new("krill-admins", Role::Admin, vec![]),
+            OidcRoleMapping::new("krill-readers", Role::ReadOnly, vec![]),
+        ];
+    };
+
+    test_with_configured_krill_server(configure, |_d| {
+        let service_uri = "https://localhost:3000/";
+        let session = login_with_mock_idp(service_uri);
+
+        // The session can be used to read, but not to make changes
+        let authorized = format!("{}api/v1/authorized", service_uri);
+        assert_eq!(200, get_with_session(&authorized, &session).as_u16());
+
+        let cas = format!("{}api/v1/cas", service_uri);
+        let ca_init = json!({ "handle": "ca" });
+        assert_eq!(403, post_with_session(&cas, &ca_init, &session).as_u16());
+
+        // Unknown sessions are refused
+        let unknown = LoginSession::unknown();
+        assert_eq!(403, get_with_session(&authorized, &unknown).as_u16());
+
+        // Sessions end when logging out
+        let logout = format!("{}auth/logout", service_uri);
+        // .. but only when asked for with the CSRF token
+        get_with_session(&logout, &session);
+        post_without_csrf_token(&logout, &json!({}), &session);
+        assert_eq!(200, get_with_session(&authorized, &session).as_u16());
+        assert_eq!(
+            200,
+            post_with_session(&logout, &json!({}), &session).as_u16()
+        );
+        assert_eq!(403, get_with_session(&authorized, &session).as_u16());
+
+        // Changes need the CSRF token of the session, not just the cookie
+        idp.set_claims(json!({ "email": "admin@example.com", "groups": ["krill-admins"] }));
+        let mut session = login_with_mock_idp(service_uri);
+        assert_eq!(
+            403,
+            post_without_csrf_token(&cas, &ca_init, &session).as_u16()
+        );
+
+        let csrf_token = session.csrf_token.clone();
+        session.csrf_token = "forged".to_string();
+        assert_eq!(403, post_with_session(&cas, &ca_init, &session).as_u16());
+
+        session.csrf_token = csrf_token;
+        assert_eq!(200, post_with_session(&cas, &ca_init, &session).as_u16());
+    });
+}
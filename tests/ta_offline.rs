@@ -0,0 +1,81 @@
+extern crate krill;
+
+use krill::cli::options::{Command, TaOfflineCommand};
+use krill::cli::Error;
+use krill::commons::api::{
+    Handle, ParentCaContact, ParentCaReq, ResourceSet, TaRequestBundle, TaResponseBundle,
+};
+use krill::commons::util::httpclient;
+use krill::daemon::ca::ta_handle;
+use krill::daemon::test::*;
+
+fn ta_offline_import_invalid(responses: TaResponseBundle) {
+    let command = Command::TaOffline(TaOfflineCommand::Import(responses));
+    match krill_admin_expect_error(command) {
+        Error::HttpClientError(httpclient::Error::ErrorWithJson(_, res)) => {
+            assert_eq!("ta-offline-response-invalid", res.label())
+        }
+        e => panic!("Expected error response, got: {}", e),
+    }
+}
+
+#[test]
+/// Test that a CA can get its certificate from, and roll its key under, an
+/// offline TA parent by exchanging exported requests and responses. The TA
+/// of the test server plays the part of the offline TA.
+fn ta_offline() {
+    test_with_krill_server(|_d| {
+        let ta_handle = ta_handle();
+
+        let child = Handle::from_str_unsafe("offline-child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        init_child_with_embedded_repo(&child);
+        let req = child_request(&child);
+
+        let parent = {
+            let contact = match add_child_to_ta_rfc6492(&child, req, child_resources.clone()) {
+                ParentCaContact::Rfc6492(response) => ParentCaContact::Offline(response),
+                _ => panic!("Expected RFC 6492 parent contact"),
+            };
+            ParentCaReq::new(ta_handle, contact)
+        };
+        add_parent_to_ca(&child, parent);
+
+        // The first exchange gets the entitlements, which results in a
+        // certificate request for the second exchange.
+        let requests = ta_offline_requests(false);
+        assert_eq!(1, requests.requests().len());
+        ta_offline_import(ta_offline_sign(requests));
+
+        let requests = ta_offline_requests(true);
+        assert_eq!(2, requests.requests().len());
+        assert!(requests.republish());
+
+        let responses = ta_offline_sign(requests);
+        let content = ta_offline_responses_content(&responses);
+        assert_eq!(2, content.responses().len());
+        assert!(!content.objects().is_empty());
+
+        // Bundles which are changed after signing, or which have no
+        // responses, are refused.
+        let mut cms = responses.to_bytes().to_vec();
+        let last = cms.len() - 1;
+        cms[last] ^= 0xff;
+        ta_offline_import_invalid(TaResponseBundle::new(cms.into()));
+        ta_offline_import_invalid(ta_offline_sign(TaRequestBundle::new(vec![], false)));
+
+        ta_offline_import(responses);
+        wait_for_current_resources(&child, &child_resources);
+
+        // Roll the key: the new key is certified in one exchange, and the
+        // old key is revoked in the next.
+        ca_roll_init(&child);
+        ta_offline_import(ta_offline_sign(ta_offline_requests(false)));
+        wait_for_new_key(&child);
+
+        ca_roll_activate(&child);
+        ta_offline_import(ta_offline_sign(ta_offline_requests(false)));
+        wait_for_key_roll_complete(&child);
+    });
+}
@@ -0,0 +1,136 @@
+extern crate krill;
+extern crate rpki;
+
+use std::fs;
+use std::str::FromStr;
+
+use rpki::uri;
+
+use krill::cli::options::{CaCommand, Command, PublishersCommand, TaOfflineCommand};
+use krill::cli::report::ApiResponse;
+use krill::commons::api::{
+    CertAuthInit, Handle, ParentCaContact, ParentCaReq, PublisherDetails, RepositoryUpdate,
+    ResourceSet, TaRequestBundle, TaRequestOptions, TaResponseBundle,
+};
+use krill::commons::remote::rfc8183;
+use krill::commons::util::httpclient;
+use krill::daemon::ca::ta_handle;
+use krill::daemon::config::Config;
+use krill::daemon::test::*;
+
+// The online server, which does not have the TA itself.
+fn online(command: Command) -> ApiResponse {
+    krill_pubd_admin(command, PubdTestContext::Secondary)
+}
+
+fn online_child_request(handle: &Handle) -> rfc8183::ChildRequest {
+    match online(Command::CertAuth(CaCommand::ChildRequest(handle.clone()))) {
+        ApiResponse::Rfc8183ChildRequest(req) => req,
+        _ => panic!("Expected child request"),
+    }
+}
+
+fn online_requests(republish: bool) -> TaRequestBundle {
+    let options = TaRequestOptions::new(republish);
+    match online(Command::TaOffline(TaOfflineCommand::Requests(options))) {
+        ApiResponse::TaRequestBundle(bundle) => bundle,
+        _ => panic!("Expected offline TA requests"),
+    }
+}
+
+fn online_import(responses: TaResponseBundle) {
+    online(Command::TaOffline(TaOfflineCommand::Import(responses)));
+}
+
+fn online_current_resources(handle: &Handle) -> ResourceSet {
+    match online(Command::CertAuth(CaCommand::Show(handle.clone()))) {
+        ApiResponse::CertAuthInfo(ca) => {
+            let mut res = ResourceSet::default();
+            for rc in ca.resource_classes().values() {
+                if let Some(resources) = rc.current_resources() {
+                    res = res.union(resources)
+                }
+            }
+            res
+        }
+        _ => panic!("Expected cert auth info"),
+    }
+}
+
+fn online_ta_publisher() -> PublisherDetails {
+    let command = Command::Publishers(PublishersCommand::ShowPublisher(ta_handle()));
+    match online(command) {
+        ApiResponse::PublisherDetails(details) => details,
+        _ => panic!("Expected publisher details"),
+    }
+}
+
+#[test]
+/// Test that an online server without the TA can act on behalf of an
+/// offline TA on an air-gapped server: the TA is added as a publisher, its
+/// objects are published, its TAL is served, and its responses are
+/// processed by the CAs of the online server.
+fn ta_offline_air_gapped() {
+    // The offline TA uses the repository of the online server.
+    let configure = |config: &mut Config| {
+        config.rsync_base = uri::Rsync::from_str("rsync://remotehost/repo/").unwrap();
+    };
+
+    test_with_configured_krill_server(configure, |d| {
+        // The online server only trusts bundles signed by the offline TA,
+        // whose identity certificate is taken there out of band.
+        let ta_id_cert = d.join("ta-id.pem");
+        fs::write(&ta_id_cert, ca_details(&ta_handle()).id_cert().pem()).unwrap();
+
+        let online_dir = start_configured_krill_pubd_server(|config: &mut Config| {
+            config.ta_offline_id_cert = Some(ta_id_cert);
+        });
+
+        let child = Handle::from_str_unsafe("online-child");
+        let child_resources = ResourceSet::from_strs("", "10.0.0.0/16", "").unwrap();
+
+        online(Command::CertAuth(CaCommand::Init(CertAuthInit::new(
+            child.clone(),
+        ))));
+        online(Command::CertAuth(CaCommand::RepoUpdate(
+            child.clone(),
+            RepositoryUpdate::Embedded,
+        )));
+
+        let req = online_child_request(&child);
+        let parent = {
+            let contact = match add_child_to_ta_rfc6492(&child, req, child_resources.clone()) {
+                ParentCaContact::Rfc6492(response) => ParentCaContact::Offline(response),
+                _ => panic!("Expected RFC 6492 parent contact"),
+            };
+            ParentCaReq::new(ta_handle(), contact)
+        };
+        online(Command::CertAuth(CaCommand::AddParent(
+            child.clone(),
+            parent,
+        )));
+
+        // The first exchange gets the entitlements, the second one the
+        // certificate for the child.
+        online_import(ta_offline_sign(online_requests(false)));
+
+        let responses = ta_offline_sign(online_requests(true));
+        let ta_objects = ta_offline_responses_content(&responses).objects().len();
+        assert!(ta_objects > 0);
+        online_import(responses);
+
+        wait_for(30, "online child did not get its certificate", || {
+            online_current_resources(&child) == child_resources
+        });
+
+        // The online server publishes the objects of the offline TA.
+        assert_eq!(ta_objects, online_ta_publisher().current_files().len());
+
+        // The online server serves the TAL of the offline TA.
+        let tal = httpclient::get_text("https://localhost:3000/ta/ta.tal", None).unwrap();
+        let online_tal = httpclient::get_text("https://localhost:3001/ta/ta.tal", None).unwrap();
+        assert_eq!(tal, online_tal);
+
+        fs::remove_dir_all(online_dir).unwrap();
+    });
+}